@@ -0,0 +1,94 @@
+use word_library::store::dict;
+use word_library::store::exchange::command;
+use word_library::store::history;
+use word_library::store::merge;
+use word_library::store::server::{self, Server};
+use word_library::store::shell::Shell;
+use word_library::store::sync;
+use word_library::store::trash;
+use word_library::store::tui::TUI;
+
+const USAGE: &str = r#"usage: wordlib <command> [args]
+commands:
+  shell [path]...    open library files or directories in an interactive shell
+  tui [path]...      open library files or directories in the terminal ui
+  serve <workspace> [addr]
+                     serve the libraries of workspace over http, default addr 127.0.0.1:8737"#;
+
+fn exit_on_err(res: Result<(), String>) {
+    if let Err(err) = res {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("shell") => {
+            let mut shell = Shell::new().unwrap();
+            for p in &args[1..] {
+                shell.open(p);
+            }
+            if let Err(err) = shell.run() {
+                eprintln!("shell exit with error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some("tui") => {
+            let mut tui = TUI::new();
+            for p in &args[1..] {
+                if let Err(err) = tui.open(p) {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+            }
+            if let Err(err) = tui.run() {
+                eprintln!("tui exit with error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some("serve") if args.len() >= 2 => {
+            let addr = args
+                .get(2)
+                .map(|s| s.as_str())
+                .unwrap_or(server::DEFAULT_ADDR);
+            let res = Server::open(&args[1]).and_then(|s| Server::bind(addr).map(|l| (s, l)));
+            match res {
+                Ok((s, listener)) => {
+                    println!("serving {} on http://{}", args[1], addr);
+                    s.run(listener);
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some("import") => exit_on_err(command::import(&args[1..])),
+        Some("export") => exit_on_err(command::export(&args[1..])),
+        Some("lookups") => exit_on_err(command::lookups(&args[1..])),
+        Some("extract") => exit_on_err(command::extract(&args[1..])),
+        Some("glossary") => exit_on_err(command::glossary(&args[1..])),
+        Some("dump") => exit_on_err(command::dump(&args[1..])),
+        Some("restore") => exit_on_err(command::restore(&args[1..])),
+        Some("dict") => exit_on_err(dict::command(&args[1..])),
+        Some("diff") | Some("merge") => exit_on_err(merge::command(&args)),
+        Some("sync") | Some("conflicts") => exit_on_err(sync::command(&args)),
+        Some("history") => exit_on_err(history::command(&args[1..])),
+        Some("trash") => exit_on_err(trash::command(&args[1..])),
+        _ => {
+            eprintln!(
+                "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+                USAGE,
+                command::USAGE,
+                merge::USAGE,
+                sync::USAGE,
+                history::USAGE,
+                trash::USAGE,
+                dict::USAGE
+            );
+            std::process::exit(2);
+        }
+    }
+}
@@ -1,93 +1,960 @@
 use sqlite::Connection;
+use std::ops::Deref;
+use std::sync::{Mutex, MutexGuard};
 
-use crate::store::{Read, Store, Transaction as StoreTransaction, World, Write};
+use crate::store::attachment::Attachment;
+use crate::store::history::Snapshot;
+use crate::store::links::{self, Link};
+use crate::store::review::Schedule;
+use crate::store::sync::{self, Conflict, Record, Revision};
+use crate::store::trash::Trashed;
+use crate::store::{Entry, Read, Store, Transaction as StoreTransaction, World, Write};
 const LIBNAME: &str = "library";
+const REVIEWNAME: &str = "review";
+const METANAME: &str = "meta";
+const REVISIONNAME: &str = "revision";
+const CONFLICTNAME: &str = "conflict";
+const HISTORYNAME: &str = "history";
+const TRASHNAME: &str = "trash";
+const TAGNAME: &str = "tag";
+const LINKNAME: &str = "link";
+const ATTACHMENTNAME: &str = "attachment";
+const TRASH_ATTACHMENTNAME: &str = "trash_attachment";
 const CREATE_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS library (
       word TEXT PRIMARY KEY,
-      message TEXT NOT NULL
+      message TEXT NOT NULL,
+      version INTEGER NOT NULL DEFAULT 1
+    )"#;
+// library file created before the version column
+const ADD_VERSION: &str = "ALTER TABLE library ADD COLUMN version INTEGER NOT NULL DEFAULT 1";
+const CREATE_REVIEW_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS review (
+      word TEXT PRIMARY KEY,
+      due INTEGER NOT NULL,
+      interval INTEGER NOT NULL,
+      ease REAL NOT NULL,
+      reps INTEGER NOT NULL
+    )"#;
+const CREATE_META_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS meta (
+      key TEXT PRIMARY KEY,
+      value TEXT NOT NULL
+    )"#;
+
+// the last change of the fields of a word, a row without word is a tombstone
+const CREATE_REVISION_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS revision (
+      word TEXT PRIMARY KEY,
+      message_at INTEGER NOT NULL,
+      message_device TEXT NOT NULL,
+      schedule_at INTEGER NOT NULL,
+      schedule_device TEXT NOT NULL
+    )"#;
+const CREATE_CONFLICT_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS conflict (
+      id INTEGER PRIMARY KEY,
+      word TEXT NOT NULL,
+      field TEXT NOT NULL,
+      value TEXT,
+      updated_at INTEGER NOT NULL,
+      device TEXT NOT NULL,
+      kept TEXT,
+      recorded_at INTEGER NOT NULL
+    )"#;
+// the message of a word before a change
+const CREATE_HISTORY_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS history (
+      id INTEGER PRIMARY KEY,
+      word TEXT NOT NULL,
+      message TEXT NOT NULL,
+      version INTEGER NOT NULL,
+      action TEXT NOT NULL,
+      changed_at INTEGER NOT NULL
+    )"#;
+const CREATE_HISTORY_INDEX: &str = "CREATE INDEX IF NOT EXISTS history_word ON history (word)";
+// words deleted from the libraries of a workspace, only used in its trash file
+const CREATE_TRASH_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS trash (
+      id INTEGER PRIMARY KEY,
+      origin TEXT NOT NULL,
+      word TEXT NOT NULL,
+      message TEXT NOT NULL,
+      version INTEGER NOT NULL,
+      due INTEGER,
+      interval INTEGER,
+      ease REAL,
+      reps INTEGER,
+      deleted_at INTEGER NOT NULL,
+      tags TEXT NOT NULL DEFAULT ''
     )"#;
 
+// the trash of an older version has no tags
+const ADD_TRASH_TAGS: &str = "ALTER TABLE trash ADD COLUMN tags TEXT NOT NULL DEFAULT ''";
+const CREATE_TAG_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS tag (
+      word TEXT NOT NULL,
+      tag TEXT NOT NULL,
+      PRIMARY KEY (word, tag)
+    )"#;
+const CREATE_TAG_INDEX: &str = "CREATE INDEX IF NOT EXISTS tag_tag ON tag (tag)";
+// the links in the messages, library is '' for a link in the same library
+const CREATE_LINK_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS link (
+      word TEXT NOT NULL,
+      library TEXT NOT NULL,
+      target TEXT NOT NULL,
+      PRIMARY KEY (word, library, target)
+    )"#;
+const CREATE_LINK_INDEX: &str = "CREATE INDEX IF NOT EXISTS link_target ON link (target)";
+const CREATE_ATTACHMENT_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS attachment (
+      word TEXT NOT NULL,
+      name TEXT NOT NULL,
+      mime TEXT NOT NULL,
+      data BLOB NOT NULL,
+      PRIMARY KEY (word, name)
+    )"#;
+// the files of the words in the trash by the id of the word there
+const CREATE_TRASH_ATTACHMENT_TABLE: &str = r#"CREATE TABLE  IF NOT EXISTS trash_attachment (
+      id INTEGER NOT NULL,
+      name TEXT NOT NULL,
+      mime TEXT NOT NULL,
+      data BLOB NOT NULL,
+      PRIMARY KEY (id, name)
+    )"#;
+
+// idle connections kept by a library file
+const POOL_SIZE: usize = 4;
+// milliseconds to wait for the lock of another writer
+const BUSY_TIMEOUT: usize = 5000;
+
 use super::*;
 
 extern crate sqlite;
 
+enum Connect {
+    // connections shared by threads, every operation takes one from the pool
+    Pool(Mutex<Vec<Connection>>),
+    // the connection of a transaction, all operations must run on it
+    Single(Mutex<Connection>),
+}
+
+/// a connection borrowed from Connect, returned to the pool when dropped
+enum Conn<'a> {
+    Pooled(&'a Mutex<Vec<Connection>>, Option<Connection>),
+    Single(MutexGuard<'a, Connection>),
+}
+
+impl<'a> Deref for Conn<'a> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            Conn::Pooled(_, con) => con.as_ref().unwrap(),
+            Conn::Single(con) => con,
+        }
+    }
+}
+
+impl<'a> Drop for Conn<'a> {
+    fn drop(&mut self) {
+        if let Conn::Pooled(pool, con) = self {
+            let mut pool = pool.lock().unwrap_or_else(|e| e.into_inner());
+            if pool.len() < POOL_SIZE {
+                pool.extend(con.take());
+            }
+        }
+    }
+}
+
 pub struct Sqlite {
     _path: String,
-    connect: sqlite::Connection,
+    connect: Connect,
     _insert_stmt: String,
     _delete_stmt: String,
     _update_stmt: String,
     _get_stmt: String,
     _dump_stmt: String,
     _dump_size_stmt: String,
+    _scan_stmt: String,
+    _version_stmt: String,
+    _schedule_stmt: String,
+    _set_schedule_stmt: String,
+    _delete_schedule_stmt: String,
+    _due_stmt: String,
+    _entry_stmt: String,
+    _put_stmt: String,
+    _metadata_stmt: String,
+    _set_metadata_stmt: String,
+    _touch_stmt: String,
+    _touch_message_stmt: String,
+    _touch_schedule_stmt: String,
+    _records_stmt: String,
+    _put_word_stmt: String,
+    _conflicts_stmt: String,
+    _add_conflict_stmt: String,
+    _remove_conflict_stmt: String,
+    _snapshot_stmt: String,
+    _history_stmt: String,
+    _deleted_stmt: String,
+    _put_trashed_stmt: String,
+    _remove_trashed_stmt: String,
+    _trashed_stmt: String,
+    _last_trashed_stmt: String,
+    _add_tag_stmt: String,
+    _remove_tag_stmt: String,
+    _delete_tags_stmt: String,
+    _tags_stmt: String,
+    _word_tags_stmt: String,
+    _tagged_stmt: String,
+    _add_link_stmt: String,
+    _delete_links_stmt: String,
+    _links_stmt: String,
+    _backlinks_stmt: String,
+    _all_links_stmt: String,
+    _put_attachment_stmt: String,
+    _remove_attachment_stmt: String,
+    _delete_attachments_stmt: String,
+    _attachments_stmt: String,
+    _attachment_stmt: String,
+    _put_trashed_attachment_stmt: String,
+    _remove_trashed_attachments_stmt: String,
+    _trashed_attachments_stmt: String,
 }
 
 impl Sqlite {
     fn create_table(&self) -> Result<()> {
-        let con = &self.connect;
+        let con = self.conn()?;
+        // WAL mode let readers run concurrently with a writer
+        con.execute("PRAGMA journal_mode=WAL")?;
         con.execute(CREATE_TABLE)?;
+        if !self.has_column(LIBNAME, "version")? {
+            con.execute(ADD_VERSION)?;
+        }
+        con.execute(CREATE_REVIEW_TABLE)?;
+        con.execute(CREATE_META_TABLE)?;
+        con.execute(CREATE_REVISION_TABLE)?;
+        con.execute(CREATE_CONFLICT_TABLE)?;
+        con.execute(CREATE_HISTORY_TABLE)?;
+        con.execute(CREATE_HISTORY_INDEX)?;
+        con.execute(CREATE_TRASH_TABLE)?;
+        if !self.has_column(TRASHNAME, "tags")? {
+            con.execute(ADD_TRASH_TAGS)?;
+        }
+        con.execute(CREATE_TAG_TABLE)?;
+        con.execute(CREATE_TAG_INDEX)?;
+        // the words written before the links are indexed once
+        let indexed = self.has_column(LINKNAME, "word")?;
+        con.execute(CREATE_LINK_TABLE)?;
+        con.execute(CREATE_LINK_INDEX)?;
+        if !indexed {
+            self.index_links(&con)?;
+        }
+        con.execute(CREATE_ATTACHMENT_TABLE)?;
+        con.execute(CREATE_TRASH_ATTACHMENT_TABLE)?;
+        Ok(())
+    }
+
+    fn index_links(&self, con: &Connection) -> Result<()> {
+        let mut stmt = con.prepare(format!("SELECT word, message FROM {}", LIBNAME))?;
+        let mut words: Vec<(String, String)> = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            words.push((stmt.read(0)?, stmt.read(1)?));
+        }
+        for (word, message) in words {
+            self.write_links(con, &word, Some(&message))?;
+        }
         Ok(())
     }
 
+    fn has_column(&self, table: &str, column: &str) -> Result<bool> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(format!("PRAGMA table_info({})", table))?;
+        while let sqlite::State::Row = stmt.next()? {
+            if stmt.read::<String>(1)? == column {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     pub fn new(path: String) -> Result<Sqlite> {
-        let s = Sqlite::new_conn(path)?;
-        println!("create table");
+        let con = Sqlite::open(&path)?;
+        let s = Sqlite::new_conn(path, Connect::Pool(Mutex::new(vec![con])));
         s.create_table()?;
         Ok(s)
     }
 
-    fn new_conn(path: String) -> Result<Sqlite> {
-        let con = Connection::open_with_flags(
-            &path,
+    fn open(path: &str) -> Result<Connection> {
+        let mut con = Connection::open_with_flags(
+            path,
             sqlite::OpenFlags::new()
                 // .set_full_mutex()
                 // .set_no_mutex()
                 .set_read_write()
                 .set_create(),
         )?;
+        con.set_busy_timeout(BUSY_TIMEOUT)?;
+        Ok(con)
+    }
+
+    // take a connection, a new one is opened when all pooled connections are in use
+    fn conn(&self) -> Result<Conn<'_>> {
+        match &self.connect {
+            Connect::Pool(pool) => {
+                let con = pool.lock().unwrap_or_else(|e| e.into_inner()).pop();
+                let con = match con {
+                    Some(con) => con,
+                    None => Sqlite::open(&self._path)?,
+                };
+                Ok(Conn::Pooled(pool, Some(con)))
+            }
+            Connect::Single(con) => Ok(Conn::Single(con.lock().unwrap_or_else(|e| e.into_inner()))),
+        }
+    }
+
+    // run the writes of f on one connection as a whole, a transaction
+    // connection is already inside its own
+    fn write<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        let con = self.conn()?;
+        if let Conn::Single(_) = con {
+            return f(&con);
+        }
+        con.execute("BEGIN IMMEDIATE")?;
+        match f(&con) {
+            Ok(res) => {
+                con.execute("COMMIT")?;
+                Ok(res)
+            }
+            Err(e) => {
+                let _ = con.execute("ROLLBACK");
+                Err(e)
+            }
+        }
+    }
+
+    // the number of rows changed by the last statement on con
+    fn changes(con: &Connection) -> Result<i64> {
+        let mut stmt = con.prepare("SELECT changes()")?;
+        stmt.next()?;
+        Ok(stmt.read(0)?)
+    }
 
-        let s = Sqlite {
-            connect: con,
+    fn new_conn(path: String, connect: Connect) -> Sqlite {
+        Sqlite {
+            connect,
             _path: path,
             _insert_stmt: format!("INSERT INTO {} (word, message) VALUES (?, ?);", LIBNAME),
             _delete_stmt: format!("DELETE FROM {} WHERE word=?", LIBNAME),
-            _update_stmt: format!("UPDATE {} SET message=? WHERE word=?;", LIBNAME),
+            _update_stmt: format!(
+                "UPDATE {} SET message=?, version=version+1 WHERE word=?;",
+                LIBNAME
+            ),
             _get_stmt: format!("SELECT message FROM {} WHERE word=?", LIBNAME),
             _dump_stmt: format!("SELECT word, message from {}", LIBNAME),
             _dump_size_stmt: format!("SELECT word, message from {} LIMIT ?", LIBNAME),
-        };
-        Ok(s)
+            _scan_stmt: format!(
+                "SELECT word, message FROM {} WHERE word>? ORDER BY word LIMIT ?",
+                LIBNAME
+            ),
+            _version_stmt: format!("SELECT version FROM {} WHERE word=?", LIBNAME),
+            _schedule_stmt: format!(
+                "SELECT r.due, r.interval, r.ease, r.reps FROM {} l LEFT JOIN {} r \
+                ON l.word=r.word WHERE l.word=?",
+                LIBNAME, REVIEWNAME
+            ),
+            _set_schedule_stmt: format!(
+                "INSERT OR REPLACE INTO {} (word, due, interval, ease, reps) VALUES (?, ?, ?, ?, ?)",
+                REVIEWNAME
+            ),
+            _delete_schedule_stmt: format!("DELETE FROM {} WHERE word=?", REVIEWNAME),
+            _due_stmt: format!(
+                "SELECT l.word, l.message FROM {} l LEFT JOIN {} r ON l.word=r.word \
+                WHERE IFNULL(r.due, 0)<=? ORDER BY IFNULL(r.due, 0) LIMIT ?",
+                LIBNAME, REVIEWNAME
+            ),
+            _entry_stmt: format!(
+                "SELECT l.message, l.version, r.due, r.interval, r.ease, r.reps FROM {} l \
+                LEFT JOIN {} r ON l.word=r.word WHERE l.word=?",
+                LIBNAME, REVIEWNAME
+            ),
+            _put_stmt: format!(
+                "INSERT OR REPLACE INTO {} (word, message, version) VALUES (?, ?, ?)",
+                LIBNAME
+            ),
+            _metadata_stmt: format!("SELECT key, value FROM {} ORDER BY key", METANAME),
+            _set_metadata_stmt: format!(
+                "INSERT OR REPLACE INTO {} (key, value) VALUES (?, ?)",
+                METANAME
+            ),
+            _touch_stmt: format!(
+                "INSERT OR REPLACE INTO {} (word, message_at, message_device, schedule_at, \
+                schedule_device) VALUES (?, ?, ?, ?, ?)",
+                REVISIONNAME
+            ),
+            // only for a word in the library, a missing word is not a tombstone
+            _touch_message_stmt: format!(
+                "INSERT INTO {0} (word, message_at, message_device, schedule_at, schedule_device) \
+                SELECT ?1, ?2, ?3, 0, '' WHERE EXISTS (SELECT 1 FROM {1} WHERE word=?1) \
+                ON CONFLICT(word) DO UPDATE SET message_at=excluded.message_at, \
+                message_device=excluded.message_device",
+                REVISIONNAME, LIBNAME
+            ),
+            _touch_schedule_stmt: format!(
+                "INSERT INTO {0} (word, message_at, message_device, schedule_at, schedule_device) \
+                SELECT ?1, 0, '', ?2, ?3 WHERE EXISTS (SELECT 1 FROM {1} WHERE word=?1) \
+                ON CONFLICT(word) DO UPDATE SET schedule_at=excluded.schedule_at, \
+                schedule_device=excluded.schedule_device",
+                REVISIONNAME, LIBNAME
+            ),
+            _records_stmt: format!(
+                "SELECT l.word, l.message, IFNULL(v.message_at, 0), IFNULL(v.message_device, ''), \
+                IFNULL(v.schedule_at, 0), IFNULL(v.schedule_device, ''), \
+                r.due, r.interval, r.ease, r.reps FROM {0} l \
+                LEFT JOIN {1} v ON l.word=v.word LEFT JOIN {2} r ON l.word=r.word WHERE l.word>?1 \
+                UNION ALL \
+                SELECT word, NULL, message_at, message_device, schedule_at, schedule_device, \
+                NULL, NULL, NULL, NULL FROM {1} \
+                WHERE word>?1 AND word NOT IN (SELECT word FROM {0}) \
+                ORDER BY 1 LIMIT ?2",
+                LIBNAME, REVISIONNAME, REVIEWNAME
+            ),
+            _put_word_stmt: format!(
+                "INSERT INTO {} (word, message) VALUES (?, ?) \
+                ON CONFLICT(word) DO UPDATE SET message=excluded.message, version=version+1",
+                LIBNAME
+            ),
+            _conflicts_stmt: format!(
+                "SELECT id, word, field, value, updated_at, device, kept, recorded_at FROM {} \
+                ORDER BY id",
+                CONFLICTNAME
+            ),
+            _add_conflict_stmt: format!(
+                "INSERT INTO {} (word, field, value, updated_at, device, kept, recorded_at) \
+                VALUES (?, ?, ?, ?, ?, ?, ?)",
+                CONFLICTNAME
+            ),
+            _remove_conflict_stmt: format!("DELETE FROM {} WHERE id=?", CONFLICTNAME),
+            // kept only when the message is changed, a delete binds no new message
+            _snapshot_stmt: format!(
+                "INSERT INTO {} (word, message, version, action, changed_at) \
+                SELECT word, message, version, ?2, ?3 FROM {} WHERE word=?1 AND message IS NOT ?4",
+                HISTORYNAME, LIBNAME
+            ),
+            _history_stmt: format!(
+                "SELECT id, message, version, action, changed_at FROM {} WHERE word=? \
+                ORDER BY id DESC",
+                HISTORYNAME
+            ),
+            _deleted_stmt: format!(
+                "SELECT id, word, message, version, action, changed_at FROM {0} h \
+                WHERE id=(SELECT MAX(id) FROM {0} WHERE word=h.word) \
+                AND word NOT IN (SELECT word FROM {1}) ORDER BY id DESC LIMIT ?",
+                HISTORYNAME, LIBNAME
+            ),
+            _put_trashed_stmt: format!(
+                "INSERT INTO {} (origin, word, message, version, due, interval, ease, reps, \
+                deleted_at, tags) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                TRASHNAME
+            ),
+            _remove_trashed_stmt: format!("DELETE FROM {} WHERE id=?", TRASHNAME),
+            _trashed_stmt: format!(
+                "SELECT id, origin, word, message, version, due, interval, ease, reps, deleted_at, \
+                tags FROM {} ORDER BY deleted_at DESC, id DESC LIMIT ?",
+                TRASHNAME
+            ),
+            _last_trashed_stmt: format!("SELECT IFNULL(MAX(id), 0) FROM {}", TRASHNAME),
+            _add_tag_stmt: format!(
+                "INSERT OR IGNORE INTO {} (word, tag) VALUES (?, ?)",
+                TAGNAME
+            ),
+            _remove_tag_stmt: format!("DELETE FROM {} WHERE word=? AND tag=?", TAGNAME),
+            _delete_tags_stmt: format!("DELETE FROM {} WHERE word=?", TAGNAME),
+            _tags_stmt: format!(
+                "SELECT tag, COUNT(*) FROM {} GROUP BY tag ORDER BY tag",
+                TAGNAME
+            ),
+            _word_tags_stmt: format!("SELECT tag FROM {} WHERE word=? ORDER BY tag", TAGNAME),
+            _tagged_stmt: format!("SELECT word FROM {} WHERE tag=? ORDER BY word", TAGNAME),
+            _add_link_stmt: format!(
+                "INSERT OR IGNORE INTO {} (word, library, target) VALUES (?, ?, ?)",
+                LINKNAME
+            ),
+            _delete_links_stmt: format!("DELETE FROM {} WHERE word=?", LINKNAME),
+            _links_stmt: format!(
+                "SELECT library, target FROM {} WHERE word=? ORDER BY rowid",
+                LINKNAME
+            ),
+            _backlinks_stmt: format!(
+                "SELECT word, library, target FROM {} WHERE target=? ORDER BY word",
+                LINKNAME
+            ),
+            _all_links_stmt: format!(
+                "SELECT word, library, target FROM {} ORDER BY word, rowid",
+                LINKNAME
+            ),
+            _put_attachment_stmt: format!(
+                "INSERT OR REPLACE INTO {} (word, name, mime, data) VALUES (?, ?, ?, ?)",
+                ATTACHMENTNAME
+            ),
+            _remove_attachment_stmt: format!(
+                "DELETE FROM {} WHERE word=? AND name=?",
+                ATTACHMENTNAME
+            ),
+            _delete_attachments_stmt: format!("DELETE FROM {} WHERE word=?", ATTACHMENTNAME),
+            _attachments_stmt: format!(
+                "SELECT name, mime, data FROM {} WHERE word=? ORDER BY name",
+                ATTACHMENTNAME
+            ),
+            _attachment_stmt: format!(
+                "SELECT name, mime, data FROM {} WHERE word=? AND name=?",
+                ATTACHMENTNAME
+            ),
+            _put_trashed_attachment_stmt: format!(
+                "INSERT INTO {} (id, name, mime, data) VALUES (?, ?, ?, ?)",
+                TRASH_ATTACHMENTNAME
+            ),
+            _remove_trashed_attachments_stmt: format!(
+                "DELETE FROM {} WHERE id=?",
+                TRASH_ATTACHMENTNAME
+            ),
+            _trashed_attachments_stmt: format!(
+                "SELECT name, mime, data FROM {} WHERE id=? ORDER BY name",
+                TRASH_ATTACHMENTNAME
+            ),
+        }
+    }
+
+    fn write_revision(
+        &self,
+        con: &Connection,
+        word: &str,
+        message: &Revision,
+        schedule: &Revision,
+    ) -> Result<()> {
+        let mut stmt = con
+            .prepare(self._touch_stmt.as_str())?
+            .bind(1, word)?
+            .bind(2, message.updated_at)?
+            .bind(3, message.device.as_str())?
+            .bind(4, schedule.updated_at)?
+            .bind(5, schedule.device.as_str())?;
+        stmt.next()?;
+        Ok(())
+    }
+
+    // keep the message of the word before the action changes it to message,
+    // None for a delete
+    fn snapshot(
+        &self,
+        con: &Connection,
+        word: &str,
+        action: &str,
+        message: Option<&str>,
+    ) -> Result<()> {
+        let mut stmt = con
+            .prepare(self._snapshot_stmt.as_str())?
+            .bind(1, word)?
+            .bind(2, action)?
+            .bind(3, sync::now())?
+            .bind(4, message)?;
+        stmt.next()?;
+        Ok(())
+    }
+
+    fn read_tags(&self, con: &Connection, word: &str) -> Result<Vec<String>> {
+        let mut stmt = con.prepare(self._word_tags_stmt.as_str())?.bind(1, word)?;
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(stmt.read(0)?);
+        }
+        Ok(res)
+    }
+
+    // the tags of the word are replaced by tags, none for a deleted word
+    fn write_tags(&self, con: &Connection, word: &str, tags: &[String]) -> Result<()> {
+        let mut stmt = con
+            .prepare(self._delete_tags_stmt.as_str())?
+            .bind(1, word)?;
+        stmt.next()?;
+        for tag in tags {
+            let mut stmt = con
+                .prepare(self._add_tag_stmt.as_str())?
+                .bind(1, word)?
+                .bind(2, tag.as_str())?;
+            stmt.next()?;
+        }
+        Ok(())
+    }
+
+    // the links of the word are replaced by the ones in the message, none for
+    // a deleted word
+    fn write_links(&self, con: &Connection, word: &str, message: Option<&str>) -> Result<()> {
+        let mut stmt = con
+            .prepare(self._delete_links_stmt.as_str())?
+            .bind(1, word)?;
+        stmt.next()?;
+        for link in links::parse(message.unwrap_or("")) {
+            let mut stmt = con
+                .prepare(self._add_link_stmt.as_str())?
+                .bind(1, word)?
+                .bind(2, link.library.as_deref().unwrap_or(""))?
+                .bind(3, link.word.as_str())?;
+            stmt.next()?;
+        }
+        Ok(())
+    }
+
+    // the attachments of the rows name, mime, data of stmt
+    fn read_attachments(stmt: &mut sqlite::Statement) -> Result<Vec<Attachment>> {
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(Attachment {
+                name: stmt.read(0)?,
+                mime: stmt.read(1)?,
+                data: stmt.read(2)?,
+            });
+        }
+        Ok(res)
+    }
+
+    fn read_word_attachments(&self, con: &Connection, word: &str) -> Result<Vec<Attachment>> {
+        let mut stmt = con
+            .prepare(self._attachments_stmt.as_str())?
+            .bind(1, word)?;
+        Sqlite::read_attachments(&mut stmt)
+    }
+
+    // the attachments of the word are replaced, none for a deleted word
+    fn write_attachments(
+        &self,
+        con: &Connection,
+        word: &str,
+        attachments: &[Attachment],
+    ) -> Result<()> {
+        let mut stmt = con
+            .prepare(self._delete_attachments_stmt.as_str())?
+            .bind(1, word)?;
+        stmt.next()?;
+        for a in attachments {
+            let mut stmt = con
+                .prepare(self._put_attachment_stmt.as_str())?
+                .bind(1, word)?
+                .bind(2, a.name.as_str())?
+                .bind(3, a.mime.as_str())?
+                .bind(4, a.data.as_slice())?;
+            stmt.next()?;
+        }
+        Ok(())
+    }
+
+    // the links of the rows word, library, target of stmt
+    fn read_links(stmt: &mut sqlite::Statement) -> Result<Vec<(String, Link)>> {
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            let library: String = stmt.read(1)?;
+            let link = Link {
+                library: Some(library).filter(|l| !l.is_empty()),
+                word: stmt.read(2)?,
+            };
+            res.push((stmt.read(0)?, link));
+        }
+        Ok(res)
+    }
+
+    fn read_version(&self, con: &Connection, id: &str) -> Result<i64> {
+        let mut stmt = con.prepare(self._version_stmt.as_str())?.bind(1, id)?;
+
+        match stmt.next()? {
+            sqlite::State::Done => Err(InternalError::NotFound),
+            sqlite::State::Row => Ok(stmt.read(0)?),
+        }
+    }
+
+    // record the change of a field of the word on this device, stmt is
+    // _touch_message_stmt or _touch_schedule_stmt
+    fn touch(&self, con: &Connection, stmt: &str, word: &str) -> Result<()> {
+        let rev = Revision::now();
+        let mut stmt = con
+            .prepare(stmt)?
+            .bind(1, word)?
+            .bind(2, rev.updated_at)?
+            .bind(3, rev.device.as_str())?;
+        stmt.next()?;
+        Ok(())
     }
 }
 
 impl Write<World, InternalError<Error>> for Sqlite {
     fn insert(&self, word: &World) -> Result<Option<World>> {
-        let con = &self.connect;
+        self.write(|con| {
+            let mut stmt = con
+                .prepare(self._insert_stmt.as_str())?
+                // .bind(0, LIBNAME)?
+                .bind(1, word.name.as_str())?
+                .bind(2, word.message.as_str())?;
+            stmt.next()?;
+            self.write_links(con, &word.name, Some(&word.message))?;
+
+            let rev = Revision::now();
+            self.write_revision(con, &word.name, &rev, &rev)?;
+            Ok(None)
+        })
+    }
+    fn update(&self, word: &World) -> Result<Option<World>> {
+        self.write(|con| {
+            self.snapshot(con, &word.name, "update", Some(&word.message))?;
+            let mut stmt = con
+                .prepare(self._update_stmt.as_str())?
+                .bind(1, word.message.as_str())?
+                .bind(2, word.name.as_str())?;
+            stmt.next()?;
+            // a missing word has no links to index
+            if Sqlite::changes(con)? == 0 {
+                return Ok(None);
+            }
+            self.write_links(con, &word.name, Some(&word.message))?;
+
+            self.touch(con, &self._touch_message_stmt, &word.name)?;
+            Ok(None)
+        })
+    }
+    fn delete(&self, word: &str) -> Result<Option<World>> {
+        self.write(|con| {
+            // the revision is kept as the tombstone of the word
+            self.touch(con, &self._touch_message_stmt, word)?;
+            self.snapshot(con, word, "delete", None)?;
+            let mut stmt = con
+                .prepare(self._delete_stmt.as_str())?
+                // .bind(1, LIBNAME)?
+                .bind(1, word)?;
+            stmt.next()?;
+
+            let mut stmt = con
+                .prepare(self._delete_schedule_stmt.as_str())?
+                .bind(1, word)?;
+            stmt.next()?;
+            self.write_tags(con, word, &[])?;
+            self.write_links(con, word, None)?;
+            self.write_attachments(con, word, &[])?;
+            Ok(None)
+        })
+    }
+
+    fn set_schedule(&self, word: &str, schedule: &Schedule) -> Result<Option<World>> {
+        self.write(|con| {
+            // make sure the word exist
+            self.read_version(con, word)?;
+
+            let mut stmt = con
+                .prepare(self._set_schedule_stmt.as_str())?
+                .bind(1, word)?
+                .bind(2, schedule.due)?
+                .bind(3, schedule.interval)?
+                .bind(4, schedule.ease)?
+                .bind(5, schedule.reps)?;
+            stmt.next()?;
+
+            self.touch(con, &self._touch_schedule_stmt, word)?;
+            Ok(None)
+        })
+    }
+
+    fn put(&self, entry: &Entry) -> Result<Option<World>> {
+        self.write(|con| {
+            let word = entry.word.name.as_str();
+            self.snapshot(con, word, "replace", Some(&entry.word.message))?;
+            let mut stmt = con
+                .prepare(self._put_stmt.as_str())?
+                .bind(1, word)?
+                .bind(2, entry.word.message.as_str())?
+                .bind(3, entry.version)?;
+            stmt.next()?;
+
+            let mut stmt = match &entry.schedule {
+                Some(schedule) => con
+                    .prepare(self._set_schedule_stmt.as_str())?
+                    .bind(1, word)?
+                    .bind(2, schedule.due)?
+                    .bind(3, schedule.interval)?
+                    .bind(4, schedule.ease)?
+                    .bind(5, schedule.reps)?,
+                None => con
+                    .prepare(self._delete_schedule_stmt.as_str())?
+                    .bind(1, word)?,
+            };
+            stmt.next()?;
+            self.write_tags(con, word, &entry.tags)?;
+            self.write_links(con, word, Some(&entry.word.message))?;
+            self.write_attachments(con, word, &entry.attachments)?;
+
+            let rev = Revision::now();
+            self.write_revision(con, word, &rev, &rev)?;
+            Ok(None)
+        })
+    }
+
+    fn set_metadata(&self, key: &str, value: &str) -> Result<Option<World>> {
+        let con = self.conn()?;
         let mut stmt = con
-            .prepare(self._insert_stmt.as_str())?
-            // .bind(0, LIBNAME)?
-            .bind(1, word.name.as_str())?
-            .bind(2, word.message.as_str())?;
+            .prepare(self._set_metadata_stmt.as_str())?
+            .bind(1, key)?
+            .bind(2, value)?;
         stmt.next()?;
+        Ok(None)
+    }
+
+    fn put_record(&self, record: &Record) -> Result<Option<World>> {
+        self.write(|con| {
+            let word = record.name.as_str();
+            self.snapshot(con, word, "sync", record.message.as_deref())?;
+            match &record.message {
+                Some(message) => {
+                    let mut stmt = con
+                        .prepare(self._put_word_stmt.as_str())?
+                        .bind(1, word)?
+                        .bind(2, message.as_str())?;
+                    stmt.next()?;
+                }
+                None => {
+                    let mut stmt = con.prepare(self._delete_stmt.as_str())?.bind(1, word)?;
+                    stmt.next()?;
+                    self.write_tags(con, word, &[])?;
+                    self.write_attachments(con, word, &[])?;
+                }
+            }
+            self.write_links(con, word, record.message.as_deref())?;
+            let mut stmt = match (&record.message, &record.schedule) {
+                (Some(_), Some(schedule)) => con
+                    .prepare(self._set_schedule_stmt.as_str())?
+                    .bind(1, word)?
+                    .bind(2, schedule.due)?
+                    .bind(3, schedule.interval)?
+                    .bind(4, schedule.ease)?
+                    .bind(5, schedule.reps)?,
+                _ => con
+                    .prepare(self._delete_schedule_stmt.as_str())?
+                    .bind(1, word)?,
+            };
+            stmt.next()?;
 
+            self.write_revision(con, word, &record.message_rev, &record.schedule_rev)?;
+            Ok(None)
+        })
+    }
+
+    fn add_conflict(&self, conflict: &Conflict) -> Result<Option<World>> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._add_conflict_stmt.as_str())?
+            .bind(1, conflict.name.as_str())?
+            .bind(2, conflict.field.as_str())?
+            .bind(3, conflict.value.as_deref())?
+            .bind(4, conflict.rev.updated_at)?
+            .bind(5, conflict.rev.device.as_str())?
+            .bind(6, conflict.kept.as_deref())?
+            .bind(7, conflict.recorded_at)?;
+        stmt.next()?;
         Ok(None)
     }
-    fn update(&self, word: &World) -> Result<Option<World>> {
-        let mut stmt = self
-            .connect
-            .prepare(self._update_stmt.as_str())?
-            .bind(1, word.message.as_str())?
-            .bind(2, word.name.as_str())?;
+
+    fn remove_conflict(&self, id: i64) -> Result<Option<World>> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._remove_conflict_stmt.as_str())?
+            .bind(1, id)?;
         stmt.next()?;
+        Ok(None)
+    }
 
+    fn put_trashed(&self, trashed: &Trashed) -> Result<Option<World>> {
+        self.write(|con| {
+            let schedule = trashed.entry.schedule.as_ref();
+            let mut stmt = con
+                .prepare(self._put_trashed_stmt.as_str())?
+                .bind(1, trashed.origin.as_str())?
+                .bind(2, trashed.entry.word.name.as_str())?
+                .bind(3, trashed.entry.word.message.as_str())?
+                .bind(4, trashed.entry.version)?
+                .bind(5, schedule.map(|s| s.due))?
+                .bind(6, schedule.map(|s| s.interval))?
+                .bind(7, schedule.map(|s| s.ease))?
+                .bind(8, schedule.map(|s| s.reps))?
+                .bind(9, trashed.deleted_at)?
+                .bind(10, trashed.entry.tags.join(" ").as_str())?;
+            stmt.next()?;
+            if trashed.entry.attachments.is_empty() {
+                return Ok(None);
+            }
+
+            // the files are kept by the id given to the word
+            let mut stmt = con.prepare("SELECT last_insert_rowid()")?;
+            stmt.next()?;
+            let id: i64 = stmt.read(0)?;
+            for a in &trashed.entry.attachments {
+                let mut stmt = con
+                    .prepare(self._put_trashed_attachment_stmt.as_str())?
+                    .bind(1, id)?
+                    .bind(2, a.name.as_str())?
+                    .bind(3, a.mime.as_str())?
+                    .bind(4, a.data.as_slice())?;
+                stmt.next()?;
+            }
+            Ok(None)
+        })
+    }
+
+    fn remove_trashed(&self, id: i64) -> Result<Option<World>> {
+        self.write(|con| {
+            let mut stmt = con
+                .prepare(self._remove_trashed_stmt.as_str())?
+                .bind(1, id)?;
+            stmt.next()?;
+            let mut stmt = con
+                .prepare(self._remove_trashed_attachments_stmt.as_str())?
+                .bind(1, id)?;
+            stmt.next()?;
+            Ok(None)
+        })
+    }
+
+    fn add_tag(&self, word: &str, tag: &str) -> Result<Option<World>> {
+        self.write(|con| {
+            // make sure the word exist
+            self.read_version(con, word)?;
+
+            let mut stmt = con
+                .prepare(self._add_tag_stmt.as_str())?
+                .bind(1, word)?
+                .bind(2, tag)?;
+            stmt.next()?;
+            Ok(None)
+        })
+    }
+
+    fn remove_tag(&self, word: &str, tag: &str) -> Result<Option<World>> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._remove_tag_stmt.as_str())?
+            .bind(1, word)?
+            .bind(2, tag)?;
+        stmt.next()?;
         Ok(None)
     }
-    fn delete(&self, word: &str) -> Result<Option<World>> {
-        let con = &self.connect;
+
+    fn put_attachment(&self, word: &str, attachment: &Attachment) -> Result<Option<World>> {
+        self.write(|con| {
+            // make sure the word exist
+            self.read_version(con, word)?;
+
+            let mut stmt = con
+                .prepare(self._put_attachment_stmt.as_str())?
+                .bind(1, word)?
+                .bind(2, attachment.name.as_str())?
+                .bind(3, attachment.mime.as_str())?
+                .bind(4, attachment.data.as_slice())?;
+            stmt.next()?;
+            Ok(None)
+        })
+    }
+
+    fn remove_attachment(&self, word: &str, name: &str) -> Result<Option<World>> {
+        let con = self.conn()?;
         let mut stmt = con
-            .prepare(self._delete_stmt.as_str())?
-            // .bind(1, LIBNAME)?
-            .bind(1, word)?;
+            .prepare(self._remove_attachment_stmt.as_str())?
+            .bind(1, word)?
+            .bind(2, name)?;
         stmt.next()?;
         Ok(None)
     }
@@ -95,7 +962,8 @@ impl Write<World, InternalError<Error>> for Sqlite {
 
 impl Read<World, InternalError<Error>> for Sqlite {
     fn get(&self, id: &str) -> Result<World> {
-        let mut stmt = self.connect.prepare(self._get_stmt.as_str())?.bind(1, id)?;
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._get_stmt.as_str())?.bind(1, id)?;
 
         let state = stmt.next()?;
         match state {
@@ -113,14 +981,12 @@ impl Read<World, InternalError<Error>> for Sqlite {
     // WARN memory head may be very big
     // TODO add a iterator for batch select sql
     fn list(&self, size: i64) -> Result<Vec<World>> {
+        let con = self.conn()?;
         let mut stmt: sqlite::Statement;
         if size <= 0 {
-            stmt = self.connect.prepare(self._dump_stmt.as_str())?
+            stmt = con.prepare(self._dump_stmt.as_str())?
         } else {
-            stmt = self
-                .connect
-                .prepare(self._dump_size_stmt.as_str())?
-                .bind(1, size)?;
+            stmt = con.prepare(self._dump_size_stmt.as_str())?.bind(1, size)?;
         }
 
         let mut res: Vec<World> = Vec::new();
@@ -138,16 +1004,330 @@ impl Read<World, InternalError<Error>> for Sqlite {
 
         Ok(res)
     }
+
+    fn scan(&self, after: &str, size: i64) -> Result<Vec<World>> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._scan_stmt.as_str())?
+            .bind(1, after)?
+            .bind(2, if size <= 0 { -1 } else { size })?;
+
+        let mut res: Vec<World> = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(World {
+                name: stmt.read(0)?,
+                message: stmt.read(1)?,
+            })
+        }
+        Ok(res)
+    }
+
+    fn version(&self, id: &str) -> Result<i64> {
+        self.read_version(&*self.conn()?, id)
+    }
+
+    fn schedule(&self, id: &str) -> Result<Schedule> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._schedule_stmt.as_str())?.bind(1, id)?;
+
+        match stmt.next()? {
+            sqlite::State::Done => Err(InternalError::NotFound),
+            sqlite::State::Row => match stmt.read::<Option<i64>>(0)? {
+                // never reviewed
+                None => Ok(Schedule::default()),
+                Some(due) => Ok(Schedule {
+                    due,
+                    interval: stmt.read(1)?,
+                    ease: stmt.read(2)?,
+                    reps: stmt.read(3)?,
+                }),
+            },
+        }
+    }
+
+    fn due(&self, now: i64, size: i64) -> Result<Vec<World>> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._due_stmt.as_str())?
+            .bind(1, now)?
+            .bind(2, if size <= 0 { -1 } else { size })?;
+
+        let mut res: Vec<World> = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(World {
+                name: stmt.read(0)?,
+                message: stmt.read(1)?,
+            })
+        }
+        Ok(res)
+    }
+
+    fn entry(&self, id: &str) -> Result<Entry> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._entry_stmt.as_str())?.bind(1, id)?;
+
+        match stmt.next()? {
+            sqlite::State::Done => Err(InternalError::NotFound),
+            sqlite::State::Row => Ok(Entry {
+                tags: self.read_tags(&con, id)?,
+                attachments: self.read_word_attachments(&con, id)?,
+                word: World {
+                    name: id.to_string(),
+                    message: stmt.read(0)?,
+                },
+                version: stmt.read(1)?,
+                schedule: match stmt.read::<Option<i64>>(2)? {
+                    None => None,
+                    Some(due) => Some(Schedule {
+                        due,
+                        interval: stmt.read(3)?,
+                        ease: stmt.read(4)?,
+                        reps: stmt.read(5)?,
+                    }),
+                },
+            }),
+        }
+    }
+
+    fn metadata(&self) -> Result<Vec<(String, String)>> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._metadata_stmt.as_str())?;
+
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push((stmt.read(0)?, stmt.read(1)?));
+        }
+        Ok(res)
+    }
+
+    fn records(&self, after: &str, size: i64) -> Result<Vec<Record>> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._records_stmt.as_str())?
+            .bind(1, after)?
+            .bind(2, if size <= 0 { -1 } else { size })?;
+
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(Record {
+                name: stmt.read(0)?,
+                message: stmt.read(1)?,
+                message_rev: Revision {
+                    updated_at: stmt.read(2)?,
+                    device: stmt.read(3)?,
+                },
+                schedule: match stmt.read::<Option<i64>>(6)? {
+                    None => None,
+                    Some(due) => Some(Schedule {
+                        due,
+                        interval: stmt.read(7)?,
+                        ease: stmt.read(8)?,
+                        reps: stmt.read(9)?,
+                    }),
+                },
+                schedule_rev: Revision {
+                    updated_at: stmt.read(4)?,
+                    device: stmt.read(5)?,
+                },
+            })
+        }
+        Ok(res)
+    }
+
+    fn conflicts(&self) -> Result<Vec<Conflict>> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._conflicts_stmt.as_str())?;
+
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(Conflict {
+                id: stmt.read(0)?,
+                name: stmt.read(1)?,
+                field: stmt.read(2)?,
+                value: stmt.read(3)?,
+                rev: Revision {
+                    updated_at: stmt.read(4)?,
+                    device: stmt.read(5)?,
+                },
+                kept: stmt.read(6)?,
+                recorded_at: stmt.read(7)?,
+            })
+        }
+        Ok(res)
+    }
+
+    fn history(&self, id: &str) -> Result<Vec<Snapshot>> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._history_stmt.as_str())?.bind(1, id)?;
+
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(Snapshot {
+                id: stmt.read(0)?,
+                name: id.to_owned(),
+                message: stmt.read(1)?,
+                version: stmt.read(2)?,
+                action: stmt.read(3)?,
+                changed_at: stmt.read(4)?,
+            })
+        }
+        Ok(res)
+    }
+
+    fn deleted(&self, size: i64) -> Result<Vec<Snapshot>> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._deleted_stmt.as_str())?
+            .bind(1, if size <= 0 { -1 } else { size })?;
+
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(Snapshot {
+                id: stmt.read(0)?,
+                name: stmt.read(1)?,
+                message: stmt.read(2)?,
+                version: stmt.read(3)?,
+                action: stmt.read(4)?,
+                changed_at: stmt.read(5)?,
+            })
+        }
+        Ok(res)
+    }
+
+    fn trashed(&self, size: i64) -> Result<Vec<Trashed>> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._trashed_stmt.as_str())?
+            .bind(1, if size <= 0 { -1 } else { size })?;
+
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(Trashed {
+                id: stmt.read(0)?,
+                origin: stmt.read(1)?,
+                entry: Entry {
+                    word: World {
+                        name: stmt.read(2)?,
+                        message: stmt.read(3)?,
+                    },
+                    version: stmt.read(4)?,
+                    schedule: match stmt.read::<Option<i64>>(5)? {
+                        None => None,
+                        Some(due) => Some(Schedule {
+                            due,
+                            interval: stmt.read(6)?,
+                            ease: stmt.read(7)?,
+                            reps: stmt.read(8)?,
+                        }),
+                    },
+                    tags: stmt
+                        .read::<String>(10)?
+                        .split_whitespace()
+                        .map(|t| t.to_owned())
+                        .collect(),
+                    attachments: Vec::new(),
+                },
+                deleted_at: stmt.read(9)?,
+            })
+        }
+        for t in res.iter_mut() {
+            let mut stmt = con
+                .prepare(self._trashed_attachments_stmt.as_str())?
+                .bind(1, t.id)?;
+            t.entry.attachments = Sqlite::read_attachments(&mut stmt)?;
+        }
+        Ok(res)
+    }
+
+    fn last_trashed(&self) -> Result<i64> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._last_trashed_stmt.as_str())?;
+        stmt.next()?;
+        Ok(stmt.read(0)?)
+    }
+
+    fn tags(&self) -> Result<Vec<(String, i64)>> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._tags_stmt.as_str())?;
+
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push((stmt.read(0)?, stmt.read(1)?));
+        }
+        Ok(res)
+    }
+
+    fn word_tags(&self, id: &str) -> Result<Vec<String>> {
+        let con = self.conn()?;
+        self.read_tags(&con, id)
+    }
+
+    fn tagged(&self, tag: &str) -> Result<Vec<String>> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._tagged_stmt.as_str())?.bind(1, tag)?;
+
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            res.push(stmt.read(0)?);
+        }
+        Ok(res)
+    }
+
+    fn links(&self, id: &str) -> Result<Vec<Link>> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._links_stmt.as_str())?.bind(1, id)?;
+
+        let mut res = Vec::new();
+        while let sqlite::State::Row = stmt.next()? {
+            let library: String = stmt.read(0)?;
+            res.push(Link {
+                library: Some(library).filter(|l| !l.is_empty()),
+                word: stmt.read(1)?,
+            });
+        }
+        Ok(res)
+    }
+
+    fn backlinks(&self, target: &str) -> Result<Vec<(String, Link)>> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._backlinks_stmt.as_str())?
+            .bind(1, target)?;
+        Sqlite::read_links(&mut stmt)
+    }
+
+    fn all_links(&self) -> Result<Vec<(String, Link)>> {
+        let con = self.conn()?;
+        let mut stmt = con.prepare(self._all_links_stmt.as_str())?;
+        Sqlite::read_links(&mut stmt)
+    }
+
+    fn attachments(&self, id: &str) -> Result<Vec<Attachment>> {
+        let con = self.conn()?;
+        self.read_word_attachments(&con, id)
+    }
+
+    fn attachment(&self, id: &str, name: &str) -> Result<Attachment> {
+        let con = self.conn()?;
+        let mut stmt = con
+            .prepare(self._attachment_stmt.as_str())?
+            .bind(1, id)?
+            .bind(2, name)?;
+        Sqlite::read_attachments(&mut stmt)?
+            .pop()
+            .ok_or(InternalError::NotFound)
+    }
 }
 
 impl Store<World, InternalError<Error>, Tansaction> for Sqlite {
     fn begin(&self) -> Result<Tansaction> {
-        let s = Sqlite::new_conn(String::from(self._path.as_str()))?;
-        {
-            let con = &s.connect;
-            let mut state = con.prepare("BEGIN")?;
-            state.next()?;
-        }
+        let con = Sqlite::open(&self._path)?;
+        // take the write lock at once, so the check before a write is not outdated
+        con.execute("BEGIN IMMEDIATE")?;
+        let s = Sqlite::new_conn(
+            String::from(self._path.as_str()),
+            Connect::Single(Mutex::new(con)),
+        );
 
         Ok(Tansaction { stmt: s })
     }
@@ -168,6 +1348,85 @@ impl Read<World, InternalError<Error>> for Tansaction {
     fn list(&self, size: i64) -> Result<Vec<World>> {
         self.stmt.list(size)
     }
+
+    fn scan(&self, after: &str, size: i64) -> Result<Vec<World>> {
+        self.stmt.scan(after, size)
+    }
+
+    fn version(&self, id: &str) -> Result<i64> {
+        self.stmt.version(id)
+    }
+
+    fn schedule(&self, id: &str) -> Result<Schedule> {
+        self.stmt.schedule(id)
+    }
+
+    fn due(&self, now: i64, size: i64) -> Result<Vec<World>> {
+        self.stmt.due(now, size)
+    }
+
+    fn entry(&self, id: &str) -> Result<Entry> {
+        self.stmt.entry(id)
+    }
+
+    fn metadata(&self) -> Result<Vec<(String, String)>> {
+        self.stmt.metadata()
+    }
+
+    fn records(&self, after: &str, size: i64) -> Result<Vec<Record>> {
+        self.stmt.records(after, size)
+    }
+
+    fn conflicts(&self) -> Result<Vec<Conflict>> {
+        self.stmt.conflicts()
+    }
+
+    fn history(&self, id: &str) -> Result<Vec<Snapshot>> {
+        self.stmt.history(id)
+    }
+
+    fn deleted(&self, size: i64) -> Result<Vec<Snapshot>> {
+        self.stmt.deleted(size)
+    }
+
+    fn trashed(&self, size: i64) -> Result<Vec<Trashed>> {
+        self.stmt.trashed(size)
+    }
+    fn last_trashed(&self) -> Result<i64> {
+        self.stmt.last_trashed()
+    }
+
+    fn tags(&self) -> Result<Vec<(String, i64)>> {
+        self.stmt.tags()
+    }
+
+    fn word_tags(&self, id: &str) -> Result<Vec<String>> {
+        self.stmt.word_tags(id)
+    }
+
+    fn tagged(&self, tag: &str) -> Result<Vec<String>> {
+        self.stmt.tagged(tag)
+    }
+
+    fn links(&self, id: &str) -> Result<Vec<Link>> {
+        self.stmt.links(id)
+    }
+
+    fn backlinks(&self, target: &str) -> Result<Vec<(String, Link)>> {
+        self.stmt.backlinks(target)
+    }
+
+    fn all_links(&self) -> Result<Vec<(String, Link)>> {
+        self.stmt.all_links()
+    }
+
+    fn attachments(&self, id: &str) -> Result<Vec<Attachment>> {
+        self.stmt.attachments(id)
+    }
+
+    fn attachment(&self, id: &str, name: &str) -> Result<Attachment> {
+        self.stmt.attachment(id, name)
+    }
 }
 
 impl<'a> Write<World, InternalError<Error>> for Tansaction {
@@ -182,16 +1441,64 @@ impl<'a> Write<World, InternalError<Error>> for Tansaction {
     fn delete(&self, word: &str) -> Result<Option<World>> {
         self.stmt.delete(word)
     }
+
+    fn set_schedule(&self, word: &str, schedule: &Schedule) -> Result<Option<World>> {
+        self.stmt.set_schedule(word, schedule)
+    }
+
+    fn put(&self, entry: &Entry) -> Result<Option<World>> {
+        self.stmt.put(entry)
+    }
+
+    fn set_metadata(&self, key: &str, value: &str) -> Result<Option<World>> {
+        self.stmt.set_metadata(key, value)
+    }
+
+    fn put_record(&self, record: &Record) -> Result<Option<World>> {
+        self.stmt.put_record(record)
+    }
+
+    fn add_conflict(&self, conflict: &Conflict) -> Result<Option<World>> {
+        self.stmt.add_conflict(conflict)
+    }
+
+    fn remove_conflict(&self, id: i64) -> Result<Option<World>> {
+        self.stmt.remove_conflict(id)
+    }
+
+    fn put_trashed(&self, trashed: &Trashed) -> Result<Option<World>> {
+        self.stmt.put_trashed(trashed)
+    }
+
+    fn remove_trashed(&self, id: i64) -> Result<Option<World>> {
+        self.stmt.remove_trashed(id)
+    }
+
+    fn add_tag(&self, word: &str, tag: &str) -> Result<Option<World>> {
+        self.stmt.add_tag(word, tag)
+    }
+
+    fn remove_tag(&self, word: &str, tag: &str) -> Result<Option<World>> {
+        self.stmt.remove_tag(word, tag)
+    }
+
+    fn put_attachment(&self, word: &str, attachment: &Attachment) -> Result<Option<World>> {
+        self.stmt.put_attachment(word, attachment)
+    }
+
+    fn remove_attachment(&self, word: &str, name: &str) -> Result<Option<World>> {
+        self.stmt.remove_attachment(word, name)
+    }
 }
 
 impl<'a> StoreTransaction<World, InternalError<Error>> for Tansaction {
     fn rollback(self) -> std::result::Result<(), InternalError<Error>> {
-        self.stmt.connect.execute("ROLLBACK")?;
+        self.stmt.conn()?.execute("ROLLBACK")?;
         Ok(())
     }
 
     fn commit(self) -> std::result::Result<(), InternalError<Error>> {
-        self.stmt.connect.execute("COMMIT")?;
+        self.stmt.conn()?.execute("COMMIT")?;
         Ok(())
     }
 }
@@ -254,6 +1561,148 @@ mod tests {
         clean_sqlite();
     }
 
+    #[test]
+    fn update_missing() {
+        let _ = std::fs::remove_file("target/test_update.sql");
+        let sql = Sqlite::new(String::from("target/test_update.sql")).unwrap();
+        let word = World {
+            name: String::from("missing"),
+            message: String::from("see [[apple]]"),
+        };
+
+        // nothing is indexed or recorded for a word not in the library
+        sql.update(&word).unwrap();
+        assert!(sql.backlinks("apple").unwrap().is_empty());
+        assert!(sql.records("", 10).unwrap().is_empty());
+
+        sql.insert(&word).unwrap();
+        let word = World {
+            name: String::from("missing"),
+            message: String::from("see [[pear]]"),
+        };
+        sql.update(&word).unwrap();
+        assert!(sql.backlinks("apple").unwrap().is_empty());
+        assert_eq!(1, sql.backlinks("pear").unwrap().len());
+
+        drop(sql);
+        std::fs::remove_file("target/test_update.sql").unwrap();
+    }
+
+    #[test]
+    fn write_existing() {
+        let _ = std::fs::remove_file("target/test_existing.sql");
+        let sql = Sqlite::new(String::from("target/test_existing.sql")).unwrap();
+        sql.insert(&World {
+            name: String::from("apple"),
+            message: String::from("a fruit"),
+        })
+        .unwrap();
+        let sound = Attachment::new("apple.mp3", vec![1, 2]);
+
+        // the word is checked on the connection of the transaction
+        let tran = sql.begin().unwrap();
+        tran.set_schedule("apple", &Schedule::default()).unwrap();
+        tran.add_tag("apple", "fruit").unwrap();
+        tran.put_attachment("apple", &sound).unwrap();
+        tran.commit().unwrap();
+        assert_eq!(sql.word_tags("apple").unwrap(), vec![String::from("fruit")]);
+        assert_eq!(sql.attachments("apple").unwrap(), vec![sound.clone()]);
+
+        // nothing is left for a missing word
+        expect_notfound(sql.set_schedule("pear", &Schedule::default()));
+        expect_notfound(sql.add_tag("pear", "fruit"));
+        expect_notfound(sql.put_attachment("pear", &sound));
+        assert!(sql.word_tags("pear").unwrap().is_empty());
+        assert!(sql.attachments("pear").unwrap().is_empty());
+
+        drop(sql);
+        std::fs::remove_file("target/test_existing.sql").unwrap();
+    }
+
+    #[test]
+    fn version_and_schedule() {
+        let path = "target/test_schedule.sql";
+        let sql = Sqlite::new(String::from(path)).unwrap();
+        let mut word = World {
+            name: String::from("test"),
+            message: String::from("test"),
+        };
+        sql.insert(&word).unwrap();
+        assert_eq!(sql.version(&word.name).unwrap(), 1);
+        word.message = String::from("test update");
+        sql.update(&word).unwrap();
+        assert_eq!(sql.version(&word.name).unwrap(), 2);
+        expect_notfound(sql.version("nofound"));
+
+        // a new word is due at once
+        assert_eq!(sql.schedule(&word.name).unwrap(), Schedule::default());
+        assert_eq!(sql.due(0, 0).unwrap().len(), 1);
+
+        let next = sql.schedule(&word.name).unwrap().next(4, 100);
+        sql.set_schedule(&word.name, &next).unwrap();
+        assert_eq!(sql.schedule(&word.name).unwrap(), next);
+        assert_eq!(sql.due(100, 0).unwrap().len(), 0);
+        assert_eq!(sql.due(next.due, 0).unwrap().len(), 1);
+        expect_notfound(sql.set_schedule("nofound", &next));
+
+        sql.delete(&word.name).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn entry_and_metadata() {
+        let path = "target/test_entry.sql";
+        let _ = std::fs::remove_file(path);
+        let sql = Sqlite::new(String::from(path)).unwrap();
+        let mut entry = Entry {
+            word: World {
+                name: String::from("test"),
+                message: String::from("test"),
+            },
+            version: 7,
+            schedule: Some(Schedule::default().next(5, 100)),
+            tags: vec![String::from("noun")],
+            attachments: vec![Attachment::new("test.mp3", vec![0, 255, 7])],
+        };
+        sql.put(&entry).unwrap();
+        let e = sql.entry("test").unwrap();
+        assert_eq!((e.version, e.schedule), (7, entry.schedule.clone()));
+        assert_eq!(e.tags, entry.tags);
+        assert_eq!(e.attachments, entry.attachments);
+
+        // put replaces the word, its schedule, its tags and its attachments
+        entry.schedule = None;
+        entry.tags.clear();
+        entry.attachments.clear();
+        sql.put(&entry).unwrap();
+        let e = sql.entry("test").unwrap();
+        assert!(e.schedule.is_none() && e.tags.is_empty() && e.attachments.is_empty());
+        let png = Attachment::new("test.png", vec![1, 2]);
+        sql.put_attachment("test", &png).unwrap();
+        expect_notfound(sql.put_attachment("nofound", &png));
+        assert_eq!(sql.attachment("test", "test.png").unwrap(), png);
+        sql.remove_attachment("test", "test.png").unwrap();
+        expect_notfound(sql.attachment("test", "test.png"));
+        sql.add_tag("test", "verb").unwrap();
+        sql.add_tag("test", "verb").unwrap();
+        expect_notfound(sql.add_tag("nofound", "verb"));
+        assert_eq!(sql.tags().unwrap(), vec![(String::from("verb"), 1)]);
+        assert_eq!(sql.tagged("verb").unwrap(), vec![String::from("test")]);
+        expect_notfound(sql.entry("nofound"));
+
+        sql.set_metadata("name", "a").unwrap();
+        sql.set_metadata("description", "b").unwrap();
+        sql.set_metadata("name", "c").unwrap();
+        assert_eq!(
+            sql.metadata().unwrap(),
+            vec![
+                (String::from("description"), String::from("b")),
+                (String::from("name"), String::from("c"))
+            ]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn gen_data() {
         let sql = Sqlite::new(String::from("/mnt/d/big_test.sql")).unwrap();
@@ -0,0 +1,774 @@
+// async facade over the synchronous stores. The blocking sqlite work runs on
+// a dedicated thread pool and the futures only wait for the result, so they can
+// be driven by any executor. Timeouts need a tokio runtime with time enabled.
+
+use super::attachment::Attachment;
+use super::history::Snapshot;
+use super::links::Link;
+use super::review::Schedule;
+use super::sync::{Conflict, Record};
+use super::trash::Trashed;
+use super::{Entry, InternalError, Read, Store, Transaction, WordLibrary, World, Write};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// threads running the blocking store operations
+pub struct Pool {
+    jobs: Option<mpsc::Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Pool {
+    pub fn new(size: usize) -> Pool {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let workers = (0..size.max(1))
+            .map(|_| {
+                let rx = rx.clone();
+                std::thread::spawn(move || loop {
+                    let job = rx.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                    match job {
+                        // a panic job only drops its result sender
+                        Ok(job) => drop(std::panic::catch_unwind(AssertUnwindSafe(job))),
+                        Err(_) => break,
+                    }
+                })
+            })
+            .collect();
+
+        Pool {
+            jobs: Some(tx),
+            workers,
+        }
+    }
+
+    /// run f on the pool, the future output None if the job panic
+    pub fn spawn<R, G>(&self, f: G) -> impl Future<Output = Option<R>> + Send
+    where
+        R: Send + 'static,
+        G: FnOnce() -> R + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        if let Some(jobs) = &self.jobs {
+            let _ = jobs.send(Box::new(move || {
+                let _ = tx.send(f());
+            }));
+        }
+        async move { rx.await.ok() }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        // close the channel, workers exit after the queued jobs
+        self.jobs.take();
+        for w in self.workers.drain(..) {
+            let _ = w.join();
+        }
+    }
+}
+
+/// flag checked by long operations, they stop and rollback once it is set
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Err(Canceled) once the token is canceled, for `?` in loops
+    pub fn check<E>(&self) -> Result<(), InternalError<E>> {
+        if self.is_canceled() {
+            Err(InternalError::Canceled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// cancel the job when the waiting future is dropped before it finish
+struct CancelOnDrop(Option<CancelToken>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = self.0.take() {
+            token.cancel();
+        }
+    }
+}
+
+async fn wait<R, E>(
+    fut: impl Future<Output = Option<Result<R, InternalError<E>>>>,
+    token: CancelToken,
+    timeout: Option<Duration>,
+) -> Result<R, InternalError<E>> {
+    let mut guard = CancelOnDrop(Some(token.clone()));
+    let res = match timeout {
+        Some(t) => match tokio::time::timeout(t, fut).await {
+            Ok(res) => res,
+            Err(_) => {
+                token.cancel();
+                guard.0.take();
+                return Err(InternalError::Timeout);
+            }
+        },
+        None => fut.await,
+    };
+    guard.0.take();
+    res.unwrap_or(Err(InternalError::Canceled))
+}
+
+pub trait AsyncWrite<T, E> {
+    fn insert(&self, word: &World) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn update(&self, word: &World) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn delete(&self, word: &str) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn set_schedule(
+        &self,
+        word: &str,
+        schedule: &Schedule,
+    ) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn put(&self, entry: &Entry) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn set_metadata(
+        &self,
+        key: &str,
+        value: &str,
+    ) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn put_record(&self, record: &Record) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn add_conflict(
+        &self,
+        conflict: &Conflict,
+    ) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn remove_conflict(&self, id: i64) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn put_trashed(&self, trashed: &Trashed) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn remove_trashed(&self, id: i64) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn add_tag(&self, word: &str, tag: &str) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn remove_tag(
+        &self,
+        word: &str,
+        tag: &str,
+    ) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn put_attachment(
+        &self,
+        word: &str,
+        attachment: &Attachment,
+    ) -> impl Future<Output = Result<Option<T>, E>> + Send;
+    fn remove_attachment(
+        &self,
+        word: &str,
+        name: &str,
+    ) -> impl Future<Output = Result<Option<T>, E>> + Send;
+}
+
+pub trait AsyncRead<T, E> {
+    fn get(&self, id: &str) -> impl Future<Output = Result<World, E>> + Send;
+    fn list(&self, size: i64) -> impl Future<Output = Result<Vec<World>, E>> + Send;
+    fn scan(&self, after: &str, size: i64) -> impl Future<Output = Result<Vec<World>, E>> + Send;
+    fn version(&self, id: &str) -> impl Future<Output = Result<i64, E>> + Send;
+    fn schedule(&self, id: &str) -> impl Future<Output = Result<Schedule, E>> + Send;
+    fn due(&self, now: i64, size: i64) -> impl Future<Output = Result<Vec<World>, E>> + Send;
+    fn entry(&self, id: &str) -> impl Future<Output = Result<Entry, E>> + Send;
+    fn metadata(&self) -> impl Future<Output = Result<Vec<(String, String)>, E>> + Send;
+    fn records(
+        &self,
+        after: &str,
+        size: i64,
+    ) -> impl Future<Output = Result<Vec<Record>, E>> + Send;
+    fn conflicts(&self) -> impl Future<Output = Result<Vec<Conflict>, E>> + Send;
+    fn history(&self, id: &str) -> impl Future<Output = Result<Vec<Snapshot>, E>> + Send;
+    fn deleted(&self, size: i64) -> impl Future<Output = Result<Vec<Snapshot>, E>> + Send;
+    fn trashed(&self, size: i64) -> impl Future<Output = Result<Vec<Trashed>, E>> + Send;
+    fn last_trashed(&self) -> impl Future<Output = Result<i64, E>> + Send;
+    fn tags(&self) -> impl Future<Output = Result<Vec<(String, i64)>, E>> + Send;
+    fn word_tags(&self, id: &str) -> impl Future<Output = Result<Vec<String>, E>> + Send;
+    fn tagged(&self, tag: &str) -> impl Future<Output = Result<Vec<String>, E>> + Send;
+    fn links(&self, id: &str) -> impl Future<Output = Result<Vec<Link>, E>> + Send;
+    fn backlinks(
+        &self,
+        target: &str,
+    ) -> impl Future<Output = Result<Vec<(String, Link)>, E>> + Send;
+    fn all_links(&self) -> impl Future<Output = Result<Vec<(String, Link)>, E>> + Send;
+    fn attachments(&self, id: &str) -> impl Future<Output = Result<Vec<Attachment>, E>> + Send;
+    fn attachment(
+        &self,
+        id: &str,
+        name: &str,
+    ) -> impl Future<Output = Result<Attachment, E>> + Send;
+}
+
+pub trait AsyncTransaction<T, E>: AsyncWrite<T, E> + AsyncRead<T, E> {
+    fn rollback(self) -> impl Future<Output = Result<(), E>> + Send;
+    fn commit(self) -> impl Future<Output = Result<(), E>> + Send;
+}
+
+pub trait AsyncStore<T, E, F: AsyncTransaction<T, E>>: AsyncWrite<T, E> + AsyncRead<T, E> {
+    fn begin(&self) -> impl Future<Output = Result<F, E>> + Send;
+}
+
+/// adapter running a synchronous store or transaction on the pool
+pub struct Blocking<S> {
+    inner: Arc<S>,
+    pool: Arc<Pool>,
+}
+
+impl<S> Blocking<S> {
+    pub fn new(inner: S, pool: Arc<Pool>) -> Blocking<S> {
+        Blocking {
+            inner: Arc::new(inner),
+            pool,
+        }
+    }
+
+    fn call<R, E, G>(&self, f: G) -> impl Future<Output = Result<R, InternalError<E>>> + Send
+    where
+        S: Send + Sync + 'static,
+        R: Send + 'static,
+        E: Send + 'static,
+        G: FnOnce(&S) -> Result<R, InternalError<E>> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        let fut = self.pool.spawn(move || f(&inner));
+        async move { fut.await.unwrap_or(Err(InternalError::Canceled)) }
+    }
+}
+
+impl<T, E, S> AsyncWrite<T, InternalError<E>> for Blocking<S>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+    S: Write<T, InternalError<E>> + Send + Sync + 'static,
+{
+    fn insert(
+        &self,
+        word: &World,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let word = word.clone();
+        self.call(move |s| s.insert(&word))
+    }
+
+    fn update(
+        &self,
+        word: &World,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let word = word.clone();
+        self.call(move |s| s.update(&word))
+    }
+
+    fn delete(
+        &self,
+        word: &str,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let word = word.to_owned();
+        self.call(move |s| s.delete(&word))
+    }
+
+    fn set_schedule(
+        &self,
+        word: &str,
+        schedule: &Schedule,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let (word, schedule) = (word.to_owned(), schedule.clone());
+        self.call(move |s| s.set_schedule(&word, &schedule))
+    }
+
+    fn put(
+        &self,
+        entry: &Entry,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let entry = entry.clone();
+        self.call(move |s| s.put(&entry))
+    }
+
+    fn set_metadata(
+        &self,
+        key: &str,
+        value: &str,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let (key, value) = (key.to_owned(), value.to_owned());
+        self.call(move |s| s.set_metadata(&key, &value))
+    }
+
+    fn put_record(
+        &self,
+        record: &Record,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let record = record.clone();
+        self.call(move |s| s.put_record(&record))
+    }
+
+    fn add_conflict(
+        &self,
+        conflict: &Conflict,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let conflict = conflict.clone();
+        self.call(move |s| s.add_conflict(&conflict))
+    }
+
+    fn remove_conflict(
+        &self,
+        id: i64,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        self.call(move |s| s.remove_conflict(id))
+    }
+
+    fn put_trashed(
+        &self,
+        trashed: &Trashed,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let trashed = trashed.clone();
+        self.call(move |s| s.put_trashed(&trashed))
+    }
+
+    fn remove_trashed(
+        &self,
+        id: i64,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        self.call(move |s| s.remove_trashed(id))
+    }
+
+    fn add_tag(
+        &self,
+        word: &str,
+        tag: &str,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let (word, tag) = (word.to_owned(), tag.to_owned());
+        self.call(move |s| s.add_tag(&word, &tag))
+    }
+
+    fn remove_tag(
+        &self,
+        word: &str,
+        tag: &str,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let (word, tag) = (word.to_owned(), tag.to_owned());
+        self.call(move |s| s.remove_tag(&word, &tag))
+    }
+
+    fn put_attachment(
+        &self,
+        word: &str,
+        attachment: &Attachment,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let (word, attachment) = (word.to_owned(), attachment.clone());
+        self.call(move |s| s.put_attachment(&word, &attachment))
+    }
+
+    fn remove_attachment(
+        &self,
+        word: &str,
+        name: &str,
+    ) -> impl Future<Output = Result<Option<T>, InternalError<E>>> + Send {
+        let (word, name) = (word.to_owned(), name.to_owned());
+        self.call(move |s| s.remove_attachment(&word, &name))
+    }
+}
+
+impl<T, E, S> AsyncRead<T, InternalError<E>> for Blocking<S>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+    S: Read<T, InternalError<E>> + Send + Sync + 'static,
+{
+    fn get(&self, id: &str) -> impl Future<Output = Result<World, InternalError<E>>> + Send {
+        let id = id.to_owned();
+        self.call(move |s| s.get(&id))
+    }
+
+    fn list(&self, size: i64) -> impl Future<Output = Result<Vec<World>, InternalError<E>>> + Send {
+        self.call(move |s| s.list(size))
+    }
+
+    fn scan(
+        &self,
+        after: &str,
+        size: i64,
+    ) -> impl Future<Output = Result<Vec<World>, InternalError<E>>> + Send {
+        let after = after.to_owned();
+        self.call(move |s| s.scan(&after, size))
+    }
+
+    fn version(&self, id: &str) -> impl Future<Output = Result<i64, InternalError<E>>> + Send {
+        let id = id.to_owned();
+        self.call(move |s| s.version(&id))
+    }
+
+    fn schedule(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Result<Schedule, InternalError<E>>> + Send {
+        let id = id.to_owned();
+        self.call(move |s| s.schedule(&id))
+    }
+
+    fn due(
+        &self,
+        now: i64,
+        size: i64,
+    ) -> impl Future<Output = Result<Vec<World>, InternalError<E>>> + Send {
+        self.call(move |s| s.due(now, size))
+    }
+
+    fn entry(&self, id: &str) -> impl Future<Output = Result<Entry, InternalError<E>>> + Send {
+        let id = id.to_owned();
+        self.call(move |s| s.entry(&id))
+    }
+
+    fn metadata(
+        &self,
+    ) -> impl Future<Output = Result<Vec<(String, String)>, InternalError<E>>> + Send {
+        self.call(move |s| s.metadata())
+    }
+
+    fn records(
+        &self,
+        after: &str,
+        size: i64,
+    ) -> impl Future<Output = Result<Vec<Record>, InternalError<E>>> + Send {
+        let after = after.to_owned();
+        self.call(move |s| s.records(&after, size))
+    }
+
+    fn conflicts(&self) -> impl Future<Output = Result<Vec<Conflict>, InternalError<E>>> + Send {
+        self.call(move |s| s.conflicts())
+    }
+
+    fn history(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Result<Vec<Snapshot>, InternalError<E>>> + Send {
+        let id = id.to_owned();
+        self.call(move |s| s.history(&id))
+    }
+
+    fn deleted(
+        &self,
+        size: i64,
+    ) -> impl Future<Output = Result<Vec<Snapshot>, InternalError<E>>> + Send {
+        self.call(move |s| s.deleted(size))
+    }
+
+    fn trashed(
+        &self,
+        size: i64,
+    ) -> impl Future<Output = Result<Vec<Trashed>, InternalError<E>>> + Send {
+        self.call(move |s| s.trashed(size))
+    }
+
+    fn last_trashed(&self) -> impl Future<Output = Result<i64, InternalError<E>>> + Send {
+        self.call(move |s| s.last_trashed())
+    }
+
+    fn tags(&self) -> impl Future<Output = Result<Vec<(String, i64)>, InternalError<E>>> + Send {
+        self.call(move |s| s.tags())
+    }
+
+    fn word_tags(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Result<Vec<String>, InternalError<E>>> + Send {
+        let id = id.to_owned();
+        self.call(move |s| s.word_tags(&id))
+    }
+
+    fn tagged(
+        &self,
+        tag: &str,
+    ) -> impl Future<Output = Result<Vec<String>, InternalError<E>>> + Send {
+        let tag = tag.to_owned();
+        self.call(move |s| s.tagged(&tag))
+    }
+
+    fn links(&self, id: &str) -> impl Future<Output = Result<Vec<Link>, InternalError<E>>> + Send {
+        let id = id.to_owned();
+        self.call(move |s| s.links(&id))
+    }
+
+    fn backlinks(
+        &self,
+        target: &str,
+    ) -> impl Future<Output = Result<Vec<(String, Link)>, InternalError<E>>> + Send {
+        let target = target.to_owned();
+        self.call(move |s| s.backlinks(&target))
+    }
+
+    fn all_links(
+        &self,
+    ) -> impl Future<Output = Result<Vec<(String, Link)>, InternalError<E>>> + Send {
+        self.call(move |s| s.all_links())
+    }
+
+    fn attachments(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Result<Vec<Attachment>, InternalError<E>>> + Send {
+        let id = id.to_owned();
+        self.call(move |s| s.attachments(&id))
+    }
+
+    fn attachment(
+        &self,
+        id: &str,
+        name: &str,
+    ) -> impl Future<Output = Result<Attachment, InternalError<E>>> + Send {
+        let (id, name) = (id.to_owned(), name.to_owned());
+        self.call(move |s| s.attachment(&id, &name))
+    }
+}
+
+impl<T, E, F> AsyncTransaction<T, InternalError<E>> for Blocking<F>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+    F: Transaction<T, InternalError<E>> + Send + Sync + 'static,
+{
+    fn rollback(self) -> impl Future<Output = Result<(), InternalError<E>>> + Send {
+        let fut = self.pool.spawn(move || match Arc::try_unwrap(self.inner) {
+            Ok(t) => t.rollback(),
+            // a canceled operation is still running on the transaction
+            Err(_) => Err(InternalError::Canceled),
+        });
+        async move { fut.await.unwrap_or(Err(InternalError::Canceled)) }
+    }
+
+    fn commit(self) -> impl Future<Output = Result<(), InternalError<E>>> + Send {
+        let fut = self.pool.spawn(move || match Arc::try_unwrap(self.inner) {
+            Ok(t) => t.commit(),
+            Err(_) => Err(InternalError::Canceled),
+        });
+        async move { fut.await.unwrap_or(Err(InternalError::Canceled)) }
+    }
+}
+
+impl<T, E, F, S> AsyncStore<T, InternalError<E>, Blocking<F>> for Blocking<S>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+    F: Transaction<T, InternalError<E>> + Send + Sync + 'static,
+    S: Store<T, InternalError<E>, F> + Send + Sync + 'static,
+{
+    fn begin(&self) -> impl Future<Output = Result<Blocking<F>, InternalError<E>>> + Send {
+        let pool = self.pool.clone();
+        let fut = self.call(|s| s.begin());
+        async move { fut.await.map(|t| Blocking::new(t, pool)) }
+    }
+}
+
+/// async WordLibrary, the operations run on the pool against a shared WordLibrary
+pub struct AsyncWordLibrary<T, E, F: Transaction<T, InternalError<E>>> {
+    lib: Arc<WordLibrary<T, E, F>>,
+    pool: Arc<Pool>,
+}
+
+impl<T, E, F> AsyncWordLibrary<T, E, F>
+where
+    T: Send + 'static,
+    E: std::fmt::Debug + Send + 'static,
+    F: Transaction<T, InternalError<E>> + 'static,
+{
+    pub fn new(lib: WordLibrary<T, E, F>, threads: usize) -> Self {
+        AsyncWordLibrary {
+            lib: Arc::new(lib),
+            pool: Arc::new(Pool::new(threads)),
+        }
+    }
+
+    /// the shared synchronous library
+    pub fn library(&self) -> &Arc<WordLibrary<T, E, F>> {
+        &self.lib
+    }
+
+    /// run a long job on the pool, the job should check the token between its steps.
+    /// The token is canceled on timeout or when the returned future is dropped.
+    pub async fn run<R, G>(
+        &self,
+        token: CancelToken,
+        timeout: Option<Duration>,
+        job: G,
+    ) -> Result<R, InternalError<E>>
+    where
+        R: Send + 'static,
+        G: FnOnce(&WordLibrary<T, E, F>, &CancelToken) -> Result<R, InternalError<E>>
+            + Send
+            + 'static,
+    {
+        let lib = self.lib.clone();
+        let t = token.clone();
+        let fut = self.pool.spawn(move || job(&lib, &t));
+        wait(fut, token, timeout).await
+    }
+
+    async fn call<R, G>(&self, f: G) -> Result<R, InternalError<E>>
+    where
+        R: Send + 'static,
+        G: FnOnce(&WordLibrary<T, E, F>) -> Result<R, InternalError<E>> + Send + 'static,
+    {
+        self.run(CancelToken::new(), None, move |lib, _| f(lib))
+            .await
+    }
+
+    pub async fn insert(&self, word: &World, key: &str) -> Result<Option<T>, InternalError<E>> {
+        let (word, key) = (word.clone(), key.to_owned());
+        self.call(move |lib| lib.insert(&word, &key)).await
+    }
+
+    pub async fn update(&self, word: &World, key: &str) -> Result<Option<T>, InternalError<E>> {
+        let (word, key) = (word.clone(), key.to_owned());
+        self.call(move |lib| lib.update(&word, &key)).await
+    }
+
+    pub async fn delete(&self, name: &str, key: &str) -> Result<Option<T>, InternalError<E>> {
+        let (name, key) = (name.to_owned(), key.to_owned());
+        self.call(move |lib| lib.delete(&name, &key)).await
+    }
+
+    pub async fn get(&self, name: &str, key: &str) -> Result<World, InternalError<E>> {
+        let (name, key) = (name.to_owned(), key.to_owned());
+        self.call(move |lib| lib.get(&name, &key)).await
+    }
+
+    pub async fn list(&self, size: i64, key: &str) -> Result<Vec<World>, InternalError<E>> {
+        let key = key.to_owned();
+        self.call(move |lib| lib.list(size, &key)).await
+    }
+
+    pub async fn search(
+        &self,
+        pattern: &str,
+        key: &str,
+        limit: usize,
+    ) -> Result<Vec<World>, InternalError<E>> {
+        let (pattern, key) = (pattern.to_owned(), key.to_owned());
+        self.call(move |lib| lib.search(&pattern, &key, limit))
+            .await
+    }
+
+    pub async fn due(
+        &self,
+        now: i64,
+        size: i64,
+        key: &str,
+    ) -> Result<Vec<World>, InternalError<E>> {
+        let key = key.to_owned();
+        self.call(move |lib| lib.due(now, size, &key)).await
+    }
+
+    pub async fn move_to(
+        &self,
+        name: &str,
+        src: &str,
+        dst: &str,
+    ) -> Result<Option<T>, InternalError<E>> {
+        let (name, src, dst) = (name.to_owned(), src.to_owned(), dst.to_owned());
+        self.call(move |lib| lib.move_to(&name, &src, &dst)).await
+    }
+
+    pub async fn copy_to(
+        &self,
+        name: &str,
+        src: &str,
+        dst: &str,
+    ) -> Result<Option<T>, InternalError<E>> {
+        let (name, src, dst) = (name.to_owned(), src.to_owned(), dst.to_owned());
+        self.call(move |lib| lib.copy_to(&name, &src, &dst)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::sqlite::store;
+
+    fn block_on<R>(fut: impl Future<Output = R>) -> R {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(fut)
+    }
+
+    fn word(name: &str) -> World {
+        World {
+            name: name.to_owned(),
+            message: String::from("test"),
+        }
+    }
+
+    #[test]
+    fn test_store() {
+        let path = "target/test_async_store.sql";
+        let _ = std::fs::remove_file(path);
+        let pool = Arc::new(Pool::new(2));
+        let sql = Blocking::new(store::Sqlite::new(path.to_owned()).unwrap(), pool);
+
+        block_on(async {
+            sql.insert(&word("test")).await.unwrap();
+            assert_eq!(sql.get("test").await.unwrap().message, "test");
+
+            let t = sql.begin().await.unwrap();
+            t.delete("test").await.unwrap();
+            t.rollback().await.unwrap();
+            assert_eq!(sql.list(0).await.unwrap().len(), 1);
+
+            let t = sql.begin().await.unwrap();
+            t.delete("test").await.unwrap();
+            t.commit().await.unwrap();
+            assert!(matches!(
+                sql.get("test").await,
+                Err(InternalError::NotFound)
+            ));
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_library() {
+        let k0 = "target/test_async_lib.sql";
+        let _ = std::fs::remove_file(k0);
+        let mut lib = WordLibrary::new();
+        lib.add_store(k0, Box::new(store::Sqlite::new(k0.to_owned()).unwrap()));
+        let lib = AsyncWordLibrary::new(lib, 2);
+
+        block_on(async {
+            lib.insert(&word("test"), k0).await.unwrap();
+            assert_eq!(lib.list(0, k0).await.unwrap().len(), 1);
+
+            // a long job stopped by the timeout
+            let token = CancelToken::new();
+            let res = lib
+                .run(
+                    token.clone(),
+                    Some(Duration::from_millis(50)),
+                    |_, t| loop {
+                        t.check()?;
+                        std::thread::sleep(Duration::from_millis(5));
+                    },
+                )
+                .await;
+            let res: Result<(), _> = res;
+            assert!(matches!(res, Err(InternalError::Timeout)));
+            assert!(token.is_canceled());
+
+            // canceled before start
+            let token = CancelToken::new();
+            token.cancel();
+            let res = lib.run(token, None, |_, t| t.check()).await;
+            assert!(matches!(res, Err(InternalError::Canceled)));
+        });
+        std::fs::remove_file(k0).unwrap();
+    }
+}
@@ -0,0 +1,190 @@
+// files attached to the words
+//
+// a word keeps any number of files, as a recorded pronunciation or an image,
+// stored in the library file with the word. the files go with the word when it
+// is moved, copied, renamed or deleted into the trash, in json they are
+// written in base64
+use super::{Error, InternalError};
+use serde_json::{json, Value};
+use std::io;
+use std::path::Path;
+
+/// a file attached to a word
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    /// file name, unique in the word
+    pub name: String,
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+const MIMES: &[(&str, &str)] = &[
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("oga", "audio/ogg"),
+    ("opus", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("m4a", "audio/mp4"),
+    ("flac", "audio/flac"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("bmp", "image/bmp"),
+];
+
+/// the media type of a file by its extension
+pub fn mime(name: &str) -> &'static str {
+    let ext = match name.rsplit_once('.') {
+        Some((_, ext)) => ext.to_lowercase(),
+        None => return "application/octet-stream",
+    };
+    MIMES
+        .iter()
+        .find(|(e, _)| *e == ext)
+        .map(|(_, m)| *m)
+        .unwrap_or("application/octet-stream")
+}
+
+/// a name can be used as a file name on every system
+pub fn valid(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name
+            .chars()
+            .any(|c| c.is_control() || "/\\:*?\"<>|".contains(c))
+}
+
+/// the error of an invalid name
+pub fn invalid<E>(name: &str) -> InternalError<E> {
+    InternalError::Unknow(Error {
+        message: format!("invalid attachment name {:?}", name),
+        code: 400,
+        err: None,
+    })
+}
+
+impl Attachment {
+    /// the media type is told by the name
+    pub fn new(name: &str, data: Vec<u8>) -> Attachment {
+        Attachment {
+            name: name.to_owned(),
+            mime: mime(name).to_owned(),
+            data,
+        }
+    }
+
+    /// the file as an attachment of the same name
+    pub fn read(path: &Path) -> io::Result<Attachment> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Attachment::new(&name, std::fs::read(path)?))
+    }
+
+    pub fn is_audio(&self) -> bool {
+        self.mime.starts_with("audio/")
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime.starts_with("image/")
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({"name": self.name, "mime": self.mime, "data": encode(&self.data)})
+    }
+
+    pub fn from_json(v: &Value) -> Result<Attachment, String> {
+        let name = match v["name"].as_str() {
+            Some(name) if valid(name) => name,
+            _ => return Err(format!("invalid attachment name {}", v["name"])),
+        };
+        let data = v["data"]
+            .as_str()
+            .and_then(decode)
+            .ok_or_else(|| format!("invalid data of attachment {}", name))?;
+        Ok(Attachment {
+            name: name.to_owned(),
+            mime: v["mime"].as_str().unwrap_or(mime(name)).to_owned(),
+            data,
+        })
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// standard base64 with padding
+pub fn encode(data: &[u8]) -> String {
+    let mut res = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => res.push(BASE64[((n >> (18 - 6 * i)) & 0x3f) as usize] as char),
+                false => res.push('='),
+            }
+        }
+    }
+    res
+}
+
+/// None if it is not base64, whitespace is skipped
+pub fn decode(s: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    let chunks = digits.chunks_exact(4);
+    if !chunks.remainder().is_empty() {
+        return None;
+    }
+    let mut res = Vec::with_capacity(digits.len() / 4 * 3);
+    for (i, chunk) in chunks.enumerate() {
+        let pad = chunk.iter().rev().take_while(|b| **b == b'=').count();
+        if pad > 2 || (pad > 0 && i + 1 < digits.len() / 4) {
+            return None;
+        }
+        let mut n = 0u32;
+        for b in &chunk[..4 - pad] {
+            let v = BASE64.iter().position(|c| c == b)? as u32;
+            n = (n << 6) | v;
+        }
+        n <<= 6 * pad as u32;
+        res.extend_from_slice(&n.to_be_bytes()[1..4 - pad]);
+    }
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment() {
+        for (data, text) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode(data.as_bytes()), text);
+            assert_eq!(decode(text).unwrap(), data.as_bytes());
+        }
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode(&encode(&data)).unwrap(), data);
+        for bad in ["Zg=", "Z===", "Zg==Zm8=", "Zm9*"] {
+            assert!(decode(bad).is_none(), "{}", bad);
+        }
+
+        let a = Attachment::new("apple.MP3", vec![1, 2, 3]);
+        assert!(a.is_audio() && !a.is_image());
+        assert_eq!(Attachment::from_json(&a.to_json()).unwrap(), a);
+        assert_eq!(mime("apple"), "application/octet-stream");
+        assert!(valid("apple.png") && !valid("../apple.png") && !valid(""));
+    }
+}
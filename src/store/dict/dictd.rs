@@ -0,0 +1,141 @@
+// dictd dictionaries, the .index file lists "headword\toffset\tlength" with
+// numbers in base64 digits, the definitions are in .dict or .dict.dz
+use super::{invalid, Data, Dictionary, Index};
+use crate::store::exchange::jsonl;
+use std::io;
+use std::path::Path;
+
+const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// headwords of the dictionary information
+const SHORT_NAME: [&str; 2] = ["00-database-short", "00databaseshort"];
+
+pub struct Dictd {
+    name: String,
+    index: Index,
+    data: Data,
+}
+
+fn number(s: &str) -> Option<u64> {
+    s.bytes().try_fold(0u64, |n, b| {
+        let d = DIGITS.iter().position(|c| *c == b)? as u64;
+        n.checked_mul(64).map(|n| n + d)
+    })
+}
+
+impl Dictd {
+    pub fn open(path: &Path) -> io::Result<Dictd> {
+        let text = std::fs::read_to_string(path)?;
+        let mut words = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split('\t');
+            let place = match (fields.next(), fields.next(), fields.next()) {
+                (Some(word), Some(offset), Some(size)) => {
+                    number(offset).zip(number(size)).map(|(o, s)| (word, o, s))
+                }
+                _ => None,
+            };
+            let (word, offset, size) =
+                place.ok_or_else(|| invalid(path, &format!("bad index line {}", i + 1)))?;
+            words.push((word.to_owned(), offset, size));
+        }
+
+        let dict = path.with_extension("dict");
+        let dz = path.with_extension("dict.dz");
+        let mut d = Dictd {
+            name: jsonl::library_name(&path.to_string_lossy()),
+            index: Index::new(words),
+            data: Data::open(&[dict, dz])?,
+        };
+        // the short name is the definition without its headword line
+        for key in SHORT_NAME {
+            if let Some(def) = d.lookup(key)?.first() {
+                let name = def.split_once('\n').map(|(_, n)| n).unwrap_or(def).trim();
+                if !name.is_empty() {
+                    d.name = name.to_owned();
+                }
+                break;
+            }
+        }
+        Ok(d)
+    }
+}
+
+impl Dictionary for Dictd {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn lookup(&self, word: &str) -> io::Result<Vec<String>> {
+        let mut res = Vec::new();
+        for (_, offset, size) in self.index.find(word) {
+            let data = self.data.read(*offset, *size)?;
+            let text = String::from_utf8_lossy(&data).trim().to_owned();
+            if !text.is_empty() {
+                res.push(text);
+            }
+        }
+        Ok(res)
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        !self.index.find(word).is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(mut n: u64) -> String {
+        let mut res = Vec::new();
+        loop {
+            res.insert(0, DIGITS[(n % 64) as usize]);
+            n /= 64;
+            if n == 0 {
+                break;
+            }
+        }
+        String::from_utf8(res).unwrap()
+    }
+
+    #[test]
+    fn test_dictd() {
+        assert_eq!(number("B9"), Some(125));
+        assert_eq!(number("A"), Some(0));
+        assert_eq!(number("?"), None);
+
+        let dir = "target/test_dictd";
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+
+        let defs = [
+            (
+                "00-database-short",
+                "00-database-short\n     Test Dictionary\n",
+            ),
+            ("apple", "apple\n  a fruit\n"),
+            ("Apple", "Apple\n  a company\n"),
+        ];
+        let (mut index, mut dict) = (String::new(), String::new());
+        for (word, def) in defs {
+            let (offset, size) = (encode(dict.len() as u64), encode(def.len() as u64));
+            index.push_str(&format!("{}\t{}\t{}\n", word, offset, size));
+            dict.push_str(def);
+        }
+        std::fs::write(format!("{}/test.index", dir), index).unwrap();
+        std::fs::write(format!("{}/test.dict", dir), dict).unwrap();
+
+        let d = Dictd::open(Path::new(&format!("{}/test.index", dir))).unwrap();
+        assert_eq!(d.name(), "Test Dictionary");
+        assert_eq!(
+            d.lookup("APPLE").unwrap(),
+            vec!["apple\n  a fruit", "Apple\n  a company"]
+        );
+        assert!(d.lookup("pear").unwrap().is_empty());
+        assert!(d.contains("Apple") && !d.contains("pear"));
+    }
+}
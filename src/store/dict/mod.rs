@@ -0,0 +1,356 @@
+// offline dictionaries in StarDict and dictd formats, used to fill the
+// message of new words
+//
+// the registered dictionaries are listed one path each line in the file of
+// registry_path, the headword index is read on open and the definitions on
+// lookup, a compressed .dz definition file is decompressed at the first lookup
+pub mod dictd;
+pub mod stardict;
+
+use crate::store::exchange;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const USAGE: &str = r#"  dict add <file>...   register StarDict (.ifo) or dictd (.index) dictionaries
+  dict remove <file>...
+                     unregister dictionaries
+  dict list          list the registered dictionaries
+  dict lookup <word> look up a word in every registered dictionary"#;
+
+pub trait Dictionary {
+    fn name(&self) -> &str;
+    /// the definitions of the word, case is ignored
+    fn lookup(&self, word: &str) -> io::Result<Vec<String>>;
+    /// true if the word is a headword, without reading the definitions
+    fn contains(&self, word: &str) -> bool;
+}
+
+/// a definition and the dictionary it comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub dict: String,
+    pub text: String,
+}
+
+fn invalid(path: &Path, msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), msg),
+    )
+}
+
+// the headwords sorted by lowercase name, with the place of the definition
+struct Index {
+    words: Vec<(String, u64, u64)>,
+}
+
+impl Index {
+    fn new(mut words: Vec<(String, u64, u64)>) -> Index {
+        for w in words.iter_mut() {
+            w.0 = w.0.to_lowercase();
+        }
+        words.sort();
+        words.dedup();
+        Index { words }
+    }
+
+    fn find(&self, word: &str) -> &[(String, u64, u64)] {
+        let key = word.trim().to_lowercase();
+        let start = self.words.partition_point(|w| w.0 < key);
+        let end = start + self.words[start..].partition_point(|w| w.0 == key);
+        &self.words[start..end]
+    }
+}
+
+// the definition file, plain or compressed by dictzip
+enum Data {
+    Plain(PathBuf),
+    Gzip(PathBuf, Mutex<Option<Vec<u8>>>),
+}
+
+impl Data {
+    // the first of the candidate files that exists
+    fn open(candidates: &[PathBuf]) -> io::Result<Data> {
+        for path in candidates {
+            if !path.exists() {
+                continue;
+            }
+            let gzip = path.extension().map(|e| e == "dz").unwrap_or(false);
+            return Ok(if gzip {
+                Data::Gzip(path.clone(), Mutex::new(None))
+            } else {
+                Data::Plain(path.clone())
+            });
+        }
+        let path = candidates.first().cloned().unwrap_or_default();
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} not found", path.display()),
+        ))
+    }
+
+    fn read(&self, offset: u64, size: u64) -> io::Result<Vec<u8>> {
+        match self {
+            Data::Plain(path) => {
+                let mut f = File::open(path)?;
+                f.seek(SeekFrom::Start(offset))?;
+                let mut buf = vec![0u8; size as usize];
+                f.read_exact(&mut buf)?;
+                Ok(buf)
+            }
+            Data::Gzip(path, data) => {
+                let mut data = data.lock().unwrap();
+                if data.is_none() {
+                    *data = Some(exchange::zip::gunzip(&std::fs::read(path)?)?);
+                }
+                let (start, end) = (offset as usize, (offset + size) as usize);
+                data.as_ref()
+                    .and_then(|d| d.get(start..end))
+                    .map(|d| d.to_vec())
+                    .ok_or_else(|| invalid(path, "definition out of range"))
+            }
+        }
+    }
+}
+
+/// open a dictionary by the extension of its index file
+pub fn open(path: &str) -> io::Result<Box<dyn Dictionary + Send + Sync>> {
+    let p = Path::new(path);
+    match p.extension().and_then(|e| e.to_str()) {
+        Some("ifo") => Ok(Box::new(stardict::StarDict::open(p)?)),
+        Some("index") => Ok(Box::new(dictd::Dictd::open(p)?)),
+        _ => Err(invalid(p, "not a .ifo or .index dictionary file")),
+    }
+}
+
+/// the file listing the registered dictionaries
+pub fn registry_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("WORDLIB_DICTS") {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var("HOME")
+        .ok()
+        .map(|home| Path::new(&home).join(".word_library_dicts"))
+}
+
+fn write_registry(paths: &[String]) -> io::Result<()> {
+    let registry = registry_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    let mut text = paths.join("\n");
+    text.push('\n');
+    std::fs::write(registry, text)
+}
+
+/// the registered dictionary files
+pub fn registered() -> io::Result<Vec<String>> {
+    let registry = match registry_path() {
+        Some(path) => path,
+        None => return Ok(Vec::new()),
+    };
+    match std::fs::read_to_string(registry) {
+        Ok(text) => Ok(text
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_owned())
+            .collect()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// register a dictionary after checking it opens, returns its absolute path
+pub fn register(path: &str) -> io::Result<String> {
+    open(path)?;
+    let path = std::fs::canonicalize(path)?.to_string_lossy().into_owned();
+    let mut paths = registered()?;
+    if !paths.contains(&path) {
+        paths.push(path.clone());
+        write_registry(&paths)?;
+    }
+    Ok(path)
+}
+
+/// unregister a dictionary, false if it is not registered
+pub fn unregister(path: &str) -> io::Result<bool> {
+    let abs = std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_owned());
+    let mut paths = registered()?;
+    let len = paths.len();
+    paths.retain(|p| p != path && *p != abs);
+    if paths.len() == len {
+        return Ok(false);
+    }
+    write_registry(&paths)?;
+    Ok(true)
+}
+
+/// the opened dictionaries, looked up in order
+#[derive(Default)]
+pub struct Dictionaries {
+    dicts: Vec<(String, Box<dyn Dictionary + Send + Sync>)>,
+}
+
+impl Dictionaries {
+    pub fn new() -> Self {
+        Dictionaries::default()
+    }
+
+    /// open the registered dictionaries, the ones failed to open are
+    /// skipped and returned with the error
+    pub fn load() -> (Dictionaries, Vec<String>) {
+        let mut dicts = Dictionaries::new();
+        let mut errs = Vec::new();
+        match registered() {
+            Ok(paths) => {
+                for path in paths {
+                    if let Err(err) = dicts.open(&path) {
+                        errs.push(format!("open dictionary {} fail, {}", path, err));
+                    }
+                }
+            }
+            Err(err) => errs.push(format!("read dictionary registry fail, {}", err)),
+        }
+        (dicts, errs)
+    }
+
+    pub fn open(&mut self, path: &str) -> io::Result<()> {
+        if !self.dicts.iter().any(|(p, _)| p == path) {
+            self.dicts.push((path.to_owned(), open(path)?));
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dicts.is_empty()
+    }
+
+    /// path and name of the dictionaries
+    pub fn list(&self) -> Vec<(String, String)> {
+        self.dicts
+            .iter()
+            .map(|(p, d)| (p.clone(), d.name().to_owned()))
+            .collect()
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.dicts.iter().any(|(_, d)| d.contains(word))
+    }
+
+    pub fn lookup(&self, word: &str) -> io::Result<Vec<Definition>> {
+        let mut res = Vec::new();
+        for (_, d) in &self.dicts {
+            for text in d.lookup(word)? {
+                res.push(Definition {
+                    dict: d.name().to_owned(),
+                    text,
+                });
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// the message filled by the definitions, the ones already in it are skipped
+pub fn fill(message: &str, defs: &[Definition]) -> String {
+    let mut res = message.to_owned();
+    for d in defs {
+        if let Some(m) = exchange::merge_message(&res, &d.text) {
+            res = m;
+        }
+    }
+    res
+}
+
+/// the definitions as shown in the dictionary panel, grouped by dictionary
+pub fn format(defs: &[Definition]) -> String {
+    let mut res = String::new();
+    for (i, d) in defs.iter().enumerate() {
+        if i == 0 || defs[i - 1].dict != d.dict {
+            if i > 0 {
+                res.push('\n');
+            }
+            res.push_str(&format!("[{}]\n", d.dict));
+        }
+        res.push_str(d.text.trim_end());
+        res.push('\n');
+    }
+    res
+}
+
+/// dict add|remove|list|lookup
+pub fn command(args: &[String]) -> Result<(), String> {
+    match (args.first().map(|s| s.as_str()), &args[args.len().min(1)..]) {
+        (Some("add"), paths) if !paths.is_empty() => {
+            for p in paths {
+                let path = register(p).map_err(|e| format!("add {} fail, {}", p, e))?;
+                println!("registered {}", path);
+            }
+        }
+        (Some("remove"), paths) if !paths.is_empty() => {
+            for p in paths {
+                match unregister(p) {
+                    Ok(true) => println!("unregistered {}", p),
+                    Ok(false) => println!("{} is not registered", p),
+                    Err(err) => return Err(format!("remove {} fail, {}", p, err)),
+                }
+            }
+        }
+        (Some("list"), []) => {
+            let (dicts, errs) = Dictionaries::load();
+            for (path, name) in dicts.list() {
+                println!("{:<30}{}", name, path);
+            }
+            for err in errs {
+                eprintln!("{}", err);
+            }
+        }
+        (Some("lookup"), [word]) => {
+            let (dicts, errs) = Dictionaries::load();
+            for err in errs {
+                eprintln!("{}", err);
+            }
+            let defs = dicts.lookup(word).map_err(|e| e.to_string())?;
+            if defs.is_empty() {
+                return Err(format!("{} not found", word));
+            }
+            print!("{}", format(&defs));
+        }
+        _ => return Err(USAGE.to_owned()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        let defs = vec![
+            Definition {
+                dict: String::from("a"),
+                text: String::from("fruit"),
+            },
+            Definition {
+                dict: String::from("b"),
+                text: String::from("a red fruit"),
+            },
+        ];
+        assert_eq!(fill("", &defs), "fruit\na red fruit");
+        assert_eq!(fill("a red fruit", &defs), "a red fruit");
+        assert_eq!(fill("note", &defs[..1]), "note\nfruit");
+        assert_eq!(format(&defs), "[a]\nfruit\n\n[b]\na red fruit\n");
+
+        let index = Index::new(vec![
+            (String::from("Apple"), 0, 1),
+            (String::from("apple"), 2, 3),
+            (String::from("book"), 4, 5),
+        ]);
+        assert_eq!(index.find("APPLE ").len(), 2);
+        assert!(index.find("cat").is_empty());
+    }
+}
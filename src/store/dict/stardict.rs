@@ -0,0 +1,296 @@
+// StarDict dictionaries, the .ifo file names the .idx index, the optional
+// .syn synonyms and the .dict definitions, the last two may be gzip compressed
+use super::{invalid, Data, Dictionary, Index};
+use crate::store::exchange::{anki, jsonl, zip};
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub struct StarDict {
+    name: String,
+    // types of the fields when every definition has the same fields
+    types: Option<String>,
+    index: Index,
+    data: Data,
+}
+
+// the file with the extension replaced, or appended with .gz or .dz
+fn sibling(path: &Path, ext: &str) -> [PathBuf; 2] {
+    let p = path.with_extension(ext);
+    let mut gz = p.clone().into_os_string();
+    gz.push(if ext == "dict" { ".dz" } else { ".gz" });
+    [p, PathBuf::from(gz)]
+}
+
+fn read_maybe_gz(candidates: &[PathBuf]) -> io::Result<Option<Vec<u8>>> {
+    for path in candidates {
+        if path.exists() {
+            let data = std::fs::read(path)?;
+            return if data.starts_with(&[0x1f, 0x8b]) {
+                zip::gunzip(&data).map(Some)
+            } else {
+                Ok(Some(data))
+            };
+        }
+    }
+    Ok(None)
+}
+
+fn be(data: &[u8]) -> u64 {
+    data.iter().fold(0, |n, b| n << 8 | *b as u64)
+}
+
+// the headword entries of .idx, in the file order
+fn parse_idx(path: &Path, data: &[u8], offset_bits: usize) -> io::Result<Vec<(String, u64, u64)>> {
+    let width = offset_bits / 8;
+    let mut res = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let end = data[pos..]
+            .iter()
+            .position(|b| *b == 0)
+            .map(|i| pos + i)
+            .ok_or_else(|| invalid(path, "truncated index"))?;
+        let fields = data
+            .get(end + 1..end + 1 + width + 4)
+            .ok_or_else(|| invalid(path, "truncated index"))?;
+        let word = String::from_utf8_lossy(&data[pos..end]).into_owned();
+        res.push((word, be(&fields[..width]), be(&fields[width..])));
+        pos = end + 1 + width + 4;
+    }
+    Ok(res)
+}
+
+// synonyms of .syn point to the position of an entry in .idx
+fn parse_syn(
+    path: &Path,
+    data: &[u8],
+    words: &[(String, u64, u64)],
+) -> io::Result<Vec<(String, u64, u64)>> {
+    let mut res = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let end = data[pos..]
+            .iter()
+            .position(|b| *b == 0)
+            .map(|i| pos + i)
+            .ok_or_else(|| invalid(path, "truncated synonyms"))?;
+        let i = data
+            .get(end + 1..end + 5)
+            .map(be)
+            .ok_or_else(|| invalid(path, "truncated synonyms"))?;
+        if let Some((_, offset, size)) = words.get(i as usize) {
+            let word = String::from_utf8_lossy(&data[pos..end]).into_owned();
+            res.push((word, *offset, *size));
+        }
+        pos = end + 5;
+    }
+    Ok(res)
+}
+
+// a text field by its type, markup is reduced to plain text
+fn render(kind: u8, text: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(text);
+    let text = match kind {
+        b'm' | b'l' | b'y' | b'k' | b'w' => text.into_owned(),
+        b't' => format!("[{}]", text.trim()),
+        b'g' | b'h' | b'x' => anki::strip_html(&text),
+        // resources and unknown types
+        _ => return None,
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_owned())
+    }
+}
+
+/// the text of a definition, the binary fields are skipped
+fn decode(data: &[u8], types: Option<&str>) -> String {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    let kinds = types.map(|t| t.bytes().collect::<Vec<u8>>());
+    let mut i = 0;
+    while pos < data.len() {
+        let (kind, last) = match &kinds {
+            Some(kinds) => match kinds.get(i) {
+                Some(k) => (*k, i + 1 == kinds.len()),
+                None => break,
+            },
+            None => {
+                pos += 1;
+                (data[pos - 1], false)
+            }
+        };
+        i += 1;
+
+        let field = if kind.is_ascii_lowercase() {
+            // a text field ends by a nul, the last one of a sequence by the data
+            let end = if last {
+                data.len()
+            } else {
+                data[pos..]
+                    .iter()
+                    .position(|b| *b == 0)
+                    .map(|e| pos + e)
+                    .unwrap_or(data.len())
+            };
+            let field = render(kind, &data[pos..end]);
+            pos = (end + 1).min(data.len());
+            field
+        } else {
+            // a binary field, its size is before it unless it is the last
+            let size = if last {
+                data.len() - pos
+            } else {
+                let size = data.get(pos..pos + 4).map(be).unwrap_or(0) as usize;
+                pos += 4;
+                size
+            };
+            pos = (pos + size).min(data.len());
+            None
+        };
+        fields.extend(field);
+    }
+    fields.join("\n")
+}
+
+impl StarDict {
+    pub fn open(path: &Path) -> io::Result<StarDict> {
+        let ifo = std::fs::read_to_string(path)?;
+        let mut lines = ifo.lines();
+        if lines
+            .next()
+            .map(|l| l.trim_start_matches('\u{feff}').trim())
+            != Some("StarDict's dict ifo file")
+        {
+            return Err(invalid(path, "not a StarDict ifo file"));
+        }
+        let mut name = String::new();
+        let mut types = None;
+        let mut offset_bits = 32;
+        for line in lines {
+            match line.split_once('=') {
+                Some(("bookname", v)) => name = v.trim().to_owned(),
+                Some(("sametypesequence", v)) if !v.trim().is_empty() => {
+                    types = Some(v.trim().to_owned())
+                }
+                Some(("idxoffsetbits", "64")) => offset_bits = 64,
+                _ => {}
+            }
+        }
+        if name.is_empty() {
+            name = jsonl::library_name(&path.to_string_lossy());
+        }
+
+        let idx = read_maybe_gz(&sibling(path, "idx"))?
+            .ok_or_else(|| invalid(path, "no .idx index file"))?;
+        let mut words = parse_idx(path, &idx, offset_bits)?;
+        if let Some(syn) = read_maybe_gz(&sibling(path, "syn"))? {
+            let syns = parse_syn(path, &syn, &words)?;
+            words.extend(syns);
+        }
+
+        Ok(StarDict {
+            name,
+            types,
+            index: Index::new(words),
+            data: Data::open(&sibling(path, "dict"))?,
+        })
+    }
+}
+
+impl Dictionary for StarDict {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn lookup(&self, word: &str) -> io::Result<Vec<String>> {
+        let mut res: Vec<String> = Vec::new();
+        for (_, offset, size) in self.index.find(word) {
+            let text = decode(&self.data.read(*offset, *size)?, self.types.as_deref());
+            // a synonym and its headword point to the same definition
+            if !text.is_empty() && !res.contains(&text) {
+                res.push(text);
+            }
+        }
+        Ok(res)
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        !self.index.find(word).is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a gzip file of one stored deflate block
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut res = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 1];
+        let len = data.len() as u16;
+        res.extend_from_slice(&len.to_le_bytes());
+        res.extend_from_slice(&(!len).to_le_bytes());
+        res.extend_from_slice(data);
+        res.extend_from_slice(&zip::crc32(data).to_le_bytes());
+        res.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        res
+    }
+
+    // the files of a dictionary of the words and definitions
+    fn create(dir: &str, types: Option<&str>, words: &[(&str, &[u8])], compress: bool) {
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+
+        let mut ifo = String::from("StarDict's dict ifo file\nversion=2.4.2\nbookname=Test\n");
+        if let Some(t) = types {
+            ifo.push_str(&format!("sametypesequence={}\n", t));
+        }
+        let (mut idx, mut dict) = (Vec::new(), Vec::new());
+        for (word, def) in words {
+            idx.extend_from_slice(word.as_bytes());
+            idx.push(0);
+            idx.extend_from_slice(&(dict.len() as u32).to_be_bytes());
+            idx.extend_from_slice(&(def.len() as u32).to_be_bytes());
+            dict.extend_from_slice(def);
+        }
+        // ran is a synonym of run
+        let syn = b"ran\0\0\0\0\x01";
+
+        std::fs::write(format!("{}/test.ifo", dir), ifo).unwrap();
+        std::fs::write(format!("{}/test.idx", dir), idx).unwrap();
+        std::fs::write(format!("{}/test.syn", dir), syn).unwrap();
+        if compress {
+            std::fs::write(format!("{}/test.dict.dz", dir), gzip(&dict)).unwrap();
+        } else {
+            std::fs::write(format!("{}/test.dict", dir), dict).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_stardict() {
+        let dir = "target/test_stardict";
+        create(
+            dir,
+            Some("m"),
+            &[("apple", b"a fruit"), ("run", b"to move fast")],
+            true,
+        );
+        let d = StarDict::open(Path::new(&format!("{}/test.ifo", dir))).unwrap();
+        assert_eq!(d.name(), "Test");
+        assert_eq!(d.lookup("Apple").unwrap(), vec!["a fruit"]);
+        assert_eq!(d.lookup("ran").unwrap(), vec!["to move fast"]);
+        assert!(d.lookup("cat").unwrap().is_empty());
+        assert!(d.contains("ran") && !d.contains("cat"));
+
+        // fields with their types, html markup and a binary field
+        let mut def = b"t'ap.l\0h<b>a</b> fruit<br>red\0W".to_vec();
+        def.extend_from_slice(&3u32.to_be_bytes());
+        def.extend_from_slice(b"wav");
+        create(dir, None, &[("apple", &def), ("run", b"mrun\0")], false);
+        let d = StarDict::open(Path::new(&format!("{}/test.ifo", dir))).unwrap();
+        assert_eq!(d.lookup("apple").unwrap(), vec!["['ap.l]\na fruit\nred"]);
+        assert_eq!(d.lookup("run").unwrap(), vec!["run"]);
+    }
+}
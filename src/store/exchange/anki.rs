@@ -0,0 +1,748 @@
+// Anki packages, .apkg for decks and .colpkg for a whole collection
+//
+// a package is a zip of the sqlite collection (schema 11, collection.anki2
+// or collection.anki21) and a media map. a note becomes a word with the
+// mapped fields, the first card of the note keeps the review state. the
+// attachments of a word are media files of the package, referred to in the
+// message field as [sound:..] or <img src="..">
+use super::{zip, Error, Options, Report};
+use crate::store::attachment::{self, Attachment};
+use crate::store::review::{Schedule, DAY};
+use crate::store::tags;
+use crate::store::{Entry, InternalError, Store, Transaction, World};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+const SCHEMA: &str = r#"
+CREATE TABLE col (
+    id integer primary key, crt integer not null, mod integer not null,
+    scm integer not null, ver integer not null, dty integer not null,
+    usn integer not null, ls integer not null, conf text not null,
+    models text not null, decks text not null, dconf text not null, tags text not null
+);
+CREATE TABLE notes (
+    id integer primary key, guid text not null, mid integer not null,
+    mod integer not null, usn integer not null, tags text not null,
+    flds text not null, sfld integer not null, csum integer not null,
+    flags integer not null, data text not null
+);
+CREATE TABLE cards (
+    id integer primary key, nid integer not null, did integer not null,
+    ord integer not null, mod integer not null, usn integer not null,
+    type integer not null, queue integer not null, due integer not null,
+    ivl integer not null, factor integer not null, reps integer not null,
+    lapses integer not null, left integer not null, odue integer not null,
+    odid integer not null, flags integer not null, data text not null
+);
+CREATE TABLE revlog (
+    id integer primary key, cid integer not null, usn integer not null,
+    ease integer not null, ivl integer not null, lastIvl integer not null,
+    factor integer not null, time integer not null, type integer not null
+);
+CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+CREATE INDEX ix_notes_csum on notes (csum);
+CREATE INDEX ix_cards_nid on cards (nid);
+"#;
+
+// the fields of a note are joined by the unit separator
+const FIELD_SEPARATOR: char = '\x1f';
+
+// card types
+const CARD_NEW: i64 = 0;
+const CARD_REVIEW: i64 = 2;
+
+/// where the word and its message are in the notes
+#[derive(Debug, Clone)]
+pub struct Mapping {
+    /// the note type, all note types when importing without it
+    pub note_type: Option<String>,
+    /// the field of the word name, the first field when not found
+    pub name_field: String,
+    /// the field of the message, the second field when not found
+    pub message_field: String,
+    /// the deck of the exported cards, the library name by default
+    pub deck: Option<String>,
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        Mapping {
+            note_type: None,
+            name_field: String::from("Front"),
+            message_field: String::from("Back"),
+            deck: None,
+        }
+    }
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend((data.len() as u64 * 8).to_be_bytes());
+
+    for chunk in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([
+                chunk[4 * i],
+                chunk[4 * i + 1],
+                chunk[4 * i + 2],
+                chunk[4 * i + 3],
+            ]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (x, y) in h.iter_mut().zip([a, b, c, d, e]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+
+    let mut res = [0u8; 20];
+    for (i, x) in h.iter().enumerate() {
+        res[4 * i..4 * i + 4].copy_from_slice(&x.to_be_bytes());
+    }
+    res
+}
+
+/// the checksum anki uses to find duplicated notes by the first field
+fn checksum(field: &str) -> i64 {
+    let h = sha1(strip_html(field).as_bytes());
+    u32::from_be_bytes([h[0], h[1], h[2], h[3]]) as i64
+}
+
+/// a stable note id, so that an exported word updates its note when imported again
+fn guid(library: &str, word: &str) -> String {
+    const TABLE: &[u8] =
+        b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!#$%&()*+,-./:;<=>?@[]^_`{|}~";
+    let h = sha1(format!("{}\x1f{}", library, word).as_bytes());
+    let mut n = u64::from_be_bytes([h[0], h[1], h[2], h[3], h[4], h[5], h[6], h[7]]);
+    let mut res = String::new();
+    while n > 0 {
+        res.push(TABLE[(n % TABLE.len() as u64) as usize] as char);
+        n /= TABLE.len() as u64;
+    }
+    res
+}
+
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+/// the text of a field, line breaks are kept
+pub fn strip_html(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('<') {
+        res.push_str(&rest[..i]);
+        let end = match rest[i..].find('>') {
+            Some(end) => i + end,
+            None => {
+                rest = &rest[i..];
+                break;
+            }
+        };
+        let tag = rest[i + 1..end].trim().to_lowercase();
+        let block = tag.starts_with("br") || tag == "div" || tag == "p";
+        if block && !res.is_empty() && !res.ends_with('\n') {
+            res.push('\n');
+        }
+        rest = &rest[end + 1..];
+    }
+    res.push_str(rest);
+    res.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_owned()
+}
+
+// the media files of a collection share one directory, so the name of an
+// attachment is prefixed by its word
+fn media_name(word: &str, file: &str) -> String {
+    let word: String = word
+        .chars()
+        .map(|c| match attachment::valid(&c.to_string()) {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    format!("{}_{}", word, file)
+}
+
+// the reference to the attachment in a field, None if anki can not play or show it
+fn media_ref(a: &Attachment, name: &str) -> Option<String> {
+    match (a.is_audio(), a.is_image()) {
+        (true, _) => Some(format!("[sound:{}]", name)),
+        (_, true) => Some(format!("<img src=\"{}\">", name)),
+        _ => None,
+    }
+}
+
+// the media files a field refers to, as [sound:a.mp3] or <img src="a.png">
+fn media_refs(field: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut rest = field;
+    while let Some(i) = rest.find("[sound:") {
+        rest = &rest[i + 7..];
+        if let Some(end) = rest.find(']') {
+            res.push(rest[..end].to_owned());
+            rest = &rest[end..];
+        }
+    }
+    let mut rest = field;
+    while let Some(i) = rest.find("<img") {
+        rest = &rest[i + 4..];
+        let tag = &rest[..rest.find('>').unwrap_or(rest.len())];
+        if let Some(j) = tag.find("src=") {
+            let v = &tag[j + 4..];
+            let (quote, v) = match v.chars().next() {
+                Some(q @ ('"' | '\'')) => (q, &v[1..]),
+                _ => (' ', v),
+            };
+            res.push(v[..v.find(quote).unwrap_or(v.len())].to_owned());
+        }
+    }
+    res
+}
+
+// the field without the [sound:..] references
+fn strip_sounds(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find("[sound:") {
+        let end = match rest[i..].find(']') {
+            Some(end) => i + end + 1,
+            None => break,
+        };
+        res.push_str(&rest[..i]);
+        rest = &rest[end..];
+    }
+    res.push_str(rest);
+    res
+}
+
+// a file in the temp directory removed when dropped, sqlite can only open files
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(ext: &str) -> TempFile {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let name = format!("wordlib-{}-{}.{}", std::process::id(), nanos, ext);
+        TempFile(std::env::temp_dir().join(name))
+    }
+
+    fn path(&self) -> String {
+        self.0.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn sql_err<E>(err: sqlite::Error) -> Error<E> {
+    Error::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("anki collection: {}", err),
+    ))
+}
+
+// the collection file in a package, newer packages are not readable by sqlite directly
+fn collection(archive: &zip::Archive) -> io::Result<Vec<u8>> {
+    if archive.contains("collection.anki21b") && !archive.contains("collection.anki21") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the package is compressed by a new anki, export it with \"support older anki versions\"",
+        ));
+    }
+    match archive.contains("collection.anki21") {
+        true => archive.read("collection.anki21"),
+        false => archive.read("collection.anki2"),
+    }
+}
+
+/// the entries of the notes in an anki package
+pub fn entries<E>(data: Vec<u8>, mapping: &Mapping) -> Result<Vec<Entry>, Error<E>> {
+    let archive = zip::Archive::new(data)?;
+    let file = TempFile::new("anki2");
+    std::fs::write(&file.0, collection(&archive)?)?;
+    let con = sqlite::open(file.path()).map_err(sql_err)?;
+
+    let mut stmt = con
+        .prepare("SELECT crt, models FROM col")
+        .map_err(sql_err)?;
+    if stmt.next().map_err(sql_err)? != sqlite::State::Row {
+        return Err(Error::Format(0, String::from("collection without col")));
+    }
+    let crt: i64 = stmt.read(0).map_err(sql_err)?;
+    // the files of the package by their media names
+    let media: HashMap<String, String> = match archive.contains("media") {
+        true => {
+            let map: Value = serde_json::from_slice(&archive.read("media")?)
+                .map_err(|e| Error::Format(0, format!("invalid media map, {}", e)))?;
+            map.as_object()
+                .into_iter()
+                .flatten()
+                .filter_map(|(file, name)| Some((name.as_str()?.to_owned(), file.clone())))
+                .collect()
+        }
+        false => HashMap::new(),
+    };
+    let models: Value = serde_json::from_str(&stmt.read::<String>(1).map_err(sql_err)?)
+        .map_err(|e| Error::Format(0, e.to_string()))?;
+
+    // field positions of the word and message by note type id
+    let mut fields: Vec<(i64, usize, usize)> = Vec::new();
+    for model in models.as_object().into_iter().flat_map(|m| m.values()) {
+        if let Some(name) = &mapping.note_type {
+            if model["name"].as_str() != Some(name) {
+                continue;
+            }
+        }
+        let names: Vec<&str> = model["flds"]
+            .as_array()
+            .map(|fs| fs.iter().filter_map(|f| f["name"].as_str()).collect())
+            .unwrap_or_default();
+        let pos = |field: &str, default| names.iter().position(|n| *n == field).unwrap_or(default);
+        let id = model["id"]
+            .as_i64()
+            .or_else(|| model["id"].as_str().and_then(|s| s.parse().ok()));
+        if let Some(id) = id {
+            fields.push((
+                id,
+                pos(&mapping.name_field, 0),
+                pos(&mapping.message_field, 1),
+            ));
+        }
+    }
+
+    // the first card of every note
+    let mut stmt = con
+        .prepare(
+            "SELECT n.mid, n.flds, c.type, c.due, c.ivl, c.factor, c.reps, n.tags FROM notes n \
+            LEFT JOIN cards c ON c.id = (SELECT id FROM cards WHERE nid=n.id ORDER BY ord LIMIT 1) \
+            ORDER BY n.id",
+        )
+        .map_err(sql_err)?;
+
+    let mut res = Vec::new();
+    while let sqlite::State::Row = stmt.next().map_err(sql_err)? {
+        let mid: i64 = stmt.read(0).map_err(sql_err)?;
+        let (name, message) = match fields.iter().find(|f| f.0 == mid) {
+            Some(&(_, name, message)) => (name, message),
+            None => continue,
+        };
+        let flds: String = stmt.read(1).map_err(sql_err)?;
+        let flds: Vec<&str> = flds.split(FIELD_SEPARATOR).collect();
+        let word = strip_html(flds.get(name).unwrap_or(&""));
+        if word.is_empty() {
+            continue;
+        }
+
+        let card = |i| stmt.read::<Option<i64>>(i).map_err(sql_err);
+        let schedule = match (card(2)?, card(3)?, card(4)?) {
+            (None, _, _) | (Some(CARD_NEW), _, _) => None,
+            (Some(kind), Some(due), Some(ivl)) => Some(Schedule {
+                // review cards are due in days since the collection was created,
+                // learning cards at a unix time
+                due: if kind == CARD_REVIEW {
+                    crt + due * DAY
+                } else {
+                    due
+                },
+                interval: ivl.max(0),
+                ease: card(5)?.filter(|f| *f > 0).unwrap_or(2500) as f64 / 1000.0,
+                reps: card(6)?.unwrap_or(0),
+            }),
+            _ => None,
+        };
+
+        let field = flds.get(message).unwrap_or(&"");
+        let mut attachments: Vec<Attachment> = Vec::new();
+        for name in media_refs(field) {
+            let file = match media.get(&name) {
+                Some(file) if archive.contains(file) => file,
+                _ => continue,
+            };
+            let prefix = media_name(&word, "");
+            let short = name.strip_prefix(&prefix).unwrap_or(&name);
+            if attachment::valid(short) && !attachments.iter().any(|a| a.name == short) {
+                attachments.push(Attachment::new(short, archive.read(file)?));
+            }
+        }
+        attachments.sort_by(|a, b| a.name.cmp(&b.name));
+
+        res.push(Entry {
+            word: World {
+                name: word,
+                message: strip_html(&strip_sounds(field)),
+            },
+            version: 1,
+            schedule,
+            tags: {
+                let tags: String = stmt.read(7).map_err(sql_err)?;
+                let mut tags: Vec<String> = tags
+                    .split_whitespace()
+                    .filter(|t| tags::valid(t))
+                    .map(|t| t.to_owned())
+                    .collect();
+                tags.sort();
+                tags.dedup();
+                tags
+            },
+            attachments,
+        });
+    }
+    Ok(res)
+}
+
+/// import the notes of an anki package into the store, with their review state
+pub fn import<T, E, F, S, R>(
+    store: &S,
+    mut reader: R,
+    mapping: &Mapping,
+    opts: &Options,
+) -> Result<Report, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+    R: io::Read,
+{
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let entries = entries(data, mapping)?;
+    super::import(store, entries.into_iter().map(Ok), opts)
+}
+
+fn note_type(id: i64, did: i64, now: i64, mapping: &Mapping) -> Value {
+    let field = |name: &str, ord: i64| {
+        json!({"name": name, "ord": ord, "sticky": false, "rtl": false,
+            "font": "Arial", "size": 20, "media": []})
+    };
+    let answer = format!(
+        "{{{{FrontSide}}}}<hr id=answer>{{{{{}}}}}",
+        mapping.message_field
+    );
+    json!({
+        "id": id,
+        "name": mapping.note_type.as_deref().unwrap_or("Word Library"),
+        "type": 0,
+        "mod": now,
+        "usn": -1,
+        "sortf": 0,
+        "did": did,
+        "tmpls": [{
+            "name": "Card 1", "ord": 0, "did": null, "bqfmt": "", "bafmt": "",
+            "qfmt": format!("{{{{{}}}}}", mapping.name_field),
+            "afmt": answer,
+        }],
+        "flds": [field(&mapping.name_field, 0), field(&mapping.message_field, 1)],
+        "css": ".card { font-family: arial; font-size: 20px; text-align: center; \
+            color: black; background-color: white; }",
+        "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\
+            \\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\
+            \\setlength{\\parindent}{0in}\n\\begin{document}\n",
+        "latexPost": "\\end{document}",
+        "tags": [],
+        "vers": [],
+        "req": [[0, "any", [0]]],
+    })
+}
+
+fn deck(id: i64, name: &str, desc: &str, now: i64) -> Value {
+    json!({
+        "id": id, "name": name, "desc": desc, "mod": now, "usn": -1, "conf": 1, "dyn": 0,
+        "collapsed": false, "browserCollapsed": false, "extendNew": 0, "extendRev": 0,
+        "newToday": [0, 0], "revToday": [0, 0], "lrnToday": [0, 0], "timeToday": [0, 0],
+    })
+}
+
+fn deck_conf() -> Value {
+    json!({"1": {
+        "id": 1, "name": "Default", "mod": 0, "usn": 0, "maxTaken": 60, "autoplay": true,
+        "timer": 0, "replayq": true, "dyn": false,
+        "new": {"bury": false, "delays": [1.0, 10.0], "initialFactor": 2500,
+            "ints": [1, 4, 0], "order": 1, "perDay": 20},
+        "rev": {"bury": false, "ease4": 1.3, "ivlFct": 1.0, "maxIvl": 36500,
+            "perDay": 200, "hardFactor": 1.2},
+        "lapse": {"delays": [10.0], "leechAction": 1, "leechFails": 8, "minInt": 1, "mult": 0.0},
+    }})
+}
+
+/// export every word of the store to an anki package, returns the number of words
+pub fn export<T, E, F, S, W>(
+    store: &S,
+    library: &str,
+    w: W,
+    mapping: &Mapping,
+) -> Result<usize, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+    W: io::Write,
+{
+    let file = TempFile::new("anki2");
+    let mut media = Vec::new();
+    let count = write_collection(store, library, &file.path(), mapping, &mut media)?;
+
+    let mut zip = zip::Writer::new(w);
+    zip.add("collection.anki2", &std::fs::read(&file.0)?)?;
+    // the media files are numbered in the package, the map gives their names
+    let mut map = serde_json::Map::new();
+    for (i, (name, data)) in media.iter().enumerate() {
+        zip.add(&i.to_string(), data)?;
+        map.insert(i.to_string(), Value::String(name.clone()));
+    }
+    zip.add("media", Value::Object(map).to_string().as_bytes())?;
+    zip.finish()?;
+    Ok(count)
+}
+
+fn write_collection<T, E, F, S>(
+    store: &S,
+    library: &str,
+    path: &str,
+    mapping: &Mapping,
+    media: &mut Vec<(String, Vec<u8>)>,
+) -> Result<usize, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    let con = sqlite::open(path).map_err(sql_err)?;
+    con.execute(SCHEMA).map_err(sql_err)?;
+    con.execute("BEGIN").map_err(sql_err)?;
+
+    let now = crate::store::review::now();
+    // the collection is created today, cards are due in days since then
+    let crt = now - now % DAY;
+    let (mid, did) = (now * 1000, now * 1000 + 1);
+    let desc = store
+        .metadata()?
+        .into_iter()
+        .find(|(k, _)| k == "description")
+        .map(|(_, v)| v)
+        .unwrap_or_default();
+    let deck_name = mapping.deck.as_deref().unwrap_or(library);
+
+    let mut count = 0;
+    let mut after = String::new();
+    loop {
+        let ws = store.scan(&after, 1000)?;
+        for word in &ws {
+            let entry = store.entry(&word.name)?;
+            let id = now * 1000 + 2 + count as i64;
+            let mut message = escape_html(&entry.word.message);
+            for a in entry.attachments {
+                let name = media_name(&entry.word.name, &a.name);
+                if let Some(r) = media_ref(&a, &name) {
+                    message.push_str("<br>");
+                    message.push_str(&r);
+                    media.push((name, a.data));
+                }
+            }
+            let flds = format!(
+                "{}{}{}",
+                escape_html(&entry.word.name),
+                FIELD_SEPARATOR,
+                message
+            );
+            // anki keeps the tags between spaces
+            let anki_tags = match entry.tags.is_empty() {
+                true => String::new(),
+                false => format!(" {} ", entry.tags.join(" ")),
+            };
+            let mut stmt = con
+                .prepare("INSERT INTO notes VALUES (?, ?, ?, ?, -1, ?, ?, ?, ?, 0, '')")
+                .and_then(|s| s.bind(1, id))
+                .and_then(|s| s.bind(2, guid(library, &entry.word.name).as_str()))
+                .and_then(|s| s.bind(3, mid))
+                .and_then(|s| s.bind(4, now))
+                .and_then(|s| s.bind(5, anki_tags.as_str()))
+                .and_then(|s| s.bind(6, flds.as_str()))
+                .and_then(|s| s.bind(7, entry.word.name.as_str()))
+                .and_then(|s| s.bind(8, checksum(&entry.word.name)))
+                .map_err(sql_err)?;
+            stmt.next().map_err(sql_err)?;
+
+            // (type, queue, due, ivl, factor, reps)
+            let card = match &entry.schedule {
+                Some(s) if s.reps > 0 => (
+                    CARD_REVIEW,
+                    2,
+                    ((s.due - crt) as f64 / DAY as f64).round() as i64,
+                    s.interval,
+                    (s.ease * 1000.0) as i64,
+                    s.reps,
+                ),
+                _ => (CARD_NEW, 0, count as i64 + 1, 0, 2500, 0),
+            };
+            let mut stmt = con
+                .prepare(
+                    "INSERT INTO cards VALUES (?, ?, ?, 0, ?, -1, ?, ?, ?, ?, ?, ?, 0, 0, 0, 0, 0, '')",
+                )
+                .and_then(|s| s.bind(1, id))
+                .and_then(|s| s.bind(2, id))
+                .and_then(|s| s.bind(3, did))
+                .and_then(|s| s.bind(4, now))
+                .and_then(|s| s.bind(5, card.0))
+                .and_then(|s| s.bind(6, card.1))
+                .and_then(|s| s.bind(7, card.2))
+                .and_then(|s| s.bind(8, card.3))
+                .and_then(|s| s.bind(9, card.4))
+                .and_then(|s| s.bind(10, card.5))
+                .map_err(sql_err)?;
+            stmt.next().map_err(sql_err)?;
+            count += 1;
+        }
+        match ws.into_iter().last() {
+            Some(word) => after = word.name,
+            None => break,
+        }
+    }
+
+    let conf = json!({
+        "nextPos": count + 1, "estTimes": true, "activeDecks": [1], "sortType": "noteFld",
+        "timeLim": 0, "sortBackwards": false, "addToCur": true, "curDeck": did,
+        "newBury": true, "newSpread": 0, "dueCounts": true, "curModel": mid.to_string(),
+        "collapseTime": 1200,
+    });
+    let models = json!({ mid.to_string(): note_type(mid, did, now, mapping) });
+    let decks = json!({
+        "1": deck(1, "Default", "", now),
+        did.to_string(): deck(did, deck_name, &desc, now),
+    });
+    let mut stmt = con
+        .prepare("INSERT INTO col VALUES (1, ?, ?, ?, 11, 0, 0, 0, ?, ?, ?, ?, '{}')")
+        .and_then(|s| s.bind(1, crt))
+        .and_then(|s| s.bind(2, now * 1000))
+        .and_then(|s| s.bind(3, now * 1000))
+        .and_then(|s| s.bind(4, conf.to_string().as_str()))
+        .and_then(|s| s.bind(5, models.to_string().as_str()))
+        .and_then(|s| s.bind(6, decks.to_string().as_str()))
+        .and_then(|s| s.bind(7, deck_conf().to_string().as_str()))
+        .map_err(sql_err)?;
+    stmt.next().map_err(sql_err)?;
+    drop(stmt);
+
+    con.execute("COMMIT").map_err(sql_err)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::sqlite::store::Sqlite;
+    use crate::store::{Read, Write};
+
+    #[test]
+    fn test_helpers() {
+        let h: String = sha1(b"abc").iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(h, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            strip_html("a&amp;b<br>c <b>d</b><div>e</div>"),
+            "a&b\nc d\ne"
+        );
+        assert_eq!(strip_html(&escape_html("x < y\n&")), "x < y\n&");
+        assert_eq!(guid("lib", "word"), guid("lib", "word"));
+        assert_ne!(guid("lib", "word"), guid("lib", "other"));
+    }
+
+    #[test]
+    fn test_export_import() {
+        let src_path = "target/test_anki_src.sql";
+        let dst_path = "target/test_anki_dst.sql";
+        for p in [src_path, dst_path] {
+            let _ = std::fs::remove_file(p);
+        }
+
+        let src = Sqlite::new(src_path.to_owned()).unwrap();
+        for name in ["apple", "book"] {
+            src.insert(&World {
+                name: name.to_owned(),
+                message: format!("{} <means>\nsecond line", name),
+            })
+            .unwrap();
+        }
+        let now = crate::store::review::now();
+        let reviewed = Schedule::default().next(4, now).next(4, now + DAY);
+        src.set_schedule("book", &reviewed).unwrap();
+        src.add_tag("apple", "fruit").unwrap();
+        let sound = Attachment::new("apple.mp3", vec![73, 68, 51]);
+        src.put_attachment("apple", &sound).unwrap();
+        src.put_attachment("apple", &Attachment::new("apple.txt", vec![1]))
+            .unwrap();
+
+        let mapping = Mapping {
+            note_type: Some(String::from("Vocabulary")),
+            name_field: String::from("Word"),
+            message_field: String::from("Meaning"),
+            ..Mapping::default()
+        };
+        let mut package = Vec::new();
+        assert_eq!(export(&src, "english", &mut package, &mapping).unwrap(), 2);
+
+        let dst = Sqlite::new(dst_path.to_owned()).unwrap();
+        let report = import(&dst, package.as_slice(), &mapping, &Options::default()).unwrap();
+        assert_eq!(report.inserted, 2);
+
+        assert_eq!(
+            dst.get("apple").unwrap().message,
+            "apple <means>\nsecond line"
+        );
+        let apple = dst.entry("apple").unwrap();
+        assert!(apple.schedule.is_none());
+        assert_eq!(apple.tags, vec![String::from("fruit")]);
+        // a file anki can not play is left out
+        assert_eq!(apple.attachments, vec![sound]);
+        let s = dst.entry("book").unwrap().schedule.unwrap();
+        assert_eq!((s.interval, s.reps), (reviewed.interval, reviewed.reps));
+        assert!((s.due - reviewed.due).abs() <= DAY / 2);
+
+        // notes of other note types are not imported
+        let other = Mapping {
+            note_type: Some(String::from("Basic")),
+            ..Mapping::default()
+        };
+        assert_eq!(entries::<()>(package, &other).unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,341 @@
+// the import and export commands of wordlib
+use super::{anki, csv, ereader, extract, glossary, jsonl, Duplicate, Options};
+use crate::db::sqlite::store::{Sqlite, Tansaction};
+use crate::store::dict::Dictionaries;
+use crate::store::{WordLibrary, World};
+use std::io::{BufReader, BufWriter};
+
+pub const USAGE: &str = r#"  import <library> <file> [options]
+                     import a csv, tsv or anki package (.apkg, .colpkg) into the library file
+      --tsv | --csv          file format, by the file extension when not given
+      --no-header            the file has no header line
+      --name <column>        column of the word, index from 0 or header name
+      --message <columns>    comma separated columns joined into the message
+      --note-type <name>     anki note type, all note types by default
+      --name-field <field>   anki field of the word, default Front
+      --message-field <field>
+                             anki field of the message, default Back
+      --dup <mode>           skip, overwrite or merge duplicated words, default skip
+      --dry-run              show what would be imported without writing
+  export <library> <file> [options]
+                     export the words of the library file to csv, tsv or anki package
+      --deck <name>          anki deck, the library name by default
+  lookups <library> <database> [--with <path>]... [--dup <mode>] [--dry-run]
+                     import the lookups of a kindle vocab.db or koreader vocabulary builder,
+                     words in the library files or workspaces of --with are skipped
+  extract <file> [--with <path>]... [options]
+                     list the words of a text, srt, ass or epub file in no library of --with,
+                     lemmatized and ranked by count with example sentences
+      --top <n>              only the n most frequent words
+      --min-count <n>        words appearing at least n times
+      --sentences <n>        example sentences of every word, default 2
+      --common               keep common words like "the"
+      --add <library>        add the words to the library with their sentences
+      --words <words>        comma separated words to add, all listed words by default
+  glossary <library> <file> [options]
+                     export the words as a markdown or html (.html, .htm) glossary
+      --title <title>        title of the glossary, the library name by default
+      --sheet                a two-column "word | meaning" study sheet
+      --template <file>      sections replacing the default template
+      --search <pattern>     only the words matching the fuzzy search
+      --words <words>        only the comma separated words
+  dump <path> [file]
+                     dump a library file or workspace as json lines, to stdout without file
+  restore <file> <dir> [--overwrite]
+                     recreate the library files of a dump under dir"#;
+
+// words shown by a dry run
+const PREVIEW: usize = 20;
+
+struct Args {
+    anki: bool,
+    format: csv::Format,
+    mapping: anki::Mapping,
+    opts: Options,
+}
+
+fn parse_args(path: &str, args: &[String]) -> Result<Args, String> {
+    let mut res = Args {
+        anki: super::is_anki(path),
+        format: csv::Format::from_path(path),
+        mapping: anki::Mapping::default(),
+        opts: Options::default(),
+    };
+    let (format, mapping) = (&mut res.format, &mut res.mapping);
+
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let mut value = || it.next().ok_or_else(|| format!("missing value of {}", arg));
+        match arg.as_str() {
+            "--tsv" => format.delimiter = '\t',
+            "--csv" => format.delimiter = ',',
+            "--no-header" => format.headers = false,
+            "--name" => format.name = value()?.parse()?,
+            "--message" => {
+                format.message = value()?
+                    .split(',')
+                    .map(|c| c.trim().parse())
+                    .collect::<Result<_, _>>()?
+            }
+            "--note-type" => mapping.note_type = Some(value()?.clone()),
+            "--name-field" => mapping.name_field = value()?.clone(),
+            "--message-field" => mapping.message_field = value()?.clone(),
+            "--deck" => mapping.deck = Some(value()?.clone()),
+            "--dup" => res.opts.duplicate = value()?.parse::<Duplicate>()?,
+            "--dry-run" => {
+                res.opts.dry_run = true;
+                res.opts.preview = PREVIEW;
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    Ok(res)
+}
+
+/// import <library> <file> [options]
+pub fn import(args: &[String]) -> Result<(), String> {
+    if args.len() < 2 {
+        return Err(USAGE.to_owned());
+    }
+    let a = parse_args(&args[1], &args[2..])?;
+
+    let file = std::fs::File::open(&args[1]).map_err(|e| format!("open {}: {}", args[1], e))?;
+    let store = Sqlite::new(args[0].clone()).map_err(|e| e.to_string())?;
+    let report = if a.anki {
+        anki::import(&store, file, &a.mapping, &a.opts)
+    } else {
+        csv::import(&store, BufReader::new(file), &a.format, &a.opts)
+    }
+    .map_err(|e| format!("import fail, nothing is written, {}", e))?;
+
+    for (action, word) in &report.preview {
+        println!("{:<10}{}", action, word.name);
+    }
+    if a.opts.dry_run {
+        println!("dry run: {}", report);
+    } else {
+        println!("{}", report);
+    }
+    Ok(())
+}
+
+/// export <library> <file> [options]
+pub fn export(args: &[String]) -> Result<(), String> {
+    if args.len() < 2 {
+        return Err(USAGE.to_owned());
+    }
+    let a = parse_args(&args[1], &args[2..])?;
+
+    let store = Sqlite::new(args[0].clone()).map_err(|e| e.to_string())?;
+    let file = std::fs::File::create(&args[1]).map_err(|e| format!("create {}: {}", args[1], e))?;
+    let count = if a.anki {
+        let name = jsonl::library_name(&args[0]);
+        anki::export(&store, &name, BufWriter::new(file), &a.mapping)
+    } else {
+        csv::export(&store, BufWriter::new(file), &a.format)
+    }
+    .map_err(|e| e.to_string())?;
+    println!("{} words exported to {}", count, args[1]);
+    Ok(())
+}
+
+type Library = WordLibrary<World, sqlite::Error, Tansaction>;
+
+// open the library files or workspaces into lib
+fn open(lib: &mut Library, path: &str) -> Result<(), String> {
+    for file in crate::store::library_files(path).map_err(|e| e.to_string())? {
+        if !lib.contains(&file) {
+            let store = Sqlite::new(file.clone()).map_err(|e| e.to_string())?;
+            lib.add_store(&file, Box::new(store));
+        }
+    }
+    Ok(())
+}
+
+// open the paths of --with, the other arguments are returned
+fn open_with(lib: &mut Library, args: &[String]) -> Result<Vec<String>, String> {
+    let mut rest = Vec::new();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        if arg != "--with" {
+            rest.push(arg.clone());
+            continue;
+        }
+        open(lib, it.next().ok_or("missing value of --with")?)?;
+    }
+    Ok(rest)
+}
+
+/// lookups <library> <database> [--with <path>]... [--dup <mode>] [--dry-run]
+pub fn lookups(args: &[String]) -> Result<(), String> {
+    if args.len() < 2 {
+        return Err(USAGE.to_owned());
+    }
+
+    let mut lib = WordLibrary::new();
+    let store = Sqlite::new(args[0].clone()).map_err(|e| e.to_string())?;
+    lib.add_store(&args[0], Box::new(store));
+    let rest = open_with(&mut lib, &args[2..])?;
+    let a = parse_args(&args[1], &rest)?;
+
+    let report = ereader::import(&lib, &args[0], &args[1], &a.opts)
+        .map_err(|e| format!("import fail, nothing is written, {}", e))?;
+    for (action, word) in &report.preview {
+        println!("{:<10}{}", action, word.name);
+    }
+    if a.opts.dry_run {
+        println!("dry run: {}", report);
+    } else {
+        println!("{}", report);
+    }
+    Ok(())
+}
+
+/// extract <file> [--with <path>]... [options]
+pub fn extract(args: &[String]) -> Result<(), String> {
+    if args.is_empty() {
+        return Err(USAGE.to_owned());
+    }
+    let mut lib = WordLibrary::new();
+    let rest = open_with(&mut lib, &args[1..])?;
+
+    let mut opts = extract::Options::default();
+    let (mut top, mut library, mut words) = (None, None, None);
+    let mut import = Vec::new();
+    let mut it = rest.iter();
+    while let Some(arg) = it.next() {
+        let mut value = || it.next().ok_or_else(|| format!("missing value of {}", arg));
+        let number = |v: &String| {
+            v.parse::<usize>()
+                .map_err(|e| format!("{} {}: {}", arg, v, e))
+        };
+        match arg.as_str() {
+            "--top" => top = Some(number(value()?)?),
+            "--min-count" => opts.min_count = number(value()?)?,
+            "--sentences" => opts.sentences = number(value()?)?,
+            "--common" => opts.common = true,
+            "--add" => library = Some(value()?.clone()),
+            "--words" => {
+                let ws: Vec<String> = value()?.split(',').map(|w| w.trim().to_owned()).collect();
+                words = Some(ws)
+            }
+            _ => import.push(arg.clone()),
+        }
+    }
+    if let Some(library) = &library {
+        open(&mut lib, library)?;
+    }
+    let a = parse_args(&args[0], &import)?;
+
+    let text = extract::read_text(&args[0]).map_err(|e| format!("read {}: {}", args[0], e))?;
+    let (dicts, errs) = Dictionaries::load();
+    for err in errs {
+        eprintln!("{}", err);
+    }
+    let mut found =
+        extract::extract(&lib, &text, Some(&dicts), &opts).map_err(|e| e.to_string())?;
+    if let Some(top) = top {
+        found.truncate(top);
+    }
+    if let Some(words) = &words {
+        found.retain(|u| words.contains(&u.word));
+    }
+
+    let library = match library {
+        Some(library) => library,
+        None => {
+            for u in &found {
+                let sentence = u.sentences.first().map(|s| s.as_str()).unwrap_or("");
+                println!("{:>6}  {:<20}{}", u.count, u.word, sentence);
+            }
+            return Ok(());
+        }
+    };
+    let report = extract::add(&lib, &library, &found, &a.opts)
+        .map_err(|e| format!("add fail, nothing is written, {}", e))?;
+    for (action, word) in &report.preview {
+        println!("{:<10}{}", action, word.name);
+    }
+    if a.opts.dry_run {
+        println!("dry run: {}", report);
+    } else {
+        println!("{}", report);
+    }
+    Ok(())
+}
+
+/// glossary <library> <file> [options]
+pub fn glossary(args: &[String]) -> Result<(), String> {
+    if args.len() < 2 {
+        return Err(USAGE.to_owned());
+    }
+    let style = glossary::Style::from_path(&args[1]);
+    let mut opts = glossary::Options::new(&jsonl::library_name(&args[0]), style);
+    let (mut pattern, mut names) = (None, None);
+    let mut it = args[2..].iter();
+    while let Some(arg) = it.next() {
+        let mut value = || it.next().ok_or_else(|| format!("missing value of {}", arg));
+        match arg.as_str() {
+            "--title" => opts.title = value()?.clone(),
+            "--sheet" => opts.layout = glossary::Layout::Sheet,
+            "--template" => opts.template = glossary::Template::from_file(style, value()?)?,
+            "--search" => pattern = Some(value()?.clone()),
+            "--words" => {
+                let ws: Vec<String> = value()?.split(',').map(|w| w.trim().to_owned()).collect();
+                names = Some(ws)
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+
+    let store = Sqlite::new(args[0].clone()).map_err(|e| e.to_string())?;
+    let words = match (&pattern, &names) {
+        (_, Some(names)) => glossary::select(&store, names).map_err(|e| e.to_string())?,
+        (Some(pattern), None) => {
+            let ws = glossary::words(&store).map_err(|e| e.to_string())?;
+            crate::store::search::fuzzy(ws, pattern, usize::MAX)
+        }
+        (None, None) => glossary::words(&store).map_err(|e| e.to_string())?,
+    };
+    let count =
+        glossary::export::<sqlite::Error>(&words, &args[1], &opts).map_err(|e| e.to_string())?;
+    println!("{} words exported to {}", count, args[1]);
+    Ok(())
+}
+
+/// dump <path> [file]
+pub fn dump(args: &[String]) -> Result<(), String> {
+    let res = match args {
+        [path] => jsonl::dump(path, std::io::stdout().lock()),
+        [path, file] => {
+            let f = std::fs::File::create(file).map_err(|e| format!("create {}: {}", file, e))?;
+            jsonl::dump(path, BufWriter::new(f))
+        }
+        _ => return Err(USAGE.to_owned()),
+    };
+    let count = res.map_err(|e| e.to_string())?;
+    if args.len() == 2 {
+        println!("{} words dumped to {}", count, args[1]);
+    }
+    Ok(())
+}
+
+/// restore <file> <dir> [--overwrite]
+pub fn restore(args: &[String]) -> Result<(), String> {
+    let overwrite = match args.get(2).map(|s| s.as_str()) {
+        None => false,
+        Some("--overwrite") => true,
+        Some(arg) => return Err(format!("unknown option {}", arg)),
+    };
+    if args.len() < 2 {
+        return Err(USAGE.to_owned());
+    }
+
+    let file = std::fs::File::open(&args[0]).map_err(|e| format!("open {}: {}", args[0], e))?;
+    let libs = jsonl::restore(BufReader::new(file), &args[1], overwrite)
+        .map_err(|e| format!("restore fail, {}", e))?;
+    for (name, count) in libs {
+        println!("{:<20}{} words", name, count);
+    }
+    Ok(())
+}
@@ -0,0 +1,377 @@
+// CSV and TSV files, quoted as RFC 4180
+//
+// records are read one by one from the file, and exported words are read
+// from the store batch by batch, so big files are never held in memory
+use super::{Error, Options, Report};
+use crate::store::{Entry, InternalError, Store, Transaction, World};
+use std::io::{self, BufRead};
+
+// words read from the store at once when exporting
+const BATCH: i64 = 1000;
+
+/// a column of the file, by 0-based position or header name
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+impl std::str::FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(String::from("empty column"));
+        }
+        Ok(match s.parse::<usize>() {
+            Ok(i) => Column::Index(i),
+            Err(_) => Column::Name(s.to_owned()),
+        })
+    }
+}
+
+/// the layout of a file and where the World fields are in it
+#[derive(Debug, Clone)]
+pub struct Format {
+    pub delimiter: char,
+    // the first record is the header
+    pub headers: bool,
+    pub name: Column,
+    // the message is joined from these columns
+    pub message: Vec<Column>,
+    pub separator: String,
+}
+
+impl Format {
+    pub fn csv() -> Format {
+        Format {
+            delimiter: ',',
+            headers: true,
+            name: Column::Index(0),
+            message: vec![Column::Index(1)],
+            separator: String::from("\n"),
+        }
+    }
+
+    pub fn tsv() -> Format {
+        Format {
+            delimiter: '\t',
+            ..Format::csv()
+        }
+    }
+
+    /// tsv for .tsv and .tab files, csv for the others
+    pub fn from_path(path: &str) -> Format {
+        let ext = std::path::Path::new(path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        match ext.as_deref() {
+            Some("tsv") | Some("tab") => Format::tsv(),
+            _ => Format::csv(),
+        }
+    }
+
+    fn index(&self, column: &Column, header: &[String]) -> io::Result<usize> {
+        match column {
+            Column::Index(i) => Ok(*i),
+            Column::Name(name) => header.iter().position(|h| h == name).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("column {} not found in header", name),
+                )
+            }),
+        }
+    }
+
+    fn header_name(column: &Column, default: &str) -> String {
+        match column {
+            Column::Name(name) => name.clone(),
+            Column::Index(_) => default.to_owned(),
+        }
+    }
+}
+
+/// a streaming reader of delimited records
+pub struct Reader<R> {
+    inner: R,
+    delimiter: char,
+    // lines read, a quoted field may take many lines
+    line: usize,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(inner: R, delimiter: char) -> Reader<R> {
+        Reader {
+            inner,
+            delimiter,
+            line: 0,
+        }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// the next record, blank lines are skipped
+    pub fn read_record(&mut self) -> io::Result<Option<Vec<String>>> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut buf = String::new();
+
+        loop {
+            buf.clear();
+            if self.inner.read_line(&mut buf)? == 0 {
+                if quoted {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "quoted field is not closed",
+                    ));
+                }
+                return Ok(None);
+            }
+            self.line += 1;
+
+            let mut line = buf.trim_end_matches(['\n', '\r']);
+            if self.line == 1 {
+                // excel writes a byte order mark
+                line = line.trim_start_matches('\u{feff}');
+            }
+            if !quoted && fields.is_empty() && line.is_empty() {
+                continue;
+            }
+
+            let mut at_start = !quoted;
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                if quoted {
+                    if c == '"' {
+                        if chars.peek() == Some(&'"') {
+                            chars.next();
+                            field.push('"');
+                        } else {
+                            quoted = false;
+                        }
+                    } else {
+                        field.push(c);
+                    }
+                } else if c == '"' && at_start {
+                    quoted = true;
+                    at_start = false;
+                } else if c == self.delimiter {
+                    fields.push(std::mem::take(&mut field));
+                    at_start = true;
+                } else {
+                    field.push(c);
+                    at_start = false;
+                }
+            }
+
+            if quoted {
+                field.push('\n');
+                continue;
+            }
+            fields.push(field);
+            return Ok(Some(fields));
+        }
+    }
+}
+
+/// write one record, quoting the fields when needed
+pub fn write_record<W: io::Write>(w: &mut W, fields: &[&str], delimiter: char) -> io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(w, "{}", delimiter)?;
+        }
+        if field.contains([delimiter, '"', '\n', '\r']) {
+            write!(w, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            w.write_all(field.as_bytes())?;
+        }
+    }
+    w.write_all(b"\n")
+}
+
+/// the words of a file in the format, one by one
+pub fn words<R: BufRead, E>(
+    reader: R,
+    format: &Format,
+) -> io::Result<impl Iterator<Item = Result<Entry, Error<E>>>> {
+    let mut reader = Reader::new(reader, format.delimiter);
+
+    let header = if format.headers {
+        reader.read_record()?.unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let name = format.index(&format.name, &header)?;
+    let message = format
+        .message
+        .iter()
+        .map(|c| format.index(c, &header))
+        .collect::<io::Result<Vec<usize>>>()?;
+    let separator = format.separator.clone();
+
+    Ok(std::iter::from_fn(move || {
+        let record = match reader.read_record() {
+            Ok(Some(record)) => record,
+            Ok(None) => return None,
+            Err(err) => return Some(Err(Error::Format(reader.line(), err.to_string()))),
+        };
+
+        let word = match record.get(name).map(|s| s.trim()) {
+            Some(w) if !w.is_empty() => w.to_owned(),
+            _ => {
+                let msg = format!("no word in column {}", name);
+                return Some(Err(Error::Format(reader.line(), msg)));
+            }
+        };
+        let parts: Vec<&str> = message
+            .iter()
+            .filter_map(|i| record.get(*i))
+            .map(|s| s.as_str())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        Some(Ok(Entry::from(World {
+            name: word,
+            message: parts.join(&separator),
+        })))
+    }))
+}
+
+/// import a CSV or TSV file into the store
+pub fn import<T, E, F, S, R>(
+    store: &S,
+    reader: R,
+    format: &Format,
+    opts: &Options,
+) -> Result<Report, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+    R: BufRead,
+{
+    super::import(store, words(reader, format)?, opts)
+}
+
+/// export every word of the store, returns the number of words
+pub fn export<T, E, F, S, W>(store: &S, mut w: W, format: &Format) -> Result<usize, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+    W: io::Write,
+{
+    if format.headers {
+        let message = match format.message.first() {
+            Some(c) => Format::header_name(c, "message"),
+            None => String::from("message"),
+        };
+        let name = Format::header_name(&format.name, "name");
+        write_record(&mut w, &[&name, &message], format.delimiter)?;
+    }
+
+    let mut count = 0;
+    let mut after = String::new();
+    loop {
+        let ws = store.scan(&after, BATCH)?;
+        for word in &ws {
+            write_record(&mut w, &[&word.name, &word.message], format.delimiter)?;
+        }
+        count += ws.len();
+        match ws.into_iter().last() {
+            Some(word) => after = word.name,
+            None => break,
+        }
+    }
+    w.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::sqlite::store::Sqlite;
+    use crate::store::exchange::{Action, Duplicate};
+    use crate::store::{Read, Write};
+
+    #[test]
+    fn test_reader() {
+        let data = "\u{feff}word,message\n\napple,\"a fruit, red\"\n\"say \"\"hi\"\"\",\"line1\r\nline2\"\n";
+        let mut r = Reader::new(data.as_bytes(), ',');
+        assert_eq!(r.read_record().unwrap().unwrap(), vec!["word", "message"]);
+        assert_eq!(
+            r.read_record().unwrap().unwrap(),
+            vec!["apple", "a fruit, red"]
+        );
+        assert_eq!(
+            r.read_record().unwrap().unwrap(),
+            vec!["say \"hi\"", "line1\nline2"]
+        );
+        assert_eq!(r.line(), 5);
+        assert!(r.read_record().unwrap().is_none());
+
+        let mut out = Vec::new();
+        write_record(&mut out, &["say \"hi\"", "a,b", "c"], ',').unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\"say \"\"hi\"\"\",\"a,b\",c\n"
+        );
+
+        let mut r = Reader::new("a,\"b\n".as_bytes(), ',');
+        assert!(r.read_record().is_err());
+    }
+
+    #[test]
+    fn test_import_export() {
+        let path = "target/test_csv.sql";
+        let _ = std::fs::remove_file(path);
+        let sql = Sqlite::new(path.to_owned()).unwrap();
+        sql.insert(&World {
+            name: String::from("apple"),
+            message: String::from("a fruit"),
+        })
+        .unwrap();
+
+        let data = "pos\tword\tmeaning\nn\tapple\tred\nn\tbook\tto read\n";
+        let format = Format {
+            name: Column::Name(String::from("word")),
+            message: vec![Column::Name(String::from("meaning")), Column::Index(0)],
+            ..Format::tsv()
+        };
+
+        // dry run keeps the store unchanged
+        let opts = Options {
+            duplicate: Duplicate::Merge,
+            dry_run: true,
+            preview: 1,
+            ..Options::default()
+        };
+        let report = import(&sql, data.as_bytes(), &format, &opts).unwrap();
+        assert_eq!((report.inserted, report.merged), (1, 1));
+        assert_eq!(report.preview.len(), 1);
+        assert_eq!(report.preview[0].0, Action::Merge);
+        assert!(matches!(sql.get("book"), Err(InternalError::NotFound)));
+
+        let opts = Options {
+            duplicate: Duplicate::Merge,
+            ..Options::default()
+        };
+        import(&sql, data.as_bytes(), &format, &opts).unwrap();
+        assert_eq!(sql.get("apple").unwrap().message, "a fruit\nred\nn");
+        assert_eq!(sql.get("book").unwrap().message, "to read\nn");
+
+        // a bad row rolls back the whole file
+        let bad = "pos\tword\tmeaning\nn\tcat\tanimal\nn\t\tnothing\n";
+        let err = import(&sql, bad.as_bytes(), &format, &opts).unwrap_err();
+        assert!(matches!(err, Error::Format(3, _)));
+        assert!(matches!(sql.get("cat"), Err(InternalError::NotFound)));
+
+        let mut out = Vec::new();
+        assert_eq!(export(&sql, &mut out, &Format::csv()).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "name,message\napple,\"a fruit\nred\nn\"\nbook,\"to read\nn\"\n"
+        );
+    }
+}
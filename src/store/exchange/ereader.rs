@@ -0,0 +1,264 @@
+// lookup history of e-readers: the Kindle vocab.db and the
+// vocabulary_builder.sqlite3 of KOReader
+//
+// every looked-up stem becomes one word, the sentences it was read in and
+// the book titles are the message
+use super::{Error, Options, Report};
+use crate::store::{Entry, InternalError, Transaction, WordLibrary, World};
+use std::io;
+
+/// a word looked up while reading
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lookup {
+    pub stem: String,
+    pub word: String,
+    pub sentence: String,
+    pub book: String,
+}
+
+fn sql_err<E>(err: sqlite::Error) -> Error<E> {
+    Error::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("e-reader database: {}", err),
+    ))
+}
+
+fn tables(con: &sqlite::Connection) -> Result<Vec<String>, sqlite::Error> {
+    let mut stmt = con.prepare("SELECT lower(name) FROM sqlite_master WHERE type='table'")?;
+    let mut res = Vec::new();
+    while let sqlite::State::Row = stmt.next()? {
+        res.push(stmt.read(0)?);
+    }
+    Ok(res)
+}
+
+fn columns(con: &sqlite::Connection, table: &str) -> Result<Vec<String>, sqlite::Error> {
+    let mut stmt = con.prepare(format!("PRAGMA table_info({})", table))?;
+    let mut res = Vec::new();
+    while let sqlite::State::Row = stmt.next()? {
+        res.push(stmt.read(1)?);
+    }
+    Ok(res)
+}
+
+fn kindle(con: &sqlite::Connection) -> Result<Vec<Lookup>, sqlite::Error> {
+    let mut stmt = con.prepare(
+        "SELECT w.stem, w.word, l.usage, b.title FROM LOOKUPS l \
+        JOIN WORDS w ON l.word_key = w.id LEFT JOIN BOOK_INFO b ON l.book_key = b.id \
+        ORDER BY l.timestamp",
+    )?;
+    let mut res = Vec::new();
+    while let sqlite::State::Row = stmt.next()? {
+        let word: String = stmt.read(1)?;
+        let stem = stmt.read::<Option<String>>(0)?.unwrap_or_default();
+        res.push(Lookup {
+            stem: if stem.is_empty() { word.clone() } else { stem },
+            word,
+            sentence: stmt.read::<Option<String>>(2)?.unwrap_or_default(),
+            book: stmt.read::<Option<String>>(3)?.unwrap_or_default(),
+        });
+    }
+    Ok(res)
+}
+
+fn koreader(con: &sqlite::Connection) -> Result<Vec<Lookup>, sqlite::Error> {
+    // older versions keep the title in the word row
+    let book = if columns(con, "vocabulary")?.iter().any(|c| c == "title_id") {
+        "(SELECT name FROM title t WHERE t.id = v.title_id)"
+    } else {
+        "v.book_title"
+    };
+    let mut stmt = con.prepare(format!(
+        "SELECT v.word, v.prev_context, v.next_context, {} FROM vocabulary v ORDER BY v.create_time",
+        book
+    ))?;
+
+    let mut res = Vec::new();
+    while let sqlite::State::Row = stmt.next()? {
+        let word: String = stmt.read(0)?;
+        let prev = stmt.read::<Option<String>>(1)?.unwrap_or_default();
+        let next = stmt.read::<Option<String>>(2)?.unwrap_or_default();
+        let sentence = if prev.is_empty() && next.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}{}", prev, word, next)
+        };
+        res.push(Lookup {
+            stem: word.to_lowercase(),
+            word,
+            sentence: sentence.trim().to_owned(),
+            book: stmt.read::<Option<String>>(3)?.unwrap_or_default(),
+        });
+    }
+    Ok(res)
+}
+
+/// the lookups of a Kindle or KOReader database, oldest first
+pub fn lookups<E>(path: &str) -> Result<Vec<Lookup>, Error<E>> {
+    let flags = sqlite::OpenFlags::new().set_read_only();
+    let con = sqlite::Connection::open_with_flags(path, flags).map_err(sql_err)?;
+
+    let tables = tables(&con).map_err(sql_err)?;
+    let has = |t: &str| tables.iter().any(|n| n == t);
+    if has("words") && has("lookups") {
+        kindle(&con).map_err(sql_err)
+    } else if has("vocabulary") {
+        koreader(&con).map_err(sql_err)
+    } else {
+        let msg = format!("{} is not a kindle or koreader vocabulary database", path);
+        Err(Error::Format(0, msg))
+    }
+}
+
+/// one word per stem in the order first looked up, with the contexts as message
+pub fn words(lookups: &[Lookup]) -> Vec<World> {
+    let mut res: Vec<World> = Vec::new();
+    for l in lookups {
+        let stem = l.stem.trim();
+        if stem.is_empty() {
+            continue;
+        }
+        let context = match (l.sentence.is_empty(), l.book.is_empty()) {
+            (true, true) => String::new(),
+            (false, true) => l.sentence.clone(),
+            (true, false) => format!("— {}", l.book),
+            (false, false) => format!("{}\n— {}", l.sentence, l.book),
+        };
+
+        let word = match res.iter_mut().find(|w| w.name == stem) {
+            Some(word) => word,
+            None => {
+                res.push(World {
+                    name: stem.to_owned(),
+                    message: String::new(),
+                });
+                res.last_mut().unwrap()
+            }
+        };
+        if let Some(message) = super::merge_message(&word.message, &context) {
+            word.message = message;
+        }
+    }
+    res
+}
+
+/// import the lookups into the store of key, words already in another
+/// opened store are skipped, words in the store itself follow the options
+pub fn import<T, E, F>(
+    lib: &WordLibrary<T, E, F>,
+    key: &str,
+    path: &str,
+    opts: &Options,
+) -> Result<Report, Error<E>>
+where
+    E: std::fmt::Debug,
+    F: Transaction<T, InternalError<E>>,
+{
+    let store = lib.store(key)?;
+    let others: Vec<String> = lib.keys().into_iter().filter(|k| k != key).collect();
+
+    let mut known = Vec::new();
+    let mut entries = Vec::new();
+    for word in words(&lookups(path)?) {
+        let mut found = false;
+        for k in &others {
+            match lib.get(&word.name, k) {
+                Ok(_) => {
+                    found = true;
+                    break;
+                }
+                Err(InternalError::NotFound) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if found {
+            known.push(word);
+        } else {
+            entries.push(Ok(Entry::from(word)));
+        }
+    }
+
+    let mut report = super::import(&**store, entries, opts)?;
+    for word in known {
+        report.add(super::Action::Skip, word, opts.preview);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::sqlite::store::Sqlite;
+
+    fn create(path: &str, sql: &str) {
+        let _ = std::fs::remove_file(path);
+        sqlite::open(path).unwrap().execute(sql).unwrap();
+    }
+
+    #[test]
+    fn test_lookups() {
+        let kindle = "target/test_vocab.db";
+        create(
+            kindle,
+            "CREATE TABLE WORDS (id TEXT PRIMARY KEY, word TEXT, stem TEXT, lang TEXT, \
+                category INTEGER, timestamp INTEGER, profileid TEXT);
+            CREATE TABLE LOOKUPS (id TEXT PRIMARY KEY, word_key TEXT, book_key TEXT, \
+                dict_key TEXT, pos TEXT, usage TEXT, timestamp INTEGER);
+            CREATE TABLE BOOK_INFO (id TEXT PRIMARY KEY, asin TEXT, guid TEXT, lang TEXT, \
+                title TEXT, authors TEXT);
+            INSERT INTO BOOK_INFO (id, title) VALUES ('b1', 'Dune');
+            INSERT INTO WORDS (id, word, stem) VALUES ('en:running', 'running', 'run');
+            INSERT INTO WORDS (id, word, stem) VALUES ('en:ran', 'ran', 'run');
+            INSERT INTO WORDS (id, word, stem) VALUES ('en:spice', 'spice', 'spice');
+            INSERT INTO LOOKUPS VALUES ('1', 'en:running', 'b1', '', '', 'He was running.', 1);
+            INSERT INTO LOOKUPS VALUES ('2', 'en:ran', 'b1', '', '', 'She ran away.', 2);
+            INSERT INTO LOOKUPS VALUES ('3', 'en:spice', 'b1', '', '', 'The spice must flow.', 3);",
+        );
+        let ws = words(&lookups::<()>(kindle).unwrap());
+        assert_eq!(ws.len(), 2);
+        assert_eq!(ws[0].name, "run");
+        assert_eq!(
+            ws[0].message,
+            "He was running.\n— Dune\nShe ran away.\n— Dune"
+        );
+
+        let koreader = "target/test_vocabulary_builder.sqlite3";
+        create(
+            koreader,
+            "CREATE TABLE title (id INTEGER PRIMARY KEY, name TEXT UNIQUE, filter INTEGER);
+            CREATE TABLE vocabulary (word TEXT NOT NULL UNIQUE, title_id INTEGER, \
+                create_time INTEGER NOT NULL, review_time INTEGER, due_time INTEGER NOT NULL, \
+                review_count INTEGER NOT NULL DEFAULT 0, prev_context TEXT, next_context TEXT, \
+                streak_count INTEGER NOT NULL DEFAULT 0);
+            INSERT INTO title VALUES (1, 'Emma', 1);
+            INSERT INTO vocabulary (word, title_id, create_time, due_time, prev_context, next_context) \
+                VALUES ('Spice', 1, 1, 1, 'a ', ' cake'), ('quaint', 1, 2, 2, 'so ', ' a town');",
+        );
+        let ls = lookups::<()>(koreader).unwrap();
+        assert_eq!(ls[0].sentence, "a Spice cake");
+        assert_eq!(ls[0].book, "Emma");
+
+        // spice is known by another library, run is already in the destination
+        let (a, b) = ("target/test_lookups_a.sql", "target/test_lookups_b.sql");
+        let mut lib = WordLibrary::new();
+        for p in [a, b] {
+            let _ = std::fs::remove_file(p);
+            lib.add_store(p, Box::new(Sqlite::new(p.to_owned()).unwrap()));
+        }
+        let word = |name: &str| World {
+            name: name.to_owned(),
+            message: String::from("known"),
+        };
+        lib.store(b).unwrap().insert(&word("spice")).unwrap();
+        lib.store(a).unwrap().insert(&word("run")).unwrap();
+
+        let report = import(&lib, a, kindle, &Options::default()).unwrap();
+        assert_eq!((report.inserted, report.skipped), (0, 2));
+        let report = import(&lib, a, koreader, &Options::default()).unwrap();
+        assert_eq!((report.inserted, report.skipped), (1, 1));
+        assert_eq!(
+            lib.get("quaint", a).unwrap().message,
+            "so quaint a town\n— Emma"
+        );
+    }
+}
@@ -0,0 +1,911 @@
+// unknown words of a text, a subtitle or an epub book
+//
+// the text is split into sentences and words, every word is reduced to its
+// lemma by rules, checked by the registered dictionaries when there are some,
+// the lemmas in none of the opened stores are ranked by their count
+use super::{anki, zip, Error, Report};
+use crate::store::dict::Dictionaries;
+use crate::store::{Entry, InternalError, Transaction, WordLibrary, World};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+/// a word of the text in no store
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unknown {
+    pub word: String,
+    pub count: usize,
+    // the forms it appears in the text
+    pub forms: Vec<String>,
+    pub sentences: Vec<String>,
+}
+
+impl Unknown {
+    /// the word added to a library, the sentences are the context
+    pub fn to_world(&self) -> World {
+        World {
+            name: self.word.clone(),
+            message: self.sentences.join("\n"),
+        }
+    }
+}
+
+pub struct Options {
+    /// example sentences kept of every word
+    pub sentences: usize,
+    pub min_count: usize,
+    /// keep the common function words like "the" and "of"
+    pub common: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            sentences: 2,
+            min_count: 1,
+            common: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Text,
+    Srt,
+    Ass,
+    Epub,
+}
+
+impl Kind {
+    pub fn from_path(path: &str) -> Kind {
+        let ext = Path::new(path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "srt" => Kind::Srt,
+            "ass" | "ssa" => Kind::Ass,
+            "epub" => Kind::Epub,
+            _ => Kind::Text,
+        }
+    }
+}
+
+const COMMON: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "also", "am", "an", "and", "any",
+    "as", "at", "be", "because", "before", "below", "between", "both", "but", "by", "can", "could",
+    "do", "down", "each", "every", "few", "for", "from", "get", "go", "have", "he", "her", "here",
+    "him", "his", "how", "i", "if", "in", "into", "it", "its", "just", "like", "may", "me",
+    "might", "more", "most", "must", "my", "no", "nor", "not", "now", "of", "off", "oh", "ok",
+    "on", "once", "one", "only", "or", "other", "our", "out", "over", "own", "same", "say", "she",
+    "should", "so", "some", "such", "than", "that", "the", "their", "them", "then", "there",
+    "these", "they", "this", "those", "through", "to", "too", "under", "until", "up", "us", "very",
+    "was", "we", "well", "what", "when", "where", "which", "while", "who", "whom", "why", "will",
+    "with", "would", "yes", "yet", "you", "your", "mr", "mrs",
+];
+
+const IRREGULAR: &[(&str, &str)] = &[
+    ("am", "be"),
+    ("is", "be"),
+    ("are", "be"),
+    ("was", "be"),
+    ("were", "be"),
+    ("been", "be"),
+    ("being", "be"),
+    ("has", "have"),
+    ("had", "have"),
+    ("does", "do"),
+    ("did", "do"),
+    ("done", "do"),
+    ("goes", "go"),
+    ("went", "go"),
+    ("gone", "go"),
+    ("said", "say"),
+    ("made", "make"),
+    ("took", "take"),
+    ("taken", "take"),
+    ("came", "come"),
+    ("saw", "see"),
+    ("seen", "see"),
+    ("knew", "know"),
+    ("known", "know"),
+    ("got", "get"),
+    ("gotten", "get"),
+    ("gave", "give"),
+    ("given", "give"),
+    ("found", "find"),
+    ("thought", "think"),
+    ("told", "tell"),
+    ("became", "become"),
+    ("felt", "feel"),
+    ("brought", "bring"),
+    ("began", "begin"),
+    ("begun", "begin"),
+    ("kept", "keep"),
+    ("held", "hold"),
+    ("wrote", "write"),
+    ("written", "write"),
+    ("stood", "stand"),
+    ("heard", "hear"),
+    ("meant", "mean"),
+    ("met", "meet"),
+    ("ran", "run"),
+    ("paid", "pay"),
+    ("sat", "sit"),
+    ("spoke", "speak"),
+    ("spoken", "speak"),
+    ("led", "lead"),
+    ("grew", "grow"),
+    ("grown", "grow"),
+    ("lost", "lose"),
+    ("fell", "fall"),
+    ("fallen", "fall"),
+    ("sent", "send"),
+    ("built", "build"),
+    ("understood", "understand"),
+    ("drew", "draw"),
+    ("drawn", "draw"),
+    ("broke", "break"),
+    ("broken", "break"),
+    ("spent", "spend"),
+    ("rose", "rise"),
+    ("risen", "rise"),
+    ("drove", "drive"),
+    ("driven", "drive"),
+    ("bought", "buy"),
+    ("caught", "catch"),
+    ("taught", "teach"),
+    ("sought", "seek"),
+    ("fought", "fight"),
+    ("wore", "wear"),
+    ("worn", "wear"),
+    ("chose", "choose"),
+    ("chosen", "choose"),
+    ("ate", "eat"),
+    ("eaten", "eat"),
+    ("flew", "fly"),
+    ("flown", "fly"),
+    ("forgot", "forget"),
+    ("forgotten", "forget"),
+    ("dying", "die"),
+    ("lying", "lie"),
+    ("children", "child"),
+    ("men", "man"),
+    ("women", "woman"),
+    ("people", "person"),
+    ("feet", "foot"),
+    ("teeth", "tooth"),
+    ("mice", "mouse"),
+    ("geese", "goose"),
+    ("better", "good"),
+    ("best", "good"),
+    ("worse", "bad"),
+    ("worst", "bad"),
+    ("an", "a"),
+    ("these", "this"),
+];
+
+// words looking inflected but not
+const KEEP: &[&str] = &[
+    "always",
+    "news",
+    "series",
+    "species",
+    "perhaps",
+    "towards",
+    "afterwards",
+    "whereas",
+    "nothing",
+    "something",
+    "anything",
+    "everything",
+    "morning",
+    "evening",
+    "during",
+    "ceiling",
+    "wedding",
+    "darling",
+    "sibling",
+    "pudding",
+    "indeed",
+    "need",
+    "seed",
+    "speed",
+    "feed",
+    "bleed",
+    "weed",
+    "breed",
+    "greed",
+    "proceed",
+    "succeed",
+    "exceed",
+    "physics",
+];
+
+// a sentence does not end by their dot
+const ABBREVIATIONS: &[&str] = &[" mr.", " mrs.", " ms.", " dr.", " st.", " e.g.", " i.e."];
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+// the stem of a word without -ed or -ing, and the stem with e restored
+fn unsuffix(stem: &str) -> Vec<String> {
+    let cs: Vec<char> = stem.chars().collect();
+    let n = cs.len();
+    if n < 2 || !cs.iter().any(|c| is_vowel(*c) || *c == 'y') {
+        return Vec::new();
+    }
+    // stopped, running, but not calling or missing
+    if n >= 3 && cs[n - 1] == cs[n - 2] && !is_vowel(cs[n - 1]) && !"lsfz".contains(cs[n - 1]) {
+        return vec![cs[..n - 1].iter().collect()];
+    }
+    let groups = cs
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| is_vowel(**c) && (*i == 0 || !is_vowel(cs[i - 1])))
+        .count();
+    let last = cs[n - 1];
+    // liked, hoping and loved, but not visited or opened
+    let short = groups == 1
+        && !is_vowel(last)
+        && !"wxy".contains(last)
+        && is_vowel(cs[n - 2])
+        && (n < 3 || !is_vowel(cs[n - 3]));
+    let with_e = format!("{}e", stem);
+    if short || "vzcu".contains(last) {
+        vec![with_e, stem.to_owned()]
+    } else {
+        vec![stem.to_owned(), with_e]
+    }
+}
+
+// base forms of the word by rules, the most likely first
+fn rules(word: &str) -> Vec<String> {
+    if word.chars().count() < 4 || KEEP.contains(&word) {
+        return Vec::new();
+    }
+    if let Some(stem) = word.strip_suffix("ies") {
+        return vec![format!("{}y", stem)];
+    }
+    if let Some(stem) = word.strip_suffix("ied") {
+        return vec![format!("{}y", stem)];
+    }
+    for suffix in ["sses", "shes", "ches", "xes", "zes"] {
+        if word.ends_with(suffix) {
+            return vec![word[..word.len() - 2].to_owned()];
+        }
+    }
+    if word.ends_with('s') && !["ss", "us", "is"].iter().any(|s| word.ends_with(s)) {
+        return vec![word[..word.len() - 1].to_owned()];
+    }
+    if word.ends_with("eed") {
+        return vec![word[..word.len() - 1].to_owned()];
+    }
+    if let Some(stem) = word.strip_suffix("ing") {
+        return unsuffix(stem);
+    }
+    if let Some(stem) = word.strip_suffix("ed") {
+        return unsuffix(stem);
+    }
+    Vec::new()
+}
+
+/// the forms a lowercase word may come from, the most likely lemma first
+/// and the word itself before the forms of the rules
+pub fn candidates(word: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    if let Some((_, base)) = IRREGULAR.iter().find(|(w, _)| *w == word) {
+        res.push(base.to_string());
+    }
+    res.push(word.to_owned());
+    for w in rules(word) {
+        if !res.contains(&w) {
+            res.push(w);
+        }
+    }
+    res
+}
+
+/// the lemma of a lowercase word, the first candidate accepted by is_word,
+/// or the one of the rules when none is accepted
+pub fn lemma(word: &str, is_word: impl Fn(&str) -> bool) -> String {
+    let cands = candidates(word);
+    if let Some(w) = cands.iter().find(|w| is_word(w)) {
+        return w.clone();
+    }
+    if IRREGULAR.iter().any(|(w, _)| *w == word) {
+        return cands[0].clone();
+    }
+    cands.get(1).cloned().unwrap_or_else(|| word.to_owned())
+}
+
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// sentences of the text, a blank line ends a sentence, lines are joined
+pub fn sentences(text: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut paragraph = String::new();
+    for line in text.lines().chain(std::iter::once("")) {
+        if !line.trim().is_empty() {
+            paragraph.push_str(line);
+            paragraph.push(' ');
+            continue;
+        }
+
+        let mut cur = String::new();
+        let mut chars = paragraph.chars().peekable();
+        while let Some(c) = chars.next() {
+            cur.push(c);
+            if !".!?…。！？".contains(c) {
+                continue;
+            }
+            // closing quotes and brackets belong to the sentence
+            while let Some(q) = chars.peek().filter(|q| "\"'”’)]".contains(**q)) {
+                cur.push(*q);
+                chars.next();
+            }
+            let lower = format!(" {}", cur.to_lowercase());
+            let abbr = ABBREVIATIONS.iter().any(|a| lower.ends_with(a));
+            if !abbr && chars.peek().map(|n| n.is_whitespace()).unwrap_or(true) {
+                let s = collapse(&cur);
+                if !s.is_empty() {
+                    res.push(s);
+                }
+                cur.clear();
+            }
+        }
+        let s = collapse(&cur);
+        if !s.is_empty() {
+            res.push(s);
+        }
+        paragraph.clear();
+    }
+    res
+}
+
+/// the words of a sentence, and if they are capitalized
+pub fn tokens(sentence: &str) -> Vec<(String, bool)> {
+    let mut res = Vec::new();
+    let normalized = sentence.replace('’', "'");
+    for piece in normalized.split(|c: char| !c.is_alphabetic() && c != '\'') {
+        let piece = piece.trim_matches('\'');
+        // don't, can't, the contractions are common words
+        if piece.ends_with("n't") {
+            continue;
+        }
+        let word = piece.split('\'').next().unwrap_or_default();
+        if word.chars().count() < 2 {
+            continue;
+        }
+        let capital = word
+            .chars()
+            .next()
+            .map(|c| c.is_uppercase())
+            .unwrap_or(false);
+        res.push((word.to_lowercase(), capital));
+    }
+    res
+}
+
+// text without the tags and {} overrides of subtitles
+fn strip_tags(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut depth = 0;
+    for c in s.chars() {
+        match c {
+            '<' | '{' => depth += 1,
+            '>' | '}' if depth > 0 => depth -= 1,
+            _ if depth == 0 => res.push(c),
+            _ => {}
+        }
+    }
+    res
+}
+
+/// the text of a srt subtitle, the lines of a cue are joined
+pub fn srt_text(s: &str) -> String {
+    let mut res = String::new();
+    for line in s.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() || line.contains("-->") || line.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        res.push_str(&strip_tags(line));
+        res.push('\n');
+    }
+    res
+}
+
+/// the text of the dialogue lines of an ass or ssa subtitle
+pub fn ass_text(s: &str) -> String {
+    let mut res = String::new();
+    // the text is the last field of the event format
+    let mut fields = 10;
+    let mut events = false;
+    for line in s.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        if !events {
+            continue;
+        }
+        if let Some(format) = line.strip_prefix("Format:") {
+            fields = format.split(',').count();
+        } else if let Some(dialogue) = line.strip_prefix("Dialogue:") {
+            if let Some(text) = dialogue.splitn(fields, ',').nth(fields - 1) {
+                let text = text
+                    .replace("\\N", " ")
+                    .replace("\\n", " ")
+                    .replace("\\h", " ");
+                res.push_str(&strip_tags(&text));
+                res.push('\n');
+            }
+        }
+    }
+    res
+}
+
+fn attr(tag: &str, name: &str) -> Option<String> {
+    for quote in ['"', '\''] {
+        let key = format!(" {}={}", name, quote);
+        if let Some(i) = tag.find(&key) {
+            let rest = &tag[i + key.len()..];
+            return rest.find(quote).map(|e| rest[..e].to_owned());
+        }
+    }
+    None
+}
+
+// the tags of the name, like <item .../>
+fn tags<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("<{}", name);
+    xml.match_indices(open.as_str())
+        .filter_map(|(i, _)| {
+            let rest = &xml[i..];
+            let end = rest.find('>')?;
+            let tag = &rest[..end];
+            // <item but not <itemref
+            match tag[open.len()..].chars().next() {
+                Some(c) if c.is_whitespace() || c == '/' => Some(tag),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = s
+            .get(i + 1..i + 3)
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                res.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                res.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+// the content documents of the book in reading order
+fn spine(archive: &zip::Archive) -> Option<Vec<String>> {
+    let container = archive.read("META-INF/container.xml").ok()?;
+    let container = String::from_utf8_lossy(&container);
+    let opf_path = tags(&container, "rootfile")
+        .into_iter()
+        .find_map(|t| attr(t, "full-path"))?;
+    let opf = String::from_utf8_lossy(&archive.read(&opf_path).ok()?).into_owned();
+    let dir = match opf_path.rfind('/') {
+        Some(i) => &opf_path[..i + 1],
+        None => "",
+    };
+
+    let items: HashMap<String, String> = tags(&opf, "item")
+        .into_iter()
+        .filter_map(|t| Some((attr(t, "id")?, attr(t, "href")?)))
+        .collect();
+    let res: Vec<String> = tags(&opf, "itemref")
+        .into_iter()
+        .filter_map(|t| items.get(&attr(t, "idref")?))
+        .map(|href| format!("{}{}", dir, percent_decode(href)))
+        .filter(|p| archive.contains(p))
+        .collect();
+    if res.is_empty() {
+        None
+    } else {
+        Some(res)
+    }
+}
+
+fn decode_entities(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        res.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                res.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "rsquo" | "lsquo" => Some('\''),
+            "ldquo" | "rdquo" => Some('"'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "hellip" => Some('…'),
+            _ => match entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity
+                    .strip_prefix('#')
+                    .and_then(|d| d.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        };
+        match c {
+            Some(c) => {
+                res.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                res.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+/// the text of a xhtml document, the blocks are paragraphs
+pub fn html_text(html: &str) -> String {
+    let mut body = match (html.find("<body"), html.rfind("</body>")) {
+        (Some(start), Some(end)) if start < end => html[start..end].to_owned(),
+        _ => html.to_owned(),
+    };
+    for tag in ["script", "style"] {
+        let (open, close) = (format!("<{}", tag), format!("</{}>", tag));
+        while let Some(start) = body.find(&open) {
+            match body[start..].find(&close) {
+                Some(end) => body.replace_range(start..start + end + close.len(), ""),
+                None => break,
+            }
+        }
+    }
+    for tag in [
+        "p",
+        "div",
+        "li",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "blockquote",
+        "tr",
+    ] {
+        let close = format!("</{}>", tag);
+        body = body.replace(&close, &format!("{}\n\n", close));
+    }
+    decode_entities(&anki::strip_html(&body))
+}
+
+/// the text of an epub book, the documents of the spine in reading order
+pub fn epub_text(data: Vec<u8>) -> io::Result<String> {
+    let archive = zip::Archive::new(data)?;
+    let docs = match spine(&archive) {
+        Some(docs) => docs,
+        None => {
+            let mut docs: Vec<String> = archive
+                .names()
+                .into_iter()
+                .filter(|n| n.ends_with(".xhtml") || n.ends_with(".html") || n.ends_with(".htm"))
+                .map(|n| n.to_owned())
+                .collect();
+            docs.sort();
+            docs
+        }
+    };
+    if docs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "epub without documents",
+        ));
+    }
+
+    let mut res = String::new();
+    for doc in docs {
+        res.push_str(&html_text(&String::from_utf8_lossy(&archive.read(&doc)?)));
+        res.push_str("\n\n");
+    }
+    Ok(res)
+}
+
+/// the text of a file by its kind
+pub fn read_text(path: &str) -> io::Result<String> {
+    let data = std::fs::read(path)?;
+    let text = || String::from_utf8_lossy(&data).into_owned();
+    match Kind::from_path(path) {
+        Kind::Text => Ok(text()),
+        Kind::Srt => Ok(srt_text(&text())),
+        Kind::Ass => Ok(ass_text(&text())),
+        Kind::Epub => epub_text(data),
+    }
+}
+
+// a surface form of the text
+#[derive(Default)]
+struct Form {
+    count: usize,
+    // the first place it appears, for a stable rank
+    first: usize,
+    // only capitalized inside sentences, a name
+    name: bool,
+    lower: bool,
+    sentences: Vec<usize>,
+}
+
+/// the words of the text in no store of the library, most frequent first
+pub fn extract<T, E, F>(
+    lib: &WordLibrary<T, E, F>,
+    text: &str,
+    dicts: Option<&Dictionaries>,
+    opts: &Options,
+) -> Result<Vec<Unknown>, InternalError<E>>
+where
+    E: std::fmt::Debug,
+    F: Transaction<T, InternalError<E>>,
+{
+    let sents = sentences(text);
+    let mut forms: HashMap<String, Form> = HashMap::new();
+    let mut n = 0;
+    for (i, s) in sents.iter().enumerate() {
+        for (j, (word, capital)) in tokens(s).into_iter().enumerate() {
+            let f = forms.entry(word).or_insert_with(|| Form {
+                first: n,
+                ..Form::default()
+            });
+            f.count += 1;
+            f.lower |= !capital;
+            f.name |= capital && j > 0;
+            if f.sentences.last() != Some(&i) {
+                f.sentences.push(i);
+            }
+            n += 1;
+        }
+    }
+
+    let keys = lib.keys();
+    let mut known: HashMap<String, bool> = HashMap::new();
+    let mut in_store = |word: &str| -> Result<bool, InternalError<E>> {
+        if let Some(k) = known.get(word) {
+            return Ok(*k);
+        }
+        let mut found = false;
+        for key in &keys {
+            match lib.get(word, key) {
+                Ok(_) => {
+                    found = true;
+                    break;
+                }
+                Err(InternalError::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        known.insert(word.to_owned(), found);
+        Ok(found)
+    };
+
+    let mut lemmas: HashMap<String, (usize, Unknown, Vec<usize>)> = HashMap::new();
+    for (word, f) in forms {
+        if f.name && !f.lower {
+            continue;
+        }
+        let mut is_known = false;
+        for c in candidates(&word) {
+            if in_store(&c)? {
+                is_known = true;
+                break;
+            }
+        }
+        if is_known {
+            continue;
+        }
+        let base = match dicts {
+            Some(d) if !d.is_empty() => lemma(&word, |w| d.contains(w)),
+            _ => lemma(&word, |_| false),
+        };
+        if !opts.common && (COMMON.contains(&base.as_str()) || COMMON.contains(&word.as_str())) {
+            continue;
+        }
+
+        let entry = lemmas.entry(base.clone()).or_insert_with(|| {
+            let u = Unknown {
+                word: base,
+                count: 0,
+                forms: Vec::new(),
+                sentences: Vec::new(),
+            };
+            (f.first, u, Vec::new())
+        });
+        entry.0 = entry.0.min(f.first);
+        entry.1.count += f.count;
+        entry.1.forms.push(word);
+        entry.2.extend(f.sentences);
+    }
+
+    let mut res: Vec<(usize, Unknown)> = Vec::new();
+    for (_, (first, mut u, mut ss)) in lemmas {
+        if u.count < opts.min_count {
+            continue;
+        }
+        ss.sort();
+        ss.dedup();
+        u.sentences = ss
+            .into_iter()
+            .take(opts.sentences)
+            .map(|i| sents[i].clone())
+            .collect();
+        u.forms.sort();
+        res.push((first, u));
+    }
+    res.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(&b.0)));
+    Ok(res.into_iter().map(|(_, u)| u).collect())
+}
+
+/// add the words to the store of key with their sentences as message,
+/// in one transaction by the import options
+pub fn add<T, E, F>(
+    lib: &WordLibrary<T, E, F>,
+    key: &str,
+    words: &[Unknown],
+    opts: &super::Options,
+) -> Result<Report, Error<E>>
+where
+    E: std::fmt::Debug,
+    F: Transaction<T, InternalError<E>>,
+{
+    let store = lib.store(key)?;
+    let entries = words.iter().map(|u| Ok(Entry::from(u.to_world())));
+    super::import(&**store, entries, opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::sqlite::store::Sqlite;
+
+    #[test]
+    fn test_lemma() {
+        let none = |_: &str| false;
+        for (word, base) in [
+            ("studies", "study"),
+            ("watches", "watch"),
+            ("books", "book"),
+            ("running", "run"),
+            ("calling", "call"),
+            ("liked", "like"),
+            ("making", "make"),
+            ("visited", "visit"),
+            ("solved", "solve"),
+            ("agreed", "agree"),
+            ("went", "go"),
+            ("morning", "morning"),
+            ("glass", "glass"),
+        ] {
+            assert_eq!(lemma(word, none), base, "{}", word);
+        }
+        // a word of the dictionary is preferred to the rules
+        assert_eq!(lemma("lived", |w| w == "live"), "live");
+        assert_eq!(lemma("bring", |w| w == "bring"), "bring");
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            sentences("He said \"Stop.\" Then Mr. Li left!\nA new\nline.\n\nNo end"),
+            vec![
+                "He said \"Stop.\"",
+                "Then Mr. Li left!",
+                "A new line.",
+                "No end"
+            ]
+        );
+        assert_eq!(
+            tokens("Don't touch Anna’s cat-like x"),
+            vec![
+                (String::from("touch"), false),
+                (String::from("anna"), true),
+                (String::from("cat"), false),
+                (String::from("like"), false)
+            ]
+        );
+
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n<i>Hello</i> there,\n\n2\n00:00:03,000 --> 00:00:04,000\nfriend.\n";
+        assert_eq!(sentences(&srt_text(srt)), vec!["Hello there, friend."]);
+
+        let ass = "[Script Info]\nTitle: x\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\i1}Well, hi\\Nthere.\n";
+        assert_eq!(ass_text(ass), "Well, hi there.\n");
+
+        let html = "<html><head><title>T</title><style>p{}</style></head><body><h1>Chapter</h1><p>It&#8217;s a &amp; b&hellip;</p></body></html>";
+        assert_eq!(sentences(&html_text(html)), vec!["Chapter", "It’s a & b…"]);
+    }
+
+    #[test]
+    fn test_epub_extract() {
+        let mut w = zip::Writer::new(Vec::new());
+        w.add("mimetype", b"application/epub+zip").unwrap();
+        w.add(
+            "META-INF/container.xml",
+            b"<container><rootfiles><rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/></rootfiles></container>",
+        )
+        .unwrap();
+        w.add(
+            "OEBPS/content.opf",
+            b"<package><manifest><item id=\"c2\" href=\"ch%202.xhtml\" media-type=\"application/xhtml+xml\"/><item id=\"c1\" href=\"ch1.xhtml\" media-type=\"application/xhtml+xml\"/></manifest><spine><itemref idref=\"c1\"/><itemref idref=\"c2\"/></spine></package>",
+        )
+        .unwrap();
+        w.add(
+            "OEBPS/ch1.xhtml",
+            b"<html><body><p>The cats were running. Then Emma watched the cat.</p></body></html>",
+        )
+        .unwrap();
+        w.add(
+            "OEBPS/ch 2.xhtml",
+            b"<html><body><p>A quaint town. Cats ran.</p></body></html>",
+        )
+        .unwrap();
+        let text = epub_text(w.finish().unwrap()).unwrap();
+        assert!(text.find("running").unwrap() < text.find("quaint").unwrap());
+
+        let path = "target/test_extract.sql";
+        let _ = std::fs::remove_file(path);
+        let mut lib = WordLibrary::new();
+        lib.add_store(path, Box::new(Sqlite::new(path.to_owned()).unwrap()));
+        lib.insert(
+            &World {
+                name: String::from("town"),
+                message: String::new(),
+            },
+            path,
+        )
+        .unwrap();
+
+        // town is known, Emma is a name, the and a are common
+        let ws = extract(&lib, &text, None, &Options::default()).unwrap();
+        let names: Vec<&str> = ws.iter().map(|u| u.word.as_str()).collect();
+        assert_eq!(names, vec!["cat", "run", "watch", "quaint"]);
+        assert_eq!(ws[0].count, 3);
+        assert_eq!(ws[0].forms, vec!["cat", "cats"]);
+        assert_eq!(
+            ws[0].sentences,
+            vec!["The cats were running.", "Then Emma watched the cat."]
+        );
+
+        let report = add(&lib, path, &ws[..1], &super::super::Options::default()).unwrap();
+        assert_eq!(report.inserted, 1);
+        let ws = extract(&lib, &text, None, &Options::default()).unwrap();
+        assert_eq!(ws[0].word, "run");
+    }
+}
@@ -0,0 +1,384 @@
+// printable glossary of words in markdown or self-contained html
+//
+// the words are sorted alphabetically and grouped under letter headings,
+// a glossary is a list of entries or a two-column study sheet
+//
+// the output is made from a template of sections, every section starts with
+// a line "@@ <name>" and has {{placeholders}}:
+//   document   {{title}} {{count}} {{letters}} {{body}}
+//   group      {{letter}} {{anchor}} {{entries}}, a letter of the list layout
+//   entry      {{word}} {{meaning}}, a word of the list layout
+//   sheet      {{letter}} {{anchor}} {{entries}}, a letter of the sheet layout
+//   row        {{word}} {{meaning}}, a word of the sheet layout
+//   letter     {{letter}} {{anchor}}, a link of {{letters}}
+// sections missing in a template file are the default ones of the style
+use super::{anki, Error};
+use crate::store::{InternalError, Store, Transaction, World};
+use std::io;
+use std::path::Path;
+
+// words read from the store at once
+const BATCH: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Markdown,
+    Html,
+}
+
+impl Style {
+    /// html for .html and .htm files, markdown otherwise
+    pub fn from_path(path: &str) -> Style {
+        let ext = Path::new(path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "html" | "htm" => Style::Html,
+            _ => Style::Markdown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    List,
+    /// a "word | meaning" table to study with the meaning covered
+    Sheet,
+}
+
+const SECTIONS: [&str; 6] = ["document", "group", "entry", "sheet", "row", "letter"];
+
+const MARKDOWN: &str = r#"@@ document
+# {{title}}
+
+{{count}} words
+
+{{letters}}
+
+{{body}}
+@@ group
+## {{letter}}
+
+{{entries}}
+@@ entry
+**{{word}}**
+{{meaning}}
+
+@@ sheet
+## {{letter}}
+
+| word | meaning |
+| --- | --- |
+{{entries}}
+@@ row
+| {{word}} | {{meaning}} |
+@@ letter
+[{{letter}}](#{{anchor}}) "#;
+
+const HTML: &str = r##"@@ document
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+nav a { margin-right: .5em; }
+h2 { border-bottom: 1px solid #ccc; page-break-after: avoid; }
+dl { columns: 2; column-gap: 2em; }
+dt { font-weight: bold; }
+dd { margin: 0 0 .8em 1em; break-inside: avoid; }
+table { width: 100%; border-collapse: collapse; }
+td, th { border: 1px solid #999; padding: .3em .5em; vertical-align: top; text-align: left; }
+td:first-child { width: 30%; font-weight: bold; }
+tr { break-inside: avoid; }
+@media print { nav { display: none; } }
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+<p>{{count}} words</p>
+<nav>{{letters}}</nav>
+{{body}}
+</body>
+</html>
+@@ group
+<h2 id="{{anchor}}">{{letter}}</h2>
+<dl>
+{{entries}}</dl>
+@@ entry
+<dt>{{word}}</dt>
+<dd>{{meaning}}</dd>
+@@ sheet
+<h2 id="{{anchor}}">{{letter}}</h2>
+<table>
+<tr><th>word</th><th>meaning</th></tr>
+{{entries}}</table>
+@@ row
+<tr><td>{{word}}</td><td>{{meaning}}</td></tr>
+@@ letter
+<a href="#{{anchor}}">{{letter}}</a>"##;
+
+/// the sections of a glossary document
+#[derive(Debug, Clone)]
+pub struct Template {
+    style: Style,
+    sections: Vec<(String, String)>,
+}
+
+fn parse_sections(text: &str) -> Vec<(String, String)> {
+    let mut res: Vec<(String, String)> = Vec::new();
+    for line in text.split_inclusive('\n') {
+        if let Some(name) = line.strip_prefix("@@ ") {
+            res.push((name.trim().to_owned(), String::new()));
+        } else if let Some((_, body)) = res.last_mut() {
+            body.push_str(line);
+        }
+    }
+    res
+}
+
+impl Template {
+    /// the default template of the style
+    pub fn new(style: Style) -> Template {
+        let text = match style {
+            Style::Markdown => MARKDOWN,
+            Style::Html => HTML,
+        };
+        Template {
+            style,
+            sections: parse_sections(text),
+        }
+    }
+
+    /// a customized template, sections not in the text are the default ones
+    pub fn parse(style: Style, text: &str) -> Result<Template, String> {
+        let mut t = Template::new(style);
+        for (name, body) in parse_sections(text) {
+            if !SECTIONS.contains(&name.as_str()) {
+                return Err(format!("unknown template section {}", name));
+            }
+            if let Some(s) = t.sections.iter_mut().find(|(n, _)| *n == name) {
+                s.1 = body;
+            }
+        }
+        Ok(t)
+    }
+
+    pub fn from_file(style: Style, path: &str) -> Result<Template, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("read {}: {}", path, e))?;
+        Template::parse(style, &text)
+    }
+
+    fn section(&self, name: &str) -> &str {
+        self.sections
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, s)| s.as_str())
+            .unwrap_or("")
+    }
+
+    fn fill(&self, name: &str, values: &[(&str, &str)]) -> String {
+        let mut res = self.section(name).to_owned();
+        for (key, value) in values {
+            res = res.replace(&format!("{{{{{}}}}}", key), value);
+        }
+        res
+    }
+
+    // a text value as it is written in the document
+    fn escape(&self, s: &str, cell: bool) -> String {
+        match (self.style, cell) {
+            (Style::Html, _) => anki::escape_html(s.trim()),
+            (Style::Markdown, true) => s.trim().replace('|', "\\|").replace('\n', "<br>"),
+            // a hard line break keeps the lines of the meaning
+            (Style::Markdown, false) => s.trim().replace('\n', "  \n"),
+        }
+    }
+}
+
+pub struct Options {
+    pub title: String,
+    pub layout: Layout,
+    pub template: Template,
+}
+
+impl Options {
+    pub fn new(title: &str, style: Style) -> Options {
+        Options {
+            title: title.to_owned(),
+            layout: Layout::List,
+            template: Template::new(style),
+        }
+    }
+}
+
+/// the heading letter of a word, # for the ones not starting by a letter
+pub fn letter(word: &str) -> String {
+    match word.trim().chars().next() {
+        Some(c) if c.is_alphabetic() => c.to_uppercase().collect(),
+        _ => String::from("#"),
+    }
+}
+
+fn anchor(letter: &str) -> String {
+    match letter {
+        "#" => String::from("other"),
+        l => format!("letter-{}", l.to_lowercase()),
+    }
+}
+
+/// the glossary document of the words
+pub fn render(words: &[World], opts: &Options) -> String {
+    let t = &opts.template;
+    let mut words: Vec<&World> = words.iter().collect();
+    words.sort_by(|a, b| {
+        let key = |w: &World| (letter(&w.name) == "#", w.name.to_lowercase());
+        key(a).cmp(&key(b)).then(a.name.cmp(&b.name))
+    });
+
+    let (group, item) = match opts.layout {
+        Layout::List => ("group", "entry"),
+        Layout::Sheet => ("sheet", "row"),
+    };
+    let mut body = String::new();
+    let mut letters = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let l = letter(&words[i].name);
+        let mut entries = String::new();
+        while i < words.len() && letter(&words[i].name) == l {
+            let cell = opts.layout == Layout::Sheet;
+            let (word, meaning) = (
+                t.escape(&words[i].name, cell),
+                t.escape(&words[i].message, cell),
+            );
+            entries.push_str(&t.fill(item, &[("word", &word), ("meaning", &meaning)]));
+            i += 1;
+        }
+        let (letter, anchor) = (t.escape(&l, false), anchor(&l));
+        let values = [
+            ("letter", letter.as_str()),
+            ("anchor", anchor.as_str()),
+            ("entries", entries.as_str()),
+        ];
+        body.push_str(&t.fill(group, &values));
+        letters.push(t.fill("letter", &values[..2]));
+    }
+
+    let (title, count) = (t.escape(&opts.title, false), words.len().to_string());
+    let letters = letters.concat();
+    t.fill(
+        "document",
+        &[
+            ("title", &title),
+            ("count", &count),
+            ("letters", letters.trim_end()),
+            ("body", body.trim_end()),
+        ],
+    )
+}
+
+/// every word of the store
+pub fn words<T, E, F, S>(store: &S) -> Result<Vec<World>, InternalError<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    let mut res = Vec::new();
+    loop {
+        let after = res
+            .last()
+            .map(|w: &World| w.name.clone())
+            .unwrap_or_default();
+        let ws = store.scan(&after, BATCH)?;
+        if ws.is_empty() {
+            return Ok(res);
+        }
+        res.extend(ws);
+    }
+}
+
+/// write the glossary of the words to the file, returns the number of words
+pub fn export<E>(words: &[World], path: &str, opts: &Options) -> Result<usize, Error<E>> {
+    std::fs::write(path, render(words, opts)).map_err(Error::Io)?;
+    Ok(words.len())
+}
+
+/// the words of the names, names not in the store are an error
+pub fn select<T, E, F, S>(store: &S, names: &[String]) -> Result<Vec<World>, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    let mut res = Vec::new();
+    for name in names {
+        match store.get(name) {
+            Ok(word) => res.push(word),
+            Err(InternalError::NotFound) => {
+                let msg = format!("{} not found", name);
+                return Err(Error::Io(io::Error::new(io::ErrorKind::NotFound, msg)));
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(name: &str, message: &str) -> World {
+        World {
+            name: name.to_owned(),
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let words = vec![
+            word("book", "a thing to read"),
+            word("Apple", "a fruit\nred"),
+            word("apply", "to ask | use"),
+            word("3d", "three dimensions"),
+        ];
+
+        let md = render(&words, &Options::new("English", Style::Markdown));
+        let (a, b, other) = (
+            md.find("## A").unwrap(),
+            md.find("## B").unwrap(),
+            md.find("## #").unwrap(),
+        );
+        assert!(a < b && b < other);
+        assert!(md.find("**Apple**").unwrap() < md.find("**apply**").unwrap());
+        assert!(md.contains("a fruit  \nred"));
+        assert!(md.contains("4 words"));
+        assert!(md.contains("[A](#letter-a) [B](#letter-b) [#](#other)"));
+
+        let mut opts = Options::new("English", Style::Markdown);
+        opts.layout = Layout::Sheet;
+        let md = render(&words, &opts);
+        assert!(md.contains("| apply | to ask \\| use |"));
+        assert!(md.contains("| Apple | a fruit<br>red |"));
+
+        let mut opts = Options::new("<English>", Style::Html);
+        opts.layout = Layout::Sheet;
+        let html = render(&words, &opts);
+        assert!(html.contains("<title>&lt;English&gt;</title>"));
+        assert!(html.contains("<tr><td>Apple</td><td>a fruit<br>red</td></tr>"));
+        assert!(html.contains("<h2 id=\"letter-b\">B</h2>"));
+
+        // a customized entry, the other sections are the default ones
+        let t = Template::parse(Style::Markdown, "@@ entry\n- {{word}}: {{meaning}}\n").unwrap();
+        let opts = Options {
+            title: String::from("T"),
+            layout: Layout::List,
+            template: t,
+        };
+        assert!(render(&words, &opts).contains("## B\n\n- book: a thing to read\n"));
+        assert!(Template::parse(Style::Html, "@@ footer\n").is_err());
+    }
+}
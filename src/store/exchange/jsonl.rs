@@ -0,0 +1,389 @@
+// JSON Lines dump of libraries, one json object each line
+//
+// {"type":"header","format":"wordlib","version":1}
+// {"type":"library","name":"english","metadata":{"description":"..."}}
+// {"type":"word","name":"apple","message":"...","version":3,"review":{...}}
+//
+// the words follow the library they belong to, a dump may hold many libraries
+use super::Error;
+use crate::db::sqlite::store::Sqlite;
+use crate::store::attachment::Attachment;
+use crate::store::review::Schedule;
+use crate::store::tags;
+use crate::store::{Entry, InternalError, Store, Transaction, World, Write};
+use serde_json::{json, Map, Value};
+use std::io::{self, BufRead};
+use std::path::Path;
+
+pub const FORMAT: &str = "wordlib";
+/// version of the dump format, increased when a field changes meaning
+pub const VERSION: i64 = 1;
+
+// words read from the store at once
+const BATCH: i64 = 1000;
+
+fn write_line<W: io::Write>(w: &mut W, v: &Value) -> io::Result<()> {
+    serde_json::to_writer(&mut *w, v)?;
+    w.write_all(b"\n")
+}
+
+pub fn write_header<W: io::Write>(w: &mut W) -> io::Result<()> {
+    write_line(
+        w,
+        &json!({"type": "header", "format": FORMAT, "version": VERSION}),
+    )
+}
+
+fn entry_json(entry: &Entry) -> Value {
+    let mut v = json!({
+        "type": "word",
+        "name": entry.word.name,
+        "message": entry.word.message,
+        "version": entry.version,
+    });
+    if let Some(s) = &entry.schedule {
+        v["review"] = json!({"due": s.due, "interval": s.interval, "ease": s.ease, "reps": s.reps});
+    }
+    if !entry.tags.is_empty() {
+        v["tags"] = json!(entry.tags);
+    }
+    if !entry.attachments.is_empty() {
+        let files: Vec<Value> = entry.attachments.iter().map(|a| a.to_json()).collect();
+        v["attachments"] = Value::Array(files);
+    }
+    v
+}
+
+fn entry_from<E>(v: &Value, line: usize) -> Result<Entry, Error<E>> {
+    let field = |name: &str| {
+        v[name]
+            .as_str()
+            .map(|s| s.to_owned())
+            .ok_or_else(|| Error::Format(line, format!("word without {}", name)))
+    };
+    let int = |v: &Value, name: &str| {
+        v[name]
+            .as_i64()
+            .ok_or_else(|| Error::Format(line, format!("{} is not a number", name)))
+    };
+
+    let schedule = match &v["review"] {
+        Value::Null => None,
+        r => Some(Schedule {
+            due: int(r, "due")?,
+            interval: int(r, "interval")?,
+            ease: r["ease"]
+                .as_f64()
+                .ok_or_else(|| Error::Format(line, String::from("ease is not a number")))?,
+            reps: int(r, "reps")?,
+        }),
+    };
+    Ok(Entry {
+        word: World {
+            name: field("name")?,
+            message: field("message")?,
+        },
+        version: v["version"].as_i64().unwrap_or(1),
+        schedule,
+        tags: match v["tags"].as_array() {
+            Some(tags) => {
+                let mut tags = tags
+                    .iter()
+                    .map(|t| match t.as_str() {
+                        Some(t) if tags::valid(t) => Ok(t.to_owned()),
+                        _ => Err(Error::Format(line, format!("invalid tag {}", t))),
+                    })
+                    .collect::<Result<Vec<String>, Error<E>>>()?;
+                tags.sort();
+                tags
+            }
+            None => Vec::new(),
+        },
+        attachments: match v["attachments"].as_array() {
+            Some(files) => {
+                let mut files = files
+                    .iter()
+                    .map(|f| Attachment::from_json(f).map_err(|e| Error::Format(line, e)))
+                    .collect::<Result<Vec<Attachment>, Error<E>>>()?;
+                files.sort_by(|a, b| a.name.cmp(&b.name));
+                files
+            }
+            None => Vec::new(),
+        },
+    })
+}
+
+/// dump the store as library name, returns the number of words
+pub fn dump_store<T, E, F, S, W>(store: &S, name: &str, w: &mut W) -> Result<usize, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+    W: io::Write,
+{
+    let metadata: Map<String, Value> = store
+        .metadata()?
+        .into_iter()
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect();
+    write_line(
+        w,
+        &json!({"type": "library", "name": name, "metadata": metadata}),
+    )?;
+
+    let mut count = 0;
+    let mut after = String::new();
+    loop {
+        let ws = store.scan(&after, BATCH)?;
+        for word in &ws {
+            write_line(w, &entry_json(&store.entry(&word.name)?))?;
+        }
+        count += ws.len();
+        match ws.into_iter().last() {
+            Some(word) => after = word.name,
+            None => break,
+        }
+    }
+    Ok(count)
+}
+
+/// the library name of a library file
+pub fn library_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// dump a library file, or every library of a workspace directory
+pub fn dump<W: io::Write>(path: &str, mut w: W) -> Result<usize, Error<sqlite::Error>> {
+    write_header(&mut w)?;
+    let mut count = 0;
+    for file in crate::store::library_files(path)? {
+        let store = Sqlite::new(file.clone())?;
+        count += dump_store(&store, &library_name(&file), &mut w)?;
+    }
+    w.flush()?;
+    Ok(count)
+}
+
+// the write-ahead logs of a library file
+fn logs(path: &Path) -> [std::path::PathBuf; 2] {
+    ["-wal", "-shm"].map(|suffix| {
+        let mut log = path.as_os_str().to_owned();
+        log.push(suffix);
+        log.into()
+    })
+}
+
+fn remove_library(path: &Path) -> io::Result<()> {
+    std::fs::remove_file(path)?;
+    for log in logs(path) {
+        let _ = std::fs::remove_file(log);
+    }
+    Ok(())
+}
+
+// the library restored into tmp replaces the file named without the
+// .restore extension
+fn replace_library(tmp: &Path) -> io::Result<()> {
+    let path = tmp.with_extension("");
+    // the logs of the old file must not be applied to the new one
+    for log in logs(&path) {
+        let _ = std::fs::remove_file(log);
+    }
+    std::fs::rename(tmp, path)
+}
+
+/// recreate the library files of a dump under dir,
+/// returns the name and number of words of every library
+///
+/// an existing library file is an error unless overwrite is set,
+/// the libraries are written aside and replace the files only when
+/// the whole dump is restored
+pub fn restore<R: BufRead>(
+    reader: R,
+    dir: &str,
+    overwrite: bool,
+) -> Result<Vec<(String, usize)>, Error<sqlite::Error>> {
+    std::fs::create_dir_all(dir)?;
+    let mut created = Vec::new();
+    let res = restore_into(reader, Path::new(dir), overwrite, &mut created).and_then(|res| {
+        for tmp in &created {
+            replace_library(tmp)?;
+        }
+        Ok(res)
+    });
+    if res.is_err() {
+        for path in &created {
+            let _ = remove_library(path);
+        }
+    }
+    res
+}
+
+fn restore_into<R: BufRead>(
+    reader: R,
+    dir: &Path,
+    overwrite: bool,
+    created: &mut Vec<std::path::PathBuf>,
+) -> Result<Vec<(String, usize)>, Error<sqlite::Error>> {
+    use crate::db::sqlite::store::Tansaction;
+
+    let mut res: Vec<(String, usize)> = Vec::new();
+    let mut current: Option<Tansaction> = None;
+    let mut header = false;
+
+    for (i, line) in reader.lines().enumerate() {
+        let (line, n) = (line?, i + 1);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let v: Value = serde_json::from_str(&line).map_err(|e| Error::Format(n, e.to_string()))?;
+
+        match v["type"].as_str() {
+            Some("header") => {
+                if v["format"] != FORMAT {
+                    return Err(Error::Format(n, String::from("not a wordlib dump")));
+                }
+                let version = v["version"].as_i64().unwrap_or(0);
+                if version > VERSION {
+                    let msg = format!("dump version {} is newer than {}", version, VERSION);
+                    return Err(Error::Format(n, msg));
+                }
+                header = true;
+            }
+            _ if !header => return Err(Error::Format(n, String::from("no header"))),
+            Some("library") => {
+                let name = v["name"].as_str().unwrap_or_default();
+                if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+                    let msg = format!("invalid library name {:?}", name);
+                    return Err(Error::Format(n, msg));
+                }
+
+                let path = dir.join(format!("{}.sql", name));
+                if path.exists() && !overwrite {
+                    let msg = format!("library file {} exists", path.display());
+                    return Err(Error::Format(n, msg));
+                }
+                let tmp = dir.join(format!("{}.sql.restore", name));
+                if created.contains(&tmp) {
+                    let msg = format!("library {} is dumped twice", name);
+                    return Err(Error::Format(n, msg));
+                }
+                if let Some(t) = current.take() {
+                    t.commit()?;
+                }
+
+                // left by a restore that was killed
+                if tmp.exists() {
+                    remove_library(&tmp)?;
+                }
+                created.push(tmp.clone());
+                let store = Sqlite::new(tmp.to_string_lossy().into_owned())?;
+                let t = store.begin()?;
+                if let Some(metadata) = v["metadata"].as_object() {
+                    for (key, value) in metadata {
+                        t.set_metadata(key, value.as_str().unwrap_or_default())?;
+                    }
+                }
+                current = Some(t);
+                res.push((name.to_owned(), 0));
+            }
+            Some("word") => {
+                let t = current
+                    .as_ref()
+                    .ok_or_else(|| Error::Format(n, String::from("word before library")))?;
+                t.put(&entry_from(&v, n)?)?;
+                if let Some(last) = res.last_mut() {
+                    last.1 += 1;
+                }
+            }
+            // records of newer minor features are skipped
+            _ => {}
+        }
+    }
+
+    if let Some(t) = current.take() {
+        t.commit()?;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Read;
+
+    #[test]
+    fn test_dump_restore() {
+        let dir = "target/test_jsonl";
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(format!("{}/src", dir)).unwrap();
+
+        let src = Sqlite::new(format!("{}/src/english.sql", dir)).unwrap();
+        src.set_metadata("description", "daily words").unwrap();
+        for name in ["apple", "book"] {
+            src.insert(&World {
+                name: name.to_owned(),
+                message: format!("{}\n\"quoted\"", name),
+            })
+            .unwrap();
+        }
+        src.update(&src.get("apple").unwrap()).unwrap();
+        src.set_schedule("book", &Schedule::default().next(4, 100))
+            .unwrap();
+        src.put_attachment("book", &Attachment::new("book.png", vec![0, 1, 254]))
+            .unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(dump(&format!("{}/src", dir), &mut out).unwrap(), 2);
+
+        let restored = restore(out.as_slice(), &format!("{}/dst", dir), false).unwrap();
+        assert_eq!(restored, vec![(String::from("english"), 2)]);
+
+        let dst = Sqlite::new(format!("{}/dst/english.sql", dir)).unwrap();
+        assert_eq!(dst.metadata().unwrap(), src.metadata().unwrap());
+        for name in ["apple", "book"] {
+            let (a, b) = (src.entry(name).unwrap(), dst.entry(name).unwrap());
+            assert_eq!(a.word.message, b.word.message);
+            assert_eq!((a.version, a.schedule), (b.version, b.schedule));
+            assert_eq!(a.attachments, b.attachments);
+        }
+
+        // the library exists now
+        let err = restore(out.as_slice(), &format!("{}/dst", dir), false).unwrap_err();
+        assert!(matches!(err, Error::Format(2, _)));
+
+        // a broken dump leaves no file behind
+        let bad = format!(
+            "{}\n{{\"type\":\"word\"}}\n",
+            String::from_utf8(out.clone()).unwrap()
+        );
+        assert!(restore(bad.as_bytes(), &format!("{}/bad", dir), false).is_err());
+        assert!(!Path::new(&format!("{}/bad/english.sql", dir)).exists());
+        assert!(!Path::new(&format!("{}/bad/english.sql.restore", dir)).exists());
+
+        // nor touches the library it would overwrite
+        dst.update(&World {
+            name: String::from("apple"),
+            message: String::from("kept"),
+        })
+        .unwrap();
+        drop(dst);
+        assert!(restore(bad.as_bytes(), &format!("{}/dst", dir), true).is_err());
+        let dst = Sqlite::new(format!("{}/dst/english.sql", dir)).unwrap();
+        assert_eq!(dst.get("apple").unwrap().message, "kept");
+        drop(dst);
+
+        // a whole dump replaces it
+        assert_eq!(
+            restore(out.as_slice(), &format!("{}/dst", dir), true).unwrap(),
+            restored
+        );
+        let dst = Sqlite::new(format!("{}/dst/english.sql", dir)).unwrap();
+        assert_eq!(
+            dst.get("apple").unwrap().message,
+            src.get("apple").unwrap().message
+        );
+    }
+}
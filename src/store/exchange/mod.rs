@@ -0,0 +1,291 @@
+// import and export of word libraries in the file formats of other tools
+//
+// every import runs in one transaction of the destination store, so a bad
+// row leaves the library untouched, and a dry run is an import rolled back
+pub mod anki;
+pub mod command;
+pub mod csv;
+pub mod ereader;
+pub mod extract;
+pub mod glossary;
+pub mod jsonl;
+pub mod zip;
+
+use super::asynchronous::CancelToken;
+use super::{Entry, InternalError, Store, Transaction, World};
+use std::fmt;
+
+/// what to do with a word already in the library
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Duplicate {
+    Skip,
+    Overwrite,
+    // append the new message to the old one
+    Merge,
+}
+
+impl std::str::FromStr for Duplicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Duplicate::Skip),
+            "overwrite" => Ok(Duplicate::Overwrite),
+            "merge" => Ok(Duplicate::Merge),
+            _ => Err(format!(
+                "unknown duplicate mode {}, use skip, overwrite or merge",
+                s
+            )),
+        }
+    }
+}
+
+/// what the import did with a word
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Insert,
+    Overwrite,
+    Merge,
+    Skip,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Action::Insert => "insert",
+            Action::Overwrite => "overwrite",
+            Action::Merge => "merge",
+            Action::Skip => "skip",
+        };
+        f.pad(s)
+    }
+}
+
+pub struct Options {
+    pub duplicate: Duplicate,
+    // roll back at the end, only the report is kept
+    pub dry_run: bool,
+    // number of words kept in the report to preview
+    pub preview: usize,
+    pub cancel: Option<CancelToken>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            duplicate: Duplicate::Skip,
+            dry_run: false,
+            preview: 0,
+            cancel: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub inserted: usize,
+    pub overwritten: usize,
+    pub merged: usize,
+    pub skipped: usize,
+    pub preview: Vec<(Action, World)>,
+}
+
+impl Report {
+    fn add(&mut self, action: Action, word: World, preview: usize) {
+        match action {
+            Action::Insert => self.inserted += 1,
+            Action::Overwrite => self.overwritten += 1,
+            Action::Merge => self.merged += 1,
+            Action::Skip => self.skipped += 1,
+        }
+        if self.preview.len() < preview {
+            self.preview.push((action, word));
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "inserted {}, overwritten {}, merged {}, skipped {}",
+            self.inserted, self.overwritten, self.merged, self.skipped
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<E> {
+    Io(std::io::Error),
+    // the file content is not valid at the line
+    Format(usize, String),
+    Store(InternalError<E>),
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for Error<E> {}
+impl<E: fmt::Display> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Format(line, msg) => write!(f, "format error at line {}: {}", line, msg),
+            Error::Store(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl<E> From<std::io::Error> for Error<E> {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl<E> From<InternalError<E>> for Error<E> {
+    fn from(err: InternalError<E>) -> Self {
+        Error::Store(err)
+    }
+}
+
+/// true for the package files of anki
+pub fn is_anki(path: &str) -> bool {
+    let ext = std::path::Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    matches!(ext.as_deref(), Some("apkg") | Some("colpkg"))
+}
+
+/// import a file by its extension, with the default mapping of the format
+pub fn import_file<T, E, F, S>(store: &S, path: &str, opts: &Options) -> Result<Report, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    let file = std::fs::File::open(path)?;
+    if is_anki(path) {
+        anki::import(store, file, &anki::Mapping::default(), opts)
+    } else {
+        let format = csv::Format::from_path(path);
+        csv::import(store, std::io::BufReader::new(file), &format, opts)
+    }
+}
+
+/// export the store to a file by its extension, returns the number of words
+pub fn export_file<T, E, F, S>(store: &S, library: &str, path: &str) -> Result<usize, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    if is_anki(path) {
+        anki::export(store, library, file, &anki::Mapping::default())
+    } else {
+        csv::export(store, file, &csv::Format::from_path(path))
+    }
+}
+
+/// the merged message, None when the new message adds nothing
+pub fn merge_message(old: &str, new: &str) -> Option<String> {
+    let new = new.trim();
+    if new.is_empty() || old.contains(new) {
+        return None;
+    }
+    if old.trim().is_empty() {
+        return Some(new.to_owned());
+    }
+    Some(format!("{}\n{}", old.trim_end(), new))
+}
+
+/// write the words into the store in one transaction, all or nothing,
+/// the review state of an entry is kept unless the word is skipped
+pub fn import<T, E, F, S, I>(store: &S, words: I, opts: &Options) -> Result<Report, Error<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+    I: IntoIterator<Item = Result<Entry, Error<E>>>,
+{
+    let t = store.begin()?;
+    let mut report = Report::default();
+
+    for word in words {
+        let canceled = opts.cancel.as_ref().map(|c| c.is_canceled());
+        let res = match (canceled, word) {
+            (Some(true), _) => Err(Error::Store(InternalError::Canceled)),
+            (_, Ok(word)) => put(&t, word, opts.duplicate).map_err(Error::from),
+            (_, Err(err)) => Err(err),
+        };
+
+        match res {
+            Ok((action, word)) => report.add(action, word, opts.preview),
+            Err(err) => {
+                let _ = t.rollback();
+                return Err(err);
+            }
+        }
+    }
+
+    if opts.dry_run {
+        t.rollback()?;
+    } else {
+        t.commit()?;
+    }
+    Ok(report)
+}
+
+fn put<T, E, F>(
+    t: &F,
+    entry: Entry,
+    duplicate: Duplicate,
+) -> Result<(Action, World), InternalError<E>>
+where
+    F: Transaction<T, InternalError<E>>,
+{
+    let (action, word) = put_word(t, entry.word, duplicate)?;
+    if action == Action::Skip {
+        return Ok((action, word));
+    }
+    if let Some(schedule) = &entry.schedule {
+        t.set_schedule(&word.name, schedule)?;
+    }
+    for tag in &entry.tags {
+        t.add_tag(&word.name, tag)?;
+    }
+    for a in &entry.attachments {
+        t.put_attachment(&word.name, a)?;
+    }
+    Ok((action, word))
+}
+
+fn put_word<T, E, F>(
+    t: &F,
+    word: World,
+    duplicate: Duplicate,
+) -> Result<(Action, World), InternalError<E>>
+where
+    F: Transaction<T, InternalError<E>>,
+{
+    let old = match t.get(&word.name) {
+        Ok(old) => old,
+        Err(InternalError::NotFound) => {
+            t.insert(&word)?;
+            return Ok((Action::Insert, word));
+        }
+        Err(err) => return Err(err),
+    };
+
+    let word = match duplicate {
+        Duplicate::Skip => return Ok((Action::Skip, word)),
+        Duplicate::Overwrite if old.message == word.message => return Ok((Action::Skip, word)),
+        Duplicate::Overwrite => (Action::Overwrite, word),
+        Duplicate::Merge => match merge_message(&old.message, &word.message) {
+            Some(message) => (
+                Action::Merge,
+                World {
+                    name: word.name,
+                    message,
+                },
+            ),
+            None => return Ok((Action::Skip, word)),
+        },
+    };
+    t.update(&word.1)?;
+    Ok(word)
+}
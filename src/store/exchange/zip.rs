@@ -0,0 +1,513 @@
+// a small zip archive reader and writer, enough for the packages of other tools
+//
+// entries are written without compression, and read when stored or deflated,
+// gzip files like the .dict.dz of dictionaries are read by gunzip
+use std::io;
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL: u32 = 0x06054b50;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("zip: {}", msg))
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// writes the entries one by one, the directory is written by finish
+pub struct Writer<W> {
+    inner: W,
+    offset: u32,
+    // name, crc, size and offset of the local header
+    entries: Vec<(String, u32, u32, u32)>,
+}
+
+impl<W: io::Write> Writer<W> {
+    pub fn new(inner: W) -> Writer<W> {
+        Writer {
+            inner,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let size = u32::try_from(data.len()).map_err(|_| invalid("entry is bigger than 4GB"))?;
+        let crc = crc32(data);
+
+        let mut h = Vec::with_capacity(30 + name.len());
+        h.extend(LOCAL_HEADER.to_le_bytes());
+        // version 2.0, utf-8 names, stored, no time
+        h.extend([20, 0, 0, 8, 0, 0, 0, 0, 0x21, 0]);
+        h.extend(crc.to_le_bytes());
+        h.extend(size.to_le_bytes());
+        h.extend(size.to_le_bytes());
+        h.extend((name.len() as u16).to_le_bytes());
+        h.extend(0u16.to_le_bytes());
+        h.extend(name.as_bytes());
+        self.inner.write_all(&h)?;
+        self.inner.write_all(data)?;
+
+        self.entries.push((name.to_owned(), crc, size, self.offset));
+        self.offset = self
+            .offset
+            .checked_add(h.len() as u32 + size)
+            .ok_or_else(|| invalid("archive is bigger than 4GB"))?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut d = Vec::new();
+        for (name, crc, size, offset) in &self.entries {
+            d.extend(CENTRAL_HEADER.to_le_bytes());
+            d.extend([20, 0, 20, 0, 0, 8, 0, 0, 0, 0, 0x21, 0]);
+            d.extend(crc.to_le_bytes());
+            d.extend(size.to_le_bytes());
+            d.extend(size.to_le_bytes());
+            d.extend((name.len() as u16).to_le_bytes());
+            // extra, comment, disk, internal and external attributes
+            d.extend([0u8; 12]);
+            d.extend(offset.to_le_bytes());
+            d.extend(name.as_bytes());
+        }
+
+        let count = self.entries.len() as u16;
+        let mut end = Vec::with_capacity(22);
+        end.extend(END_OF_CENTRAL.to_le_bytes());
+        end.extend([0u8; 4]);
+        end.extend(count.to_le_bytes());
+        end.extend(count.to_le_bytes());
+        end.extend((d.len() as u32).to_le_bytes());
+        end.extend(self.offset.to_le_bytes());
+        end.extend(0u16.to_le_bytes());
+
+        self.inner.write_all(&d)?;
+        self.inner.write_all(&end)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+struct Entry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed: usize,
+    size: usize,
+    offset: usize,
+}
+
+/// an archive read in memory
+pub struct Archive {
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+fn u16_at(data: &[u8], i: usize) -> io::Result<u16> {
+    data.get(i..i + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid("truncated archive"))
+}
+
+fn u32_at(data: &[u8], i: usize) -> io::Result<u32> {
+    data.get(i..i + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("truncated archive"))
+}
+
+impl Archive {
+    pub fn new(data: Vec<u8>) -> io::Result<Archive> {
+        // the end record is at the end, before a comment of at most 64KB
+        let min = data.len().saturating_sub(22 + 0xffff);
+        let end = (min..data.len().saturating_sub(21))
+            .rev()
+            .find(|i| u32_at(&data, *i).ok() == Some(END_OF_CENTRAL))
+            .ok_or_else(|| invalid("not a zip archive"))?;
+
+        let count = u16_at(&data, end + 10)? as usize;
+        let mut p = u32_at(&data, end + 16)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            if u32_at(&data, p)? != CENTRAL_HEADER {
+                return Err(invalid("broken central directory"));
+            }
+            let name_len = u16_at(&data, p + 28)? as usize;
+            let skip = name_len + u16_at(&data, p + 30)? as usize + u16_at(&data, p + 32)? as usize;
+            let name = data
+                .get(p + 46..p + 46 + name_len)
+                .ok_or_else(|| invalid("truncated archive"))?;
+            entries.push(Entry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                method: u16_at(&data, p + 10)?,
+                crc: u32_at(&data, p + 16)?,
+                compressed: u32_at(&data, p + 20)? as usize,
+                size: u32_at(&data, p + 24)? as usize,
+                offset: u32_at(&data, p + 42)? as usize,
+            });
+            p += 46 + skip;
+        }
+        Ok(Archive { data, entries })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|e| e.name == name)
+    }
+
+    /// the uncompressed content of the entry
+    pub fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        let e = self
+            .entries
+            .iter()
+            .find(|e| e.name == name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("zip: no {}", name)))?;
+
+        if u32_at(&self.data, e.offset)? != LOCAL_HEADER {
+            return Err(invalid("broken local header"));
+        }
+        let start = e.offset
+            + 30
+            + u16_at(&self.data, e.offset + 26)? as usize
+            + u16_at(&self.data, e.offset + 28)? as usize;
+        let raw = self
+            .data
+            .get(start..start + e.compressed)
+            .ok_or_else(|| invalid("truncated archive"))?;
+
+        let data = match e.method {
+            STORED => raw.to_vec(),
+            DEFLATED => inflate(raw, e.size)?,
+            m => return Err(invalid(&format!("compression method {} of {}", m, name))),
+        };
+        if data.len() != e.size || crc32(&data) != e.crc {
+            return Err(invalid(&format!("checksum mismatch of {}", name)));
+        }
+        Ok(data)
+    }
+}
+
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    cnt: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn need(&mut self, n: u32) -> io::Result<u32> {
+        while self.cnt < n {
+            let b = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| invalid("truncated deflate stream"))?;
+            self.buf |= (b as u32) << self.cnt;
+            self.pos += 1;
+            self.cnt += 8;
+        }
+        let v = self.buf & ((1u32 << n) - 1);
+        self.buf >>= n;
+        self.cnt -= n;
+        Ok(v)
+    }
+}
+
+// canonical huffman code by the count of codes of every length
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for l in lengths {
+            counts[*l as usize] += 1;
+        }
+        let mut offs = [0u16; 16];
+        for len in 1..15 {
+            offs[len + 1] = offs[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (sym, l) in lengths.iter().enumerate() {
+            if *l != 0 {
+                symbols[offs[*l as usize] as usize] = sym as u16;
+                offs[*l as usize] += 1;
+            }
+        }
+        counts[0] = 0;
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= bits.need(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("bad huffman code"))
+    }
+}
+
+const LEN_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LEN_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// order of the code length codes of a dynamic block
+const CLEN_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// decompress a raw deflate stream, RFC 1951
+pub fn inflate(data: &[u8], size_hint: usize) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(size_hint);
+    let mut bits = Bits {
+        data,
+        pos: 0,
+        buf: 0,
+        cnt: 0,
+    };
+
+    loop {
+        let last = bits.need(1)?;
+        match bits.need(2)? {
+            0 => {
+                // stored block starts at a byte boundary
+                bits.buf = 0;
+                bits.cnt = 0;
+                let len = bits.need(16)? as usize;
+                if bits.need(16)? as usize != !len & 0xffff {
+                    return Err(invalid("bad stored block length"));
+                }
+                let block = data
+                    .get(bits.pos..bits.pos + len)
+                    .ok_or_else(|| invalid("truncated deflate stream"))?;
+                out.extend_from_slice(block);
+                bits.pos += len;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let lencode = Huffman::new(&lengths);
+                let distcode = Huffman::new(&[5u8; 30]);
+                codes(&mut bits, &mut out, &lencode, &distcode)?;
+            }
+            2 => {
+                let (lencode, distcode) = dynamic(&mut bits)?;
+                codes(&mut bits, &mut out, &lencode, &distcode)?;
+            }
+            _ => return Err(invalid("bad deflate block type")),
+        }
+        if last == 1 {
+            return Ok(out);
+        }
+    }
+}
+
+/// decompress a gzip file of one member, RFC 1952
+pub fn gunzip(data: &[u8]) -> io::Result<Vec<u8>> {
+    if data.len() < 18 || data[..3] != [0x1f, 0x8b, 8] {
+        return Err(invalid("not a gzip file"));
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    // extra field, the chunk table of dictzip
+    if flags & 4 != 0 {
+        pos += 2 + u16_at(data, pos)? as usize;
+    }
+    // file name and comment
+    for flag in [8, 16] {
+        if flags & flag != 0 {
+            let end = data
+                .get(pos..)
+                .and_then(|d| d.iter().position(|b| *b == 0))
+                .ok_or_else(|| invalid("truncated gzip header"))?;
+            pos += end + 1;
+        }
+    }
+    if flags & 2 != 0 {
+        pos += 2;
+    }
+
+    let trailer = data.len() - 8;
+    let (crc, size) = (u32_at(data, trailer)?, u32_at(data, trailer + 4)?);
+    let body = data
+        .get(pos..trailer)
+        .ok_or_else(|| invalid("truncated gzip header"))?;
+    let out = inflate(body, size as usize)?;
+    if out.len() as u32 != size || crc32(&out) != crc {
+        return Err(invalid("gzip checksum mismatch"));
+    }
+    Ok(out)
+}
+
+fn dynamic(bits: &mut Bits) -> io::Result<(Huffman, Huffman)> {
+    let nlen = bits.need(5)? as usize + 257;
+    let ndist = bits.need(5)? as usize + 1;
+    let ncode = bits.need(4)? as usize + 4;
+
+    let mut lengths = [0u8; 19];
+    for i in CLEN_ORDER.iter().take(ncode) {
+        lengths[*i] = bits.need(3)? as u8;
+    }
+    let clencode = Huffman::new(&lengths);
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut i = 0;
+    while i < nlen + ndist {
+        let sym = clencode.decode(bits)?;
+        let (len, repeat) = match sym {
+            0..=15 => (sym as u8, 1),
+            16 if i > 0 => (lengths[i - 1], 3 + bits.need(2)? as usize),
+            17 => (0, 3 + bits.need(3)? as usize),
+            18 => (0, 11 + bits.need(7)? as usize),
+            _ => return Err(invalid("bad code lengths")),
+        };
+        if i + repeat > lengths.len() {
+            return Err(invalid("too many code lengths"));
+        }
+        lengths[i..i + repeat].fill(len);
+        i += repeat;
+    }
+    Ok((
+        Huffman::new(&lengths[..nlen]),
+        Huffman::new(&lengths[nlen..]),
+    ))
+}
+
+fn codes(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    lencode: &Huffman,
+    distcode: &Huffman,
+) -> io::Result<()> {
+    loop {
+        let sym = lencode.decode(bits)? as usize;
+        if sym < 256 {
+            out.push(sym as u8);
+            continue;
+        }
+        if sym == 256 {
+            return Ok(());
+        }
+
+        let sym = sym - 257;
+        if sym >= LEN_BASE.len() {
+            return Err(invalid("bad length code"));
+        }
+        let len = LEN_BASE[sym] as usize + bits.need(LEN_EXTRA[sym] as u32)? as usize;
+        let sym = distcode.decode(bits)? as usize;
+        if sym >= DIST_BASE.len() {
+            return Err(invalid("bad distance code"));
+        }
+        let dist = DIST_BASE[sym] as usize + bits.need(DIST_EXTRA[sym] as u32)? as usize;
+        if dist > out.len() {
+            return Err(invalid("distance too far back"));
+        }
+        let start = out.len() - dist;
+        for i in 0..len {
+            out.push(out[start + i]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_archive() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+
+        let mut w = Writer::new(Vec::new());
+        w.add("collection.anki2", b"sqlite").unwrap();
+        w.add("media", b"{}").unwrap();
+        let data = w.finish().unwrap();
+
+        let a = Archive::new(data).unwrap();
+        assert_eq!(a.names(), vec!["collection.anki2", "media"]);
+        assert_eq!(a.read("media").unwrap(), b"{}");
+        assert_eq!(a.read("collection.anki2").unwrap(), b"sqlite");
+        assert!(a.read("nofound").is_err());
+    }
+
+    #[test]
+    fn test_inflate() {
+        // raw deflate streams made by zlib
+        let fixed = hex("cb48cdc9c957c8409000");
+        assert_eq!(inflate(&fixed, 0).unwrap(), b"hello hello hello");
+
+        let dynamic = DYNAMIC.concat();
+        let text: String = (0..40)
+            .map(|i| format!("word{} means {} ", i, i * 7 % 13))
+            .collect();
+        assert_eq!(inflate(&hex(&dynamic), 0).unwrap(), text.as_bytes());
+
+        let stored = hex("010500faff68656c6c6f");
+        assert_eq!(inflate(&stored, 0).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_gunzip() {
+        // gzip.compress(b"hello hello hello") with the name of the file
+        let gz = hex("1f8b08080000000002ff612e74787400cb48cdc9c957c84090008088f9e511000000");
+        assert_eq!(gunzip(&gz).unwrap(), b"hello hello hello");
+
+        let mut bad = gz.clone();
+        bad[gz.len() - 8] ^= 1;
+        assert!(gunzip(&bad).is_err());
+        assert!(gunzip(b"hello").is_err());
+    }
+
+    const DYNAMIC: [&str; 4] = [
+        "55d14b0a02311045d1adf41252f5f25d8ea0435bd0416f5fa924c21d5e025defd0d7eb7d4fc7f371",
+        "3b3f473aae5fd9aa16e5ab2c4aab7a545ee55165d588aaab14d5f657e689be32478dfd386fd89e53",
+        "66ee3d36cfd85e54678ae333d65bc17cabd86f0d00eb10d800c1130d6e40b853e182c233155ea0f0",
+        "0a8537fe830e850f2894a09041218742a242190a152a54a150a3421d0a8dbfe20b",
+    ];
+}
@@ -1,23 +1,128 @@
 use crate::db::sqlite::store as dbstore;
 use crate::store;
+use crate::store::dict;
+use crate::store::exchange;
 use fl::dialog;
 use fltk as fl;
 use fltk::prelude::*;
 use std::cell::RefCell;
-use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::{Arc, RwLock};
 
 pub struct APP {
-    pub lib: Rc<RefCell<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
+    pub lib: Arc<RwLock<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
     // pub output: fl::output::MultilineOutput,
     pub tabs: fl::group::Pack,
 }
 
+// the first line of the tag sidebar, lists every word
+const ALL_TAGS: &str = "(all)";
+
+// open the file or the url by the system, audio is played by the default player
+fn open_system(target: &std::ffi::OsStr) -> Result<(), String> {
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = std::process::Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    cmd.arg(target)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("open {} fail: {}", target.to_string_lossy(), e))
+}
+
+// write the attachment to the temp directory and open it by the system
+fn open_attachment(a: &store::attachment::Attachment) -> Result<(), String> {
+    let dir = std::env::temp_dir().join("wordlib");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(&a.name);
+    std::fs::write(&path, &a.data).map_err(|e| e.to_string())?;
+    open_system(path.as_os_str())
+}
+
+// the first argument of a call from the preview page, passed as a json array
+fn first_arg(req: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(req)
+        .ok()?
+        .get(0)?
+        .as_str()
+        .map(String::from)
+}
+
+// the words selected in the index
+fn selected_words(index: &fl::browser::MultiBrowser) -> Vec<String> {
+    index
+        .selected_items()
+        .into_iter()
+        .filter_map(|line| index.text(line))
+        .map(|name| name.trim_end().to_owned())
+        .collect()
+}
+
+// select the line of the index and nothing else
+fn select_only(index: &mut fl::browser::MultiBrowser, line: i32) {
+    for l in index.selected_items() {
+        index.deselect(l);
+    }
+    index.select(line);
+}
+
+// the files of a drop, as paths or file:// urls one each line, empty if
+// anything else is dropped
+fn dropped_files(text: &str) -> Vec<std::path::PathBuf> {
+    let mut res = Vec::new();
+    for line in text.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let path = match line.strip_prefix("file://") {
+            Some(url) => {
+                let bytes = url.as_bytes();
+                let mut decoded = Vec::with_capacity(bytes.len());
+                let mut i = 0;
+                while i < bytes.len() {
+                    let hex = bytes
+                        .get(i + 1..i + 3)
+                        .and_then(|h| std::str::from_utf8(h).ok())
+                        .and_then(|h| u8::from_str_radix(h, 16).ok());
+                    match (bytes[i], hex) {
+                        (b'%', Some(b)) => {
+                            decoded.push(b);
+                            i += 3;
+                        }
+                        (b, _) => {
+                            decoded.push(b);
+                            i += 1;
+                        }
+                    }
+                }
+                std::path::PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+            }
+            None => std::path::PathBuf::from(line),
+        };
+        if !path.is_file() {
+            return Vec::new();
+        }
+        res.push(path);
+    }
+    res
+}
+
 struct Current {
     but: Option<fl::button::Button>,
-    index: fl::browser::SelectBrowser,
+    // several words are selected to be cut or copied to another library
+    index: fl::browser::MultiBrowser,
+    // the tags of the store, a click filters the index by the tag
+    tags: fl::browser::HoldBrowser,
     // lib: Rc<RefCell<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
     store_key: String,
+    // the home page, hidden when a tab is opened
+    home: Option<fl::group::Group>,
+    // the menu bar holding the recent libraries
+    menu: Option<fl::menu::SysMenuBar>,
+    // shows the home page when the last tab is closed
+    home_button: Option<fl::button::Button>,
 }
 
 impl Current {
@@ -39,7 +144,7 @@ impl APP {
         tabs: fl::group::Pack,
         // output: fl::output::MultilineOutput,
         current: Rc<RefCell<Current>>,
-        lib: Rc<RefCell<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
+        lib: Arc<RwLock<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
     ) -> fl::menu::SysMenuBar {
         let mut menu = fltk::menu::SysMenuBar::new(x, y, width, height, None);
 
@@ -62,34 +167,253 @@ impl APP {
                     let mut fs = fc.filenames();
                     while let Some(i) = fs.pop() {
                         let key = i.clone().into_os_string().into_string().unwrap();
-                        match dbstore::Sqlite::new(key.clone()) {
-                            Ok(v) => {
-                                let index = &mut lib.borrow_mut().stores;
-                                index.insert(key.clone(), Box::new(v));
+                        if let Err(msg) = APP::open_tab(&key, &mut tabs, cur.clone(), lib.clone()) {
+                            fl::dialog::alert_default(&msg);
+                            return;
+                        }
+                    }
+                }
+            },
+        );
+        menu
+    }
+
+    // open the library file in a new tab, or switch to its tab if it is opened
+    fn open_tab(
+        key: &str,
+        tabs: &mut fl::group::Pack,
+        current: Rc<RefCell<Current>>,
+        lib: Arc<RwLock<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
+    ) -> Result<(), String> {
+        if !lib.read().unwrap().contains(key) {
+            let store = dbstore::Sqlite::new(key.to_owned()).map_err(|err| {
+                format!("open the file fail, check the file format, error:{}", err)
+            })?;
+            lib.write().unwrap().add_store(key, Box::new(store));
+        }
+        // a closed saved search is still in the library
+        if APP::find_tab(tabs, key).is_none() {
+            // let but = APP::tab_buttom(&key, output.clone(), cur.clone(), lib.clone());
+            let but = APP::tab_buttom(key, current.clone(), lib.clone());
+            tabs.add(&but);
+        }
+        if let Some(mut but) = APP::find_tab(tabs, key) {
+            but.do_callback();
+        }
+        // tab redraw is useless
+        tabs.parent().unwrap().redraw();
+
+        if std::path::Path::new(key).is_file() {
+            if let Some(path) = store::home::recent_path() {
+                if let Err(err) = store::home::add_recent(&path, key) {
+                    fl::dialog::alert_default(&err);
+                }
+            }
+            APP::recent_menu(tabs.clone(), current, lib);
+        }
+        Ok(())
+    }
+
+    fn find_tab<G: GroupExt>(tabs: &G, key: &str) -> Option<fl::widget::Widget> {
+        (0..tabs.children())
+            .filter_map(|i| tabs.child(i))
+            .find(|but| but.label() == key)
+    }
+
+    // fill Store/Recent with the libraries opened lately, a library which is
+    // gone is dropped from the list when it is clicked
+    fn recent_menu(
+        tabs: fl::group::Pack,
+        current: Rc<RefCell<Current>>,
+        lib: Arc<RwLock<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
+    ) {
+        // the menu can't be changed in the callback of its item
+        fl::app::add_timeout3(0.0, move |_| {
+            let mut menu = match current.borrow().menu.clone() {
+                Some(menu) => menu,
+                None => return,
+            };
+            let recent = store::home::recent_path()
+                .map(|path| store::home::load_recent(&path))
+                .unwrap_or_default();
+            let idx = menu.find_index("Store/Recent");
+            if idx >= 0 {
+                let _ = menu.clear_submenu(idx);
+            }
+            if recent.is_empty() {
+                menu.add(
+                    "Store/Recent/(none)",
+                    fl::enums::Shortcut::None,
+                    fl::menu::MenuFlag::Inactive,
+                    |_| {},
+                );
+            }
+            for key in recent {
+                // a slash of the path would make a submenu
+                let label = key
+                    .replace('\\', "\\\\")
+                    .replace('/', "\\/")
+                    .replace('&', "&&")
+                    .replace('_', "\\_");
+                menu.add(
+                    &format!("Store/Recent/{}", label),
+                    fl::enums::Shortcut::None,
+                    fl::menu::MenuFlag::Normal,
+                    {
+                        let mut tabs = tabs.clone();
+                        let current = current.clone();
+                        let lib = lib.clone();
+                        move |_| {
+                            if !lib.read().unwrap().contains(&key)
+                                && !std::path::Path::new(&key).is_file()
+                            {
+                                fl::dialog::alert_default(&format!("{} is not there", key));
+                                if let Some(path) = store::home::recent_path() {
+                                    let _ = store::home::remove_recent(&path, &key);
+                                }
+                                APP::recent_menu(tabs.clone(), current.clone(), lib.clone());
+                                return;
                             }
-                            Err(err) => {
-                                // TODO add alter window
-                                let msg = format!(
-                                    "open the file fail, check the file format, error:{}",
-                                    err
-                                );
+                            if let Err(msg) =
+                                APP::open_tab(&key, &mut tabs, current.clone(), lib.clone())
+                            {
                                 fl::dialog::alert_default(&msg);
-                                return;
                             }
                         }
+                    },
+                );
+            }
+        });
+    }
 
-                        // let but = APP::tab_buttom(&key, output.clone(), cur.clone(), lib.clone());
-                        let mut but = APP::tab_buttom(&key, cur.clone(), lib.clone());
-                        tabs.add(&but);
-                        but.do_callback();
+    // close the tab of key and the store behind it, the tab next to it is
+    // opened if it was the current one
+    fn close_tab<G: GroupExt>(
+        key: &str,
+        tabs: &mut G,
+        current: Rc<RefCell<Current>>,
+        lib: Arc<RwLock<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
+    ) {
+        let but = match APP::find_tab(tabs, key) {
+            Some(but) => but,
+            None => return,
+        };
+        let at = tabs.find(&but);
+        tabs.remove(&but);
+        fl::app::delete_widget(but);
+        // a saved search stays in the library to be opened again
+        drop(lib.write().unwrap().remove_store(key));
+
+        if current.borrow().store_key == key {
+            let (home, home_button) = {
+                let mut cur = current.borrow_mut();
+                cur.but = None;
+                cur.set_store_key(String::new());
+                cur.index.clone().clear();
+                cur.tags.clone().clear();
+                (cur.home.clone(), cur.home_button.clone())
+            };
+            let home_shown = home.map(|h| h.visible()).unwrap_or(false);
+            if tabs.children() > 0 {
+                if !home_shown {
+                    if let Some(mut next) = tabs.child(at.min(tabs.children() - 1)) {
+                        next.do_callback();
                     }
+                }
+            } else if let Some(mut home_button) = home_button {
+                home_button.do_callback();
+            }
+        }
+        if let Some(mut parent) = tabs.parent() {
+            parent.redraw();
+        }
+    }
 
-                    // tab redraw is useless
-                    tabs.parent().unwrap().redraw();
+    // the tab can't be deleted in its own event
+    fn defer_close(
+        but: &fl::button::Button,
+        current: Rc<RefCell<Current>>,
+        lib: Arc<RwLock<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
+    ) {
+        let key = but.label();
+        if let Some(mut tabs) = but.parent() {
+            fl::app::add_timeout3(0.0, move |_| {
+                APP::close_tab(&key, &mut tabs, current.clone(), lib.clone())
+            });
+        }
+    }
+
+    // rename the library of the tab, a saved search is renamed in the
+    // library, a library file is closed, renamed and opened again
+    fn rename_tab(
+        but: &mut fl::button::Button,
+        current: Rc<RefCell<Current>>,
+        lib: Arc<RwLock<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
+    ) -> Result<(), String> {
+        let key = but.label();
+        let is_search = lib
+            .read()
+            .unwrap()
+            .searches()
+            .iter()
+            .any(|(name, _)| *name == key);
+        let old_name = match is_search {
+            true => key.clone(),
+            false => store::exchange::jsonl::library_name(&key),
+        };
+        let name = match fl::dialog::input_default("new name", &old_name) {
+            Some(name) if !name.trim().is_empty() && name.trim() != old_name => {
+                name.trim().to_owned()
+            }
+            _ => return Ok(()),
+        };
+
+        let new_key = if is_search {
+            let mut lib = lib.write().unwrap();
+            if lib.contains(&name) {
+                return Err(format!("{} already exists", name));
+            }
+            let query = lib.remove_search(&key).unwrap();
+            lib.add_search(&name, query);
+            if let Some(path) = store::smart::home_path() {
+                store::smart::save(&path, &lib.searches())?;
+            }
+            name
+        } else {
+            let mut lib = lib.write().unwrap();
+            // the connection must be closed before the file is moved
+            drop(lib.remove_store(&key));
+            let renamed = store::home::rename(&key, &name);
+            let path = renamed.clone().unwrap_or_else(|_| key.clone());
+            let store = dbstore::Sqlite::new(path.clone())
+                .map_err(|err| format!("open {} fail, {}", path, err))?;
+            lib.add_store(&path, Box::new(store));
+            renamed?
+        };
+        if !is_search {
+            if let Some(recent) = store::home::recent_path() {
+                let _ = store::home::remove_recent(&recent, &key);
+                if let Err(err) = store::home::add_recent(&recent, &new_key) {
+                    fl::dialog::alert_default(&err);
                 }
-            },
-        );
-        menu
+            }
+        }
+
+        but.set_label(&new_key);
+        let (width, height) = but.measure_label();
+        but.resize(but.x(), but.y(), width + 7, height);
+        if current.borrow().store_key == key {
+            current.borrow_mut().set_store_key(new_key);
+        }
+        if let Some(tabs) = but.parent() {
+            if let Some(mut parent) = tabs.parent() {
+                parent.redraw();
+            }
+            if let Some(tabs) = fl::group::Pack::from_dyn_widget(&tabs) {
+                APP::recent_menu(tabs, current, lib);
+            }
+        }
+        Ok(())
     }
 
     fn file_tab(x: i32, y: i32, width: i32, height: i32) -> fl::group::Pack {
@@ -101,15 +425,15 @@ impl APP {
         tabs
     }
 
-    fn browser() -> fl::browser::SelectBrowser {
-        fl::browser::SelectBrowser::new(0, 0, 0, 0, None)
+    fn browser() -> fl::browser::MultiBrowser {
+        fl::browser::MultiBrowser::new(0, 0, 0, 0, None)
     }
 
     fn tab_buttom(
         key: &str,
         // mut output: fl::output::MultilineOutput,
         current: Rc<RefCell<Current>>,
-        lib: Rc<RefCell<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
+        lib: Arc<RwLock<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
     ) -> fl::button::Button {
         // let key_width = key.len() as i32;
         let mut but = fl::button::Button::new(0, 0, 0, 0, None);
@@ -123,12 +447,61 @@ impl APP {
         but.set_color(fl::enums::Color::White);
 
         but.clear_visible_focus();
+        // a right click offers to close or rename the tab, a middle click
+        // closes it and a drag moves it among the tabs
+        but.handle({
+            let current = current.clone();
+            let lib = lib.clone();
+            move |b, ev| match ev {
+                fl::enums::Event::Push => match fl::app::event_mouse_button() {
+                    fl::app::MouseButton::Right => {
+                        let menu = fl::menu::MenuItem::new(&["Close", "Rename"]);
+                        let choice = menu.popup(fl::app::event_x(), fl::app::event_y());
+                        match choice.and_then(|item| item.label()).as_deref() {
+                            Some("Close") => APP::defer_close(b, current.clone(), lib.clone()),
+                            Some("Rename") => {
+                                if let Err(err) = APP::rename_tab(b, current.clone(), lib.clone()) {
+                                    fl::dialog::alert_default(&err);
+                                }
+                            }
+                            _ => {}
+                        }
+                        true
+                    }
+                    fl::app::MouseButton::Middle => {
+                        APP::defer_close(b, current.clone(), lib.clone());
+                        true
+                    }
+                    _ => false,
+                },
+                fl::enums::Event::Drag => {
+                    if let Some(mut tabs) = b.parent() {
+                        let ex = fl::app::event_x();
+                        let from = tabs.find(&*b);
+                        let to = (0..tabs.children()).find(|i| {
+                            tabs.child(*i)
+                                .map(|t| t.x() <= ex && ex < t.x() + t.w())
+                                .unwrap_or(false)
+                        });
+                        if let Some(to) = to.filter(|to| *to != from) {
+                            tabs.insert(&*b, if to > from { to + 1 } else { to });
+                            if let Some(mut parent) = tabs.parent() {
+                                parent.redraw();
+                            }
+                        }
+                    }
+                    false
+                }
+                _ => false,
+            }
+        });
         but.set_callback({
             // let mut output = output.clone();
             let mut but = but.clone();
             move |b| {
                 let key = b.label();
-                match lib.borrow().list(0, &key) {
+                let tags = lib.read().unwrap().tags(&key).unwrap_or_default();
+                match lib.read().unwrap().list(0, &key) {
                     Ok(ws) => {
                         let mut cur = current.borrow_mut();
                         if let Some(mut cur) = cur.but.clone() {
@@ -143,6 +516,9 @@ impl APP {
                         // current.get_mut().but = Some(but);
                         cur.set_button(but.clone());
                         cur.set_store_key(key);
+                        if let Some(mut home) = cur.home.clone() {
+                            home.hide();
+                        }
 
                         // output.set_value("");
 
@@ -158,7 +534,14 @@ impl APP {
                             //     return;
                             // }
                         }
-                        browser.show()
+                        browser.show();
+
+                        let mut tag_browser = cur.tags.clone();
+                        tag_browser.clear();
+                        tag_browser.add(ALL_TAGS);
+                        for (tag, n) in &tags {
+                            tag_browser.add(&format!("{} ({})", tag, n));
+                        }
                     }
                     Err(err) => {
                         let msg = format!(
@@ -174,6 +557,423 @@ impl APP {
         but
     }
 
+    // the home page listing the libraries of the workspace, returned with the
+    // function reloading the list
+    fn home(
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        tabs: fl::group::Pack,
+        current: Rc<RefCell<Current>>,
+        lib: Arc<RwLock<store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>>>,
+    ) -> (fl::group::Group, Rc<dyn Fn()>) {
+        let mut page = fl::group::Group::new(x, y, width, height, None);
+        page.set_frame(fl::enums::FrameType::FlatBox);
+
+        let bar_height = 30;
+        let mut bar = fl::group::Pack::new(x + 2, y + 2, width - 4, bar_height, None);
+        bar.set_type(fl::group::PackType::Horizontal);
+        bar.set_spacing(3);
+        let mut buttons = Vec::new();
+        for label in [
+            "New",
+            "Open",
+            "Rename",
+            "Duplicate",
+            "Describe",
+            "Delete",
+            "Select All",
+            "Workspace",
+        ] {
+            let mut but = fl::button::Button::new(0, 0, 0, 0, None);
+            but.set_label(label);
+            let (w, _) = but.measure_label();
+            but.resize(0, 0, w + 16, bar_height);
+            but.clear_visible_focus();
+            buttons.push(but);
+        }
+        bar.end();
+        let mut place = fl::frame::Frame::new(x + 4, y + bar_height + 4, width - 8, 22, None);
+        place.set_align(fl::enums::Align::Left | fl::enums::Align::Inside);
+
+        // the libraries with a header line, and the first letters of their
+        // names to jump to
+        let top = y + bar_height + 28;
+        let letters_width = 40;
+        let mut list = fl::browser::MultiBrowser::new(
+            x,
+            top,
+            width - letters_width - 4,
+            y + height - top,
+            None,
+        );
+        let description_width = (width - letters_width - 504).max(100);
+        list.set_column_widths(&[200, description_width, 70, 70, 140]);
+        let mut letters = fl::browser::HoldBrowser::new(
+            x + width - letters_width,
+            top,
+            letters_width,
+            (y + height - top).min(27 * 20),
+            None,
+        );
+        page.end();
+
+        let dir = Rc::new(RefCell::new(
+            store::home::workspace().unwrap_or_else(|| std::path::PathBuf::from(".")),
+        ));
+        // the libraries of the lines after the header
+        let rows: Rc<RefCell<Vec<store::home::Summary>>> = Rc::new(RefCell::new(Vec::new()));
+        let refresh: Rc<dyn Fn()> = Rc::new({
+            let dir = dir.clone();
+            let rows = rows.clone();
+            let list = list.clone();
+            let letters = letters.clone();
+            move || {
+                let (mut list, mut letters, mut place) =
+                    (list.clone(), letters.clone(), place.clone());
+                list.clear();
+                letters.clear();
+                rows.borrow_mut().clear();
+                let dir = dir.borrow().clone();
+                place.set_label(&format!("workspace: {}", dir.display()));
+                list.add("@bName\t@bDescription\t@b@rWords\t@b@rDue\t@bModified");
+                let libs = match store::home::list(&dir) {
+                    Ok(libs) => libs,
+                    Err(err) => {
+                        fl::dialog::alert_default(&err);
+                        return;
+                    }
+                };
+                let mut errs = Vec::new();
+                for summary in libs {
+                    match summary {
+                        Ok(summary) => {
+                            let modified = summary
+                                .modified
+                                .map(store::sync::format_time)
+                                .unwrap_or_default();
+                            let description: Vec<&str> =
+                                summary.description.split_whitespace().collect();
+                            list.add(&format!(
+                                "@.{}\t@.{}\t@r{}\t@r{}\t@.{}",
+                                summary.name,
+                                description.join(" "),
+                                summary.words,
+                                summary.due,
+                                modified
+                            ));
+                            rows.borrow_mut().push(summary);
+                        }
+                        Err(err) => errs.push(err),
+                    }
+                }
+                let mut firsts: Vec<String> = Vec::new();
+                for summary in rows.borrow().iter() {
+                    let first: String = summary
+                        .name
+                        .chars()
+                        .take(1)
+                        .flat_map(char::to_uppercase)
+                        .collect();
+                    if !firsts.contains(&first) {
+                        firsts.push(first);
+                    }
+                }
+                for first in &firsts {
+                    letters.add(&format!("@c@.{}", first));
+                }
+                if !errs.is_empty() {
+                    fl::dialog::alert_default(&errs.join("\n"));
+                }
+            }
+        });
+        // the selected libraries
+        let selected = {
+            let list = list.clone();
+            let rows = rows.clone();
+            move || -> Vec<store::home::Summary> {
+                let rows = rows.borrow();
+                (2..=list.size())
+                    .filter(|l| list.selected(*l))
+                    .filter_map(|l| rows.get(l as usize - 2).cloned())
+                    .collect()
+            }
+        };
+        // the one selected library
+        let single = {
+            let selected = selected.clone();
+            move || -> Option<store::home::Summary> {
+                match selected().as_slice() {
+                    [summary] => Some(summary.clone()),
+                    _ => {
+                        fl::dialog::alert_default("select one library");
+                        None
+                    }
+                }
+            }
+        };
+        // an opened library can't be renamed or deleted under its tab, it is
+        // still opened unless its tab is closed
+        let opened = {
+            let lib = lib.clone();
+            let tabs = tabs.clone();
+            let current = current.clone();
+            move |summary: &store::home::Summary| -> bool {
+                if !lib.read().unwrap().contains(&summary.path) {
+                    return false;
+                }
+                let msg = format!("{} is opened, close the tab?", summary.name);
+                if fl::dialog::choice2_default(&msg, "Cancel", "Close", "") != Some(1) {
+                    return true;
+                }
+                APP::close_tab(
+                    &summary.path,
+                    &mut tabs.clone(),
+                    current.clone(),
+                    lib.clone(),
+                );
+                false
+            }
+        };
+
+        // a double click opens the library
+        list.set_callback({
+            let rows = rows.clone();
+            let mut tabs = tabs.clone();
+            let current = current.clone();
+            let lib = lib.clone();
+            move |b| {
+                if !fl::app::event_clicks() || b.value() < 2 {
+                    return;
+                }
+                let summary = rows.borrow().get(b.value() as usize - 2).cloned();
+                if let Some(summary) = summary {
+                    if let Err(msg) =
+                        APP::open_tab(&summary.path, &mut tabs, current.clone(), lib.clone())
+                    {
+                        fl::dialog::alert_default(&msg);
+                    }
+                }
+            }
+        });
+
+        letters.set_callback({
+            let rows = rows.clone();
+            let mut list = list.clone();
+            move |b| {
+                let first = match b.selected_text() {
+                    Some(text) => text.trim_start_matches("@c@.").to_owned(),
+                    None => return,
+                };
+                let line = rows.borrow().iter().position(|s| {
+                    s.name
+                        .chars()
+                        .take(1)
+                        .flat_map(char::to_uppercase)
+                        .collect::<String>()
+                        == first
+                });
+                if let Some(line) = line {
+                    list.top_line(line as i32 + 2);
+                }
+            }
+        });
+
+        buttons[0].set_callback({
+            let dir = dir.clone();
+            let refresh = refresh.clone();
+            let mut tabs = tabs.clone();
+            let current = current.clone();
+            let lib = lib.clone();
+            move |_| {
+                let name = match fl::dialog::input_default("library name:", "") {
+                    Some(name) if !name.trim().is_empty() => name,
+                    _ => return,
+                };
+                let description = match fl::dialog::input_default("description:", "") {
+                    Some(description) => description,
+                    None => return,
+                };
+                let res = store::home::create(&dir.borrow(), &name, description.trim());
+                refresh();
+                let res = res
+                    .and_then(|path| APP::open_tab(&path, &mut tabs, current.clone(), lib.clone()));
+                if let Err(msg) = res {
+                    fl::dialog::alert_default(&msg);
+                }
+            }
+        });
+
+        buttons[1].set_callback({
+            let selected = selected.clone();
+            let mut tabs = tabs.clone();
+            let current = current.clone();
+            let lib = lib.clone();
+            move |_| {
+                let libs = selected();
+                if libs.is_empty() {
+                    fl::dialog::alert_default("select the libraries first");
+                }
+                for summary in libs {
+                    if let Err(msg) =
+                        APP::open_tab(&summary.path, &mut tabs, current.clone(), lib.clone())
+                    {
+                        fl::dialog::alert_default(&msg);
+                        return;
+                    }
+                }
+            }
+        });
+
+        buttons[2].set_callback({
+            let single = single.clone();
+            let opened = opened.clone();
+            let refresh = refresh.clone();
+            move |_| {
+                let summary = match single() {
+                    Some(summary) if !opened(&summary) => summary,
+                    _ => return,
+                };
+                let name = match fl::dialog::input_default("new name:", &summary.name) {
+                    Some(name) if !name.trim().is_empty() && name.trim() != summary.name => name,
+                    _ => return,
+                };
+                if let Err(msg) = store::home::rename(&summary.path, &name) {
+                    fl::dialog::alert_default(&msg);
+                }
+                refresh();
+            }
+        });
+
+        buttons[3].set_callback({
+            let dir = dir.clone();
+            let selected = selected.clone();
+            let refresh = refresh.clone();
+            move |_| {
+                let libs = selected();
+                let mut errs = Vec::new();
+                for summary in &libs {
+                    // the name is asked for one library, the copies of more are numbered
+                    let name = if libs.len() == 1 {
+                        let name = format!("{} copy", summary.name);
+                        match fl::dialog::input_default("name of the copy:", &name) {
+                            Some(name) if !name.trim().is_empty() => name,
+                            _ => return,
+                        }
+                    } else {
+                        let dir = dir.borrow();
+                        (1..)
+                            .map(|n| match n {
+                                1 => format!("{} copy", summary.name),
+                                n => format!("{} copy {}", summary.name, n),
+                            })
+                            .find(|name| store::home::library_path(&dir, name).is_ok())
+                            .unwrap()
+                    };
+                    if let Err(err) = store::home::duplicate(&summary.path, &name) {
+                        errs.push(err);
+                    }
+                }
+                if libs.is_empty() {
+                    errs.push(String::from("select the libraries first"));
+                }
+                refresh();
+                if !errs.is_empty() {
+                    fl::dialog::alert_default(&errs.join("\n"));
+                }
+            }
+        });
+
+        buttons[4].set_callback({
+            let single = single.clone();
+            let refresh = refresh.clone();
+            move |_| {
+                let summary = match single() {
+                    Some(summary) => summary,
+                    None => return,
+                };
+                let description =
+                    match fl::dialog::input_default("description:", &summary.description) {
+                        Some(description) => description,
+                        None => return,
+                    };
+                if let Err(msg) = store::home::describe(&summary.path, description.trim()) {
+                    fl::dialog::alert_default(&msg);
+                }
+                refresh();
+            }
+        });
+
+        buttons[5].set_callback({
+            let selected = selected.clone();
+            let refresh = refresh.clone();
+            move |_| {
+                let libs = selected();
+                if libs.is_empty() {
+                    fl::dialog::alert_default("select the libraries first");
+                    return;
+                }
+                if libs.iter().any(&opened) {
+                    return;
+                }
+                let names: Vec<&str> = libs.iter().map(|s| s.name.as_str()).collect();
+                let msg = format!(
+                    "delete {} with {} words? the files are removed",
+                    names.join(", "),
+                    libs.iter().map(|s| s.words).sum::<usize>()
+                );
+                if fl::dialog::choice2_default(&msg, "Cancel", "Delete", "") != Some(1) {
+                    return;
+                }
+                let errs: Vec<String> = libs
+                    .iter()
+                    .filter_map(|s| store::home::remove(&s.path).err())
+                    .collect();
+                refresh();
+                if !errs.is_empty() {
+                    fl::dialog::alert_default(&errs.join("\n"));
+                }
+            }
+        });
+
+        buttons[6].set_callback({
+            let mut list = list.clone();
+            move |_| {
+                let all = (2..=list.size()).all(|l| list.selected(l));
+                for l in 2..=list.size() {
+                    match all {
+                        true => list.deselect(l),
+                        false => list.select(l),
+                    }
+                }
+            }
+        });
+
+        buttons[7].set_callback({
+            let dir = dir.clone();
+            let refresh = refresh.clone();
+            move |_| {
+                let mut fc = fl::dialog::NativeFileChooser::new(
+                    fl::dialog::NativeFileChooserType::BrowseDir,
+                );
+                fc.show();
+                let path = fc.filename();
+                if path.as_os_str().is_empty() {
+                    return;
+                }
+                if let Err(msg) = store::home::set_workspace(&path) {
+                    fl::dialog::alert_default(&msg);
+                }
+                *dir.borrow_mut() = path;
+                refresh();
+            }
+        });
+
+        refresh();
+        (page, refresh)
+    }
+
     pub fn new<T: Into<Option<&'static str>>>(
         x: i32,
         y: i32,
@@ -186,21 +986,152 @@ impl APP {
         let menu_height = 40;
         let mut win = fl::window::Window::new(x, y, width, height, title);
 
-        let lib = Rc::new(RefCell::new(store::WordLibrary {
-            stores: HashMap::new(),
-        }));
+        let mut lib = store::WordLibrary::new();
+        let mut errs = Vec::new();
+        // the words deleted in the gui are kept in the trash under the home directory
+        if let Some(path) = store::trash::home_path() {
+            if let Err(err) = store::trash::attach(&mut lib, &path) {
+                errs.push(err);
+            }
+        }
+        // the recent actions can be undone after a restart
+        if let Some(path) = store::undo::home_path() {
+            match store::undo::Journal::open(&path, store::undo::LIMIT) {
+                Ok(journal) => lib.set_journal(journal),
+                Err(err) => errs.push(err),
+            }
+        }
+        // the saved searches are opened as tabs of smart libraries
+        if let Some(path) = store::smart::home_path() {
+            match store::smart::load(&path) {
+                Ok(searches) => {
+                    for (name, q) in searches {
+                        lib.add_search(&name, q);
+                    }
+                }
+                Err(err) => errs.push(err),
+            }
+        }
+        let searches: Vec<String> = lib.searches().into_iter().map(|(name, _)| name).collect();
+        let lib = Arc::new(RwLock::new(lib));
 
         let tab_height = 40;
-        let tabs = APP::file_tab(x, y + menu_height, width, tab_height);
+        // the home page is opened by the button before the tabs
+        let home_width = 60;
+        let mut home_button =
+            fl::button::Button::new(x, y + menu_height + 1, home_width, tab_height - 2, "Home");
+        home_button.clear_visible_focus();
+        let tabs = APP::file_tab(
+            x + home_width + 3,
+            y + menu_height,
+            width - home_width - 3,
+            tab_height,
+        );
 
         let index_width = 100;
+        let dict_width = 240;
         let mut output = fl::text::TextEditor::new(
             x + index_width,
             y + tab_height + menu_height,
-            width - index_width,
+            width - index_width - dict_width,
+            height - menu_height - tab_height,
+            None,
+        );
+        // the rendered word, shown in place of the editor when previewing
+        let mut preview = fl::window::Window::new(
+            x + index_width,
+            y + tab_height + menu_height,
+            width - index_width - dict_width,
             height - menu_height - tab_height,
             None,
         );
+        preview.end();
+
+        // raw definitions of the selected word in the registered dictionaries
+        let (dicts, dict_errs) = dict::Dictionaries::load();
+        errs.extend(dict_errs);
+        let dicts = Rc::new(RefCell::new(dicts));
+        let files_height = 30;
+        let mut definitions = fl::text::TextDisplay::new(
+            x + width - dict_width,
+            y + tab_height + menu_height,
+            dict_width,
+            height - menu_height - tab_height - files_height,
+            None,
+        );
+        definitions.wrap_mode(fl::text::WrapMode::AtBounds, 0);
+        let dict_buffer = fl::text::TextBuffer::default();
+        definitions.set_buffer(dict_buffer.clone());
+        definitions.show();
+
+        // the files attached to the selected word, opened or played by the button
+        let files = fl::menu::Choice::new(
+            x + width - dict_width,
+            y + height - files_height + 2,
+            dict_width - 75,
+            files_height - 4,
+            None,
+        );
+        let mut open_file = fl::button::Button::new(
+            x + width - 72,
+            y + height - files_height + 2,
+            70,
+            26,
+            "Open",
+        );
+        // the names of the files in the choice, its labels are escaped
+        let file_names: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+        let show_files = {
+            let lib = lib.clone();
+            let files = files.clone();
+            let file_names = file_names.clone();
+            move |name: &str, key: &str| {
+                let mut files = files.clone();
+                files.clear();
+                let names: Vec<String> = lib
+                    .read()
+                    .unwrap()
+                    .attachments(name, key)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|a| a.name)
+                    .collect();
+                for name in &names {
+                    files.add_choice(&name.replace('&', "&&").replace('_', "\\_"));
+                }
+                files.set_value(0);
+                *file_names.borrow_mut() = names;
+            }
+        };
+        // the selected file of the selected word
+        let selected_file = {
+            let files = files.clone();
+            let file_names = file_names.clone();
+            move || -> Option<String> {
+                let i = files.value();
+                match i < 0 {
+                    true => None,
+                    false => file_names.borrow().get(i as usize).cloned(),
+                }
+            }
+        };
+        let define = {
+            let dicts = dicts.clone();
+            let dict_buffer = dict_buffer.clone();
+            move |word: &str| -> Vec<dict::Definition> {
+                let mut dict_buffer = dict_buffer.clone();
+                match dicts.borrow().lookup(word) {
+                    Ok(defs) => {
+                        dict_buffer.set_text(&dict::format(&defs));
+                        defs
+                    }
+                    Err(err) => {
+                        dict_buffer.set_text(&format!("look up fail: {}", err));
+                        Vec::new()
+                    }
+                }
+            }
+        };
 
         // output.visible_focus(false);
         // output.set_readonly(true);
@@ -208,21 +1139,34 @@ impl APP {
         output.set_buffer(buffer.clone());
         output.show();
 
-        let mut index = fl::browser::SelectBrowser::new(
+        let tags_height = 150;
+        let mut index = fl::browser::MultiBrowser::new(
             x,
             y + tab_height + menu_height,
             index_width,
-            height - menu_height - tab_height,
+            height - menu_height - tab_height - tags_height,
             None,
         );
         index.show();
         // index.set_frame(fl::enums::FrameType::DownFrame);
+        let mut tags = fl::browser::HoldBrowser::new(
+            x,
+            y + height - tags_height,
+            index_width,
+            tags_height,
+            None,
+        );
+        tags.show();
 
         let cur = Rc::new(RefCell::new(Current {
             but: None,
             index: index.clone(),
+            tags: tags.clone(),
             // lib: lib.clone(),
             store_key: String::from(""),
+            home: None,
+            menu: None,
+            home_button: None,
         }));
 
         index.set_selection_color(fl::enums::Color::Blue);
@@ -231,22 +1175,70 @@ impl APP {
         // index.set_frame(fl::enums::FrameType::UpFrame);
         index.show();
 
+        // the webview is made when the window is shown
+        let webview: Rc<RefCell<Option<fltk_webview::Webview>>> = Rc::new(RefCell::new(None));
+        let previewing = Rc::new(std::cell::Cell::new(false));
+        // the words marked in the preview, with the text of a saved search
+        let highlight = Rc::new(RefCell::new(String::new()));
+        let render = {
+            let lib = lib.clone();
+            let cur = cur.clone();
+            let index = index.clone();
+            let buffer = buffer.clone();
+            let webview = webview.clone();
+            let previewing = previewing.clone();
+            let highlight = highlight.clone();
+            move || {
+                let wv = match webview.borrow().clone() {
+                    Some(wv) if previewing.get() => wv,
+                    _ => return,
+                };
+                let name = match index.selected_text() {
+                    Some(name) => name.trim_end().to_owned(),
+                    None => {
+                        wv.set_html("");
+                        return;
+                    }
+                };
+                let key = cur.borrow().store_key.clone();
+                let lib = lib.read().unwrap();
+                let attachments = lib.attachments(&name, &key).unwrap_or_default();
+                let mut hits = vec![highlight.borrow().clone()];
+                if let Some((_, q)) = lib.searches().into_iter().find(|(n, _)| *n == key) {
+                    hits.push(q.text);
+                }
+                // the text in the editor, as it may not be saved yet
+                let word = store::World {
+                    name,
+                    message: buffer.text(),
+                };
+                let page = store::preview::page(&word, &attachments, &hits);
+                wv.navigate(&store::preview::data_url(&page));
+            }
+        };
+
         index.set_callback({
             let lib = lib.clone();
             let cur = cur.clone();
             let mut output = output.clone();
             let mut win = win.clone();
             let mut buffer = buffer.clone();
+            let define = define.clone();
+            let show_files = show_files.clone();
+            let render = render.clone();
             move |i| {
                 if let Some(word) = i.selected_text() {
                     let word = word.trim_end();
-                    match lib.borrow().get(word, &cur.borrow().store_key) {
+                    define(word);
+                    show_files(word, &cur.borrow().store_key);
+                    match lib.read().unwrap().get(word, &cur.borrow().store_key) {
                         Ok(word) => {
                             // output.set_value(&word.message);
                             // output.clear();
                             // output.add(&word.message);
 
                             buffer.set_text(&word.message);
+                            render();
                             // i.set_frame(fl::enums::FrameType::GtkDownFrame);
                             // win.redraw();
                         }
@@ -262,26 +1254,348 @@ impl APP {
             }
         });
 
-        let mut menu = APP::meun(
-            x,
-            y,
-            width,
-            menu_height,
-            tabs.clone(),
-            // output.clone(),
-            cur.clone(),
-            lib.clone(),
-        );
+        // open the word in its tab
+        let goto = {
+            let cur = cur.clone();
+            let tabs = tabs.clone();
+            move |key: &str, name: &str| {
+                if cur.borrow().store_key != key {
+                    for i in 0..tabs.children() {
+                        if let Some(mut but) = tabs.child(i) {
+                            if but.label() == key {
+                                but.do_callback();
+                            }
+                        }
+                    }
+                }
+                let mut index = cur.borrow().index.clone();
+                for line in 1..=index.size() {
+                    if index.text(line).as_deref().map(|t| t.trim_end()) == Some(name) {
+                        select_only(&mut index, line);
+                        index.middle_line(line);
+                        index.do_callback();
+                        return;
+                    }
+                }
+            }
+        };
 
-        menu.add(
-            "Store/Save",
-            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('s'),
-            fl::menu::MenuFlag::Normal,
-            {
-                let buffer = buffer.clone();
-                let lib = lib.clone();
-                let cur = cur.clone();
-                let index = index.clone();
+        // open the word the link of the selected word points to
+        let follow = {
+            let lib = lib.clone();
+            let cur = cur.clone();
+            let index = index.clone();
+            let goto = goto.clone();
+            move |link: &store::links::Link| {
+                let name = match index.selected_text() {
+                    Some(name) => name.trim_end().to_owned(),
+                    None => return,
+                };
+                let key = cur.borrow().store_key.clone();
+                let res = lib.read().unwrap().follow(link, &name, &key);
+                match res {
+                    Ok((key, word)) => goto(&key, &word.name),
+                    Err(store::InternalError::NotFound) => {
+                        fl::dialog::alert_default(&format!("{} is not there", link))
+                    }
+                    Err(store::InternalError::StoreNotFound) => {
+                        fl::dialog::alert_default(&format!("the library of {} is not opened", link))
+                    }
+                    Err(err) => fl::dialog::alert_default(&format!("follow fail: {}", err)),
+                }
+            }
+        };
+
+        // attach the files to the selected word
+        let attach = {
+            let lib = lib.clone();
+            let cur = cur.clone();
+            let index = index.clone();
+            let show_files = show_files.clone();
+            let render = render.clone();
+            move |paths: &[std::path::PathBuf]| {
+                let name = match index.selected_text() {
+                    Some(name) => name.trim_end().to_owned(),
+                    None => {
+                        fl::dialog::alert_default("select a word first");
+                        return;
+                    }
+                };
+                let key = cur.borrow().store_key.clone();
+                for path in paths {
+                    let res = match store::attachment::Attachment::read(path) {
+                        Ok(a) => lib.read().unwrap().attach(&name, &a, &key),
+                        Err(err) => Err(store::InternalError::Unknow(store::Error {
+                            message: err.to_string(),
+                            code: 0,
+                            err: None,
+                        })),
+                    };
+                    if let Err(err) = res {
+                        let msg = format!("attach {} fail: {}", path.display(), err);
+                        fl::dialog::alert_default(&msg);
+                        break;
+                    }
+                }
+                show_files(&name, &key);
+                render();
+            }
+        };
+
+        // files dropped on the text or the index are attached to the word,
+        // the drop comes as a paste after the release
+        let dropping = Rc::new(std::cell::Cell::new(false));
+        let on_drop = {
+            let dropping = dropping.clone();
+            let attach = attach.clone();
+            move |ev: fl::enums::Event| -> bool {
+                match ev {
+                    fl::enums::Event::DndRelease => dropping.set(true),
+                    fl::enums::Event::Paste if dropping.replace(false) => {
+                        let paths = dropped_files(&fl::app::event_text());
+                        if !paths.is_empty() {
+                            attach(&paths);
+                            return true;
+                        }
+                    }
+                    _ => {}
+                }
+                false
+            }
+        };
+        // move or copy the words to another library, the words it has already
+        // are skipped or replaced as asked. false if nothing is done
+        let transfer = {
+            let lib = lib.clone();
+            let cur = cur.clone();
+            move |names: &[String], src: &str, dst: &str, copy: bool| -> bool {
+                if src == dst {
+                    fl::dialog::alert_default("the words are in the library already");
+                    return false;
+                }
+                let conflicts = lib.read().unwrap().conflicts(names, dst);
+                let conflicts = match conflicts {
+                    Ok(conflicts) => conflicts,
+                    Err(err) => {
+                        let msg = format!("{} can't take the words, {}", dst, err);
+                        fl::dialog::alert_default(&msg);
+                        return false;
+                    }
+                };
+                let mut names = names.to_vec();
+                let mut replace = false;
+                if !conflicts.is_empty() {
+                    let mut shown = conflicts.iter().take(10).cloned().collect::<Vec<_>>();
+                    if conflicts.len() > shown.len() {
+                        shown.push(String::from("..."));
+                    }
+                    let msg = format!(
+                        "{} has {} of the words already:\n{}",
+                        dst,
+                        conflicts.len(),
+                        shown.join(", ")
+                    );
+                    match fl::dialog::choice2_default(&msg, "Cancel", "Skip", "Replace") {
+                        Some(1) => names.retain(|n| !conflicts.contains(n)),
+                        Some(2) => replace = true,
+                        _ => return false,
+                    }
+                }
+                if names.is_empty() {
+                    return false;
+                }
+
+                let errs = {
+                    let lib = lib.read().unwrap();
+                    match (replace, copy) {
+                        (true, _) => lib.replace_all(&names, src, dst, copy),
+                        (false, true) => lib.copy_all(&names, src, dst),
+                        (false, false) => lib.move_all(&names, src, dst),
+                    }
+                };
+                // list the words of the current tab again
+                let but = cur.borrow().but.clone();
+                if let Some(mut but) = but {
+                    but.do_callback();
+                }
+                if !errs.is_empty() {
+                    let msg: Vec<String> =
+                        errs.iter().map(|(n, e)| format!("{}: {}", n, e)).collect();
+                    fl::dialog::alert_default(&msg.join("\n"));
+                }
+                errs.len() < names.len()
+            }
+        };
+
+        // the selected words dragged onto a tab are moved to its library,
+        // copied if ctrl is held at the drop. the handler runs before the
+        // browser's to see the selection the push is going to drop
+        index.super_handle_first(false);
+        index.handle({
+            let on_drop = on_drop.clone();
+            let cur = cur.clone();
+            let tabs = tabs.clone();
+            let transfer = transfer.clone();
+            let mut pressed: Vec<i32> = Vec::new();
+            let mut dragging = false;
+            move |i, ev| match ev {
+                fl::enums::Event::DndEnter | fl::enums::Event::DndDrag => true,
+                fl::enums::Event::DndRelease => {
+                    on_drop(ev);
+                    true
+                }
+                fl::enums::Event::Push => {
+                    let plain = fl::app::event_mouse_button() == fl::app::MouseButton::Left
+                        && !fl::app::is_event_ctrl()
+                        && !fl::app::is_event_shift();
+                    pressed = match plain {
+                        true => i.selected_items(),
+                        false => Vec::new(),
+                    };
+                    false
+                }
+                // a drag from a word which was selected carries the selection
+                fl::enums::Event::Drag
+                    if dragging || (i.selected(i.value()) && pressed.contains(&i.value())) =>
+                {
+                    if !dragging {
+                        dragging = true;
+                        for line in i.selected_items() {
+                            i.deselect(line);
+                        }
+                        for line in &pressed {
+                            i.select(*line);
+                        }
+                        fl::draw::set_cursor(fl::enums::Cursor::Move);
+                    }
+                    true
+                }
+                fl::enums::Event::Release if dragging => {
+                    dragging = false;
+                    fl::draw::set_cursor(fl::enums::Cursor::Default);
+                    let (ex, ey) = (fl::app::event_x(), fl::app::event_y());
+                    let dst = (0..tabs.children())
+                        .filter_map(|t| tabs.child(t))
+                        .find(|t| {
+                            ex >= t.x() && ex < t.x() + t.w() && ey >= t.y() && ey < t.y() + t.h()
+                        })
+                        .map(|t| t.label());
+                    let src = cur.borrow().store_key.clone();
+                    if let Some(dst) = dst.filter(|dst| *dst != src) {
+                        transfer(&selected_words(i), &src, &dst, fl::app::is_event_ctrl());
+                    }
+                    true
+                }
+                _ => on_drop(ev),
+            }
+        });
+
+        // ctrl+click on a [[link]] opens the word it points to
+        output.handle({
+            let buffer = buffer.clone();
+            let index = index.clone();
+            let follow = follow.clone();
+            move |o, ev| {
+                if on_drop(ev) {
+                    return true;
+                }
+                if ev != fl::enums::Event::Push || !fl::app::is_event_ctrl() {
+                    return false;
+                }
+                let pos = o.xy_to_position(
+                    fl::app::event_x(),
+                    fl::app::event_y(),
+                    fl::text::PositionType::Character,
+                );
+                match store::links::at(&buffer.text(), pos as usize) {
+                    Some(link) if index.selected_text().is_some() => {
+                        follow(&link);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+        });
+
+        // list the words with the clicked tag, or every word
+        let filter = {
+            let lib = lib.clone();
+            let cur = cur.clone();
+            move |expr: Option<store::tags::Expr>| {
+                let key = cur.borrow().store_key.clone();
+                let res = match &expr {
+                    Some(expr) => lib.read().unwrap().filter(expr, &key),
+                    None => lib.read().unwrap().list(0, &key),
+                };
+                match res {
+                    Ok(ws) => {
+                        let mut index = cur.borrow().index.clone();
+                        index.clear();
+                        for w in ws {
+                            index.add(&w.name);
+                        }
+                    }
+                    Err(err) => fl::dialog::alert_default(&format!("filter fail: {}", err)),
+                }
+            }
+        };
+        tags.set_callback({
+            let filter = filter.clone();
+            move |t| {
+                if let Some(line) = t.selected_text() {
+                    match line.as_str() {
+                        ALL_TAGS => filter(None),
+                        line => {
+                            let tag = line.split(' ').next().unwrap_or("");
+                            filter(Some(store::tags::Expr::Tag(tag.to_owned())));
+                        }
+                    }
+                }
+            }
+        });
+
+        let mut menu = APP::meun(
+            x,
+            y,
+            width,
+            menu_height,
+            tabs.clone(),
+            // output.clone(),
+            cur.clone(),
+            lib.clone(),
+        );
+        // filled when the window is shown, kept right after Store/Open
+        menu.add(
+            "Store/Recent/(none)",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Inactive,
+            |_| {},
+        );
+
+        menu.add(
+            "Store/Close Tab",
+            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('w'),
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let tabs = tabs.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    APP::close_tab(&key, &mut tabs.clone(), cur.clone(), lib.clone());
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Save",
+            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('s'),
+            fl::menu::MenuFlag::Normal,
+            {
+                let buffer = buffer.clone();
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let index = index.clone();
                 move |_| {
                     if let Some(name) = index.selected_text() {
                         let word = store::World {
@@ -289,7 +1603,8 @@ impl APP {
                             name: name,
                         };
 
-                        if let Err(err) = lib.borrow().update(&word, &cur.borrow().store_key) {
+                        if let Err(err) = lib.read().unwrap().update(&word, &cur.borrow().store_key)
+                        {
                             let msg = format!("save doc error: {}", err);
                             fl::dialog::alert_default(&msg);
                             return;
@@ -308,14 +1623,17 @@ impl APP {
                 let lib = lib.clone();
                 let cur = cur.clone();
                 let mut index = index.clone();
+                let define = define.clone();
                 move |_| {
                     if let Some(name) = fl::dialog::input_default("word key", "") {
+                        // the message starts with the definitions of the dictionaries
                         let word = store::World {
-                            message: String::from(""),
+                            message: dict::fill("", &define(&name)),
                             name: name,
                         };
 
-                        if let Err(err) = lib.borrow().insert(&word, &cur.borrow().store_key) {
+                        if let Err(err) = lib.read().unwrap().insert(&word, &cur.borrow().store_key)
+                        {
                             let msg = format!("new doc error: {}", err);
                             fl::dialog::alert_default(&msg);
                             return;
@@ -323,7 +1641,7 @@ impl APP {
 
                         buffer.set_text(&word.message);
                         index.add(&word.name);
-                        index.select(index.size());
+                        select_only(&mut index, index.size());
                         index.bottom_line(index.size());
                     }
                 }
@@ -341,7 +1659,16 @@ impl APP {
                 let mut index = index.clone();
                 move |_| {
                     if let Some(word) = index.selected_text() {
-                        if let Err(err) = lib.borrow().delete(&word, &cur.borrow().store_key) {
+                        // without a trash the word can not be restored
+                        if !lib.read().unwrap().has_trash() {
+                            let msg = format!("delete {} for good?", word);
+                            if fl::dialog::choice2_default(&msg, "Cancel", "Delete", "") != Some(1)
+                            {
+                                return;
+                            }
+                        }
+                        if let Err(err) = lib.read().unwrap().delete(&word, &cur.borrow().store_key)
+                        {
                             let msg = format!("delete word error: {}", err);
                             fl::dialog::alert_default(&msg);
                             return;
@@ -358,11 +1685,1481 @@ impl APP {
             },
         );
 
-        // let output = output.below_of(&tabs, 6);
-        let tabs = tabs.below_of(&menu, 1);
+        menu.add(
+            "Store/History",
+            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('h'),
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let index = index.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    let name = match index.selected_text() {
+                        Some(name) => name.trim_end().to_owned(),
+                        None => return,
+                    };
+                    let res = {
+                        let lib = lib.read().unwrap();
+                        lib.history(&name, &key)
+                            .and_then(|h| Ok((h, lib.get(&name, &key)?.message)))
+                    };
+                    let (history, current) = match res {
+                        Ok((h, _)) if h.is_empty() => {
+                            fl::dialog::message_default(&format!("no history of {}", name));
+                            return;
+                        }
+                        Ok(v) => v,
+                        Err(err) => {
+                            fl::dialog::alert_default(&format!("read history fail: {}", err));
+                            return;
+                        }
+                    };
 
-        win.end();
-        win.show();
+                    let mut dlg = fl::window::Window::default()
+                        .with_size(640, 420)
+                        .with_label(&format!("history of {}", name))
+                        .center_screen();
+                    let mut list = fl::browser::HoldBrowser::new(5, 5, 250, 370, None);
+                    for s in &history {
+                        list.add(&s.to_string());
+                    }
+                    let mut view = fl::text::TextDisplay::new(260, 5, 375, 370, None);
+                    let view_buffer = fl::text::TextBuffer::default();
+                    view.set_buffer(view_buffer.clone());
+                    let mut ok = fl::button::Button::new(445, 382, 90, 30, "Restore");
+                    let mut cancel = fl::button::Button::new(545, 382, 90, 30, "Close");
+                    dlg.end();
+                    dlg.make_modal(true);
+                    dlg.show();
+
+                    // the changes from the snapshot to the current message
+                    list.set_callback({
+                        let history = history.clone();
+                        let mut view_buffer = view_buffer.clone();
+                        move |b| {
+                            if let Some(s) = history.get((b.value() - 1) as usize) {
+                                let lines = store::history::diff(&s.message, &current);
+                                view_buffer.set_text(&store::history::format(&lines));
+                            }
+                        }
+                    });
+                    list.select(1);
+                    list.do_callback();
+
+                    let chosen = Rc::new(std::cell::Cell::new(false));
+                    ok.set_callback({
+                        let mut dlg = dlg.clone();
+                        let chosen = chosen.clone();
+                        move |_| {
+                            chosen.set(true);
+                            dlg.hide();
+                        }
+                    });
+                    cancel.set_callback({
+                        let mut dlg = dlg.clone();
+                        move |_| dlg.hide()
+                    });
+                    while dlg.shown() {
+                        fl::app::wait();
+                    }
+                    let snapshot = match history.get((list.value() - 1) as usize) {
+                        Some(s) if chosen.get() => s,
+                        _ => return,
+                    };
+
+                    if let Err(err) = lib.read().unwrap().restore(snapshot, &key) {
+                        fl::dialog::alert_default(&format!("restore fail: {}", err));
+                        return;
+                    }
+                    let mut index = index.clone();
+                    index.do_callback();
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Deleted Words",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    let res = lib.read().unwrap().deleted(&key);
+                    let deleted = match res {
+                        Ok(d) if d.is_empty() => {
+                            fl::dialog::message_default("no deleted words");
+                            return;
+                        }
+                        Ok(d) => d,
+                        Err(err) => {
+                            fl::dialog::alert_default(&format!("read deleted words fail: {}", err));
+                            return;
+                        }
+                    };
+
+                    let mut dlg = fl::window::Window::default()
+                        .with_size(640, 420)
+                        .with_label("deleted words")
+                        .center_screen();
+                    let mut list = fl::browser::HoldBrowser::new(5, 5, 250, 370, None);
+                    for s in &deleted {
+                        list.add(&s.name);
+                    }
+                    let mut view = fl::text::TextDisplay::new(260, 5, 375, 370, None);
+                    let view_buffer = fl::text::TextBuffer::default();
+                    view.set_buffer(view_buffer.clone());
+                    let mut ok = fl::button::Button::new(445, 382, 90, 30, "Restore");
+                    let mut cancel = fl::button::Button::new(545, 382, 90, 30, "Close");
+                    dlg.end();
+                    dlg.make_modal(true);
+                    dlg.show();
+
+                    list.set_callback({
+                        let deleted = deleted.clone();
+                        let mut view_buffer = view_buffer.clone();
+                        move |b| {
+                            if let Some(s) = deleted.get((b.value() - 1) as usize) {
+                                view_buffer.set_text(&s.message);
+                            }
+                        }
+                    });
+                    list.select(1);
+                    list.do_callback();
+
+                    let chosen = Rc::new(std::cell::Cell::new(false));
+                    ok.set_callback({
+                        let mut dlg = dlg.clone();
+                        let chosen = chosen.clone();
+                        move |_| {
+                            chosen.set(true);
+                            dlg.hide();
+                        }
+                    });
+                    cancel.set_callback({
+                        let mut dlg = dlg.clone();
+                        move |_| dlg.hide()
+                    });
+                    while dlg.shown() {
+                        fl::app::wait();
+                    }
+                    let snapshot = match deleted.get((list.value() - 1) as usize) {
+                        Some(s) if chosen.get() => s,
+                        _ => return,
+                    };
+
+                    if let Err(err) = lib.read().unwrap().restore(snapshot, &key) {
+                        fl::dialog::alert_default(&format!("restore fail: {}", err));
+                        return;
+                    }
+                    let but = cur.borrow().but.clone();
+                    if let Some(mut but) = but {
+                        but.do_callback();
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Trash",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                move |_| {
+                    let res = lib.read().unwrap().trashed(0);
+                    let trashed = match res {
+                        Ok(t) if t.is_empty() => {
+                            fl::dialog::message_default("the trash is empty");
+                            return;
+                        }
+                        Ok(t) => t,
+                        Err(err) => {
+                            fl::dialog::alert_default(&format!("read the trash fail: {}", err));
+                            return;
+                        }
+                    };
+
+                    let mut dlg = fl::window::Window::default()
+                        .with_size(640, 420)
+                        .with_label("trash")
+                        .center_screen();
+                    let mut list = fl::browser::HoldBrowser::new(5, 5, 250, 370, None);
+                    for t in &trashed {
+                        list.add(&t.entry.word.name);
+                    }
+                    let mut view = fl::text::TextDisplay::new(260, 5, 375, 370, None);
+                    let view_buffer = fl::text::TextBuffer::default();
+                    view.set_buffer(view_buffer.clone());
+                    let mut restore = fl::button::Button::new(5, 382, 90, 30, "Restore");
+                    let mut purge = fl::button::Button::new(105, 382, 90, 30, "Purge");
+                    let mut empty = fl::button::Button::new(205, 382, 90, 30, "Empty");
+                    let mut close = fl::button::Button::new(545, 382, 90, 30, "Close");
+                    dlg.end();
+                    dlg.make_modal(true);
+                    dlg.show();
+
+                    list.set_callback({
+                        let trashed = trashed.clone();
+                        let mut view_buffer = view_buffer.clone();
+                        move |b| {
+                            if let Some(t) = trashed.get((b.value() - 1) as usize) {
+                                view_buffer.set_text(&format!(
+                                    "from {}\ndeleted {}\n\n{}",
+                                    t.origin,
+                                    store::sync::format_time(t.deleted_at),
+                                    t.entry.word.message
+                                ));
+                            }
+                        }
+                    });
+                    list.select(1);
+                    list.do_callback();
+
+                    // 1 restore, 2 purge, 3 empty
+                    let action = Rc::new(std::cell::Cell::new(0));
+                    for (but, n) in [(&mut restore, 1), (&mut purge, 2), (&mut empty, 3)] {
+                        but.set_callback({
+                            let mut dlg = dlg.clone();
+                            let action = action.clone();
+                            move |_| {
+                                action.set(n);
+                                dlg.hide();
+                            }
+                        });
+                    }
+                    close.set_callback({
+                        let mut dlg = dlg.clone();
+                        move |_| dlg.hide()
+                    });
+                    while dlg.shown() {
+                        fl::app::wait();
+                    }
+
+                    let selected = trashed.get((list.value() - 1) as usize);
+                    let lib = lib.read().unwrap();
+                    let res = match (action.get(), selected) {
+                        (1, Some(t)) if !lib.contains(&t.origin) => {
+                            let msg = format!("open {} to restore {}", t.origin, t.entry.word.name);
+                            fl::dialog::alert_default(&msg);
+                            return;
+                        }
+                        (1, Some(t)) => lib.restore_trashed(t.id).map(|_| ()),
+                        (2, Some(t)) => lib.purge_trashed(t.id).map(|_| ()),
+                        (3, _) => {
+                            let msg = format!("purge all {} words for good?", trashed.len());
+                            if fl::dialog::choice2_default(&msg, "Cancel", "Purge", "") != Some(1) {
+                                return;
+                            }
+                            lib.purge_trash(i64::MAX).map(|_| ())
+                        }
+                        _ => return,
+                    };
+                    match res {
+                        Err(store::InternalError::Conflict) => {
+                            fl::dialog::alert_default("the word is added again, rename it first");
+                        }
+                        Err(err) => fl::dialog::alert_default(&format!("trash fail: {}", err)),
+                        Ok(_) => {}
+                    }
+
+                    let but = cur.borrow().but.clone();
+                    if let Some(mut but) = but {
+                        but.do_callback();
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Import",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    if key.is_empty() {
+                        fl::dialog::alert_default("open a library first");
+                        return;
+                    }
+                    let mut fc = fl::dialog::NativeFileChooser::new(
+                        fl::dialog::NativeFileChooserType::BrowseFile,
+                    );
+                    fc.set_filter("*.{csv,tsv,tab,txt,apkg,colpkg}");
+                    fc.show();
+                    let path = fc.filename().to_string_lossy().into_owned();
+                    if path.is_empty() {
+                        return;
+                    }
+
+                    let import = |dry_run: bool| {
+                        let opts = exchange::Options {
+                            dry_run,
+                            ..exchange::Options::default()
+                        };
+                        let lib = lib.read().unwrap();
+                        let store = lib.store(&key).map_err(|e| e.to_string())?;
+                        exchange::import_file(&**store, &path, &opts).map_err(|e| e.to_string())
+                    };
+
+                    // preview with a dry run before writing
+                    let report = match import(true) {
+                        Ok(report) => report,
+                        Err(err) => {
+                            fl::dialog::alert_default(&format!("import fail: {}", err));
+                            return;
+                        }
+                    };
+                    let msg = format!("import {} into {}?\n{}", path, key, report);
+                    if fl::dialog::choice2_default(&msg, "Cancel", "Import", "") != Some(1) {
+                        return;
+                    }
+                    if let Err(err) = import(false) {
+                        fl::dialog::alert_default(&format!("import fail: {}", err));
+                        return;
+                    }
+
+                    // reload the word index of the tab
+                    let but = cur.borrow().but.clone();
+                    if let Some(mut but) = but {
+                        but.do_callback();
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Import Lookups",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    if key.is_empty() {
+                        fl::dialog::alert_default("open a library first");
+                        return;
+                    }
+                    let mut fc = fl::dialog::NativeFileChooser::new(
+                        fl::dialog::NativeFileChooserType::BrowseFile,
+                    );
+                    fc.set_filter("*.{db,sqlite3}");
+                    fc.show();
+                    let path = fc.filename().to_string_lossy().into_owned();
+                    if path.is_empty() {
+                        return;
+                    }
+
+                    // words in every opened library are skipped
+                    let import = |dry_run: bool| {
+                        let opts = exchange::Options {
+                            dry_run,
+                            ..exchange::Options::default()
+                        };
+                        let lib = lib.read().unwrap();
+                        exchange::ereader::import(&lib, &key, &path, &opts)
+                            .map_err(|e| e.to_string())
+                    };
+                    let report = match import(true) {
+                        Ok(report) => report,
+                        Err(err) => {
+                            fl::dialog::alert_default(&format!("import fail: {}", err));
+                            return;
+                        }
+                    };
+                    let msg = format!("import lookups of {} into {}?\n{}", path, key, report);
+                    if fl::dialog::choice2_default(&msg, "Cancel", "Import", "") != Some(1) {
+                        return;
+                    }
+                    if let Err(err) = import(false) {
+                        fl::dialog::alert_default(&format!("import fail: {}", err));
+                        return;
+                    }
+
+                    let but = cur.borrow().but.clone();
+                    if let Some(mut but) = but {
+                        but.do_callback();
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Export",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    if key.is_empty() {
+                        fl::dialog::alert_default("open a library first");
+                        return;
+                    }
+                    let mut fc = fl::dialog::NativeFileChooser::new(
+                        fl::dialog::NativeFileChooserType::BrowseSaveFile,
+                    );
+                    fc.set_filter("*.{csv,tsv,apkg}");
+                    fc.show();
+                    let path = fc.filename().to_string_lossy().into_owned();
+                    if path.is_empty() {
+                        return;
+                    }
+
+                    let lib = lib.read().unwrap();
+                    let res = lib
+                        .store(&key)
+                        .map_err(|e| e.to_string())
+                        .and_then(|store| {
+                            let name = exchange::jsonl::library_name(&key);
+                            exchange::export_file(&**store, &name, &path).map_err(|e| e.to_string())
+                        });
+                    if let Err(err) = res {
+                        fl::dialog::alert_default(&format!("export fail: {}", err));
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Export Glossary",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let index = index.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    if key.is_empty() {
+                        fl::dialog::alert_default("open a library first");
+                        return;
+                    }
+                    let mut fc = fl::dialog::NativeFileChooser::new(
+                        fl::dialog::NativeFileChooserType::BrowseSaveFile,
+                    );
+                    fc.set_filter("*.{md,html}");
+                    fc.show();
+                    let path = fc.filename().to_string_lossy().into_owned();
+                    if path.is_empty() {
+                        return;
+                    }
+                    let layout = match fl::dialog::choice2_default(
+                        "export the listed words as",
+                        "Cancel",
+                        "List",
+                        "Study Sheet",
+                    ) {
+                        Some(1) => exchange::glossary::Layout::List,
+                        Some(2) => exchange::glossary::Layout::Sheet,
+                        _ => return,
+                    };
+
+                    // the words listed in the index, a search result is exported as it is
+                    let names: Vec<String> = (1..=index.size())
+                        .filter_map(|i| index.text(i))
+                        .map(|n| n.trim_end().to_owned())
+                        .collect();
+                    let style = exchange::glossary::Style::from_path(&path);
+                    let title = exchange::jsonl::library_name(&key);
+                    let mut opts = exchange::glossary::Options::new(&title, style);
+                    opts.layout = layout;
+
+                    let lib = lib.read().unwrap();
+                    let res = lib
+                        .store(&key)
+                        .map_err(|e| e.to_string())
+                        .and_then(|store| {
+                            exchange::glossary::select(&**store, &names)
+                                .and_then(|ws| exchange::glossary::export(&ws, &path, &opts))
+                                .map_err(|e| e.to_string())
+                        });
+                    if let Err(err) = res {
+                        fl::dialog::alert_default(&format!("export fail: {}", err));
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Extract Words",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let dicts = dicts.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    if key.is_empty() {
+                        fl::dialog::alert_default("open a library first");
+                        return;
+                    }
+                    let mut fc = fl::dialog::NativeFileChooser::new(
+                        fl::dialog::NativeFileChooserType::BrowseFile,
+                    );
+                    fc.set_filter("*.{txt,srt,ass,ssa,epub}");
+                    fc.show();
+                    let path = fc.filename().to_string_lossy().into_owned();
+                    if path.is_empty() {
+                        return;
+                    }
+
+                    // words in every opened library are known
+                    let res = exchange::extract::read_text(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| {
+                            let opts = exchange::extract::Options::default();
+                            let dicts = dicts.borrow();
+                            exchange::extract::extract(
+                                &lib.read().unwrap(),
+                                &text,
+                                Some(&dicts),
+                                &opts,
+                            )
+                            .map_err(|e| e.to_string())
+                        });
+                    let found = match res {
+                        Ok(found) if found.is_empty() => {
+                            fl::dialog::message_default("no unknown word found");
+                            return;
+                        }
+                        Ok(found) => found,
+                        Err(err) => {
+                            fl::dialog::alert_default(&format!("extract fail: {}", err));
+                            return;
+                        }
+                    };
+
+                    let title = format!("unknown words, check the ones added to {}", key);
+                    let mut dlg = fl::window::Window::default()
+                        .with_size(640, 420)
+                        .with_label(&title)
+                        .center_screen();
+                    let mut list = fl::browser::CheckBrowser::new(5, 5, 630, 370, None);
+                    for u in &found {
+                        let sentence = u.sentences.first().map(|s| s.as_str()).unwrap_or("");
+                        list.add(&format!("{:>5}  {}  {}", u.count, u.word, sentence), false);
+                    }
+                    let mut add = fl::button::Button::new(445, 382, 90, 30, "Add");
+                    let mut cancel = fl::button::Button::new(545, 382, 90, 30, "Cancel");
+                    dlg.end();
+                    dlg.make_modal(true);
+                    dlg.show();
+
+                    let chosen = Rc::new(std::cell::Cell::new(false));
+                    add.set_callback({
+                        let mut dlg = dlg.clone();
+                        let chosen = chosen.clone();
+                        move |_| {
+                            chosen.set(true);
+                            dlg.hide();
+                        }
+                    });
+                    cancel.set_callback({
+                        let mut dlg = dlg.clone();
+                        move |_| dlg.hide()
+                    });
+                    while dlg.shown() {
+                        fl::app::wait();
+                    }
+                    if !chosen.get() {
+                        return;
+                    }
+
+                    let checked: Vec<exchange::extract::Unknown> = found
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(i, _)| list.is_checked(*i as i32 + 1))
+                        .map(|(_, u)| u)
+                        .collect();
+                    let opts = exchange::Options::default();
+                    let res = exchange::extract::add(&lib.read().unwrap(), &key, &checked, &opts);
+                    if let Err(err) = res {
+                        fl::dialog::alert_default(&format!("add words fail: {}", err));
+                        return;
+                    }
+
+                    let but = cur.borrow().but.clone();
+                    if let Some(mut but) = but {
+                        but.do_callback();
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Merge From",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    if key.is_empty() {
+                        fl::dialog::alert_default("open a library first");
+                        return;
+                    }
+                    let mut fc = fl::dialog::NativeFileChooser::new(
+                        fl::dialog::NativeFileChooserType::BrowseFile,
+                    );
+                    fc.show();
+                    let path = fc.filename().to_string_lossy().into_owned();
+                    if path.is_empty() {
+                        return;
+                    }
+                    if path == key {
+                        fl::dialog::alert_default("merge a library into itself");
+                        return;
+                    }
+
+                    // a library not opened in a tab is opened for the merge only
+                    let temp = if lib.read().unwrap().contains(&path) {
+                        None
+                    } else {
+                        match dbstore::Sqlite::new(path.clone()) {
+                            Ok(s) => Some(s),
+                            Err(err) => {
+                                fl::dialog::alert_default(&format!("open {} fail: {}", path, err));
+                                return;
+                            }
+                        }
+                    };
+
+                    let res = {
+                        let lib = lib.read().unwrap();
+                        lib.store(&key).and_then(|dst| match &temp {
+                            Some(src) => store::merge::diff(&**dst, src),
+                            None => store::merge::diff(&**dst, &**lib.store(&path)?),
+                        })
+                    };
+                    let d = match res {
+                        Ok(d) if d.only_b.is_empty() && d.changed.is_empty() => {
+                            fl::dialog::message_default("nothing to merge");
+                            return;
+                        }
+                        Ok(d) => d,
+                        Err(err) => {
+                            fl::dialog::alert_default(&format!("diff fail: {}", err));
+                            return;
+                        }
+                    };
+
+                    let mut text = String::new();
+                    for w in &d.only_a {
+                        text.push_str(&format!("< {}\n", w.name));
+                    }
+                    for w in &d.only_b {
+                        text.push_str(&format!("> {}\n", w.name));
+                    }
+                    for (ours, theirs) in &d.changed {
+                        text.push_str(&format!(
+                            "! {}\n    {}\n    {}\n",
+                            ours.name,
+                            ours.message.replace('\n', " "),
+                            theirs.message.replace('\n', " ")
+                        ));
+                    }
+
+                    let title = format!("merge {} into {}, {}", path, key, d);
+                    let mut dlg = fl::window::Window::default()
+                        .with_size(640, 420)
+                        .with_label(&title)
+                        .center_screen();
+                    let mut view = fl::text::TextDisplay::new(5, 5, 630, 370, None);
+                    let mut view_buffer = fl::text::TextBuffer::default();
+                    view_buffer.set_text(&text);
+                    view.set_buffer(view_buffer);
+                    let mut strategy = fl::menu::Choice::new(105, 382, 150, 30, "Conflicts");
+                    strategy.add_choice("Keep Ours|Take Theirs|Concatenate|Ask Each");
+                    strategy.set_value(0);
+                    let mut ok = fl::button::Button::new(445, 382, 90, 30, "Merge");
+                    let mut cancel = fl::button::Button::new(545, 382, 90, 30, "Cancel");
+                    dlg.end();
+                    dlg.make_modal(true);
+                    dlg.show();
+
+                    let chosen = Rc::new(std::cell::Cell::new(false));
+                    ok.set_callback({
+                        let mut dlg = dlg.clone();
+                        let chosen = chosen.clone();
+                        move |_| {
+                            chosen.set(true);
+                            dlg.hide();
+                        }
+                    });
+                    cancel.set_callback({
+                        let mut dlg = dlg.clone();
+                        move |_| dlg.hide()
+                    });
+                    while dlg.shown() {
+                        fl::app::wait();
+                    }
+                    if !chosen.get() {
+                        return;
+                    }
+
+                    let fixed = match strategy.value() {
+                        0 => Some(store::merge::Resolution::Ours),
+                        1 => Some(store::merge::Resolution::Theirs),
+                        2 => Some(store::merge::Resolution::Concatenate),
+                        _ => None,
+                    };
+                    let resolve = |ours: &store::World, theirs: &store::World| {
+                        if let Some(r) = fixed {
+                            return r;
+                        }
+                        let msg = format!(
+                            "{}\n\nours:\n{}\n\ntheirs:\n{}",
+                            ours.name, ours.message, theirs.message
+                        );
+                        match fl::dialog::choice2_default(&msg, "Ours", "Theirs", "Concatenate") {
+                            Some(0) => store::merge::Resolution::Ours,
+                            Some(1) => store::merge::Resolution::Theirs,
+                            Some(2) => store::merge::Resolution::Concatenate,
+                            _ => store::merge::Resolution::Abort,
+                        }
+                    };
+                    let res = {
+                        let lib = lib.read().unwrap();
+                        lib.store(&key).and_then(|dst| match &temp {
+                            Some(src) => store::merge::merge(&**dst, src, resolve, false),
+                            None => lib.merge(&key, &path, resolve, false),
+                        })
+                    };
+                    match res {
+                        Ok(report) => fl::dialog::message_default(&report.to_string()),
+                        Err(store::InternalError::Canceled) => {
+                            fl::dialog::message_default("merge aborted, nothing is written");
+                        }
+                        Err(err) => fl::dialog::alert_default(&format!("merge fail: {}", err)),
+                    }
+
+                    let but = cur.borrow().but.clone();
+                    if let Some(mut but) = but {
+                        but.do_callback();
+                    }
+                }
+            },
+        );
+
+        let z = fl::enums::Shortcut::from_char('z');
+        for (label, shortcut, redo) in [
+            ("Edit/Undo", fl::enums::Shortcut::Ctrl | z, false),
+            (
+                "Edit/Redo",
+                fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::Shift | z,
+                true,
+            ),
+        ] {
+            menu.add(label, shortcut, fl::menu::MenuFlag::Normal, {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                move |_| {
+                    let (what, res) = match redo {
+                        true => ("redo", lib.read().unwrap().redo()),
+                        false => ("undo", lib.read().unwrap().undo()),
+                    };
+                    match res {
+                        Ok(Some(_)) => {}
+                        Ok(None) => return,
+                        Err(store::InternalError::Conflict) => {
+                            let msg = format!("the words are changed since, can not {}", what);
+                            fl::dialog::alert_default(&msg);
+                            return;
+                        }
+                        Err(err) => {
+                            fl::dialog::alert_default(&format!("{} fail: {}", what, err));
+                            return;
+                        }
+                    }
+
+                    // the words may be moved between the tabs
+                    let but = cur.borrow().but.clone();
+                    if let Some(mut but) = but {
+                        but.do_callback();
+                    }
+                }
+            });
+        }
+
+        for (label, add) in [("Edit/Add Tags", true), ("Edit/Remove Tags", false)] {
+            menu.add(
+                label,
+                fl::enums::Shortcut::None,
+                fl::menu::MenuFlag::Normal,
+                {
+                    let lib = lib.clone();
+                    let cur = cur.clone();
+                    let index = index.clone();
+                    move |_| {
+                        let name = match index.selected_text() {
+                            Some(name) => name.trim_end().to_owned(),
+                            None => {
+                                fl::dialog::alert_default("select a word first");
+                                return;
+                            }
+                        };
+                        let tags = match fl::dialog::input_default("tags, separated by spaces:", "")
+                        {
+                            Some(tags) => tags,
+                            None => return,
+                        };
+                        let tags: Vec<String> =
+                            tags.split_whitespace().map(|t| t.to_owned()).collect();
+                        let key = cur.borrow().store_key.clone();
+                        let res = match add {
+                            true => lib.read().unwrap().add_tags(&name, &tags, &key),
+                            false => lib.read().unwrap().remove_tags(&name, &tags, &key),
+                        };
+                        if let Err(err) = res {
+                            fl::dialog::alert_default(&format!("tag {} fail: {}", name, err));
+                            return;
+                        }
+
+                        // the counts in the tag sidebar are changed
+                        let but = cur.borrow().but.clone();
+                        if let Some(mut but) = but {
+                            but.do_callback();
+                        }
+                    }
+                },
+            );
+        }
+
+        menu.add(
+            "Edit/Filter by Tags",
+            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('g'),
+            fl::menu::MenuFlag::Normal,
+            {
+                let filter = filter.clone();
+                move |_| {
+                    let text = match fl::dialog::input_default(
+                        "tag expression, as verb AND NOT mastered:",
+                        "",
+                    ) {
+                        Some(text) => text,
+                        None => return,
+                    };
+                    if text.trim().is_empty() {
+                        filter(None);
+                        return;
+                    }
+                    match text.parse() {
+                        Ok(expr) => filter(Some(expr)),
+                        Err(err) => fl::dialog::alert_default(&err),
+                    }
+                }
+            },
+        );
+
+        // cut and copy keep the words selected in the index, paste puts them
+        // into the library of the current tab. in the editor the keys work on
+        // the text
+        let clipboard: Rc<RefCell<Option<(String, Vec<String>, bool)>>> =
+            Rc::new(RefCell::new(None));
+        for (label, key, cut) in [
+            ("Edit/Cut Words", 'x', true),
+            ("Edit/Copy Words", 'c', false),
+        ] {
+            menu.add(
+                label,
+                fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char(key),
+                fl::menu::MenuFlag::Normal,
+                {
+                    let cur = cur.clone();
+                    let index = index.clone();
+                    let output = output.clone();
+                    let clipboard = clipboard.clone();
+                    move |_| {
+                        if output.has_focus() {
+                            match cut {
+                                true => output.cut(),
+                                false => output.copy(),
+                            }
+                            return;
+                        }
+                        let names = selected_words(&index);
+                        if names.is_empty() {
+                            fl::dialog::alert_default("select the words first");
+                            return;
+                        }
+                        let key = cur.borrow().store_key.clone();
+                        *clipboard.borrow_mut() = Some((key, names, cut));
+                    }
+                },
+            );
+        }
+        menu.add(
+            "Edit/Paste Words",
+            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('v'),
+            fl::menu::MenuFlag::Normal,
+            {
+                let cur = cur.clone();
+                let output = output.clone();
+                let clipboard = clipboard.clone();
+                let transfer = transfer.clone();
+                move |_| {
+                    if output.has_focus() {
+                        output.paste();
+                        return;
+                    }
+                    let (src, names, cut) = match clipboard.borrow().clone() {
+                        Some(words) => words,
+                        None => return,
+                    };
+                    let dst = cur.borrow().store_key.clone();
+                    if dst.is_empty() {
+                        fl::dialog::alert_default("open a library first");
+                        return;
+                    }
+                    // the cut words are gone from where they were
+                    if transfer(&names, &src, &dst, !cut) && cut {
+                        clipboard.borrow_mut().take();
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Edit/Rename",
+            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('r'),
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let index = index.clone();
+                let goto = goto.clone();
+                move |_| {
+                    let name = match index.selected_text() {
+                        Some(name) => name.trim_end().to_owned(),
+                        None => {
+                            fl::dialog::alert_default("select a word first");
+                            return;
+                        }
+                    };
+                    let new = match fl::dialog::input_default("new name:", &name) {
+                        Some(new) if !new.trim().is_empty() && new.trim() != name => {
+                            new.trim().to_owned()
+                        }
+                        _ => return,
+                    };
+                    let key = cur.borrow().store_key.clone();
+                    let backlinks = lib
+                        .read()
+                        .unwrap()
+                        .backlinks(&name, &key)
+                        .unwrap_or_default();
+                    let links = match backlinks.len() {
+                        0 => false,
+                        n => {
+                            let msg =
+                                format!("{} words link to {}, rename the links too?", n, name);
+                            match fl::dialog::choice2_default(&msg, "Cancel", "Keep", "Rename") {
+                                Some(1) => false,
+                                Some(2) => true,
+                                _ => return,
+                            }
+                        }
+                    };
+                    let res = lib.read().unwrap().rename(&name, &new, &key, links);
+                    match res {
+                        Ok(_) => {}
+                        Err(store::InternalError::Conflict) => {
+                            fl::dialog::alert_default(&format!("{} already exists", new));
+                            return;
+                        }
+                        Err(err) => {
+                            fl::dialog::alert_default(&format!("rename fail: {}", err));
+                            return;
+                        }
+                    }
+
+                    let but = cur.borrow().but.clone();
+                    if let Some(mut but) = but {
+                        but.do_callback();
+                    }
+                    goto(&key, &new);
+                }
+            },
+        );
+
+        open_file.set_callback({
+            let lib = lib.clone();
+            let cur = cur.clone();
+            let index = index.clone();
+            let selected_file = selected_file.clone();
+            move |_| {
+                let (name, file) = match (index.selected_text(), selected_file()) {
+                    (Some(name), Some(file)) => (name.trim_end().to_owned(), file),
+                    _ => return,
+                };
+                let key = cur.borrow().store_key.clone();
+                let res = lib.read().unwrap().attachment(&name, &file, &key);
+                let res = match res {
+                    Ok(a) => open_attachment(&a),
+                    Err(err) => Err(format!("get {} fail: {}", file, err)),
+                };
+                if let Err(msg) = res {
+                    fl::dialog::alert_default(&msg);
+                }
+            }
+        });
+
+        menu.add(
+            "Edit/Attach Files",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let attach = attach.clone();
+                move |_| {
+                    let mut fc = fl::dialog::NativeFileChooser::new(
+                        fl::dialog::NativeFileChooserType::BrowseMultiFile,
+                    );
+                    fc.set_filter(
+                        "*.{mp3,ogg,oga,opus,wav,m4a,flac,png,jpg,jpeg,gif,webp,svg,bmp}",
+                    );
+                    fc.show();
+                    let paths = fc.filenames();
+                    if !paths.is_empty() {
+                        attach(&paths);
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Edit/Remove Attachment",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let index = index.clone();
+                let show_files = show_files.clone();
+                let render = render.clone();
+                move |_| {
+                    let (name, file) = match (index.selected_text(), selected_file()) {
+                        (Some(name), Some(file)) => (name.trim_end().to_owned(), file),
+                        _ => {
+                            fl::dialog::alert_default("select a word with files first");
+                            return;
+                        }
+                    };
+                    let msg = format!("remove {} from {}?", file, name);
+                    if fl::dialog::choice2_default(&msg, "Cancel", "Remove", "") != Some(1) {
+                        return;
+                    }
+                    let key = cur.borrow().store_key.clone();
+                    let res = lib.read().unwrap().detach(&name, &file, &key);
+                    if let Err(err) = res {
+                        fl::dialog::alert_default(&format!("remove fail: {}", err));
+                    }
+                    show_files(&name, &key);
+                    render();
+                }
+            },
+        );
+
+        menu.add(
+            "View/Preview",
+            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('p'),
+            fl::menu::MenuFlag::Toggle,
+            {
+                let mut output = output.clone();
+                let mut preview = preview.clone();
+                let previewing = previewing.clone();
+                let render = render.clone();
+                move |_| {
+                    let on = !previewing.get();
+                    previewing.set(on);
+                    if on {
+                        output.hide();
+                        preview.show();
+                        render();
+                    } else {
+                        preview.hide();
+                        output.show();
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "View/Highlight",
+            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('f'),
+            fl::menu::MenuFlag::Normal,
+            {
+                let highlight = highlight.clone();
+                let render = render.clone();
+                move |_| {
+                    let text = highlight.borrow().clone();
+                    if let Some(text) = fl::dialog::input_default("highlight words:", &text) {
+                        *highlight.borrow_mut() = text.trim().to_owned();
+                        render();
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Links",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let index = index.clone();
+                let goto = goto.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    let name = index.selected_text().map(|n| n.trim_end().to_owned());
+                    // the lines of the dialog with the words they open
+                    let mut lines: Vec<(String, Option<(String, String)>)> = Vec::new();
+                    {
+                        let lib = lib.read().unwrap();
+                        if let Some(name) = &name {
+                            for link in lib.links(name, &key).unwrap_or_default() {
+                                match lib.follow(&link, name, &key) {
+                                    Ok((k, w)) => {
+                                        lines.push((format!("-> {}", link), Some((k, w.name))))
+                                    }
+                                    Err(_) => lines.push((format!("-> {}  broken", link), None)),
+                                }
+                            }
+                            for (k, w) in lib.backlinks(name, &key).unwrap_or_default() {
+                                lines.push((format!("<- {}", w), Some((k, w))));
+                            }
+                        }
+                        match lib.broken_links(&key) {
+                            Ok(broken) => {
+                                for (w, link) in broken {
+                                    let line = format!("broken {} in {}", link, w);
+                                    lines.push((line, Some((key.clone(), w))));
+                                }
+                            }
+                            Err(err) => {
+                                fl::dialog::alert_default(&format!("read links fail: {}", err));
+                                return;
+                            }
+                        }
+                    }
+                    if lines.is_empty() {
+                        fl::dialog::message_default("no links");
+                        return;
+                    }
+
+                    let mut dlg = fl::window::Window::default()
+                        .with_size(400, 300)
+                        .with_label("links, double click to open")
+                        .center_screen();
+                    let mut list = fl::browser::HoldBrowser::new(5, 5, 390, 290, None);
+                    for (line, _) in &lines {
+                        list.add(line);
+                    }
+                    dlg.end();
+                    dlg.make_modal(true);
+                    dlg.show();
+
+                    let target = Rc::new(RefCell::new(None));
+                    list.set_callback({
+                        let target = target.clone();
+                        let mut dlg = dlg.clone();
+                        move |l| {
+                            if !fl::app::event_clicks() || l.value() < 1 {
+                                return;
+                            }
+                            if let Some((_, Some(word))) = lines.get(l.value() as usize - 1) {
+                                *target.borrow_mut() = Some(word.clone());
+                                dlg.hide();
+                            }
+                        }
+                    });
+                    while dlg.shown() {
+                        fl::app::wait();
+                    }
+                    let target = target.borrow_mut().take();
+                    if let Some((k, w)) = target {
+                        goto(&k, &w);
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Dict/Add Dictionary",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let dicts = dicts.clone();
+                move |_| {
+                    let mut fc = fl::dialog::NativeFileChooser::new(
+                        fl::dialog::NativeFileChooserType::BrowseFile,
+                    );
+                    fc.set_filter("*.{ifo,index}");
+                    fc.show();
+                    let path = fc.filename().to_string_lossy().into_owned();
+                    if path.is_empty() {
+                        return;
+                    }
+                    let res = dict::register(&path).and_then(|p| dicts.borrow_mut().open(&p));
+                    if let Err(err) = res {
+                        fl::dialog::alert_default(&format!("add dictionary fail: {}", err));
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Dict/Fill Definitions",
+            fl::enums::Shortcut::Ctrl | fl::enums::Shortcut::from_char('l'),
+            fl::menu::MenuFlag::Normal,
+            {
+                let mut buffer = buffer.clone();
+                let index = index.clone();
+                let define = define.clone();
+                move |_| {
+                    // appended to the editor, written by Store/Save
+                    if let Some(name) = index.selected_text() {
+                        let defs = define(name.trim_end());
+                        buffer.set_text(&dict::fill(&buffer.text(), &defs));
+                    }
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Save Search",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let mut tabs = tabs.clone();
+                move |_| {
+                    let mut dlg = fl::window::Window::default()
+                        .with_size(420, 230)
+                        .with_label("save search")
+                        .center_screen();
+                    let fields = ["name", "word name", "text", "tags", "due in days"];
+                    let inputs: Vec<fl::input::Input> = fields
+                        .iter()
+                        .enumerate()
+                        .map(|(i, f)| {
+                            fl::input::Input::new(110, 10 + 35 * i as i32, 300, 25, None)
+                                .with_label(f)
+                        })
+                        .collect();
+                    let mut save = fl::button::Button::new(250, 192, 75, 28, "Save");
+                    let mut cancel = fl::button::Button::new(335, 192, 75, 28, "Cancel");
+                    dlg.end();
+                    dlg.make_modal(true);
+                    dlg.show();
+
+                    let saved = Rc::new(std::cell::Cell::new(false));
+                    for (but, ok) in [(&mut save, true), (&mut cancel, false)] {
+                        let saved = saved.clone();
+                        let mut dlg = dlg.clone();
+                        but.set_callback(move |_| {
+                            saved.set(ok);
+                            dlg.hide();
+                        });
+                    }
+                    while dlg.shown() {
+                        fl::app::wait();
+                    }
+                    if !saved.get() {
+                        return;
+                    }
+
+                    let values: Vec<String> = inputs.iter().map(|i| i.value()).collect();
+                    let name = values[0].trim().to_owned();
+                    if name.is_empty() || lib.read().unwrap().keys().contains(&name) {
+                        fl::dialog::alert_default("the search needs a name of no library");
+                        return;
+                    }
+                    let mut args: Vec<&str> = Vec::new();
+                    for (opt, v) in ["--name", "--text", "--tag", "--due"]
+                        .iter()
+                        .zip(&values[1..])
+                    {
+                        if !v.trim().is_empty() {
+                            args.push(*opt);
+                            args.extend(v.split_whitespace());
+                        }
+                    }
+                    let query = match store::smart::Query::from_args(&args) {
+                        Ok(q) => q,
+                        Err(err) => {
+                            fl::dialog::alert_default(&err);
+                            return;
+                        }
+                    };
+
+                    let replaced = lib.read().unwrap().contains(&name);
+                    lib.write().unwrap().add_search(&name, query);
+                    if let Some(path) = store::smart::home_path() {
+                        if let Err(err) = store::smart::save(&path, &lib.read().unwrap().searches())
+                        {
+                            fl::dialog::alert_default(&err);
+                        }
+                    }
+                    if !replaced {
+                        let but = APP::tab_buttom(&name, cur.clone(), lib.clone());
+                        tabs.add(&but);
+                    }
+                    for i in 0..tabs.children() {
+                        if let Some(mut but) = tabs.child(i) {
+                            if but.label() == name {
+                                but.do_callback();
+                            }
+                        }
+                    }
+                    tabs.parent().unwrap().redraw();
+                }
+            },
+        );
+
+        menu.add(
+            "Store/Remove Search",
+            fl::enums::Shortcut::None,
+            fl::menu::MenuFlag::Normal,
+            {
+                let lib = lib.clone();
+                let cur = cur.clone();
+                let mut tabs = tabs.clone();
+                move |_| {
+                    let key = cur.borrow().store_key.clone();
+                    if lib.write().unwrap().remove_search(&key).is_none() {
+                        fl::dialog::alert_default("the current tab is not a saved search");
+                        return;
+                    }
+                    if let Some(path) = store::smart::home_path() {
+                        if let Err(err) = store::smart::save(&path, &lib.read().unwrap().searches())
+                        {
+                            fl::dialog::alert_default(&err);
+                        }
+                    }
+
+                    let but = cur.borrow_mut().but.take();
+                    if let Some(but) = but {
+                        tabs.remove(&but);
+                        fl::app::delete_widget(but);
+                    }
+                    cur.borrow_mut().set_store_key(String::new());
+                    cur.borrow().index.clone().clear();
+                    cur.borrow().tags.clone().clear();
+                    tabs.parent().unwrap().redraw();
+                }
+            },
+        );
+
+        for name in &searches {
+            let but = APP::tab_buttom(name, cur.clone(), lib.clone());
+            tabs.clone().add(&but);
+        }
+
+        // the home page covers the words until a tab is opened
+        let (home, refresh_home) = APP::home(
+            x,
+            y + menu_height + tab_height,
+            width,
+            height - menu_height - tab_height,
+            tabs.clone(),
+            cur.clone(),
+            lib.clone(),
+        );
+        cur.borrow_mut().home = Some(home.clone());
+        cur.borrow_mut().home_button = Some(home_button.clone());
+        cur.borrow_mut().menu = Some(menu.clone());
+        APP::recent_menu(tabs.clone(), cur.clone(), lib.clone());
+        home_button.set_callback({
+            let mut home = home.clone();
+            let mut output = output.clone();
+            let mut preview = preview.clone();
+            let previewing = previewing.clone();
+            let menu = menu.clone();
+            move |_| {
+                // the webview is a window over the page
+                if previewing.get() {
+                    previewing.set(false);
+                    preview.hide();
+                    output.show();
+                    if let Some(mut item) = menu.find_item("View/Preview") {
+                        item.clear();
+                    }
+                }
+                refresh_home();
+                home.show();
+            }
+        });
+
+        // let output = output.below_of(&tabs, 6);
+        let tabs = tabs.right_of(&home_button, 3);
+
+        win.end();
+        win.show();
+
+        // the page calls back to follow a link and to open a file or a url
+        let wv = fltk_webview::Webview::create(false, &mut preview);
+        wv.bind("follow", {
+            let wv = wv.clone();
+            move |seq, req| {
+                wv.return_(seq, 0, "null");
+                if let Some(link) = first_arg(req).and_then(|l| store::links::at(&l, 0)) {
+                    // the page is replaced by following, not in its own callback
+                    let follow = follow.clone();
+                    fl::app::add_timeout3(0.0, move |_| follow(&link));
+                }
+            }
+        });
+        wv.bind("open_file", {
+            let wv = wv.clone();
+            let lib = lib.clone();
+            let cur = cur.clone();
+            let index = index.clone();
+            move |seq, req| {
+                wv.return_(seq, 0, "null");
+                let (name, file) = match (index.selected_text(), first_arg(req)) {
+                    (Some(name), Some(file)) => (name.trim_end().to_owned(), file),
+                    _ => return,
+                };
+                let key = cur.borrow().store_key.clone();
+                let res = lib.read().unwrap().attachment(&name, &file, &key);
+                let res = match res {
+                    Ok(a) => open_attachment(&a),
+                    Err(err) => Err(format!("get {} fail: {}", file, err)),
+                };
+                if let Err(msg) = res {
+                    fl::dialog::alert_default(&msg);
+                }
+            }
+        });
+        wv.bind("browse", {
+            let wv = wv.clone();
+            move |seq, req| {
+                wv.return_(seq, 0, "null");
+                if let Some(url) = first_arg(req).filter(|u| store::preview::browsable(u)) {
+                    if let Err(msg) = open_system(url.as_ref()) {
+                        fl::dialog::alert_default(&msg);
+                    }
+                }
+            }
+        });
+        preview.hide();
+        *webview.borrow_mut() = Some(wv);
+
+        for err in errs {
+            fl::dialog::alert_default(&err);
+        }
         app.run().unwrap();
         APP { lib, tabs }
     }
@@ -0,0 +1,280 @@
+// edit history of the words
+//
+// a store keeps the previous message of a word before every update, delete,
+// replace or sync that changes it, a deleted word is restored from the last
+// snapshot of its history
+use super::sync::{format_time, now};
+use super::{InternalError, Read, Store, Transaction, World};
+use crate::db::sqlite::store::Sqlite;
+use std::fmt;
+
+pub const USAGE: &str = r#"  history <library> <word> [--diff <id> [<id>] | --restore <id>]
+                     list the earlier messages of a word, diff one with the current
+                     message or with another one, or restore it
+  history <library> --deleted [--restore <word>]
+                     list the deleted words, or restore one from its last message"#;
+
+/// the message of a word before a change
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub id: i64,
+    pub name: String,
+    pub message: String,
+    pub version: i64,
+    /// the change made after it, "update", "delete", "replace" or "sync"
+    pub action: String,
+    /// milliseconds since the unix epoch
+    pub changed_at: i64,
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<6}{}  version {}, {}",
+            self.id,
+            format_time(self.changed_at),
+            self.version,
+            self.action
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl<'a> fmt::Display for Line<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Same(l) => write!(f, "  {}", l),
+            Line::Removed(l) => write!(f, "- {}", l),
+            Line::Added(l) => write!(f, "+ {}", l),
+        }
+    }
+}
+
+/// the lines removed from old and added in new, by their longest common lines
+pub fn diff<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let (a, b): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    // lcs[i][j] is the common lines of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut res = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            res.push(Line::Same(a[i]));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            res.push(Line::Added(b[j]));
+            j += 1;
+        } else {
+            res.push(Line::Removed(a[i]));
+            i += 1;
+        }
+    }
+    res
+}
+
+/// the diff as text, one line each
+pub fn format(lines: &[Line]) -> String {
+    lines.iter().map(|l| format!("{}\n", l)).collect()
+}
+
+/// the snapshot of the word by its id
+pub fn find<T, E, S>(store: &S, name: &str, id: i64) -> Result<Snapshot, InternalError<E>>
+where
+    S: Read<T, InternalError<E>> + ?Sized,
+{
+    store
+        .history(name)?
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or(InternalError::NotFound)
+}
+
+/// write the message of the snapshot back, a deleted word is inserted again,
+/// the current message is kept in the history
+pub fn restore<T, E, F, S>(store: &S, snapshot: &Snapshot) -> Result<(), InternalError<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    let word = World {
+        name: snapshot.name.clone(),
+        message: snapshot.message.clone(),
+    };
+    let t = store.begin()?;
+    let res = match t.version(&word.name) {
+        Ok(_) => t.update(&word),
+        Err(InternalError::NotFound) => t.insert(&word),
+        Err(err) => Err(err),
+    };
+    match res {
+        Ok(_) => t.commit(),
+        Err(err) => {
+            t.rollback()?;
+            Err(err)
+        }
+    }
+}
+
+/// the last snapshot of a deleted word
+pub fn deleted<T, E, S>(store: &S, name: &str) -> Result<Snapshot, InternalError<E>>
+where
+    S: Read<T, InternalError<E>> + ?Sized,
+{
+    match store.get(name) {
+        Ok(_) => return Err(InternalError::Conflict),
+        Err(InternalError::NotFound) => {}
+        Err(err) => return Err(err),
+    }
+    store
+        .history(name)?
+        .into_iter()
+        .next()
+        .ok_or(InternalError::NotFound)
+}
+
+fn open(path: &str) -> Result<Sqlite, String> {
+    if !std::path::Path::new(path).is_file() {
+        return Err(format!("{} is not a library file", path));
+    }
+    Sqlite::new(path.to_owned()).map_err(|e| format!("open {}: {}", path, e))
+}
+
+/// history <library> <word> [options] and history <library> --deleted [options]
+pub fn command(args: &[String]) -> Result<(), String> {
+    let strs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let (path, rest) = match strs.as_slice() {
+        [path, rest @ ..] if !rest.is_empty() => (*path, rest),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let store = open(path)?;
+    let id = |s: &str| s.parse::<i64>().map_err(|_| format!("invalid id {}", s));
+    let err = |e: InternalError<sqlite::Error>| e.to_string();
+
+    match rest {
+        ["--deleted"] => {
+            for s in store.deleted(0).map_err(err)? {
+                println!("{:<20}deleted {}", s.name, format_time(s.changed_at));
+            }
+        }
+        ["--deleted", "--restore", name] => {
+            let s = match deleted(&store, name) {
+                Err(InternalError::Conflict) => return Err(format!("{} is not deleted", name)),
+                res => res.map_err(err)?,
+            };
+            restore(&store, &s).map_err(err)?;
+            println!("{} restored", name);
+        }
+        [name] => {
+            let history = store.history(name).map_err(err)?;
+            if history.is_empty() {
+                println!("no history of {}", name);
+            }
+            for s in history {
+                println!("{}", s);
+            }
+        }
+        [name, "--diff", from, to @ ..] => {
+            let old = find(&store, name, id(from)?).map_err(err)?;
+            let new = match to {
+                [] => store.get(name).map_err(err)?.message,
+                [to] => find(&store, name, id(to)?).map_err(err)?.message,
+                _ => return Err(USAGE.to_owned()),
+            };
+            print!("{}", format(&diff(&old.message, &new)));
+        }
+        [name, "--restore", s] => {
+            let s = find(&store, name, id(s)?).map_err(err)?;
+            restore(&store, &s).map_err(err)?;
+            println!(
+                "{} restored to version {} at {}",
+                name,
+                s.version,
+                format_time(now())
+            );
+        }
+        _ => return Err(USAGE.to_owned()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Write;
+
+    fn word(name: &str, message: &str) -> World {
+        World {
+            name: name.to_owned(),
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let lines = diff("a\nb\nc", "a\nc\nd");
+        assert_eq!(
+            lines,
+            vec![
+                Line::Same("a"),
+                Line::Removed("b"),
+                Line::Same("c"),
+                Line::Added("d")
+            ]
+        );
+        assert_eq!(format(&lines), "  a\n- b\n  c\n+ d\n");
+        assert!(diff("", "").is_empty());
+    }
+
+    #[test]
+    fn test_history() {
+        let path = "target/test_history.sql";
+        let _ = std::fs::remove_file(path);
+        let store = Sqlite::new(path.to_owned()).unwrap();
+        store.insert(&word("apple", "a fruit")).unwrap();
+        store.update(&word("apple", "a red fruit")).unwrap();
+        // an update without a change is not kept
+        store.update(&word("apple", "a red fruit")).unwrap();
+        store.update(&word("apple", "a red fruit\nround")).unwrap();
+
+        let history = store.history("apple").unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            (history[0].message.as_str(), history[0].version),
+            ("a red fruit", 3)
+        );
+        assert_eq!(history[1].message, "a fruit");
+
+        restore(&store, &find(&store, "apple", history[1].id).unwrap()).unwrap();
+        assert_eq!(store.get("apple").unwrap().message, "a fruit");
+        assert_eq!(store.history("apple").unwrap().len(), 3);
+
+        store.delete("apple").unwrap();
+        let gone = store.deleted(0).unwrap();
+        assert_eq!((gone.len(), gone[0].action.as_str()), (1, "delete"));
+        restore(&store, &deleted(&store, "apple").unwrap()).unwrap();
+        assert_eq!(store.get("apple").unwrap().message, "a fruit");
+        assert!(store.deleted(0).unwrap().is_empty());
+        assert!(matches!(
+            deleted(&store, "apple"),
+            Err(InternalError::Conflict)
+        ));
+    }
+}
@@ -0,0 +1,308 @@
+// the libraries of a workspace, as the home page of the gui lists them
+//
+// every library file of the workspace directory is listed with its
+// description, the count of its words and of the words due for review, and
+// the time it was last written. the gui keeps its libraries in ~/word_library
+// unless another directory is chosen, which is remembered in
+// ~/.word_library_workspace. the libraries opened lately are remembered in
+// ~/.word_library_recent for the recent menu
+use super::exchange::jsonl;
+use super::sync::now;
+use super::{attachment, InternalError, Read, Store, Transaction, Write};
+use crate::db::sqlite::store::Sqlite;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// words copied at once by duplicate
+const BATCH: i64 = 1000;
+
+/// libraries kept in the recent menu
+pub const RECENT_LIMIT: usize = 10;
+
+/// a library of the workspace
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// the library file, the key of the library when it is opened
+    pub path: String,
+    pub name: String,
+    pub description: String,
+    pub words: usize,
+    /// words due for review now
+    pub due: usize,
+    /// milliseconds since the unix epoch, None if the file time can't be read
+    pub modified: Option<i64>,
+}
+
+/// the file remembering the workspace of the gui
+pub fn home_path() -> Option<PathBuf> {
+    std::env::var("HOME")
+        .ok()
+        .map(|home| Path::new(&home).join(".word_library_workspace"))
+}
+
+/// the workspace of the gui, the one chosen last or ~/word_library
+pub fn workspace() -> Option<PathBuf> {
+    let chosen = home_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty());
+    match chosen {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => std::env::var("HOME")
+            .ok()
+            .map(|home| Path::new(&home).join("word_library")),
+    }
+}
+
+/// remember the workspace of the gui
+pub fn set_workspace(dir: &Path) -> Result<(), String> {
+    let path = home_path().ok_or("HOME is not set")?;
+    std::fs::write(&path, dir.to_string_lossy().as_bytes())
+        .map_err(|e| format!("write {} fail, {}", path.display(), e))
+}
+
+/// the file remembering the libraries opened lately in the gui
+pub fn recent_path() -> Option<PathBuf> {
+    std::env::var("HOME")
+        .ok()
+        .map(|home| Path::new(&home).join(".word_library_recent"))
+}
+
+/// the keys of the libraries opened lately, the last one first, none if
+/// there is no file
+pub fn load_recent(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn save_recent(path: &Path, libs: &[String]) -> Result<(), String> {
+    let mut text = libs.join("\n");
+    text.push('\n');
+    std::fs::write(path, text).map_err(|e| format!("write {} fail, {}", path.display(), e))
+}
+
+/// put the library first in the recent libraries of the file
+pub fn add_recent(path: &Path, key: &str) -> Result<Vec<String>, String> {
+    let mut libs = load_recent(path);
+    libs.retain(|l| l != key);
+    libs.insert(0, key.to_owned());
+    libs.truncate(RECENT_LIMIT);
+    save_recent(path, &libs)?;
+    Ok(libs)
+}
+
+/// drop the library from the recent libraries of the file
+pub fn remove_recent(path: &Path, key: &str) -> Result<Vec<String>, String> {
+    let mut libs = load_recent(path);
+    libs.retain(|l| l != key);
+    save_recent(path, &libs)?;
+    Ok(libs)
+}
+
+/// the libraries of the workspace sorted by name, the directory is created
+/// if it is not there
+pub fn list(dir: &Path) -> Result<Vec<Result<Summary, String>>, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("open {} fail, {}", dir.display(), e))?;
+    let files = super::library_files(&dir.to_string_lossy())
+        .map_err(|e| format!("read {} fail, {}", dir.display(), e))?;
+    Ok(files.iter().map(|path| read(path)).collect())
+}
+
+/// the summary of a library file
+pub fn read(path: &str) -> Result<Summary, String> {
+    let store = open(path)?;
+    summary(&store, path).map_err(|e| format!("read {} fail, {}", path, e))
+}
+
+fn summary(store: &Sqlite, path: &str) -> Result<Summary, InternalError<sqlite::Error>> {
+    let description = store
+        .metadata()?
+        .into_iter()
+        .find(|(k, _)| k == "description")
+        .map(|(_, v)| v)
+        .unwrap_or_default();
+    Ok(Summary {
+        path: path.to_owned(),
+        name: jsonl::library_name(path),
+        description,
+        words: store.list(0)?.len(),
+        due: store.due(now(), 0)?.len(),
+        modified: modified(path),
+    })
+}
+
+// the last time the library or its write-ahead log was written
+fn modified(path: &str) -> Option<i64> {
+    [path.to_owned(), format!("{}-wal", path)]
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+        .filter_map(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .max()
+}
+
+fn open(path: &str) -> Result<Sqlite, String> {
+    Sqlite::new(path.to_owned()).map_err(|e| format!("open {} fail, {}", path, e))
+}
+
+/// the file of a new library named name in dir, an error if the name can't be
+/// a file name or the library is there
+pub fn library_path(dir: &Path, name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if !attachment::valid(name) || name.starts_with('.') {
+        return Err(format!("invalid library name {:?}", name));
+    }
+    let path = dir.join(format!("{}.sql", name));
+    match path.exists() {
+        true => Err(format!("library {} already exists", name)),
+        false => Ok(path.to_string_lossy().into_owned()),
+    }
+}
+
+/// create an empty library in dir, returns its file
+pub fn create(dir: &Path, name: &str, description: &str) -> Result<String, String> {
+    let path = library_path(dir, name)?;
+    let store = open(&path)?;
+    if !description.is_empty() {
+        if let Err(err) = store.set_metadata("description", description) {
+            drop(store);
+            let _ = remove(&path);
+            return Err(format!("create {} fail, {}", path, err));
+        }
+    }
+    Ok(path)
+}
+
+/// set the description of the library file
+pub fn describe(path: &str, description: &str) -> Result<(), String> {
+    open(path)?
+        .set_metadata("description", description)
+        .map(|_| ())
+        .map_err(|e| format!("write {} fail, {}", path, e))
+}
+
+/// rename a library which is not opened, returns its new file
+pub fn rename(path: &str, name: &str) -> Result<String, String> {
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let new = library_path(dir, name)?;
+    std::fs::rename(path, &new).map_err(|e| format!("rename {} fail, {}", path, e))?;
+    for suffix in ["-wal", "-shm"] {
+        let side = format!("{}{}", path, suffix);
+        if Path::new(&side).exists() {
+            let _ = std::fs::rename(&side, format!("{}{}", new, suffix));
+        }
+    }
+    Ok(new)
+}
+
+/// copy the words of a library with everything stored for them and its
+/// metadata to a new library named name, returns the new file
+pub fn duplicate(path: &str, name: &str) -> Result<String, String> {
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let new = library_path(dir, name)?;
+    let src = open(path)?;
+    let dst = open(&new)?;
+    let res = (|| {
+        let t = dst.begin()?;
+        for (key, value) in src.metadata()? {
+            t.set_metadata(&key, &value)?;
+        }
+        let mut after = String::new();
+        loop {
+            let ws = src.scan(&after, BATCH)?;
+            for word in &ws {
+                t.put(&src.entry(&word.name)?)?;
+            }
+            match ws.into_iter().last() {
+                Some(word) => after = word.name,
+                None => break,
+            }
+        }
+        t.commit()
+    })();
+    match res {
+        Ok(_) => Ok(new),
+        Err(err) => {
+            drop(dst);
+            let _ = remove(&new);
+            Err(format!("copy {} fail, {}", path, err))
+        }
+    }
+}
+
+/// delete the files of a library which is not opened
+pub fn remove(path: &str) -> Result<(), String> {
+    std::fs::remove_file(path).map_err(|e| format!("delete {} fail, {}", path, e))?;
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::World;
+
+    #[test]
+    fn test_home() {
+        let dir = std::env::temp_dir().join(format!("wordlib_home_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = create(&dir, "english", "daily words").unwrap();
+        assert!(create(&dir, "english", "").is_err());
+        assert!(create(&dir, "../english", "").is_err());
+        {
+            let store = open(&path).unwrap();
+            for name in ["school", "scholar"] {
+                let word = World {
+                    name: name.to_owned(),
+                    message: String::from("a place"),
+                };
+                store.insert(&word).unwrap();
+            }
+            store.add_tag("school", "noun").unwrap();
+        }
+
+        let copy = duplicate(&path, "english copy").unwrap();
+        let renamed = rename(&copy, "words").unwrap();
+        describe(&renamed, "copied words").unwrap();
+        let libs: Vec<Summary> = list(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(libs.len(), 2);
+        assert_eq!(
+            (libs[0].name.as_str(), libs[0].description.as_str()),
+            ("english", "daily words")
+        );
+        assert_eq!((libs[1].name.as_str(), libs[1].words), ("words", 2));
+        assert_eq!(libs[1].description, "copied words");
+        assert!(libs[1].modified.is_some());
+        let store = open(&renamed).unwrap();
+        assert_eq!(store.word_tags("school").unwrap(), vec!["noun"]);
+        drop(store);
+
+        remove(&renamed).unwrap();
+        assert_eq!(list(&dir).unwrap().len(), 1);
+
+        let recent = dir.join("recent");
+        assert!(load_recent(&recent).is_empty());
+        for i in 0..RECENT_LIMIT + 2 {
+            add_recent(&recent, &format!("{}.sql", i)).unwrap();
+        }
+        add_recent(&recent, "5.sql").unwrap();
+        let libs = remove_recent(&recent, "11.sql").unwrap();
+        assert_eq!(libs.len(), RECENT_LIMIT - 1);
+        assert_eq!((libs[0].as_str(), libs[1].as_str()), ("5.sql", "10.sql"));
+        assert_eq!(load_recent(&recent), libs);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+// links between the words
+//
+// a message links to another word by `[[word]]`, or to a word of another
+// library by `[[library:word]]`. the links of the words are kept in an index of
+// the store written with the message, so the words linking to a word are found
+// without reading every message
+use std::fmt;
+
+/// a link in a message
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Link {
+    /// the library named in the link, None for the library of the message
+    pub library: Option<String>,
+    pub word: String,
+}
+
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.library {
+            Some(library) => write!(f, "[[{}:{}]]", library, self.word),
+            None => write!(f, "[[{}]]", self.word),
+        }
+    }
+}
+
+impl Link {
+    // the text between the brackets, None if it is not a link
+    fn from_inner(inner: &str) -> Option<Link> {
+        if inner.contains(['[', ']', '\n']) {
+            return None;
+        }
+        let (library, word) = match inner.split_once(':') {
+            Some((library, word)) if !library.trim().is_empty() => {
+                (Some(library.trim().to_owned()), word.trim())
+            }
+            _ => (None, inner.trim()),
+        };
+        match word.is_empty() {
+            true => None,
+            false => Some(Link {
+                library,
+                word: word.to_owned(),
+            }),
+        }
+    }
+}
+
+/// the links with their byte ranges in the message, in order
+pub(crate) fn find(message: &str) -> Vec<(usize, usize, Link)> {
+    let mut res = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = message[pos..].find("[[").map(|i| pos + i) {
+        let end = match message[start + 2..].find("]]") {
+            Some(i) => start + 2 + i,
+            None => break,
+        };
+        match Link::from_inner(&message[start + 2..end]) {
+            Some(link) => {
+                res.push((start, end + 2, link));
+                pos = end + 2;
+            }
+            // as `[[[word]]`, the link may start later
+            None => pos = start + 1,
+        }
+    }
+    res
+}
+
+/// the links of the message, each once in the order they first occur
+pub fn parse(message: &str) -> Vec<Link> {
+    let mut res: Vec<Link> = Vec::new();
+    for (_, _, link) in find(message) {
+        if !res.contains(&link) {
+            res.push(link);
+        }
+    }
+    res
+}
+
+/// the link at the byte offset of the message, for following a click
+pub fn at(message: &str, pos: usize) -> Option<Link> {
+    find(message)
+        .into_iter()
+        .find(|(start, end, _)| *start <= pos && pos < *end)
+        .map(|(_, _, link)| link)
+}
+
+/// point the links the target holds for to the word new, the library
+/// written in a link is kept
+pub fn rewrite<P: Fn(&Link) -> bool>(message: &str, target: P, new: &str) -> String {
+    let mut res = String::with_capacity(message.len());
+    let mut last = 0;
+    for (start, end, link) in find(message) {
+        if target(&link) {
+            res.push_str(&message[last..start]);
+            let link = Link {
+                library: link.library,
+                word: new.to_owned(),
+            };
+            res.push_str(&link.to_string());
+            last = end;
+        }
+    }
+    res.push_str(&message[last..]);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links() {
+        let message =
+            "see also [[school]], [[ english : scholar ]] and [[school]]\n[[]] [[a\nb]] [[[x]]";
+        let links = parse(message);
+        let texts: Vec<String> = links.iter().map(|l| l.to_string()).collect();
+        assert_eq!(texts, vec!["[[school]]", "[[english:scholar]]", "[[x]]"]);
+        assert_eq!(at(message, 12), Some(links[0].clone()));
+        assert_eq!(at(message, 5), None);
+
+        let renamed = rewrite(message, |l| l.word == "school", "schools");
+        assert_eq!(
+            renamed,
+            "see also [[schools]], [[ english : scholar ]] and [[schools]]\n[[]] [[a\nb]] [[[x]]"
+        );
+        let renamed = rewrite(message, |l| l.library.is_some(), "student");
+        assert!(renamed.contains("[[english:student]]") && renamed.contains("[[school]]"));
+    }
+}
@@ -0,0 +1,473 @@
+// diff and merge of two libraries
+//
+// the words of both stores are read in name order batch by batch and joined,
+// a merge writes the words of the source into the destination in one
+// transaction, the conflicts are resolved before the transaction begins and
+// the words resolved are checked again in it, a word of dst changed meanwhile
+// fails the merge with a conflict
+use super::exchange::merge_message;
+use super::{InternalError, Read, Store, Transaction, World};
+use crate::db::sqlite::store::Sqlite;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, Write as _};
+use std::str::FromStr;
+
+pub const USAGE: &str = r#"  diff <a> <b> [--messages]
+                     list the words only in library a (<), only in b (>) and different (!)
+  merge <dst> <src> [--ours | --theirs | --concat | --interactive] [--dry-run]
+                     merge the words of library src into dst in one transaction, a word
+                     in both with different messages keeps dst by default, --interactive
+                     asks for every one"#;
+
+// words read from a store at once
+const BATCH: i64 = 1000;
+
+/// the difference of library a to library b
+#[derive(Debug, Default)]
+pub struct Diff {
+    pub only_a: Vec<World>,
+    pub only_b: Vec<World>,
+    /// words in both with a different message, the word of a first
+    pub changed: Vec<(World, World)>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.only_a.is_empty() && self.only_b.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "only in a {}, only in b {}, different {}",
+            self.only_a.len(),
+            self.only_b.len(),
+            self.changed.len()
+        )
+    }
+}
+
+/// how a word in both libraries with different messages is merged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    /// keep the message of the destination
+    Ours,
+    /// replace it by the message of the source
+    Theirs,
+    /// append the message of the source unless it is in the destination
+    Concatenate,
+    /// stop the merge, nothing is written
+    Abort,
+}
+
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ours" => Ok(Resolution::Ours),
+            "theirs" => Ok(Resolution::Theirs),
+            "concat" | "concatenate" => Ok(Resolution::Concatenate),
+            _ => Err(format!("unknown resolution {}, ours, theirs or concat", s)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    pub added: usize,
+    pub ours: usize,
+    pub theirs: usize,
+    pub concatenated: usize,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "added {}, kept ours {}, took theirs {}, concatenated {}",
+            self.added, self.ours, self.theirs, self.concatenated
+        )
+    }
+}
+
+// the words of a store in name order
+struct Cursor {
+    buf: VecDeque<World>,
+    after: String,
+    done: bool,
+}
+
+impl Cursor {
+    fn new() -> Cursor {
+        Cursor {
+            buf: VecDeque::new(),
+            after: String::new(),
+            done: false,
+        }
+    }
+
+    fn next<T, E, S>(&mut self, store: &S) -> Result<Option<World>, InternalError<E>>
+    where
+        S: Read<T, InternalError<E>> + ?Sized,
+    {
+        if self.buf.is_empty() && !self.done {
+            let ws = store.scan(&self.after, BATCH)?;
+            self.done = (ws.len() as i64) < BATCH;
+            self.buf.extend(ws);
+        }
+        let word = self.buf.pop_front();
+        if let Some(w) = &word {
+            self.after = w.name.clone();
+        }
+        Ok(word)
+    }
+}
+
+/// the words only in a, only in b and in both with different messages
+pub fn diff<T, E, A, B>(a: &A, b: &B) -> Result<Diff, InternalError<E>>
+where
+    A: Read<T, InternalError<E>> + ?Sized,
+    B: Read<T, InternalError<E>> + ?Sized,
+{
+    let mut res = Diff::default();
+    let (mut ca, mut cb) = (Cursor::new(), Cursor::new());
+    let (mut x, mut y) = (ca.next(a)?, cb.next(b)?);
+    loop {
+        match (x.take(), y.take()) {
+            (None, None) => return Ok(res),
+            (Some(wa), None) => {
+                res.only_a.push(wa);
+                x = ca.next(a)?;
+            }
+            (None, Some(wb)) => {
+                res.only_b.push(wb);
+                y = cb.next(b)?;
+            }
+            (Some(wa), Some(wb)) => match wa.name.cmp(&wb.name) {
+                std::cmp::Ordering::Less => {
+                    res.only_a.push(wa);
+                    x = ca.next(a)?;
+                    y = Some(wb);
+                }
+                std::cmp::Ordering::Greater => {
+                    res.only_b.push(wb);
+                    x = Some(wa);
+                    y = cb.next(b)?;
+                }
+                std::cmp::Ordering::Equal => {
+                    if wa.message != wb.message {
+                        res.changed.push((wa, wb));
+                    }
+                    x = ca.next(a)?;
+                    y = cb.next(b)?;
+                }
+            },
+        }
+    }
+}
+
+/// merge the words of src into dst, the words only in src are copied with
+/// their review state, resolve is called with the word of dst and of src for
+/// every conflict, nothing is written for a dry run, an abort or a conflict
+pub fn merge<T, E, F, D, S, R>(
+    dst: &D,
+    src: &S,
+    mut resolve: R,
+    dry_run: bool,
+) -> Result<Report, InternalError<E>>
+where
+    D: Store<T, InternalError<E>, F> + ?Sized,
+    S: Read<T, InternalError<E>> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+    R: FnMut(&World, &World) -> Resolution,
+{
+    let d = diff(dst, src)?;
+    let mut updates = Vec::new();
+    let mut report = Report::default();
+    for (ours, theirs) in &d.changed {
+        let version = match dst.version(&ours.name) {
+            Err(InternalError::NotFound) => return Err(InternalError::Conflict),
+            v => v?,
+        };
+        let message = match resolve(ours, theirs) {
+            Resolution::Ours => {
+                report.ours += 1;
+                continue;
+            }
+            Resolution::Theirs => {
+                report.theirs += 1;
+                theirs.message.clone()
+            }
+            Resolution::Concatenate => {
+                report.concatenated += 1;
+                match merge_message(&ours.message, &theirs.message) {
+                    Some(m) => m,
+                    None => continue,
+                }
+            }
+            Resolution::Abort => return Err(InternalError::Canceled),
+        };
+        let w = World {
+            name: ours.name.clone(),
+            message,
+        };
+        updates.push((w, ours, version));
+    }
+
+    let t = dst.begin()?;
+    let res = (|| {
+        // insert fails if the word was added to dst meanwhile
+        for w in &d.only_b {
+            let entry = src.entry(&w.name)?;
+            t.insert(&entry.word)?;
+            t.put(&entry)?;
+        }
+        // the resolution holds only for the word diff has seen
+        for (w, ours, version) in &updates {
+            let seen = match t.get(&w.name) {
+                Ok(cur) => cur.message == ours.message && t.version(&w.name)? == *version,
+                Err(InternalError::NotFound) => false,
+                Err(err) => return Err(err),
+            };
+            if !seen {
+                return Err(InternalError::Conflict);
+            }
+            t.update(w)?;
+        }
+        Ok(())
+    })();
+    match res {
+        Ok(()) if !dry_run => t.commit()?,
+        Ok(()) => t.rollback()?,
+        Err(err) => {
+            t.rollback()?;
+            return Err(err);
+        }
+    }
+    report.added = d.only_b.len();
+    Ok(report)
+}
+
+fn indent(message: &str) -> String {
+    message
+        .lines()
+        .map(|l| format!("    {}\n", l))
+        .collect::<String>()
+}
+
+// ask on the terminal how to merge a conflict
+fn ask(ours: &World, theirs: &World) -> Resolution {
+    print!(
+        "! {}\n  ours:\n{}  theirs:\n{}",
+        ours.name,
+        indent(&ours.message),
+        indent(&theirs.message)
+    );
+    let stdin = io::stdin();
+    loop {
+        print!("[o]urs, [t]heirs, [c]oncatenate, [a]bort? ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => return Resolution::Abort,
+            Ok(_) => {}
+        }
+        match line.trim() {
+            "o" | "ours" => return Resolution::Ours,
+            "t" | "theirs" => return Resolution::Theirs,
+            "c" | "concat" | "concatenate" => return Resolution::Concatenate,
+            "a" | "abort" => return Resolution::Abort,
+            _ => {}
+        }
+    }
+}
+
+fn open(path: &str) -> Result<Sqlite, String> {
+    if !std::path::Path::new(path).is_file() {
+        return Err(format!("{} is not a library file", path));
+    }
+    Sqlite::new(path.to_owned()).map_err(|e| format!("open {}: {}", path, e))
+}
+
+/// diff <a> <b> [--messages] and merge <dst> <src> [options]
+pub fn command(args: &[String]) -> Result<(), String> {
+    let (cmd, paths, opts) = match args {
+        [cmd, a, b, opts @ ..] if cmd == "diff" || cmd == "merge" => (cmd.as_str(), (a, b), opts),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let (a, b) = (open(paths.0)?, open(paths.1)?);
+
+    if cmd == "diff" {
+        let messages = match opts {
+            [] => false,
+            [o] if o == "--messages" => true,
+            _ => return Err(USAGE.to_owned()),
+        };
+        let d = diff(&a, &b).map_err(|e| e.to_string())?;
+        let show = |mark: &str, w: &World| {
+            println!("{} {}", mark, w.name);
+            if messages {
+                print!("{}", indent(&w.message));
+            }
+        };
+        d.only_a.iter().for_each(|w| show("<", w));
+        d.only_b.iter().for_each(|w| show(">", w));
+        for (wa, wb) in &d.changed {
+            println!("! {}", wa.name);
+            if messages {
+                print!("  <\n{}  >\n{}", indent(&wa.message), indent(&wb.message));
+            }
+        }
+        println!("{}", d);
+        return Ok(());
+    }
+
+    let (mut fixed, mut dry_run) = (Some(Resolution::Ours), false);
+    for opt in opts {
+        match opt.as_str() {
+            "--ours" => fixed = Some(Resolution::Ours),
+            "--theirs" => fixed = Some(Resolution::Theirs),
+            "--concat" => fixed = Some(Resolution::Concatenate),
+            "--interactive" => fixed = None,
+            "--dry-run" => dry_run = true,
+            _ => return Err(format!("unknown option {}", opt)),
+        }
+    }
+    let resolve = |ours: &World, theirs: &World| match fixed {
+        Some(r) => r,
+        None => ask(ours, theirs),
+    };
+    let report = match merge(&a, &b, resolve, dry_run) {
+        Ok(report) => report,
+        Err(InternalError::Canceled) => return Err("merge aborted, nothing is written".to_owned()),
+        Err(InternalError::Conflict) => {
+            return Err(format!(
+                "{} is changed during the merge, nothing is written",
+                paths.0
+            ))
+        }
+        Err(err) => return Err(format!("merge fail, nothing is written, {}", err)),
+    };
+    if dry_run {
+        println!("dry run: {}", report);
+    } else {
+        println!("{}", report);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::review::Schedule;
+    use crate::store::Write;
+
+    fn store(path: &str, words: &[(&str, &str)]) -> Sqlite {
+        let _ = std::fs::remove_file(path);
+        let s = Sqlite::new(path.to_owned()).unwrap();
+        for (name, message) in words {
+            s.insert(&World {
+                name: name.to_string(),
+                message: message.to_string(),
+            })
+            .unwrap();
+        }
+        s
+    }
+
+    #[test]
+    fn test_diff_merge() {
+        let a = store(
+            "target/test_merge_a.sql",
+            &[("apple", "fruit"), ("book", "read"), ("cat", "pet")],
+        );
+        let b = store(
+            "target/test_merge_b.sql",
+            &[("book", "to reserve"), ("cat", "pet"), ("dog", "pet")],
+        );
+        b.set_schedule("dog", &Schedule::default().next(4, 100))
+            .unwrap();
+
+        let d = diff(&a, &b).unwrap();
+        let names = |ws: &[World]| ws.iter().map(|w| w.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&d.only_a), vec!["apple"]);
+        assert_eq!(names(&d.only_b), vec!["dog"]);
+        assert_eq!(d.changed.len(), 1);
+        assert_eq!(d.changed[0].1.message, "to reserve");
+
+        // a dry run and an abort write nothing
+        let report = merge(&a, &b, |_, _| Resolution::Theirs, true).unwrap();
+        assert_eq!((report.added, report.theirs), (1, 1));
+        assert!(merge(&a, &b, |_, _| Resolution::Abort, false).is_err());
+        assert_eq!(diff(&a, &b).unwrap().only_b.len(), 1);
+
+        let mut asked = Vec::new();
+        let report = merge(
+            &a,
+            &b,
+            |ours, _| {
+                asked.push(ours.name.clone());
+                Resolution::Concatenate
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(asked, vec!["book"]);
+        assert_eq!(report.concatenated, 1);
+        assert_eq!(a.get("book").unwrap().message, "read\nto reserve");
+        assert_eq!(a.schedule("dog").unwrap(), b.schedule("dog").unwrap());
+
+        let d = diff(&a, &b).unwrap();
+        assert!(d.only_b.is_empty());
+        assert_eq!(names(&d.only_a), vec!["apple"]);
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let a = store("target/test_merge_c.sql", &[("book", "read")]);
+        let b = store(
+            "target/test_merge_d.sql",
+            &[("book", "to reserve"), ("dog", "pet")],
+        );
+
+        // dst is edited while the conflict is resolved
+        let res = merge(
+            &a,
+            &b,
+            |ours, _| {
+                let w = World {
+                    name: ours.name.clone(),
+                    message: "to read".to_owned(),
+                };
+                a.update(&w).unwrap();
+                Resolution::Theirs
+            },
+            false,
+        );
+        assert!(matches!(res, Err(InternalError::Conflict)));
+        assert_eq!(a.get("book").unwrap().message, "to read");
+        assert!(matches!(a.get("dog"), Err(InternalError::NotFound)));
+
+        // a word kept as ours is not written, its edit does not conflict
+        let report = merge(
+            &a,
+            &b,
+            |ours, _| {
+                let w = World {
+                    name: ours.name.clone(),
+                    message: "read".to_owned(),
+                };
+                a.update(&w).unwrap();
+                Resolution::Ours
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!((report.added, report.ours), (1, 1));
+        assert_eq!(a.get("book").unwrap().message, "read");
+    }
+}
@@ -1,13 +1,67 @@
+pub mod asynchronous;
+pub mod attachment;
+pub mod dict;
+pub mod exchange;
 pub mod gui;
+pub mod history;
+pub mod home;
+pub mod links;
+pub mod merge;
+pub mod preview;
+pub mod review;
+pub mod search;
+pub mod server;
+pub mod shell;
+pub mod smart;
+pub mod sync;
+pub mod tags;
+pub mod trash;
+pub mod tui;
+pub mod undo;
 
+use attachment::Attachment;
+use history::Snapshot;
+use links::Link;
+use review::Schedule;
+use smart::{Query, Smart};
+use std::sync::Mutex;
 use std::{collections::HashMap, fmt};
+use sync::{Conflict, Record};
+use trash::Trashed;
+use undo::{Action, Change, Journal};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct World {
     pub name: String,
     pub message: String,
 }
 
+/// a word with everything stored for it, copied as a whole between stores
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub word: World,
+    pub version: i64,
+    // None if never reviewed
+    pub schedule: Option<Schedule>,
+    /// sorted
+    pub tags: Vec<String>,
+    /// sorted by name
+    pub attachments: Vec<Attachment>,
+}
+
+impl From<World> for Entry {
+    /// a new word, never reviewed
+    fn from(word: World) -> Self {
+        Entry {
+            word,
+            version: 1,
+            schedule: None,
+            tags: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+}
+
 pub trait Transaction<T, E>: Write<T, E> + Read<T, E> {
     fn rollback(self) -> Result<(), E>;
     fn commit(self) -> Result<(), E>;
@@ -17,11 +71,64 @@ pub trait Write<T, E> {
     fn insert(&self, word: &World) -> Result<Option<T>, E>;
     fn update(&self, word: &World) -> Result<Option<T>, E>;
     fn delete(&self, word: &str) -> Result<Option<T>, E>;
+    fn set_schedule(&self, word: &str, schedule: &Schedule) -> Result<Option<T>, E>;
+    /// write the entry as it is, the word with same name is replaced
+    fn put(&self, entry: &Entry) -> Result<Option<T>, E>;
+    fn set_metadata(&self, key: &str, value: &str) -> Result<Option<T>, E>;
+    /// write the word with the revisions of the record as they are, used by sync
+    fn put_record(&self, record: &Record) -> Result<Option<T>, E>;
+    fn add_conflict(&self, conflict: &Conflict) -> Result<Option<T>, E>;
+    fn remove_conflict(&self, id: i64) -> Result<Option<T>, E>;
+    /// keep a deleted word in the trash, a new id is given to it
+    fn put_trashed(&self, trashed: &Trashed) -> Result<Option<T>, E>;
+    fn remove_trashed(&self, id: i64) -> Result<Option<T>, E>;
+    fn add_tag(&self, word: &str, tag: &str) -> Result<Option<T>, E>;
+    fn remove_tag(&self, word: &str, tag: &str) -> Result<Option<T>, E>;
+    /// attach the file to the word, the file with the same name is replaced
+    fn put_attachment(&self, word: &str, attachment: &Attachment) -> Result<Option<T>, E>;
+    fn remove_attachment(&self, word: &str, name: &str) -> Result<Option<T>, E>;
 }
 
 pub trait Read<T, E> {
     fn get(&self, id: &str) -> Result<World, E>;
     fn list(&self, size: i64) -> Result<Vec<World>, E>;
+    /// words ordered by name after the given name, for reading a store batch by batch
+    fn scan(&self, after: &str, size: i64) -> Result<Vec<World>, E>;
+    /// version of the word, increased by every update
+    fn version(&self, id: &str) -> Result<i64, E>;
+    fn schedule(&self, id: &str) -> Result<Schedule, E>;
+    /// words due for review at the time now, most overdue first
+    fn due(&self, now: i64, size: i64) -> Result<Vec<World>, E>;
+    fn entry(&self, id: &str) -> Result<Entry, E>;
+    /// metadata of the library, like its description, sorted by key
+    fn metadata(&self) -> Result<Vec<(String, String)>, E>;
+    /// the revisions of the words and of the deleted words ordered by name after the given name
+    fn records(&self, after: &str, size: i64) -> Result<Vec<Record>, E>;
+    /// conflicts kept by sync, oldest first
+    fn conflicts(&self) -> Result<Vec<Conflict>, E>;
+    /// the earlier messages of the word, newest first
+    fn history(&self, id: &str) -> Result<Vec<Snapshot>, E>;
+    /// the last snapshot of every deleted word, newest first
+    fn deleted(&self, size: i64) -> Result<Vec<Snapshot>, E>;
+    /// the words in the trash, the last deleted first
+    fn trashed(&self, size: i64) -> Result<Vec<Trashed>, E>;
+    /// the id of the word last put in the trash, 0 for an empty trash
+    fn last_trashed(&self) -> Result<i64, E>;
+    /// every tag with the count of its words, sorted by tag
+    fn tags(&self) -> Result<Vec<(String, i64)>, E>;
+    /// the tags of the word, sorted
+    fn word_tags(&self, id: &str) -> Result<Vec<String>, E>;
+    /// the words with the tag, sorted
+    fn tagged(&self, tag: &str) -> Result<Vec<String>, E>;
+    /// the links in the message of the word, in the order they occur
+    fn links(&self, id: &str) -> Result<Vec<Link>, E>;
+    /// the words linking to a word named target with the links, sorted by word
+    fn backlinks(&self, target: &str) -> Result<Vec<(String, Link)>, E>;
+    /// every link with the word it is in, sorted by word
+    fn all_links(&self) -> Result<Vec<(String, Link)>, E>;
+    /// the files attached to the word, sorted by name
+    fn attachments(&self, id: &str) -> Result<Vec<Attachment>, E>;
+    fn attachment(&self, id: &str, name: &str) -> Result<Attachment, E>;
 }
 pub trait Store<T, E, F: Transaction<T, E>>: Write<T, E> + Read<T, E> {
     fn begin(&self) -> Result<F, E>;
@@ -56,6 +163,13 @@ impl<T: fmt::Display> fmt::Display for Error<T> {
 pub enum InternalError<E> {
     NotFound,
     StoreNotFound,
+    // the word has been changed since the version the caller read
+    Conflict,
+    // the operation is canceled before it finish
+    Canceled,
+    Timeout,
+    // an undo or redo is written to some of its stores only
+    Partial(Box<InternalError<E>>),
     Unknow(Error<E>),
 }
 
@@ -69,6 +183,12 @@ impl<E: fmt::Display> fmt::Display for InternalError<E> {
             InternalError::StoreNotFound => {
                 return write!(f, "internal error for store not found");
             }
+            InternalError::Conflict => write!(f, "internal error for version conflict"),
+            InternalError::Canceled => write!(f, "internal error for operation canceled"),
+            InternalError::Timeout => write!(f, "internal error for operation timeout"),
+            InternalError::Partial(err) => {
+                write!(f, "internal error for operation partly applied, {}", err)
+            }
             InternalError::Unknow(err) => {
                 return write!(f, "internal error for unknow {}", err);
             }
@@ -77,14 +197,76 @@ impl<E: fmt::Display> fmt::Display for InternalError<E> {
     }
 }
 
+/// library files of a path: the path itself for a file,
+/// or every .sql file under it for a workspace directory
+pub fn library_files(path: &str) -> std::io::Result<Vec<String>> {
+    let p = std::path::Path::new(path);
+    if !p.is_dir() {
+        return Ok(vec![path.to_owned()]);
+    }
+
+    let mut files: Vec<String> = std::fs::read_dir(p)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().map(|e| e == "sql").unwrap_or(false))
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+// the word is the same in both, or in none
+fn same(a: &Option<Entry>, b: &Option<Entry>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.word.message == b.word.message
+                && a.schedule == b.schedule
+                && a.tags == b.tags
+                && a.attachments == b.attachments
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+// the words of the stores matching the pattern, best match first
+fn rank(words: Vec<(String, World)>, pattern: &str, limit: usize) -> Vec<(String, World)> {
+    let mut res: Vec<(usize, String, World)> = words
+        .into_iter()
+        .filter_map(|(key, word)| search::score(pattern, &word.name).map(|s| (s, key, word)))
+        .collect();
+    res.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.2.name.cmp(&b.2.name)));
+    res.truncate(limit);
+    res.into_iter().map(|(_, k, w)| (k, w)).collect()
+}
+
+/// stores are shared between threads, so WordLibrary is Send + Sync
+/// and can be shared by Arc without a lock for the word operations
+pub type BoxStore<T, E, F> = Box<dyn Store<T, InternalError<E>, F> + Send + Sync>;
+
+// the words an op deleted into the trash, as key, name and id in the trash
+type Discarded = Vec<(String, String, i64)>;
+
 // pub struct WordLibrary<T, E, F: Transaction<T, InternalError<E>>, S: Store<T, InternalError<E>, F>>
 pub struct WordLibrary<T, E, F>
 where
     F: Transaction<T, InternalError<E>>,
     // S: Store<T, InternalError<E>, F>,
 {
-    stores: HashMap<String, Box<dyn Store<T, InternalError<E>, F>>>,
+    stores: HashMap<String, BoxStore<T, E, F>>,
     // stores: HashMap<String, S>,
+    // the deleted words of the stores, dropped on delete if None
+    trash: Option<BoxStore<T, E, F>>,
+    // the actions to undo, not recorded if None
+    journal: Option<Mutex<Journal>>,
+    // the saved searches read as smart libraries by name
+    searches: HashMap<String, Query>,
+}
+
+impl<T, E: std::fmt::Debug, F: Transaction<T, InternalError<E>>> Default for WordLibrary<T, E, F> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<
@@ -94,21 +276,203 @@ impl<
         // S: Store<T, InternalError<E>, F>,
     > WordLibrary<T, E, F>
 {
+    pub fn new() -> Self {
+        WordLibrary {
+            stores: HashMap::new(),
+            trash: None,
+            journal: None,
+            searches: HashMap::new(),
+        }
+    }
+
+    /// register a store under key, the old store with same key will be replaced
+    pub fn add_store(&mut self, key: &str, store: BoxStore<T, E, F>) {
+        self.stores.insert(key.to_owned(), store);
+    }
+
+    /// close the store of key, its connections are released when it is dropped
+    pub fn remove_store(&mut self, key: &str) -> Option<BoxStore<T, E, F>> {
+        self.stores.remove(key)
+    }
+
+    /// a store or a smart library is registered under key
+    pub fn contains(&self, key: &str) -> bool {
+        self.stores.contains_key(key) || self.searches.contains_key(key)
+    }
+
+    /// keys of all opened stores, sorted
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.stores.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
     pub fn insert(&self, word: &World, key: &str) -> Result<Option<T>, InternalError<E>> {
-        let s = self.get_store(key)?;
-        s.insert(word)
+        let label = format!("insert {}", word.name);
+        self.record(label, &[(key, &word.name)], || {
+            self.get_store(key)?.insert(word)
+        })
     }
 
     pub fn update(&self, word: &World, key: &str) -> Result<Option<T>, InternalError<E>> {
-        let s = self.get_store(key)?;
-        s.update(word)
+        let key = &self.owner(key, &word.name)?;
+        let label = format!("edit {}", word.name);
+        self.record(label, &[(key, &word.name)], || {
+            self.get_store(key)?.update(word)
+        })
     }
+
+    /// delete the word, it's kept in the trash if there is one
     pub fn delete(&self, name: &str, key: &str) -> Result<Option<T>, InternalError<E>> {
+        let key = &self.owner(key, name)?;
+        let label = format!("delete {}", name);
+        self.record_trashed(label, &[(key, name)], |discarded| {
+            self.delete_word(name, key, discarded)
+        })
+    }
+
+    fn delete_word(
+        &self,
+        name: &str,
+        key: &str,
+        discarded: &mut Discarded,
+    ) -> Result<Option<T>, InternalError<E>> {
         let s = self.get_store(key)?;
-        s.delete(name)
+        let trash = match &self.trash {
+            Some(trash) => trash,
+            None => return s.delete(name),
+        };
+
+        let t = s.begin()?;
+        match trash::discard(&**trash, &t, name, key) {
+            Ok(id) => {
+                t.commit()?;
+                discarded.push((key.to_owned(), name.to_owned(), id));
+                Ok(None)
+            }
+            Err(err) => {
+                t.rollback()?;
+                Err(err)
+            }
+        }
+    }
+
+    /// update the word only if its version is still the given one,
+    /// return the new version
+    pub fn update_if(
+        &self,
+        word: &World,
+        key: &str,
+        version: i64,
+    ) -> Result<i64, InternalError<E>> {
+        let key = &self.owner(key, &word.name)?;
+        let label = format!("edit {}", word.name);
+        self.record(label, &[(key, &word.name)], || {
+            self.update_word_if(word, key, version)
+        })
+    }
+
+    fn update_word_if(
+        &self,
+        word: &World,
+        key: &str,
+        version: i64,
+    ) -> Result<i64, InternalError<E>> {
+        let s = self.get_store(key)?;
+        let t = s.begin()?;
+
+        let res = match t.version(&word.name) {
+            Ok(v) if v == version => t.update(word),
+            Ok(_) => Err(InternalError::Conflict),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            t.rollback()?;
+            return Err(err);
+        }
+
+        t.commit()?;
+        Ok(version + 1)
+    }
+
+    /// delete the word only if its version is still the given one
+    pub fn delete_if(
+        &self,
+        name: &str,
+        key: &str,
+        version: i64,
+    ) -> Result<Option<T>, InternalError<E>> {
+        let key = &self.owner(key, name)?;
+        let label = format!("delete {}", name);
+        self.record_trashed(label, &[(key, name)], |discarded| {
+            self.delete_word_if(name, key, version, discarded)
+        })
+    }
+
+    fn delete_word_if(
+        &self,
+        name: &str,
+        key: &str,
+        version: i64,
+        discarded: &mut Discarded,
+    ) -> Result<Option<T>, InternalError<E>> {
+        let s = self.get_store(key)?;
+        let t = s.begin()?;
+
+        let res = match (t.version(name), &self.trash) {
+            (Ok(v), Some(trash)) if v == version => {
+                trash::discard(&**trash, &t, name, key).map(Some)
+            }
+            (Ok(v), None) if v == version => t.delete(name).map(|_| None),
+            (Ok(_), _) => Err(InternalError::Conflict),
+            (Err(err), _) => Err(err),
+        };
+        match res {
+            Ok(id) => {
+                t.commit()?;
+                discarded.extend(id.map(|id| (key.to_owned(), name.to_owned(), id)));
+                Ok(None)
+            }
+            Err(err) => {
+                t.rollback()?;
+                Err(err)
+            }
+        }
+    }
+
+    pub fn version(&self, name: &str, key: &str) -> Result<i64, InternalError<E>> {
+        if let Some(s) = self.smart_of(key) {
+            return s.version(name);
+        }
+        self.get_store(key)?.version(name)
+    }
+
+    /// words of the store due for review at the time now
+    pub fn due(&self, now: i64, size: i64, key: &str) -> Result<Vec<World>, InternalError<E>> {
+        if let Some(s) = self.smart_of(key) {
+            return s.due(now, size);
+        }
+        self.get_store(key)?.due(now, size)
+    }
+
+    /// grade a review of the word and schedule the next one
+    pub fn review(
+        &self,
+        name: &str,
+        key: &str,
+        grade: u8,
+        now: i64,
+    ) -> Result<Schedule, InternalError<E>> {
+        let s = self.get_store(&self.owner(key, name)?)?;
+        let next = s.schedule(name)?.next(grade, now);
+        s.set_schedule(name, &next)?;
+        Ok(next)
     }
 
     pub fn get(&self, name: &str, key: &str) -> Result<World, InternalError<E>> {
+        if let Some(s) = self.smart_of(key) {
+            return s.get(name);
+        }
         match self.stores.get(key) {
             Some(s) => s.get(name),
             None => Err(InternalError::StoreNotFound),
@@ -116,37 +480,260 @@ impl<
     }
 
     pub fn list(&self, size: i64, key: &str) -> Result<Vec<World>, InternalError<E>> {
+        if let Some(s) = self.smart_of(key) {
+            return s.list(size);
+        }
         match self.stores.get(key) {
             Some(s) => s.list(size),
             None => Err(InternalError::StoreNotFound),
         }
     }
 
-    fn get_store(
+    /// fuzzy search word names in the store, best match first
+    pub fn search(
+        &self,
+        pattern: &str,
+        key: &str,
+        limit: usize,
+    ) -> Result<Vec<World>, InternalError<E>> {
+        Ok(search::fuzzy(self.list(0, key)?, pattern, limit))
+    }
+
+    /// fuzzy search word names in every store, return the store key with the word
+    pub fn search_all(
+        &self,
+        pattern: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, World)>, InternalError<E>> {
+        let mut words = Vec::new();
+        for key in self.keys() {
+            for word in self.get_store(&key)?.list(0)? {
+                words.push((key.clone(), word));
+            }
+        }
+        Ok(rank(words, pattern, limit))
+    }
+
+    /// fuzzy search the words of every store the tag expression holds for
+    pub fn search_all_tagged(
         &self,
+        pattern: &str,
+        expr: &tags::Expr,
+        limit: usize,
+    ) -> Result<Vec<(String, World)>, InternalError<E>> {
+        Ok(rank(self.filter_all(expr)?, pattern, limit))
+    }
+
+    /// tag the word, the tags it has already are skipped
+    pub fn add_tags(&self, name: &str, tags: &[String], key: &str) -> Result<(), InternalError<E>> {
+        let key = &self.owner(key, name)?;
+        let label = format!("tag {}", name);
+        self.record(label, &[(key, name)], || {
+            self.write_tags(name, tags, key, true)
+        })
+    }
+
+    /// take the tags off the word
+    pub fn remove_tags(
+        &self,
+        name: &str,
+        tags: &[String],
         key: &str,
-    ) -> Result<&Box<dyn Store<T, InternalError<E>, F>>, InternalError<E>> {
+    ) -> Result<(), InternalError<E>> {
+        let key = &self.owner(key, name)?;
+        let label = format!("untag {}", name);
+        self.record(label, &[(key, name)], || {
+            self.write_tags(name, tags, key, false)
+        })
+    }
+
+    fn write_tags(
+        &self,
+        name: &str,
+        tags: &[String],
+        key: &str,
+        add: bool,
+    ) -> Result<(), InternalError<E>> {
+        if let Some(tag) = tags.iter().find(|t| !tags::valid(t)) {
+            return Err(tags::invalid(tag));
+        }
+        let t = self.get_store(key)?.begin()?;
+        let res = t.version(name).and_then(|_| {
+            for tag in tags {
+                match add {
+                    true => t.add_tag(name, tag)?,
+                    false => t.remove_tag(name, tag)?,
+                };
+            }
+            Ok(())
+        });
+        match res {
+            Ok(_) => t.commit(),
+            Err(err) => {
+                t.rollback()?;
+                Err(err)
+            }
+        }
+    }
+
+    /// attach the file to the word, the file with the same name is replaced
+    pub fn attach(
+        &self,
+        name: &str,
+        attachment: &Attachment,
+        key: &str,
+    ) -> Result<(), InternalError<E>> {
+        if !attachment::valid(&attachment.name) {
+            return Err(attachment::invalid(&attachment.name));
+        }
+        let key = &self.owner(key, name)?;
+        let label = format!("attach {} to {}", attachment.name, name);
+        self.record(label, &[(key, name)], || {
+            self.get_store(key)?
+                .put_attachment(name, attachment)
+                .map(|_| ())
+        })
+    }
+
+    /// remove the file from the word
+    pub fn detach(&self, name: &str, file: &str, key: &str) -> Result<(), InternalError<E>> {
+        let key = &self.owner(key, name)?;
+        let label = format!("detach {} from {}", file, name);
+        self.record(label, &[(key, name)], || {
+            let t = self.get_store(key)?.begin()?;
+            match t
+                .attachment(name, file)
+                .and_then(|_| t.remove_attachment(name, file))
+            {
+                Ok(_) => t.commit(),
+                Err(err) => {
+                    t.rollback()?;
+                    Err(err)
+                }
+            }
+        })
+    }
+
+    /// the files attached to the word, sorted by name
+    pub fn attachments(&self, name: &str, key: &str) -> Result<Vec<Attachment>, InternalError<E>> {
+        if let Some(s) = self.smart_of(key) {
+            return s.attachments(name);
+        }
+        self.get_store(key)?.attachments(name)
+    }
+
+    pub fn attachment(
+        &self,
+        name: &str,
+        file: &str,
+        key: &str,
+    ) -> Result<Attachment, InternalError<E>> {
+        if let Some(s) = self.smart_of(key) {
+            return s.attachment(name, file);
+        }
+        self.get_store(key)?.attachment(name, file)
+    }
+
+    /// the tags of the store with the count of their words
+    pub fn tags(&self, key: &str) -> Result<Vec<(String, i64)>, InternalError<E>> {
+        if let Some(s) = self.smart_of(key) {
+            return s.tags();
+        }
+        self.get_store(key)?.tags()
+    }
+
+    /// the tags of the word, sorted
+    pub fn word_tags(&self, name: &str, key: &str) -> Result<Vec<String>, InternalError<E>> {
+        if let Some(s) = self.smart_of(key) {
+            return s.word_tags(name);
+        }
+        self.get_store(key)?.word_tags(name)
+    }
+
+    /// the tags of every store, the counts are added up
+    pub fn tags_all(&self) -> Result<Vec<(String, i64)>, InternalError<E>> {
+        let mut counts: HashMap<String, i64> = HashMap::new();
+        for s in self.stores.values() {
+            for (tag, n) in s.tags()? {
+                *counts.entry(tag).or_insert(0) += n;
+            }
+        }
+        let mut res: Vec<(String, i64)> = counts.into_iter().collect();
+        res.sort();
+        Ok(res)
+    }
+
+    /// the words of the store the tag expression holds for, sorted by name
+    pub fn filter(&self, expr: &tags::Expr, key: &str) -> Result<Vec<World>, InternalError<E>> {
+        let mut names: Vec<String> = match self.smart_of(key) {
+            Some(s) => expr.eval(&s)?,
+            None => expr.eval(&**self.get_store(key)?)?,
+        }
+        .into_iter()
+        .collect();
+        names.sort();
+        names.iter().map(|name| self.get(name, key)).collect()
+    }
+
+    /// the words of every store the tag expression holds for, with the store key
+    pub fn filter_all(&self, expr: &tags::Expr) -> Result<Vec<(String, World)>, InternalError<E>> {
+        let mut res = Vec::new();
+        for key in self.keys() {
+            for word in self.filter(expr, &key)? {
+                res.push((key.clone(), word));
+            }
+        }
+        Ok(res)
+    }
+
+    /// fuzzy search the words of the store the tag expression holds for
+    pub fn search_tagged(
+        &self,
+        pattern: &str,
+        expr: &tags::Expr,
+        key: &str,
+        limit: usize,
+    ) -> Result<Vec<World>, InternalError<E>> {
+        Ok(search::fuzzy(self.filter(expr, key)?, pattern, limit))
+    }
+
+    /// the store registered under key
+    pub fn store(&self, key: &str) -> Result<&BoxStore<T, E, F>, InternalError<E>> {
+        self.get_store(key)
+    }
+
+    fn get_store(&self, key: &str) -> Result<&BoxStore<T, E, F>, InternalError<E>> {
         self.stores.get(key).ok_or(InternalError::StoreNotFound)
     }
 
     pub fn move_to(&self, name: &str, src: &str, dst: &str) -> Result<Option<T>, InternalError<E>> {
+        let src = &self.owner(src, name)?;
+        let label = format!("move {}", name);
+        self.record(label, &[(src, name), (dst, name)], || {
+            self.move_word(name, src, dst)
+        })
+    }
+
+    fn move_word(&self, name: &str, src: &str, dst: &str) -> Result<Option<T>, InternalError<E>> {
         let s = self.get_store(src)?;
         let d = self.get_store(dst)?;
 
         let t0 = s.begin()?;
 
-        let word: World;
-        match t0.get(name) {
-            Ok(w) => word = w,
+        let entry: Entry;
+        match t0.entry(name) {
+            Ok(e) => entry = e,
             Err(err) => {
                 t0.rollback().unwrap();
                 return Err(err);
             }
         }
+        let word = &entry.word;
 
         let t1 = d.begin()?;
 
-        if let Err(err) = t1.insert(&word) {
+        // insert fails if the word is in dst already, then put the rest of the entry
+        if let Err(err) = t1.insert(word).and_then(|_| t1.put(&entry)) {
             t0.rollback().unwrap();
             t1.rollback().unwrap();
             return Err(err);
@@ -171,6 +758,710 @@ impl<
 
         Ok(None)
     }
+
+    /// copy the word to dst store, the word in src store is kept
+    pub fn copy_to(&self, name: &str, src: &str, dst: &str) -> Result<Option<T>, InternalError<E>> {
+        let src = &self.owner(src, name)?;
+        let label = format!("copy {}", name);
+        self.record(label, &[(dst, name)], || self.copy_word(name, src, dst))
+    }
+
+    fn copy_word(&self, name: &str, src: &str, dst: &str) -> Result<Option<T>, InternalError<E>> {
+        let s = self.get_store(src)?;
+        let d = self.get_store(dst)?;
+
+        let entry = s.entry(name)?;
+        let t = d.begin()?;
+        match t.insert(&entry.word).and_then(|_| t.put(&entry)) {
+            Ok(v) => {
+                t.commit()?;
+                Ok(v)
+            }
+            Err(err) => {
+                t.rollback()?;
+                Err(err)
+            }
+        }
+    }
+
+    /// rename the word in the store, the schedule is kept. with links the
+    /// links to it in the messages of the stores are renamed too, all in one
+    /// action
+    pub fn rename(
+        &self,
+        name: &str,
+        new: &str,
+        key: &str,
+        links: bool,
+    ) -> Result<Option<T>, InternalError<E>> {
+        let key = &self.owner(key, name)?;
+        // a word linking to itself is rewritten under the new name
+        let linking: Vec<(String, String)> = match links {
+            true => self
+                .backlinks(name, key)?
+                .into_iter()
+                .map(|(k, w)| match k == *key && w == name {
+                    true => (k, new.to_owned()),
+                    false => (k, w),
+                })
+                .collect(),
+            false => Vec::new(),
+        };
+        let mut words = vec![(key.as_str(), name), (key.as_str(), new)];
+        for (k, w) in &linking {
+            if !words.contains(&(k.as_str(), w.as_str())) {
+                words.push((k, w));
+            }
+        }
+
+        let label = format!("rename {} to {}", name, new);
+        self.record(label, &words, || {
+            let t = self.get_store(key)?.begin()?;
+            let res = match t.version(new) {
+                Ok(_) => Err(InternalError::Conflict),
+                Err(InternalError::NotFound) => t.entry(name).and_then(|mut entry| {
+                    entry.word.name = new.to_owned();
+                    t.insert(&entry.word)?;
+                    t.put(&entry)?;
+                    let v = t.delete(name)?;
+                    for (_, w) in linking.iter().filter(|(k, _)| k == key) {
+                        self.relink(&t, w, key, name, new, key)?;
+                    }
+                    Ok(v)
+                }),
+                Err(err) => Err(err),
+            };
+            let v = match res {
+                Ok(v) => {
+                    t.commit()?;
+                    v
+                }
+                Err(err) => {
+                    t.rollback()?;
+                    return Err(err);
+                }
+            };
+            // the words of the other stores, each store in its own transaction
+            for (k, w) in linking.iter().filter(|(k, _)| k != key) {
+                let t = self.get_store(k)?.begin()?;
+                match self.relink(&t, w, k, name, new, key) {
+                    Ok(_) => t.commit()?,
+                    Err(err) => {
+                        t.rollback()?;
+                        return Err(err);
+                    }
+                }
+            }
+            Ok(v)
+        })
+    }
+
+    // point the links of word in store from to name of store key to new
+    fn relink(
+        &self,
+        t: &F,
+        word: &str,
+        from: &str,
+        name: &str,
+        new: &str,
+        key: &str,
+    ) -> Result<(), InternalError<E>> {
+        let mut w = t.get(word)?;
+        let message = links::rewrite(
+            &w.message,
+            |l| l.word == name && self.link_key(l, from).as_deref() == Some(key),
+            new,
+        );
+        if message != w.message {
+            w.message = message;
+            t.update(&w)?;
+        }
+        Ok(())
+    }
+
+    /// the key of the library a link names, a store is named by its key or
+    /// by the name of its file
+    pub fn library_key(&self, library: &str) -> Option<String> {
+        if self.contains(library) {
+            return Some(library.to_owned());
+        }
+        self.keys().into_iter().find(|k| {
+            std::path::Path::new(k)
+                .file_stem()
+                .is_some_and(|stem| stem == library)
+        })
+    }
+
+    // the key of the library the link in a word of store from points to
+    fn link_key(&self, link: &Link, from: &str) -> Option<String> {
+        match &link.library {
+            Some(library) => self.library_key(library),
+            None => Some(from.to_owned()),
+        }
+    }
+
+    /// the links in the message of the word
+    pub fn links(&self, name: &str, key: &str) -> Result<Vec<Link>, InternalError<E>> {
+        if let Some(s) = self.smart_of(key) {
+            return s.links(name);
+        }
+        self.get_store(key)?.links(name)
+    }
+
+    /// the key and the word the link in the word name of store key points to
+    pub fn follow(
+        &self,
+        link: &Link,
+        name: &str,
+        key: &str,
+    ) -> Result<(String, World), InternalError<E>> {
+        let from = self.owner(key, name)?;
+        let key = self
+            .link_key(link, &from)
+            .ok_or(InternalError::StoreNotFound)?;
+        let word = self.get(&link.word, &key)?;
+        Ok((key, word))
+    }
+
+    /// the words linking to the word with the keys of their stores, sorted
+    pub fn backlinks(
+        &self,
+        name: &str,
+        key: &str,
+    ) -> Result<Vec<(String, String)>, InternalError<E>> {
+        let key = &self.owner(key, name)?;
+        let mut res = Vec::new();
+        for (k, store) in &self.stores {
+            for (w, link) in store.backlinks(name)? {
+                if self.link_key(&link, k).as_ref() == Some(key) {
+                    res.push((k.clone(), w));
+                }
+            }
+        }
+        res.sort();
+        res.dedup();
+        Ok(res)
+    }
+
+    /// the links of the store to words or libraries which are not there, with
+    /// the words they are in
+    pub fn broken_links(&self, key: &str) -> Result<Vec<(String, Link)>, InternalError<E>> {
+        let links = match self.smart_of(key) {
+            Some(s) => s.all_links()?,
+            None => self.get_store(key)?.all_links()?,
+        };
+        let mut res = Vec::new();
+        for (w, link) in links {
+            match self.follow(&link, &w, key) {
+                Ok(_) => {}
+                Err(InternalError::NotFound) | Err(InternalError::StoreNotFound) => {
+                    res.push((w, link))
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(res)
+    }
+
+    /// move the words to dst store as one action, return the failed words
+    /// with the errors
+    pub fn move_all(
+        &self,
+        names: &[String],
+        src: &str,
+        dst: &str,
+    ) -> Vec<(String, InternalError<E>)> {
+        let srcs = self.owners(names, src);
+        let words: Vec<(&str, &str)> = names
+            .iter()
+            .zip(&srcs)
+            .flat_map(|(n, src)| [(src.as_str(), n.as_str()), (dst, n.as_str())])
+            .collect();
+        let label = format!("move {} words", names.len());
+        self.record(label, &words, || {
+            names
+                .iter()
+                .zip(&srcs)
+                .filter_map(|(n, src)| self.move_word(n, src, dst).err().map(|e| (n.clone(), e)))
+                .collect()
+        })
+    }
+
+    /// copy the words to dst store as one action, return the failed words
+    /// with the errors
+    pub fn copy_all(
+        &self,
+        names: &[String],
+        src: &str,
+        dst: &str,
+    ) -> Vec<(String, InternalError<E>)> {
+        let srcs = self.owners(names, src);
+        let words: Vec<(&str, &str)> = names.iter().map(|n| (dst, n.as_str())).collect();
+        let label = format!("copy {} words", names.len());
+        self.record(label, &words, || {
+            names
+                .iter()
+                .zip(&srcs)
+                .filter_map(|(n, src)| self.copy_word(n, src, dst).err().map(|e| (n.clone(), e)))
+                .collect()
+        })
+    }
+
+    /// the words dst store has already, moving or copying them there fails
+    pub fn conflicts(&self, names: &[String], dst: &str) -> Result<Vec<String>, InternalError<E>> {
+        let d = self.get_store(dst)?;
+        let mut res = Vec::new();
+        for name in names {
+            match d.get(name) {
+                Ok(_) => res.push(name.clone()),
+                Err(InternalError::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(res)
+    }
+
+    /// move or copy the words to dst store as one action, the words dst has
+    /// already are deleted first, into the trash if there is one. return the
+    /// failed words with the errors
+    pub fn replace_all(
+        &self,
+        names: &[String],
+        src: &str,
+        dst: &str,
+        copy: bool,
+    ) -> Vec<(String, InternalError<E>)> {
+        let srcs = self.owners(names, src);
+        let words: Vec<(&str, &str)> = names
+            .iter()
+            .zip(&srcs)
+            .flat_map(|(n, src)| [(src.as_str(), n.as_str()), (dst, n.as_str())])
+            .collect();
+        let label = match copy {
+            true => format!("copy {} words", names.len()),
+            false => format!("move {} words", names.len()),
+        };
+        self.record_trashed(label, &words, |discarded| {
+            names
+                .iter()
+                .zip(&srcs)
+                .filter_map(|(n, src)| {
+                    // a word is never replaced by itself
+                    let res = match self.get_store(dst).and_then(|d| d.get(n)) {
+                        Ok(_) if src != dst => self.delete_word(n, dst, discarded),
+                        Ok(_) | Err(InternalError::NotFound) => Ok(None),
+                        Err(err) => Err(err),
+                    };
+                    res.and_then(|_| match copy {
+                        true => self.copy_word(n, src, dst),
+                        false => self.move_word(n, src, dst),
+                    })
+                    .err()
+                    .map(|e| (n.clone(), e))
+                })
+                .collect()
+        })
+    }
+
+    /// delete the words as one action, return the failed words with the errors
+    pub fn delete_all(&self, names: &[String], key: &str) -> Vec<(String, InternalError<E>)> {
+        let keys = self.owners(names, key);
+        let words: Vec<(&str, &str)> = names
+            .iter()
+            .zip(&keys)
+            .map(|(n, key)| (key.as_str(), n.as_str()))
+            .collect();
+        let label = format!("delete {} words", names.len());
+        self.record_trashed(label, &words, |discarded| {
+            names
+                .iter()
+                .zip(&keys)
+                .filter_map(|(n, key)| {
+                    let res = self.delete_word(n, key, discarded);
+                    res.err().map(|e| (n.clone(), e))
+                })
+                .collect()
+        })
+    }
+
+    /// record the actions in the journal so they can be undone
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.journal = Some(Mutex::new(journal))
+    }
+
+    // the entry of the word, None if the word is not in the store
+    fn current(&self, key: &str, name: &str) -> Result<Option<Entry>, InternalError<E>> {
+        match self.get_store(key)?.entry(name) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(InternalError::NotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    // run the op and record the words it changed as an action, the words of
+    // a failed op are recorded too as some may be changed
+    fn record<R, O: FnOnce() -> R>(&self, label: String, words: &[(&str, &str)], op: O) -> R {
+        self.record_trashed(label, words, |_| op())
+    }
+
+    // record an op which lists the words it deletes into the trash
+    fn record_trashed<R, O: FnOnce(&mut Discarded) -> R>(
+        &self,
+        label: String,
+        words: &[(&str, &str)],
+        op: O,
+    ) -> R {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return op(&mut Vec::new()),
+        };
+        let before: Vec<Option<Entry>> = words
+            .iter()
+            .map(|(key, name)| self.current(key, name).unwrap_or(None))
+            .collect();
+        let mut discarded = Vec::new();
+        let res = op(&mut discarded);
+
+        let mut changes = Vec::new();
+        for ((key, name), before) in words.iter().zip(before) {
+            let after = self.current(key, name).unwrap_or(None);
+            if !same(&before, &after) {
+                changes.push(Change {
+                    key: key.to_string(),
+                    name: name.to_string(),
+                    trashed: discarded
+                        .iter()
+                        .find(|(k, n, _)| k == key && n == name)
+                        .map(|(_, _, id)| *id),
+                    before,
+                    after,
+                });
+            }
+        }
+        journal.lock().unwrap().push(Action { label, changes });
+        res
+    }
+
+    /// what the next undo and redo will do
+    pub fn undo_labels(&self) -> (Option<String>, Option<String>) {
+        match &self.journal {
+            Some(journal) => {
+                let j = journal.lock().unwrap();
+                (
+                    j.last().map(|a| a.label.clone()),
+                    j.next().map(|a| a.label.clone()),
+                )
+            }
+            None => (None, None),
+        }
+    }
+
+    /// undo the last action, return what is undone, None if nothing to undo.
+    /// Conflict if a word of it is changed since, Partial if it's undone in
+    /// some stores only
+    pub fn undo(&self) -> Result<Option<String>, InternalError<E>> {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return Ok(None),
+        };
+        let mut j = journal.lock().unwrap();
+        let mut action = match j.last() {
+            Some(action) => action.clone(),
+            None => return Ok(None),
+        };
+        self.replay(&mut action, true)?;
+        j.undone();
+        Ok(Some(action.label))
+    }
+
+    /// redo the last undone action, return what is redone
+    pub fn redo(&self) -> Result<Option<String>, InternalError<E>> {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return Ok(None),
+        };
+        let mut j = journal.lock().unwrap();
+        let mut action = match j.next() {
+            Some(action) => action.clone(),
+            None => return Ok(None),
+        };
+        self.replay(&mut action, false)?;
+        let label = action.label.clone();
+        j.redone(action);
+        Ok(Some(label))
+    }
+
+    // put the words of the action back as they were before it for undo, or as
+    // they were after it for redo, in one transaction of each store. every
+    // store is checked and written before any is committed
+    fn replay(&self, action: &mut Action, undo: bool) -> Result<(), InternalError<E>> {
+        let tt = match &self.trash {
+            Some(trash) if action.changes.iter().any(|c| c.trashed.is_some()) => {
+                Some(trash.begin()?)
+            }
+            _ => None,
+        };
+        let mut ts: Vec<(String, F)> = Vec::new();
+        if let Err(err) = self.replay_in(action, undo, &mut ts, tt.as_ref()) {
+            for (_, t) in ts {
+                t.rollback()?;
+            }
+            if let Some(tt) = tt {
+                tt.rollback()?;
+            }
+            return Err(err);
+        }
+
+        // the trash is committed first for redo and last for undo, a failed
+        // commit leaves the word in both rather than in none
+        let mut ts: Vec<F> = ts.into_iter().map(|(_, t)| t).collect();
+        match (tt, undo) {
+            (Some(tt), true) => ts.push(tt),
+            (Some(tt), false) => ts.insert(0, tt),
+            (None, _) => {}
+        }
+        let mut ts = ts.into_iter();
+        let mut committed = false;
+        while let Some(t) = ts.next() {
+            if let Err(err) = t.commit() {
+                for t in ts {
+                    let _ = t.rollback();
+                }
+                return Err(match committed {
+                    true => InternalError::Partial(Box::new(err)),
+                    false => err,
+                });
+            }
+            committed = true;
+        }
+        Ok(())
+    }
+
+    // the writes of replay in the transactions ts of the stores and tt of the
+    // trash, the ids of the words trashed again are kept in the action
+    fn replay_in(
+        &self,
+        action: &mut Action,
+        undo: bool,
+        ts: &mut Vec<(String, F)>,
+        tt: Option<&F>,
+    ) -> Result<(), InternalError<E>> {
+        for c in &action.changes {
+            if !ts.iter().any(|(key, _)| *key == c.key) {
+                ts.push((c.key.clone(), self.get_store(&c.key)?.begin()?));
+            }
+        }
+        let t = |key: &str| &ts.iter().find(|(k, _)| k == key).unwrap().1;
+
+        // nothing is written if a word is changed since
+        for c in &action.changes {
+            let current = match t(&c.key).entry(&c.name) {
+                Ok(entry) => Some(entry),
+                Err(InternalError::NotFound) => None,
+                Err(err) => return Err(err),
+            };
+            if !same(&current, if undo { &c.after } else { &c.before }) {
+                return Err(InternalError::Conflict);
+            }
+        }
+
+        let mut changes: Vec<&mut Change> = action.changes.iter_mut().collect();
+        if undo {
+            changes.reverse();
+        }
+        for c in changes {
+            let t = t(&c.key);
+            let state = if undo { &c.before } else { &c.after };
+            match (state.clone(), tt) {
+                // the version goes on from the current one
+                (Some(mut entry), _) => {
+                    if let Ok(v) = t.version(&c.name) {
+                        entry.version = v + 1;
+                    }
+                    t.put(&entry)?;
+                }
+                (None, Some(tt)) if c.trashed.is_some() => {
+                    c.trashed = Some(trash::discard_in(tt, t, &c.name, &c.key)?);
+                }
+                (None, _) => {
+                    t.delete(&c.name)?;
+                }
+            }
+            // the copy of the word left in the trash by the delete undone
+            if let (true, Some(tt), Some(id)) = (undo, tt, c.trashed) {
+                tt.remove_trashed(id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// the words only in store a, only in store b and different in both
+    pub fn diff(&self, a: &str, b: &str) -> Result<merge::Diff, InternalError<E>> {
+        merge::diff(&**self.get_store(a)?, &**self.get_store(b)?)
+    }
+
+    /// merge the words of src store into dst store in one transaction
+    pub fn merge<R: FnMut(&World, &World) -> merge::Resolution>(
+        &self,
+        dst: &str,
+        src: &str,
+        resolve: R,
+        dry_run: bool,
+    ) -> Result<merge::Report, InternalError<E>> {
+        merge::merge(
+            &**self.get_store(dst)?,
+            &**self.get_store(src)?,
+            resolve,
+            dry_run,
+        )
+    }
+
+    /// the earlier messages of the word in the store, newest first
+    pub fn history(&self, id: &str, key: &str) -> Result<Vec<Snapshot>, InternalError<E>> {
+        self.get_store(&self.owner(key, id)?)?.history(id)
+    }
+
+    /// the last snapshot of every deleted word of the store, newest first
+    pub fn deleted(&self, key: &str) -> Result<Vec<Snapshot>, InternalError<E>> {
+        self.get_store(key)?.deleted(0)
+    }
+
+    /// write the message of the snapshot back to the store
+    pub fn restore(&self, snapshot: &Snapshot, key: &str) -> Result<(), InternalError<E>> {
+        history::restore(&**self.get_store(key)?, snapshot)
+    }
+
+    /// save the query as a smart library, the one with the same name is replaced
+    pub fn add_search(&mut self, name: &str, query: Query) {
+        self.searches.insert(name.to_owned(), query);
+    }
+
+    pub fn remove_search(&mut self, name: &str) -> Option<Query> {
+        self.searches.remove(name)
+    }
+
+    /// the saved searches, sorted by name
+    pub fn searches(&self) -> Vec<(String, Query)> {
+        let mut res: Vec<(String, Query)> = self
+            .searches
+            .iter()
+            .map(|(name, q)| (name.clone(), q.clone()))
+            .collect();
+        res.sort_by(|a, b| a.0.cmp(&b.0));
+        res
+    }
+
+    /// the saved search read as a store
+    pub fn smart(&self, name: &str) -> Result<Smart<'_, T, E, F>, InternalError<E>> {
+        self.smart_of(name).ok_or(InternalError::StoreNotFound)
+    }
+
+    fn smart_of(&self, key: &str) -> Option<Smart<'_, T, E, F>> {
+        self.searches
+            .get(key)
+            .map(|query| Smart { lib: self, query })
+    }
+
+    // the key of the store the word is written to, the store a word of a
+    // smart library is read from
+    fn owner(&self, key: &str, name: &str) -> Result<String, InternalError<E>> {
+        match self.smart_of(key) {
+            Some(s) => s.owner(name),
+            None => Ok(key.to_owned()),
+        }
+    }
+
+    // the owners of the words, a word not found is left to fail in the store
+    fn owners(&self, names: &[String], key: &str) -> Vec<String> {
+        names
+            .iter()
+            .map(|n| self.owner(key, n).unwrap_or_else(|_| key.to_owned()))
+            .collect()
+    }
+
+    /// keep the words deleted from the stores in trash instead of dropping them
+    pub fn set_trash(&mut self, trash: BoxStore<T, E, F>) {
+        self.trash = Some(trash)
+    }
+
+    pub fn has_trash(&self) -> bool {
+        self.trash.is_some()
+    }
+
+    fn get_trash(&self) -> Result<&BoxStore<T, E, F>, InternalError<E>> {
+        self.trash.as_ref().ok_or(InternalError::StoreNotFound)
+    }
+
+    /// the words in the trash, the last deleted first
+    pub fn trashed(&self, size: i64) -> Result<Vec<Trashed>, InternalError<E>> {
+        self.get_trash()?.trashed(size)
+    }
+
+    /// put the trashed word back to the store it's deleted from, the origin
+    /// store must be opened and the word must not be added again
+    pub fn restore_trashed(&self, id: i64) -> Result<Trashed, InternalError<E>> {
+        let trash = self.get_trash()?;
+        let trashed = trash
+            .trashed(0)?
+            .into_iter()
+            .find(|t| t.id == id)
+            .ok_or(InternalError::NotFound)?;
+        let s = self.get_store(&trashed.origin)?;
+
+        let t0 = trash.begin()?;
+        let t1 = s.begin()?;
+        let entry = &trashed.entry;
+        let res: Result<Option<T>, InternalError<E>> = match t1.version(&entry.word.name) {
+            Ok(_) => Err(InternalError::Conflict),
+            Err(InternalError::NotFound) => Ok(None),
+            Err(err) => Err(err),
+        };
+        let res = res
+            .and_then(|_| t1.insert(&entry.word))
+            .and_then(|_| t1.put(entry))
+            .and_then(|_| t0.remove_trashed(id));
+        if let Err(err) = res {
+            t0.rollback()?;
+            t1.rollback()?;
+            return Err(err);
+        }
+
+        // the word is in both if the commit of the trash fails, never in none
+        if let Err(err) = t1.commit() {
+            t0.rollback()?;
+            return Err(err);
+        }
+        t0.commit()?;
+        Ok(trashed)
+    }
+
+    /// drop the word from the trash for good
+    pub fn purge_trashed(&self, id: i64) -> Result<Option<T>, InternalError<E>> {
+        self.get_trash()?.remove_trashed(id)
+    }
+
+    /// drop the words deleted before the time, return the number of them
+    pub fn purge_trash(&self, before: i64) -> Result<usize, InternalError<E>> {
+        let t = self.get_trash()?.begin()?;
+        let mut n = 0;
+        let res = t.trashed(0).and_then(|trashed| {
+            for item in trashed.iter().filter(|item| item.deleted_at < before) {
+                t.remove_trashed(item.id)?;
+                n += 1;
+            }
+            Ok(())
+        });
+        match res {
+            Ok(_) => {
+                t.commit()?;
+                Ok(n)
+            }
+            Err(err) => {
+                t.rollback()?;
+                Err(err)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -187,6 +1478,9 @@ mod tests {
 
         let mut res = WordLibrary {
             stores: HashMap::new(),
+            trash: None,
+            journal: None,
+            searches: HashMap::new(),
         };
 
         res.stores.insert(k0.to_owned(), Box::new(sql0));
@@ -194,6 +1488,70 @@ mod tests {
         return res;
     }
 
+    // a library of new stores in the files, with a journal and the trash if
+    // one is given
+    fn test_library(
+        keys: &[&str],
+        trash: Option<&str>,
+    ) -> WordLibrary<World, sqlite::Error, store::Tansaction> {
+        remove_files(keys);
+        let mut wd = WordLibrary::new();
+        for k in keys {
+            wd.add_store(k, Box::new(store::Sqlite::new(k.to_string()).unwrap()));
+        }
+        if let Some(trash) = trash {
+            remove_files(&[trash]);
+            wd.set_trash(Box::new(store::Sqlite::new(trash.to_owned()).unwrap()));
+        }
+        wd.set_journal(Journal::new(10));
+        wd
+    }
+
+    // remove the store files with their write-ahead logs
+    fn remove_files(files: &[&str]) {
+        for f in files {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", f, suffix));
+            }
+        }
+    }
+
+    #[test]
+    fn test_threads() {
+        let k0 = "target/test_threads.sql";
+        let _ = std::fs::remove_file(k0);
+        let mut wd = WordLibrary::new();
+        wd.add_store(k0, Box::new(store::Sqlite::new(k0.to_owned()).unwrap()));
+        let wd = std::sync::Arc::new(wd);
+
+        // a writer and some readers run at the same time
+        let mut handles = Vec::new();
+        let writer = wd.clone();
+        handles.push(std::thread::spawn(move || {
+            for i in 0..50 {
+                let word = World {
+                    name: format!("test-{}", i),
+                    message: String::from("test"),
+                };
+                writer.insert(&word, k0).unwrap();
+            }
+        }));
+        for _ in 0..3 {
+            let reader = wd.clone();
+            handles.push(std::thread::spawn(move || {
+                for _ in 0..50 {
+                    reader.list(0, k0).unwrap();
+                }
+            }));
+        }
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        assert_eq!(wd.list(0, k0).unwrap().len(), 50);
+        std::fs::remove_file(k0).unwrap();
+    }
+
     // #[test]
     fn clean_sqlite() {
         let k0 = "target/test.sql";
@@ -230,4 +1588,390 @@ mod tests {
 
         // clean_sqlite()
     }
+
+    #[test]
+    fn test_remove_store() {
+        let (k0, k1) = ("target/test_remove0.sql", "target/test_remove1.sql");
+        let mut wd = test_library(&[k0, k1], None);
+        let word = World {
+            name: String::from("test"),
+            message: String::from("test"),
+        };
+        wd.insert(&word, k1).unwrap();
+        assert!(wd.remove_store(k1).is_some());
+        assert!(!wd.contains(k1) && wd.keys() == vec![k0]);
+        assert!(wd.remove_store(k1).is_none());
+        assert!(matches!(
+            wd.get(&word.name, k1),
+            Err(InternalError::StoreNotFound)
+        ));
+
+        // the file is closed and can be opened again
+        wd.add_store(k1, Box::new(store::Sqlite::new(k1.to_owned()).unwrap()));
+        assert_eq!(wd.get(&word.name, k1).unwrap().message, "test");
+
+        drop(wd);
+        remove_files(&[k0, k1]);
+    }
+
+    #[test]
+    fn test_copy() {
+        let (k0, k1) = ("target/test_copy0.sql", "target/test_copy1.sql");
+        let wd = test_library(&[k0, k1], None);
+        let word = World {
+            name: String::from("test"),
+            message: String::from("test"),
+        };
+        wd.insert(&word, k0).unwrap();
+        wd.copy_to(&word.name, k0, k1).unwrap();
+        assert_eq!(wd.get(&word.name, k0).unwrap().message, word.message);
+        assert_eq!(wd.get(&word.name, k1).unwrap().message, word.message);
+        // the copy fails if the word is there
+        assert!(wd.copy_to(&word.name, k0, k1).is_err());
+        assert!(matches!(
+            wd.copy_to("none", k0, k1),
+            Err(InternalError::NotFound)
+        ));
+
+        drop(wd);
+        remove_files(&[k0, k1]);
+    }
+
+    #[test]
+    fn test_undo() {
+        let (k0, k1) = ("target/test_undo0.sql", "target/test_undo1.sql");
+        let wd = test_library(&[k0, k1], None);
+        let word = |name: &str, message: &str| World {
+            name: name.to_owned(),
+            message: message.to_owned(),
+        };
+        let names = |k: &str| -> Vec<String> {
+            let mut names: Vec<String> =
+                wd.list(0, k).unwrap().into_iter().map(|w| w.name).collect();
+            names.sort();
+            names
+        };
+
+        wd.insert(&word("apple", "a fruit"), k0).unwrap();
+        wd.insert(&word("pear", "a fruit"), k0).unwrap();
+        wd.update(&word("apple", "a red fruit"), k0).unwrap();
+        wd.rename("apple", "apples", k0, false).unwrap();
+        let errs = wd.move_all(&[String::from("apples"), String::from("pear")], k0, k1);
+        assert!(errs.is_empty());
+        assert_eq!((names(k0).len(), names(k1).len()), (0, 2));
+
+        // the bulk move is undone as a whole, back to the source store
+        assert_eq!(wd.undo().unwrap().as_deref(), Some("move 2 words"));
+        assert_eq!(
+            (names(k0), names(k1).len()),
+            (vec![String::from("apples"), String::from("pear")], 0)
+        );
+        wd.undo().unwrap();
+        assert_eq!(wd.get("apple", k0).unwrap().message, "a red fruit");
+        wd.undo().unwrap();
+        assert_eq!(wd.get("apple", k0).unwrap().message, "a fruit");
+
+        assert_eq!(wd.redo().unwrap().as_deref(), Some("edit apple"));
+        assert_eq!(wd.get("apple", k0).unwrap().message, "a red fruit");
+        assert_eq!(
+            wd.undo_labels().1.as_deref(),
+            Some("rename apple to apples")
+        );
+
+        // a new action drops the redo, a changed word can not be undone
+        wd.delete("pear", k0).unwrap();
+        assert_eq!(wd.undo_labels(), (Some(String::from("delete pear")), None));
+        wd.store(k0)
+            .unwrap()
+            .insert(&word("pear", "changed"))
+            .unwrap();
+        assert!(matches!(wd.undo(), Err(InternalError::Conflict)));
+
+        // the words in the target are replaced as one action
+        wd.insert(&word("plum", "in k0"), k0).unwrap();
+        wd.insert(&word("fig", "in k0"), k0).unwrap();
+        wd.insert(&word("plum", "in k1"), k1).unwrap();
+        let moving = vec![String::from("plum"), String::from("fig")];
+        assert_eq!(wd.conflicts(&moving, k1).unwrap(), vec!["plum"]);
+        let errs = wd.copy_all(&moving, k0, k1);
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].0, "plum");
+        assert!(wd.replace_all(&moving, k0, k1, false).is_empty());
+        assert_eq!(wd.get("plum", k1).unwrap().message, "in k0");
+        assert!(!names(k0).contains(&String::from("fig")));
+        assert_eq!(wd.undo().unwrap().as_deref(), Some("move 2 words"));
+        assert_eq!(wd.get("plum", k1).unwrap().message, "in k1");
+        assert_eq!(wd.get("plum", k0).unwrap().message, "in k0");
+
+        drop(wd);
+        remove_files(&[k0, k1]);
+    }
+
+    #[test]
+    fn test_undo_trash() {
+        let (k0, k1) = ("target/test_undo_trash0.sql", "target/test_undo_trash1.sql");
+        let trash = "target/test_undo_trash.sql";
+        let wd = test_library(&[k0, k1], Some(trash));
+        let word = |name: &str| World {
+            name: name.to_owned(),
+            message: String::from("a fruit"),
+        };
+        let trashed = || -> Vec<i64> { wd.trashed(0).unwrap().iter().map(|t| t.id).collect() };
+
+        // an earlier copy of the word in the trash is not the one undone
+        wd.insert(&word("apple"), k0).unwrap();
+        wd.delete("apple", k0).unwrap();
+        let kept = trashed();
+        assert_eq!(kept.len(), 1);
+
+        // moves and renames put nothing in the trash, and take nothing out
+        wd.insert(&word("apple"), k0).unwrap();
+        wd.move_to("apple", k0, k1).unwrap();
+        wd.rename("apple", "apples", k1, false).unwrap();
+        wd.undo().unwrap();
+        wd.undo().unwrap();
+        assert_eq!(wd.get("apple", k0).unwrap().message, "a fruit");
+        assert_eq!(trashed(), kept);
+        wd.redo().unwrap();
+        wd.redo().unwrap();
+        assert_eq!(wd.get("apples", k1).unwrap().message, "a fruit");
+        assert_eq!(trashed(), kept);
+
+        // the undone delete takes out the copy it put in the trash
+        wd.delete("apples", k1).unwrap();
+        assert_eq!(trashed().len(), 2);
+        wd.undo().unwrap();
+        assert_eq!(trashed(), kept);
+        wd.redo().unwrap();
+        assert_eq!(trashed().len(), 2);
+        wd.undo().unwrap();
+        assert_eq!(trashed(), kept);
+        assert_eq!(wd.get("apples", k1).unwrap().message, "a fruit");
+
+        drop(wd);
+        remove_files(&[k0, k1, trash]);
+    }
+
+    #[test]
+    fn test_tags() {
+        let (k0, k1) = ("target/test_tags0.sql", "target/test_tags1.sql");
+        let wd = test_library(&[k0, k1], None);
+        let tags = |ts: &[&str]| -> Vec<String> { ts.iter().map(|t| t.to_string()).collect() };
+        for name in ["run", "eat", "apple"] {
+            let word = World {
+                name: name.to_owned(),
+                message: String::from("test"),
+            };
+            wd.insert(&word, k0).unwrap();
+        }
+        wd.add_tags("run", &tags(&["verb", "mastered"]), k0)
+            .unwrap();
+        wd.add_tags("eat", &tags(&["verb"]), k0).unwrap();
+        wd.add_tags("apple", &tags(&["noun"]), k0).unwrap();
+        assert!(matches!(
+            wd.add_tags("apple", &tags(&["two words"]), k0),
+            Err(InternalError::Unknow(_))
+        ));
+        assert!(matches!(
+            wd.add_tags("pear", &tags(&["noun"]), k0),
+            Err(InternalError::NotFound)
+        ));
+
+        let expr: tags::Expr = "verb AND NOT mastered".parse().unwrap();
+        let names =
+            |words: Vec<World>| -> Vec<String> { words.into_iter().map(|w| w.name).collect() };
+        assert_eq!(names(wd.filter(&expr, k0).unwrap()), tags(&["eat"]));
+        assert_eq!(
+            wd.tags(k0).unwrap(),
+            vec![
+                (String::from("mastered"), 1),
+                (String::from("noun"), 1),
+                (String::from("verb"), 2)
+            ]
+        );
+
+        // the tags go with the word to another store
+        wd.move_to("run", k0, k1).unwrap();
+        assert_eq!(
+            wd.store(k1).unwrap().word_tags("run").unwrap(),
+            tags(&["mastered", "verb"])
+        );
+        let verbs: Vec<String> = wd
+            .filter_all(&"verb".parse().unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|(_, w)| w.name)
+            .collect();
+        assert_eq!(verbs, tags(&["eat", "run"]));
+        assert_eq!(wd.tags_all().unwrap().len(), 3);
+
+        wd.remove_tags("eat", &tags(&["verb"]), k0).unwrap();
+        assert!(wd.filter(&expr, k0).unwrap().is_empty());
+        assert_eq!(wd.undo().unwrap().as_deref(), Some("untag eat"));
+        assert_eq!(names(wd.filter(&expr, k0).unwrap()), tags(&["eat"]));
+
+        drop(wd);
+        remove_files(&[k0, k1]);
+    }
+
+    #[test]
+    fn test_smart() {
+        let (k0, k1) = ("target/test_smart0.sql", "target/test_smart1.sql");
+        let mut wd = test_library(&[k0, k1], None);
+        let word = |name: &str, message: &str| World {
+            name: name.to_owned(),
+            message: message.to_owned(),
+        };
+        wd.insert(&word("school", "a place"), k0).unwrap();
+        wd.insert(&word("scholar", "a person"), k1).unwrap();
+        wd.insert(&word("apple", "a fruit"), k1).unwrap();
+        wd.add_tags("scholar", &[String::from("noun")], k1).unwrap();
+
+        wd.add_search("sch", Query::from_args(&["--name", "sch"]).unwrap());
+        assert!(wd.contains("sch") && !wd.keys().contains(&String::from("sch")));
+        // best match first, across the stores
+        let names: Vec<String> = wd
+            .list(0, "sch")
+            .unwrap()
+            .into_iter()
+            .map(|w| w.name)
+            .collect();
+        assert_eq!(names, vec![String::from("school"), String::from("scholar")]);
+        assert_eq!(wd.smart("sch").unwrap().owner("scholar").unwrap(), k1);
+        assert!(matches!(
+            wd.get("apple", "sch"),
+            Err(InternalError::NotFound)
+        ));
+
+        // the edits are written to the store the word is read from
+        wd.update(&word("scholar", "a learned person"), "sch")
+            .unwrap();
+        assert_eq!(wd.get("scholar", k1).unwrap().message, "a learned person");
+        assert_eq!(wd.version("scholar", "sch").unwrap(), 2);
+        let nouns = wd.filter(&"noun".parse().unwrap(), "sch").unwrap();
+        assert_eq!(nouns[0].name, "scholar");
+
+        wd.add_search(
+            "due",
+            Query::from_args(&["--due", "0", "--lib", k1]).unwrap(),
+        );
+        assert_eq!(wd.list(0, "due").unwrap().len(), 2);
+        wd.review("apple", "due", 5, review::now()).unwrap();
+        assert_eq!(wd.list(0, "due").unwrap().len(), 1);
+
+        // a library of the query which is not opened is skipped
+        wd.add_search(
+            "closed",
+            Query::from_args(&["--lib", k1, "target/test_smart_closed.sql"]).unwrap(),
+        );
+        assert_eq!(wd.list(0, "closed").unwrap().len(), 2);
+        assert_eq!(wd.smart("closed").unwrap().owner("apple").unwrap(), k1);
+
+        wd.delete("school", "sch").unwrap();
+        assert!(matches!(wd.get("school", k0), Err(InternalError::NotFound)));
+        assert!(wd.remove_search("sch").is_some());
+        assert!(!wd.contains("sch"));
+
+        drop(wd);
+        remove_files(&[k0, k1]);
+    }
+
+    #[test]
+    fn test_links() {
+        let (k0, k1) = ("target/test_links0.sql", "target/test_links1.sql");
+        let wd = test_library(&[k0, k1], None);
+        let word = |name: &str, message: &str| World {
+            name: name.to_owned(),
+            message: message.to_owned(),
+        };
+        wd.insert(&word("school", "a place, see [[scholar]]"), k0)
+            .unwrap();
+        wd.insert(&word("scholar", "a person of a [[school]]"), k0)
+            .unwrap();
+        wd.insert(&word("student", "[[test_links0:school]] [[nothing]]"), k1)
+            .unwrap();
+
+        let link = &wd.links("school", k0).unwrap()[0];
+        assert_eq!(link.to_string(), "[[scholar]]");
+        assert_eq!(wd.follow(link, "school", k0).unwrap().0, k0);
+        let link = &wd.links("student", k1).unwrap()[0];
+        assert_eq!(wd.follow(link, "student", k1).unwrap().1.name, "school");
+        assert_eq!(
+            wd.backlinks("school", k0).unwrap(),
+            vec![
+                (k0.to_owned(), String::from("scholar")),
+                (k1.to_owned(), String::from("student"))
+            ]
+        );
+        // the links follow the messages
+        wd.update(&word("scholar", "a person"), k0).unwrap();
+        assert_eq!(wd.backlinks("school", k0).unwrap().len(), 1);
+        let broken = wd.broken_links(k1).unwrap();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].1.word, "nothing");
+
+        // the links to the renamed word are renamed in one action
+        wd.rename("school", "schools", k0, true).unwrap();
+        assert_eq!(
+            wd.get("student", k1).unwrap().message,
+            "[[test_links0:schools]] [[nothing]]"
+        );
+        assert_eq!(wd.backlinks("schools", k0).unwrap().len(), 1);
+        wd.undo().unwrap();
+        assert_eq!(
+            wd.get("student", k1).unwrap().message,
+            "[[test_links0:school]] [[nothing]]"
+        );
+        assert_eq!(wd.backlinks("school", k0).unwrap().len(), 1);
+        assert!(wd.links("schools", k0).unwrap().is_empty());
+
+        drop(wd);
+        remove_files(&[k0, k1]);
+    }
+
+    #[test]
+    fn test_attachments() {
+        let (k0, k1) = ("target/test_attach0.sql", "target/test_attach1.sql");
+        let trash = "target/test_attach_trash.sql";
+        let wd = test_library(&[k0, k1], Some(trash));
+        let word = World {
+            name: String::from("apple"),
+            message: String::from("a fruit"),
+        };
+        let sound = Attachment::new("apple.mp3", vec![73, 68, 51]);
+        wd.insert(&word, k0).unwrap();
+        wd.attach("apple", &sound, k0).unwrap();
+        wd.attach("apple", &Attachment::new("apple.png", vec![1]), k0)
+            .unwrap();
+        assert!(matches!(
+            wd.attach("apple", &Attachment::new("a/b", vec![]), k0),
+            Err(InternalError::Unknow(_))
+        ));
+        assert!(matches!(
+            wd.attach("pear", &sound, k0),
+            Err(InternalError::NotFound)
+        ));
+
+        // the files go with the word
+        wd.copy_to("apple", k0, k1).unwrap();
+        assert_eq!(wd.attachments("apple", k1).unwrap().len(), 2);
+        wd.delete("apple", k1).unwrap();
+        assert_eq!(wd.trashed(0).unwrap()[0].entry.attachments.len(), 2);
+        wd.restore_trashed(wd.trashed(0).unwrap()[0].id).unwrap();
+        assert_eq!(wd.attachment("apple", "apple.mp3", k1).unwrap(), sound);
+
+        wd.detach("apple", "apple.mp3", k0).unwrap();
+        assert!(matches!(
+            wd.detach("apple", "apple.mp3", k0),
+            Err(InternalError::NotFound)
+        ));
+        assert_eq!(
+            wd.undo().unwrap().as_deref(),
+            Some("detach apple.mp3 from apple")
+        );
+        assert_eq!(wd.attachment("apple", "apple.mp3", k0).unwrap(), sound);
+
+        drop(wd);
+        remove_files(&[k0, k1, trash]);
+    }
 }
@@ -0,0 +1,450 @@
+// words rendered as html for the preview
+//
+// the message is read as markdown: headings, lists, quotes, fenced code,
+// emphasis, code spans and links. a line break is kept as it is, the
+// meanings of a word are often written one a line. a [[link]] is rendered as
+// a link to the word, the attachments are played or shown in the page from
+// data urls, and the hits of a search are marked
+use super::attachment::{self, Attachment};
+use super::links;
+use super::World;
+
+const STYLE: &str = "body{font-family:sans-serif;line-height:1.5;margin:12px 16px}\
+h1{font-size:1.6em;margin:0 0 .5em}\
+a.link{color:#0645ad;text-decoration:none;border-bottom:1px dotted}\
+mark{background:#ffe066}\
+pre{background:#f4f4f4;padding:8px;overflow:auto}\
+code{background:#f4f4f4;padding:0 2px}\
+blockquote{margin:0;padding-left:12px;border-left:3px solid #ccc;color:#555}\
+.files{border-top:1px solid #ddd;margin-top:1em;padding-top:.5em}\
+figure{margin:.5em 0}img{max-width:100%}";
+
+// the clicks on the links call the functions bound by the viewer
+const SCRIPT: &str = "document.addEventListener('click',function(e){\
+var a=e.target.closest('a');if(!a)return;e.preventDefault();\
+if(a.dataset.link)follow(a.dataset.link);\
+else if(a.dataset.file)open_file(a.dataset.file);\
+else browse(a.getAttribute('href'));});\
+var m=document.querySelector('mark');if(m)m.scrollIntoView({block:'center'});";
+
+/// the page of the word, the script calls the functions `follow(link)`,
+/// `open_file(name)` and `browse(url)` of the viewer
+pub fn page(word: &World, attachments: &[Attachment], hits: &[String]) -> String {
+    let hits = terms(hits);
+    let mut body = String::from("<h1>");
+    mark(&word.name, &hits, &mut body);
+    body.push_str("</h1>\n");
+    blocks(&word.message.lines().collect::<Vec<_>>(), &hits, &mut body);
+    if !attachments.is_empty() {
+        body.push_str("<div class=\"files\">\n");
+        for a in attachments {
+            let name = escape(&a.name);
+            let url = format!("data:{};base64,{}", a.mime, attachment::encode(&a.data));
+            if a.is_audio() {
+                body.push_str(&format!(
+                    "<figure><figcaption>{}</figcaption><audio controls src=\"{}\"></audio></figure>\n",
+                    name, url
+                ));
+            } else if a.is_image() {
+                body.push_str(&format!(
+                    "<figure><img src=\"{}\" alt=\"{}\"><figcaption>{}</figcaption></figure>\n",
+                    url, name, name
+                ));
+            } else {
+                body.push_str(&format!(
+                    "<figure><a href=\"#\" data-file=\"{}\">{}</a></figure>\n",
+                    name, name
+                ));
+            }
+        }
+        body.push_str("</div>\n");
+    }
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head>\n<body>\n{}<script>{}</script>\n</body></html>\n",
+        escape(&word.name),
+        STYLE,
+        body,
+        SCRIPT
+    )
+}
+
+/// the markdown as html, the words of the hits are marked case insensitive
+pub fn to_html(text: &str, hits: &[String]) -> String {
+    let mut res = String::new();
+    blocks(&text.lines().collect::<Vec<_>>(), &terms(hits), &mut res);
+    res
+}
+
+/// the page as a data url to be navigated to, `#` and `%` can't be in it as
+/// they are
+pub fn data_url(html: &str) -> String {
+    let mut res = String::from("data:text/html;charset=utf-8,");
+    for b in html.bytes() {
+        match b {
+            b'#' | b'%' | b'\r' | b'\n' | b'\t' | b'"' | b' ' | 0x80..=0xff => {
+                res.push_str(&format!("%{:02X}", b))
+            }
+            _ => res.push(b as char),
+        }
+    }
+    res
+}
+
+/// the url can be opened by the system, a page on the web or a mail
+pub fn browsable(url: &str) -> bool {
+    ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|p| url.len() > p.len() && url[..p.len()].eq_ignore_ascii_case(p))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// the words searched, in lowercase
+fn terms(hits: &[String]) -> Vec<Vec<char>> {
+    hits.iter()
+        .flat_map(|h| h.split_whitespace())
+        .map(|t| t.to_lowercase().chars().collect())
+        .collect()
+}
+
+fn blocks(lines: &[&str], hits: &[Vec<char>], out: &mut String) {
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+        if line.is_empty() {
+            i += 1;
+        } else if line.starts_with("```") {
+            let end = lines[i + 1..]
+                .iter()
+                .position(|l| l.trim_start().starts_with("```"))
+                .map(|n| i + 1 + n)
+                .unwrap_or(lines.len());
+            out.push_str("<pre><code>");
+            for l in &lines[i + 1..end] {
+                out.push_str(&escape(l));
+                out.push('\n');
+            }
+            out.push_str("</code></pre>\n");
+            i = end + 1;
+        } else if let Some((level, title)) = heading(line) {
+            out.push_str(&format!("<h{}>", level + 1));
+            inline(title, hits, out);
+            out.push_str(&format!("</h{}>\n", level + 1));
+            i += 1;
+        } else if rule(line) {
+            out.push_str("<hr>\n");
+            i += 1;
+        } else if line.starts_with('>') {
+            let end = lines[i..]
+                .iter()
+                .position(|l| !l.trim_start().starts_with('>'))
+                .map(|n| i + n)
+                .unwrap_or(lines.len());
+            let quoted: Vec<&str> = lines[i..end]
+                .iter()
+                .map(|l| {
+                    let l = &l.trim_start()[1..];
+                    l.strip_prefix(' ').unwrap_or(l)
+                })
+                .collect();
+            out.push_str("<blockquote>\n");
+            blocks(&quoted, hits, out);
+            out.push_str("</blockquote>\n");
+            i = end;
+        } else if let Some((ordered, _)) = item(line) {
+            let tag = if ordered { "ol" } else { "ul" };
+            out.push_str(&format!("<{}>\n", tag));
+            while let Some((o, text)) = lines.get(i).and_then(|l| item(l.trim_start())) {
+                if o != ordered {
+                    break;
+                }
+                // the indented lines go on with the item
+                let mut texts = vec![text];
+                i += 1;
+                while let Some(l) = lines.get(i) {
+                    if !l.starts_with([' ', '\t']) || starts_block(l) {
+                        break;
+                    }
+                    texts.push(l.trim());
+                    i += 1;
+                }
+                out.push_str("<li>");
+                inline_lines(&texts, hits, out);
+                out.push_str("</li>\n");
+            }
+            out.push_str(&format!("</{}>\n", tag));
+        } else {
+            let start = i;
+            i += 1;
+            while i < lines.len() && !starts_block(lines[i]) {
+                i += 1;
+            }
+            let texts: Vec<&str> = lines[start..i].iter().map(|l| l.trim()).collect();
+            out.push_str("<p>");
+            inline_lines(&texts, hits, out);
+            out.push_str("</p>\n");
+        }
+    }
+}
+
+fn starts_block(line: &str) -> bool {
+    let line = line.trim_start();
+    line.is_empty()
+        || line.starts_with("```")
+        || line.starts_with('>')
+        || heading(line).is_some()
+        || rule(line)
+        || item(line).is_some()
+}
+
+// the level from 0 and the title of `## title`
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    match (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')) {
+        true => Some((level - 1, rest.trim().trim_end_matches('#').trim_end())),
+        false => None,
+    }
+}
+
+// `---`, `***` or `___`
+fn rule(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3 && ['-', '*', '_'].iter().any(|r| chars.iter().all(|c| c == r))
+}
+
+// an item of a list, and if the list is ordered
+fn item(line: &str) -> Option<(bool, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(bullet) {
+            return Some((false, text.trim()));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = &line[digits..];
+    match (1..=9).contains(&digits) && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        true => Some((true, rest[2..].trim())),
+        false => None,
+    }
+}
+
+fn inline_lines(texts: &[&str], hits: &[Vec<char>], out: &mut String) {
+    for (i, text) in texts.iter().enumerate() {
+        if i > 0 {
+            out.push_str("<br>\n");
+        }
+        inline(text, hits, out);
+    }
+}
+
+fn inline(s: &str, hits: &[Vec<char>], out: &mut String) {
+    let spans = links::find(s);
+    // the text not written yet
+    let mut text = String::new();
+    let mut i = 0;
+    'next: while i < s.len() {
+        let rest = &s[i..];
+        if let Some((_, end, link)) = spans.iter().find(|(start, _, _)| *start == i) {
+            mark(&text, hits, out);
+            text.clear();
+            out.push_str(&format!(
+                "<a class=\"link\" href=\"#\" data-link=\"{}\">",
+                escape(&link.to_string())
+            ));
+            mark(s[i + 2..end - 2].trim(), hits, out);
+            out.push_str("</a>");
+            i = *end;
+            continue;
+        }
+        let mut chars = rest.chars();
+        let c = chars.next().unwrap();
+        match c {
+            '\\' => {
+                if let Some(p) = chars.next().filter(|p| p.is_ascii_punctuation()) {
+                    text.push(p);
+                    i += 2;
+                    continue;
+                }
+            }
+            '`' => {
+                if let Some(n) = rest[1..].find('`') {
+                    mark(&text, hits, out);
+                    text.clear();
+                    out.push_str("<code>");
+                    out.push_str(&escape(&rest[1..1 + n]));
+                    out.push_str("</code>");
+                    i += n + 2;
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some((label, url, len)) = md_link(rest) {
+                    mark(&text, hits, out);
+                    text.clear();
+                    out.push_str(&format!("<a href=\"{}\">", escape(url)));
+                    inline(label, hits, out);
+                    out.push_str("</a>");
+                    i += len;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        let prev = s[..i].chars().next_back();
+        for (delim, tag) in [
+            ("**", "strong"),
+            ("__", "strong"),
+            ("~~", "del"),
+            ("*", "em"),
+            ("_", "em"),
+        ] {
+            if let Some(n) = emphasis(rest, prev, delim) {
+                mark(&text, hits, out);
+                text.clear();
+                out.push_str(&format!("<{}>", tag));
+                inline(&rest[delim.len()..delim.len() + n], hits, out);
+                out.push_str(&format!("</{}>", tag));
+                i += n + 2 * delim.len();
+                continue 'next;
+            }
+        }
+        text.push(c);
+        i += c.len_utf8();
+    }
+    mark(&text, hits, out);
+}
+
+// the length of the text emphasized by the delimiter at the start, the text
+// doesn't start or end with a space, `_` is not in a word as `snake_case`
+fn emphasis(rest: &str, prev: Option<char>, delim: &str) -> Option<usize> {
+    let inner = rest.strip_prefix(delim)?;
+    if inner.starts_with(char::is_whitespace) || inner.starts_with(delim) {
+        return None;
+    }
+    let underscore = delim.starts_with('_');
+    if underscore && prev.is_some_and(|p| p.is_alphanumeric()) {
+        return None;
+    }
+    let mut from = 0;
+    while let Some(n) = inner[from..].find(delim).map(|n| from + n) {
+        let before = inner[..n].chars().next_back();
+        let after = inner[n + delim.len()..].chars().next();
+        if n > 0
+            && !before.is_some_and(char::is_whitespace)
+            && !(underscore && after.is_some_and(|a| a.is_alphanumeric()))
+        {
+            return Some(n);
+        }
+        from = n + delim.len();
+    }
+    None
+}
+
+// the label, the url and the length of `[label](url)` at the start
+fn md_link(rest: &str) -> Option<(&str, &str, usize)> {
+    let close = rest.find("](")?;
+    let label = &rest[1..close];
+    if label.is_empty() || label.contains(['[', ']']) {
+        return None;
+    }
+    let end = close + 2 + rest[close + 2..].find(')')?;
+    let url = rest[close + 2..end].trim();
+    match url.is_empty() || url.contains(char::is_whitespace) {
+        true => None,
+        false => Some((label, url, end + 1)),
+    }
+}
+
+// the text escaped, with the hits marked
+fn mark(text: &str, hits: &[Vec<char>], out: &mut String) {
+    let mut last = 0;
+    let mut i = 0;
+    while i < text.len() {
+        match hits.iter().filter_map(|h| hit_len(&text[i..], h)).max() {
+            Some(n) => {
+                out.push_str(&escape(&text[last..i]));
+                out.push_str("<mark>");
+                out.push_str(&escape(&text[i..i + n]));
+                out.push_str("</mark>");
+                i += n;
+                last = i;
+            }
+            None => i += text[i..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    out.push_str(&escape(&text[last..]));
+}
+
+// the length in bytes of the hit at the start of the text
+fn hit_len(text: &str, hit: &[char]) -> Option<usize> {
+    let mut n = 0;
+    for (i, c) in text.char_indices() {
+        if n == hit.len() {
+            return Some(i);
+        }
+        for l in c.to_lowercase() {
+            if hit.get(n) != Some(&l) {
+                return None;
+            }
+            n += 1;
+        }
+    }
+    (n == hit.len() && n > 0).then_some(text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_html() {
+        let text = "# School\n\n1. a place\n   for learning\n2. **fish** in *groups*\n\n\
+                    > see [[scholar]] and [[english:pupil]]\n\n\
+                    noun `school_bus` my_school_bus\n[web](https://example.com) <b>\n\n\
+                    ```\n# not a title\n```\n---\n- a\n- b";
+        let html = to_html(text, &[]);
+        assert_eq!(
+            html,
+            "<h1>School</h1>\n\
+             <ol>\n<li>a place<br>\nfor learning</li>\n\
+             <li><strong>fish</strong> in <em>groups</em></li>\n</ol>\n\
+             <blockquote>\n<p>see <a class=\"link\" href=\"#\" data-link=\"[[scholar]]\">scholar</a> \
+             and <a class=\"link\" href=\"#\" data-link=\"[[english:pupil]]\">english:pupil</a></p>\n</blockquote>\n\
+             <p>noun <code>school_bus</code> my_school_bus<br>\n\
+             <a href=\"https://example.com\">web</a> &lt;b&gt;</p>\n\
+             <pre><code># not a title\n</code></pre>\n<hr>\n\
+             <ul>\n<li>a</li>\n<li>b</li>\n</ul>\n"
+        );
+
+        let html = to_html("A SCHOOL of fish, schools", &[String::from("school  Fish")]);
+        assert_eq!(
+            html,
+            "<p>A <mark>SCHOOL</mark> of <mark>fish</mark>, <mark>school</mark>s</p>\n"
+        );
+        assert_eq!(to_html("a * b * c **", &[]), "<p>a * b * c **</p>\n");
+    }
+
+    #[test]
+    fn test_page() {
+        let word = World {
+            name: String::from("school"),
+            message: String::from("a [[place]] #1"),
+        };
+        let files = [
+            Attachment::new("school.mp3", vec![1, 2, 3]),
+            Attachment::new("school.png", vec![4]),
+        ];
+        let html = page(&word, &files, &[]);
+        assert!(html.contains("<title>school</title>"));
+        assert!(html.contains("<audio controls src=\"data:audio/mpeg;base64,AQID\">"));
+        assert!(html.contains("<img src=\"data:image/png;base64,BA==\""));
+        let url = data_url(&html);
+        assert!(!url[5..].contains(['#', ' ', '\n']) && url.contains("%231"));
+        assert!(browsable("https://example.com") && !browsable("javascript:alert(1)"));
+    }
+}
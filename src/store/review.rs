@@ -0,0 +1,83 @@
+/// seconds of a day, intervals of schedule are counted in days
+pub const DAY: i64 = 24 * 60 * 60;
+
+/// review schedule of a word, based on the SM-2 algorithm
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    /// unix time in seconds the next review is due, 0 for a never reviewed word
+    pub due: i64,
+    /// days between the last review and the due time
+    pub interval: i64,
+    pub ease: f64,
+    /// count of successful reviews in a row
+    pub reps: i64,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule {
+            due: 0,
+            interval: 0,
+            ease: 2.5,
+            reps: 0,
+        }
+    }
+}
+
+impl Schedule {
+    /// the schedule after a review graded 0 (forgot) to 5 (perfect) at the time now
+    pub fn next(&self, grade: u8, now: i64) -> Schedule {
+        let grade = grade.min(5) as f64;
+        let ease = self.ease + 0.1 - (5.0 - grade) * (0.08 + (5.0 - grade) * 0.02);
+        let ease = ease.max(1.3);
+
+        let (reps, interval) = if grade < 3.0 {
+            (0, 1)
+        } else {
+            let reps = self.reps + 1;
+            let interval = match reps {
+                1 => 1,
+                2 => 6,
+                _ => (self.interval as f64 * self.ease).round() as i64,
+            };
+            (reps, interval)
+        };
+
+        Schedule {
+            due: now + interval * DAY,
+            interval,
+            ease,
+            reps,
+        }
+    }
+}
+
+/// current unix time in seconds
+pub fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next() {
+        let s = Schedule::default();
+        let s = s.next(4, 0);
+        assert_eq!((s.reps, s.interval, s.due), (1, 1, DAY));
+        let s = s.next(4, DAY);
+        assert_eq!((s.reps, s.interval), (2, 6));
+        let s = s.next(5, 7 * DAY);
+        assert_eq!(s.reps, 3);
+        assert_eq!(s.interval, 15);
+
+        // forgot, start again
+        let s = s.next(1, 22 * DAY);
+        assert_eq!((s.reps, s.interval, s.due), (0, 1, 23 * DAY));
+        assert!(s.ease >= 1.3);
+    }
+}
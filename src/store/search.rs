@@ -0,0 +1,110 @@
+use super::World;
+
+/// edit distance between two words, counted by chars
+pub fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur: Vec<usize> = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        cur[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+fn is_subsequence(pattern: &str, name: &str) -> bool {
+    let mut it = name.chars();
+    pattern.chars().all(|c| it.any(|n| n == c))
+}
+
+/// score the word name for the pattern, the lower the better.
+/// None means the name is not related to the pattern.
+///
+/// * exact match: 0
+/// * prefix match, e.g. "school" for "schooler"
+/// * substring match
+/// * subsequence match, e.g. "shl" for "school"
+/// * edit distance less than a third of the pattern, e.g. "schools" for "school"
+pub fn score(pattern: &str, name: &str) -> Option<usize> {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+
+    let extra = name.chars().count().saturating_sub(pattern.chars().count());
+    if name == pattern {
+        Some(0)
+    } else if name.starts_with(&pattern) {
+        Some(100 + extra)
+    } else if name.contains(&pattern) {
+        Some(200 + extra)
+    } else if is_subsequence(&pattern, &name) {
+        Some(300 + extra)
+    } else {
+        let d = distance(&pattern, &name);
+        if d <= (pattern.chars().count() / 3).max(1) {
+            Some(400 + d)
+        } else {
+            None
+        }
+    }
+}
+
+/// filter the words related to pattern, best match first
+pub fn fuzzy(words: Vec<World>, pattern: &str, limit: usize) -> Vec<World> {
+    let mut res: Vec<(usize, World)> = words
+        .into_iter()
+        .filter_map(|w| score(pattern, &w.name).map(|s| (s, w)))
+        .collect();
+
+    res.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name)));
+    res.truncate(limit);
+    res.into_iter().map(|(_, w)| w).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(name: &str) -> World {
+        World {
+            name: name.to_owned(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("school", "school"), 0);
+        assert_eq!(distance("school", "schools"), 1);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let words = vec![
+            word("scholar"),
+            word("schooler"),
+            word("school"),
+            word("apple"),
+        ];
+
+        let res: Vec<String> = fuzzy(words, "school", 10)
+            .into_iter()
+            .map(|w| w.name)
+            .collect();
+        assert_eq!(res, vec!["school", "schooler"]);
+
+        assert!(score("schools", "school").is_some());
+        assert!(score("shl", "school").is_some());
+        assert!(score("xyz", "school").is_none());
+    }
+}
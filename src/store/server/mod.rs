@@ -0,0 +1,952 @@
+use crate::db::sqlite::store as dbstore;
+use crate::store;
+use crate::store::attachment;
+use crate::store::review;
+use crate::store::smart;
+use crate::store::sync;
+use crate::store::tags::Expr;
+use crate::store::trash;
+use serde_json::{json, Value};
+use std::path::Path;
+
+type Library = store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>;
+
+/// the server only listen on localhost by default
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8737";
+// threads handling the requests concurrently
+const WORKERS: usize = 4;
+
+/// http reply before it is written to the client
+pub struct Reply {
+    pub status: u16,
+    pub body: Value,
+    /// version of the word for the ETag header
+    pub etag: Option<i64>,
+}
+
+impl Reply {
+    fn ok(body: Value) -> Reply {
+        Reply {
+            status: 200,
+            body,
+            etag: None,
+        }
+    }
+
+    fn error(status: u16, msg: &str) -> Reply {
+        Reply {
+            status,
+            body: json!({ "error": msg }),
+            etag: None,
+        }
+    }
+
+    fn from_err(err: store::InternalError<sqlite::Error>) -> Reply {
+        match &err {
+            store::InternalError::NotFound | store::InternalError::StoreNotFound => {
+                Reply::error(404, &err.to_string())
+            }
+            store::InternalError::Conflict => Reply::error(412, &err.to_string()),
+            store::InternalError::Canceled => Reply::error(503, &err.to_string()),
+            store::InternalError::Timeout => Reply::error(504, &err.to_string()),
+            store::InternalError::Partial(_) => Reply::error(500, &err.to_string()),
+            store::InternalError::Unknow(e) => {
+                let constraint = e
+                    .err
+                    .as_ref()
+                    .and_then(|e| e.code)
+                    .map(|c| c as i32 == sqlite3_sys::SQLITE_CONSTRAINT)
+                    .unwrap_or(false);
+                if constraint {
+                    Reply::error(409, &err.to_string())
+                } else if e.code == 400 {
+                    // an invalid argument, as a tag with spaces
+                    Reply::error(400, &e.message)
+                } else {
+                    Reply::error(500, &err.to_string())
+                }
+            }
+        }
+    }
+}
+
+/// http request reduced to what the routes need
+pub struct Request<'a> {
+    pub method: &'a str,
+    pub url: &'a str,
+    pub if_match: Option<&'a str>,
+    pub body: &'a str,
+}
+
+/// REST api over a workspace of word libraries, every library is keyed by its file name
+pub struct Server {
+    lib: Library,
+}
+
+// a path segment, a plus is kept as it is
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut res: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            // from_str_radix takes a sign, so the digits are checked first
+            b'%' if i + 2 < bytes.len()
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit() =>
+            {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+                res.push(u8::from_str_radix(hex, 16).unwrap());
+                i += 3;
+                continue;
+            }
+            b => res.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+// a key or value of the query string, where a plus is a space
+fn query_decode(s: &str) -> String {
+    percent_decode(&s.replace('+', " "))
+}
+
+fn query<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+fn parse_version(etag: &str) -> Option<i64> {
+    etag.trim()
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse()
+        .ok()
+}
+
+impl Server {
+    pub fn new(lib: Library) -> Server {
+        Server { lib }
+    }
+
+    /// open every library file of the workspace directory
+    pub fn open(workspace: &str) -> Result<Server, String> {
+        let mut lib = Library::new();
+        let files = store::library_files(workspace)
+            .map_err(|e| format!("read workspace {} fail, {}", workspace, e))?;
+        for f in files {
+            let key = Path::new(&f)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| f.clone());
+            let s =
+                dbstore::Sqlite::new(f.clone()).map_err(|e| format!("open {} fail, {}", f, e))?;
+            lib.add_store(&key, Box::new(s));
+        }
+
+        // deleted words are kept in the trash of the workspace
+        trash::attach(&mut lib, &trash::path(workspace))?;
+        // the saved searches are served as libraries
+        for (name, q) in smart::load(&smart::path(workspace))? {
+            lib.add_search(&name, q);
+        }
+        Ok(Server::new(lib))
+    }
+
+    pub fn bind(addr: &str) -> Result<tiny_http::Server, String> {
+        tiny_http::Server::http(addr).map_err(|e| format!("listen on {} fail, {}", addr, e))
+    }
+
+    /// serve the requests by worker threads until the listener is closed
+    pub fn run(&self, server: tiny_http::Server) {
+        std::thread::scope(|scope| {
+            for _ in 0..WORKERS {
+                scope.spawn(|| {
+                    for req in server.incoming_requests() {
+                        self.respond(req);
+                    }
+                });
+            }
+        });
+    }
+
+    fn respond(&self, mut req: tiny_http::Request) {
+        let mut body = String::new();
+        let reply = match req.as_reader().read_to_string(&mut body) {
+            Ok(_) => {
+                let method = req.method().to_string();
+                let if_match = req
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("If-Match"))
+                    .map(|h| h.value.to_string());
+                self.handle(&Request {
+                    method: &method,
+                    url: req.url(),
+                    if_match: if_match.as_deref(),
+                    body: &body,
+                })
+            }
+            Err(err) => Reply::error(400, &err.to_string()),
+        };
+
+        let mut resp = tiny_http::Response::from_string(reply.body.to_string())
+            .with_status_code(reply.status)
+            .with_header(
+                tiny_http::Header::from_bytes("Content-Type", "application/json").unwrap(),
+            );
+        if let Some(v) = reply.etag {
+            let etag = format!("\"{}\"", v);
+            resp = resp.with_header(tiny_http::Header::from_bytes("ETag", etag).unwrap());
+        }
+        if let Err(err) = req.respond(resp) {
+            eprintln!("write response fail, {}", err);
+        }
+    }
+
+    pub fn handle(&self, req: &Request) -> Reply {
+        let (path, params) = match req.url.split_once('?') {
+            Some((path, q)) => (path, q),
+            None => (req.url, ""),
+        };
+        let params: Vec<(String, String)> = params
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| match p.split_once('=') {
+                Some((k, v)) => (query_decode(k), query_decode(v)),
+                None => (query_decode(p), String::new()),
+            })
+            .collect();
+        let segs: Vec<String> = path
+            .trim_matches('/')
+            .split('/')
+            .map(percent_decode)
+            .collect();
+        let segs: Vec<&str> = segs.iter().map(|s| s.as_str()).collect();
+
+        let body: Value = if req.body.trim().is_empty() {
+            Value::Null
+        } else {
+            match serde_json::from_str(req.body) {
+                Ok(v) => v,
+                Err(err) => return Reply::error(400, &format!("invalid json body, {}", err)),
+            }
+        };
+        let limit = |default: i64| -> Result<i64, Reply> {
+            match query(&params, "limit") {
+                Some(l) => l
+                    .parse()
+                    .map_err(|_| Reply::error(400, "limit should be a number")),
+                None => Ok(default),
+            }
+        };
+
+        let tag = match query(&params, "tag").map(|t| t.parse::<Expr>()) {
+            Some(Ok(expr)) => Some(expr),
+            Some(Err(err)) => return Reply::error(400, &err),
+            None => None,
+        };
+
+        let res = match (req.method, segs.as_slice()) {
+            ("GET", ["libraries"]) => self.libraries(),
+            ("GET", ["libraries", lib, "words"]) => match limit(0) {
+                Ok(size) => self.list(lib, size, tag.as_ref()),
+                Err(reply) => return reply,
+            },
+            ("POST", ["libraries", lib, "words"]) => self.insert(lib, &body),
+            ("GET", ["libraries", lib, "words", name]) => self.get(lib, name),
+            ("PUT", ["libraries", lib, "words", name]) => {
+                self.update(lib, name, req.if_match, &body)
+            }
+            ("DELETE", ["libraries", lib, "words", name]) => self.delete(lib, name, req.if_match),
+            ("POST", ["libraries", lib, "words", name, op @ ("move" | "copy")]) => {
+                self.transfer(lib, name, op, &body)
+            }
+            ("POST", ["libraries", lib, "words", name, "review"]) => self.review(lib, name, &body),
+            ("POST", ["libraries", lib, "words", name, "rename"]) => self.rename(lib, name, &body),
+            ("GET", ["libraries", lib, "words", name, "links"]) => self.links(lib, name),
+            ("GET", ["libraries", lib, "links", "broken"]) => self.broken_links(lib),
+            ("GET", ["libraries", lib, "words", name, "attachments"]) => {
+                self.attachments(lib, name)
+            }
+            ("GET", ["libraries", lib, "words", name, "attachments", file]) => {
+                self.attachment(lib, name, file)
+            }
+            ("PUT", ["libraries", lib, "words", name, "attachments", file]) => {
+                self.attach(lib, name, file, &body)
+            }
+            ("DELETE", ["libraries", lib, "words", name, "attachments", file]) => self
+                .lib
+                .detach(name, file, lib)
+                .and_then(|_| self.attachments(lib, name)),
+            ("PUT", ["libraries", lib, "words", name, "tags", tag]) => {
+                self.tag(lib, name, tag, true)
+            }
+            ("DELETE", ["libraries", lib, "words", name, "tags", tag]) => {
+                self.tag(lib, name, tag, false)
+            }
+            ("GET", ["libraries", lib, "tags"]) => self.tags(lib),
+            ("GET", ["libraries", lib, "due"]) => match limit(0) {
+                Ok(size) => self.due(lib, size),
+                Err(reply) => return reply,
+            },
+            ("GET", ["libraries", lib, "records"]) => match limit(0) {
+                Ok(size) => self.records(lib, query(&params, "after").unwrap_or(""), size),
+                Err(reply) => return reply,
+            },
+            ("POST", ["libraries", lib, "records"]) => self.apply(lib, &body),
+            ("GET", ["search"]) => match (query(&params, "q"), limit(20)) {
+                (Some(q), Ok(size)) => self.search(q, query(&params, "lib"), size, tag.as_ref()),
+                (None, _) => return Reply::error(400, "query parameter q is required"),
+                (_, Err(reply)) => return reply,
+            },
+            _ => return Reply::error(404, "no such route"),
+        };
+
+        res.unwrap_or_else(Reply::from_err)
+    }
+
+    fn libraries(&self) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let mut libs = Vec::new();
+        for key in self.lib.keys() {
+            let words = self.lib.list(0, &key)?.len();
+            libs.push(json!({ "name": key, "words": words }));
+        }
+        for (name, q) in self.lib.searches() {
+            let words = self.lib.list(0, &name)?.len();
+            libs.push(json!({ "name": name, "words": words, "query": q.to_string() }));
+        }
+        Ok(Reply::ok(Value::Array(libs)))
+    }
+
+    fn list(
+        &self,
+        lib: &str,
+        size: i64,
+        tag: Option<&Expr>,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let words = match tag {
+            Some(expr) => {
+                let mut words = self.lib.filter(expr, lib)?;
+                if size > 0 {
+                    words.truncate(size as usize);
+                }
+                words
+            }
+            None => self.lib.list(size, lib)?,
+        };
+        let words: Vec<Value> = words
+            .into_iter()
+            .map(|w| json!({ "name": w.name, "message": w.message }))
+            .collect();
+        Ok(Reply::ok(Value::Array(words)))
+    }
+
+    fn get(&self, lib: &str, name: &str) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let word = self.lib.get(name, lib)?;
+        let version = self.lib.version(name, lib)?;
+        let tags = self.lib.word_tags(name, lib)?;
+        Ok(Reply {
+            status: 200,
+            body: json!({
+                "name": word.name,
+                "message": word.message,
+                "version": version,
+                "tags": tags,
+            }),
+            etag: Some(version),
+        })
+    }
+
+    fn tag(
+        &self,
+        lib: &str,
+        name: &str,
+        tag: &str,
+        add: bool,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let tags = [tag.to_owned()];
+        if add {
+            self.lib.add_tags(name, &tags, lib)?;
+        } else {
+            self.lib.remove_tags(name, &tags, lib)?;
+        }
+        self.get(lib, name)
+    }
+
+    fn tags(&self, lib: &str) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let tags: Vec<Value> = self
+            .lib
+            .tags(lib)?
+            .into_iter()
+            .map(|(tag, n)| json!({ "tag": tag, "words": n }))
+            .collect();
+        Ok(Reply::ok(Value::Array(tags)))
+    }
+
+    fn insert(
+        &self,
+        lib: &str,
+        body: &Value,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let word = match (body["name"].as_str(), body["message"].as_str()) {
+            (Some(name), message) if !name.is_empty() => store::World {
+                name: name.to_owned(),
+                message: message.unwrap_or("").to_owned(),
+            },
+            _ => return Ok(Reply::error(400, "field name is required")),
+        };
+
+        self.lib.insert(&word, lib)?;
+        let mut reply = self.get(lib, &word.name)?;
+        reply.status = 201;
+        Ok(reply)
+    }
+
+    fn update(
+        &self,
+        lib: &str,
+        name: &str,
+        if_match: Option<&str>,
+        body: &Value,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let word = match body["message"].as_str() {
+            Some(message) => store::World {
+                name: name.to_owned(),
+                message: message.to_owned(),
+            },
+            None => return Ok(Reply::error(400, "field message is required")),
+        };
+
+        match if_match.filter(|m| m.trim() != "*") {
+            Some(etag) => match parse_version(etag) {
+                Some(version) => {
+                    self.lib.update_if(&word, lib, version)?;
+                }
+                None => return Ok(Reply::error(400, "invalid If-Match header")),
+            },
+            None => {
+                // make sure the word exist
+                self.lib.version(name, lib)?;
+                self.lib.update(&word, lib)?;
+            }
+        }
+        self.get(lib, name)
+    }
+
+    fn delete(
+        &self,
+        lib: &str,
+        name: &str,
+        if_match: Option<&str>,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        match if_match.filter(|m| m.trim() != "*") {
+            Some(etag) => match parse_version(etag) {
+                Some(version) => {
+                    self.lib.delete_if(name, lib, version)?;
+                }
+                None => return Ok(Reply::error(400, "invalid If-Match header")),
+            },
+            None => {
+                self.lib.version(name, lib)?;
+                self.lib.delete(name, lib)?;
+            }
+        }
+        Ok(Reply::ok(json!({ "name": name })))
+    }
+
+    fn transfer(
+        &self,
+        lib: &str,
+        name: &str,
+        op: &str,
+        body: &Value,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let dst = match body["to"].as_str() {
+            Some(dst) => dst,
+            None => return Ok(Reply::error(400, "field to is required")),
+        };
+
+        if op == "move" {
+            self.lib.move_to(name, lib, dst)?;
+        } else {
+            self.lib.copy_to(name, lib, dst)?;
+        }
+        self.get(dst, name)
+    }
+
+    fn rename(
+        &self,
+        lib: &str,
+        name: &str,
+        body: &Value,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let new = match body["to"].as_str() {
+            Some(new) if !new.is_empty() => new,
+            _ => return Ok(Reply::error(400, "field to is required")),
+        };
+        let links = body["links"].as_bool().unwrap_or(false);
+        self.lib.rename(name, new, lib, links)?;
+        self.get(lib, new)
+    }
+
+    fn links(&self, lib: &str, name: &str) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let mut links = Vec::new();
+        for link in self.lib.links(name, lib)? {
+            // the library the link points to, null when broken
+            let target = match self.lib.follow(&link, name, lib) {
+                Ok((key, _)) => Some(key),
+                Err(store::InternalError::NotFound) | Err(store::InternalError::StoreNotFound) => {
+                    None
+                }
+                Err(err) => return Err(err),
+            };
+            links.push(json!({
+                "library": link.library,
+                "word": link.word,
+                "target": target,
+            }));
+        }
+        let backlinks: Vec<Value> = self
+            .lib
+            .backlinks(name, lib)?
+            .into_iter()
+            .map(|(key, word)| json!({ "library": key, "word": word }))
+            .collect();
+        Ok(Reply::ok(json!({ "links": links, "backlinks": backlinks })))
+    }
+
+    fn broken_links(&self, lib: &str) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let links: Vec<Value> = self
+            .lib
+            .broken_links(lib)?
+            .into_iter()
+            .map(|(word, link)| json!({ "word": word, "link": link.to_string() }))
+            .collect();
+        Ok(Reply::ok(Value::Array(links)))
+    }
+
+    // the attached files without their data
+    fn attachments(
+        &self,
+        lib: &str,
+        name: &str,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let files: Vec<Value> = self
+            .lib
+            .attachments(name, lib)?
+            .into_iter()
+            .map(|a| json!({ "name": a.name, "mime": a.mime, "size": a.data.len() }))
+            .collect();
+        Ok(Reply::ok(Value::Array(files)))
+    }
+
+    fn attachment(
+        &self,
+        lib: &str,
+        name: &str,
+        file: &str,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        Ok(Reply::ok(self.lib.attachment(name, file, lib)?.to_json()))
+    }
+
+    fn attach(
+        &self,
+        lib: &str,
+        name: &str,
+        file: &str,
+        body: &Value,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let data = match body["data"].as_str().and_then(attachment::decode) {
+            Some(data) => data,
+            None => return Ok(Reply::error(400, "field data should be base64")),
+        };
+        let mut a = attachment::Attachment::new(file, data);
+        if let Some(mime) = body["mime"].as_str() {
+            a.mime = mime.to_owned();
+        }
+        self.lib.attach(name, &a, lib)?;
+        self.attachments(lib, name)
+    }
+
+    fn due(&self, lib: &str, size: i64) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let words: Vec<Value> = self
+            .lib
+            .due(review::now(), size, lib)?
+            .into_iter()
+            .map(|w| json!({ "name": w.name, "message": w.message }))
+            .collect();
+        Ok(Reply::ok(Value::Array(words)))
+    }
+
+    fn review(
+        &self,
+        lib: &str,
+        name: &str,
+        body: &Value,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let grade = match body["grade"].as_u64() {
+            Some(g) if g <= 5 => g as u8,
+            _ => return Ok(Reply::error(400, "field grade should be 0 to 5")),
+        };
+
+        let s = self.lib.review(name, lib, grade, review::now())?;
+        Ok(Reply::ok(json!({
+            "name": name,
+            "due": s.due,
+            "interval": s.interval,
+            "ease": s.ease,
+            "reps": s.reps,
+        })))
+    }
+
+    fn records(
+        &self,
+        lib: &str,
+        after: &str,
+        size: i64,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let records: Vec<Value> = self
+            .lib
+            .store(lib)?
+            .records(after, size)?
+            .iter()
+            .map(sync::record_json)
+            .collect();
+        Ok(Reply::ok(Value::Array(records)))
+    }
+
+    // the records of a peer sync, written in one transaction
+    fn apply(&self, lib: &str, body: &Value) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let records = match body.as_array().map(|rs| {
+            rs.iter()
+                .map(sync::record_from)
+                .collect::<Result<Vec<_>, _>>()
+        }) {
+            Some(Ok(records)) => records,
+            Some(Err(err)) => return Ok(Reply::error(400, &err)),
+            None => return Ok(Reply::error(400, "body should be an array of records")),
+        };
+        sync::apply(&**self.lib.store(lib)?, &records, &[], None)?;
+        Ok(Reply::ok(json!({ "applied": records.len() })))
+    }
+
+    fn search(
+        &self,
+        pattern: &str,
+        lib: Option<&str>,
+        size: i64,
+        tag: Option<&Expr>,
+    ) -> Result<Reply, store::InternalError<sqlite::Error>> {
+        let size = if size <= 0 { usize::MAX } else { size as usize };
+        let words: Vec<Value> = match lib {
+            Some(lib) => match tag {
+                Some(expr) => self.lib.search_tagged(pattern, expr, lib, size)?,
+                None => self.lib.search(pattern, lib, size)?,
+            }
+            .into_iter()
+            .map(|w| json!({ "library": lib, "name": w.name, "message": w.message }))
+            .collect(),
+            None => match tag {
+                Some(expr) => self.lib.search_all_tagged(pattern, expr, size)?,
+                None => self.lib.search_all(pattern, size)?,
+            }
+            .into_iter()
+            .map(|(lib, w)| json!({ "library": lib, "name": w.name, "message": w.message }))
+            .collect(),
+        };
+        Ok(Reply::ok(Value::Array(words)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+
+    // start a server over a temporary workspace with two libraries
+    fn start(dir: &str) -> SocketAddr {
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+        let dir = dir.to_owned();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for name in ["a", "b"] {
+                dbstore::Sqlite::new(format!("{}/{}.sql", dir, name)).unwrap();
+            }
+            let q = smart::Query::from_args(&["--tag", "noun"]).unwrap();
+            let searches = [(String::from("nouns"), q)];
+            smart::save(&smart::path(&dir), &searches).unwrap();
+            let server = Server::open(&dir).unwrap();
+            let listener = Server::bind("127.0.0.1:0").unwrap();
+            tx.send(listener.server_addr().to_ip().unwrap()).unwrap();
+            server.run(listener);
+        });
+        rx.recv().unwrap()
+    }
+
+    fn request(
+        addr: SocketAddr,
+        method: &str,
+        path: &str,
+        if_match: Option<&str>,
+        body: &str,
+    ) -> (u16, Option<String>, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut req = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n",
+            method,
+            path,
+            body.len()
+        );
+        if let Some(m) = if_match {
+            req.push_str(&format!("If-Match: {}\r\n", m));
+        }
+        req.push_str("\r\n");
+        req.push_str(body);
+        stream.write_all(req.as_bytes()).unwrap();
+
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).unwrap();
+        let (head, body) = resp.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        let etag = head
+            .lines()
+            .find(|l| l.to_lowercase().starts_with("etag:"))
+            .map(|l| l[5..].trim().to_owned());
+        (status, etag, serde_json::from_str(body).unwrap())
+    }
+
+    // add a word to a library of the server
+    fn post(addr: SocketAddr, lib: &str, name: &str, message: &str) {
+        let body = json!({"name": name, "message": message}).to_string();
+        let path = format!("/libraries/{}/words", lib);
+        let (status, _, _) = request(addr, "POST", &path, None, &body);
+        assert_eq!(status, 201);
+    }
+
+    #[test]
+    fn test_libraries() {
+        let addr = start("target/test_server_libraries");
+
+        let (status, _, libs) = request(addr, "GET", "/libraries", None, "");
+        assert_eq!(status, 200);
+        assert_eq!(libs.as_array().unwrap().len(), 3);
+        assert_eq!(libs[2]["query"], "tags noun");
+        let (status, _, _) = request(addr, "GET", "/libraries/c/words", None, "");
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn test_words() {
+        let addr = start("target/test_server_words");
+
+        let body = r#"{"name": "school", "message": "a place"}"#;
+        let (status, etag, _) = request(addr, "POST", "/libraries/a/words", None, body);
+        assert_eq!((status, etag.as_deref()), (201, Some("\"1\"")));
+        let (status, _, _) = request(addr, "POST", "/libraries/a/words", None, body);
+        assert_eq!(status, 409);
+
+        let (status, _, word) = request(addr, "GET", "/libraries/a/words/school", None, "");
+        assert_eq!(status, 200);
+        assert_eq!(word["message"], "a place");
+
+        // optimistic concurrency
+        let body = r#"{"message": "a new place"}"#;
+        let path = "/libraries/a/words/school";
+        let (status, etag, _) = request(addr, "PUT", path, Some("\"1\""), body);
+        assert_eq!((status, etag.as_deref()), (200, Some("\"2\"")));
+        let (status, _, _) = request(addr, "PUT", path, Some("\"1\""), body);
+        assert_eq!(status, 412);
+        let (status, _, _) = request(addr, "DELETE", path, Some("\"1\""), "");
+        assert_eq!(status, 412);
+        let (status, _, _) = request(addr, "DELETE", path, Some("\"2\""), "");
+        assert_eq!(status, 200);
+        let (status, _, _) = request(addr, "GET", path, None, "");
+        assert_eq!(status, 404);
+
+        // a plus is a space only in the query string
+        post(addr, "a", "C++", "a language");
+        let (status, _, word) = request(addr, "GET", "/libraries/a/words/C++", None, "");
+        assert_eq!((status, &word["name"]), (200, &json!("C++")));
+        // a percent sign not followed by two hex digits is kept
+        post(addr, "a", "a%+1", "not a byte");
+        let (status, _, word) = request(addr, "GET", "/libraries/a/words/a%+1", None, "");
+        assert_eq!((status, &word["name"]), (200, &json!("a%+1")));
+        post(addr, "a", "ice cream", "a food");
+        let (_, _, res) = request(addr, "GET", "/search?q=ice+cream", None, "");
+        assert_eq!(res[0]["name"], "ice cream");
+        let (_, _, res) = request(addr, "GET", "/search?q=C%2B%2B", None, "");
+        assert_eq!(res[0]["name"], "C++");
+    }
+
+    #[test]
+    fn test_search() {
+        let addr = start("target/test_server_search");
+        post(addr, "b", "school", "a place");
+
+        let (status, _, res) = request(addr, "GET", "/search?q=scho", None, "");
+        assert_eq!(status, 200);
+        assert_eq!(res[0]["library"], "b");
+    }
+
+    #[test]
+    fn test_move() {
+        let addr = start("target/test_server_move");
+        post(addr, "a", "school", "a place");
+        let path = "/libraries/a/words/school";
+        request(addr, "PUT", path, None, r#"{"message": "a new place"}"#);
+
+        let (status, _, _) = request(
+            addr,
+            "POST",
+            "/libraries/a/words/school/move",
+            None,
+            r#"{"to": "b"}"#,
+        );
+        assert_eq!(status, 200);
+        let (status, _, _) = request(addr, "GET", path, None, "");
+        assert_eq!(status, 404);
+
+        // the moved word keeps its version in the destination
+        let path = "/libraries/b/words/school";
+        let (status, _, _) = request(addr, "DELETE", path, Some("\"2\""), "");
+        assert_eq!(status, 200);
+    }
+
+    #[test]
+    fn test_tags() {
+        let addr = start("target/test_server_tags");
+        post(addr, "a", "school", "a place");
+
+        let (status, _, word) =
+            request(addr, "PUT", "/libraries/a/words/school/tags/noun", None, "");
+        assert_eq!((status, &word["tags"][0]), (200, &json!("noun")));
+        let (status, _, _) = request(addr, "PUT", "/libraries/a/words/school/tags/NOT", None, "");
+        assert_eq!(status, 400);
+        let tagged = "/libraries/a/words?tag=noun%20AND%20NOT%20mastered";
+        let (_, _, words) = request(addr, "GET", tagged, None, "");
+        assert_eq!(words.as_array().unwrap().len(), 1);
+        let (status, _, _) = request(addr, "GET", "/search?q=scho&tag=(noun", None, "");
+        assert_eq!(status, 400);
+        let (_, _, tags) = request(addr, "GET", "/libraries/a/tags", None, "");
+        assert_eq!(tags[0]["words"], 1);
+    }
+
+    #[test]
+    fn test_smart() {
+        let addr = start("target/test_server_smart");
+        post(addr, "a", "school", "a place");
+        request(addr, "PUT", "/libraries/a/words/school/tags/noun", None, "");
+
+        // a word of a smart library is written to its library
+        let body = r#"{"message": "a place to learn"}"#;
+        let (status, etag, _) = request(addr, "PUT", "/libraries/nouns/words/school", None, body);
+        assert_eq!((status, etag.as_deref()), (200, Some("\"2\"")));
+        let (_, _, word) = request(addr, "GET", "/libraries/a/words/school", None, "");
+        assert_eq!(word["message"], "a place to learn");
+    }
+
+    #[test]
+    fn test_links() {
+        let addr = start("target/test_server_links");
+        post(addr, "b", "pupil", "a [[teacher]] [[a:nothing]]");
+        post(addr, "b", "teacher", "see [[pupil]]");
+
+        let (status, _, links) = request(addr, "GET", "/libraries/b/words/pupil/links", None, "");
+        assert_eq!(status, 200);
+        assert_eq!(
+            (&links["links"][0]["target"], &links["links"][1]["target"]),
+            (&json!("b"), &Value::Null)
+        );
+        assert_eq!(links["backlinks"][0]["word"], "teacher");
+        let (_, _, broken) = request(addr, "GET", "/libraries/b/links/broken", None, "");
+        assert_eq!(broken[0]["link"], "[[a:nothing]]");
+        let body = r#"{"to": "student", "links": true}"#;
+        let (status, _, _) = request(addr, "POST", "/libraries/b/words/pupil/rename", None, body);
+        assert_eq!(status, 200);
+        let (_, _, word) = request(addr, "GET", "/libraries/b/words/teacher", None, "");
+        assert_eq!(word["message"], "see [[student]]");
+    }
+
+    #[test]
+    fn test_attachments() {
+        let addr = start("target/test_server_attachments");
+        post(addr, "b", "student", "a person");
+
+        let path = "/libraries/b/words/student/attachments/student.mp3";
+        let (status, _, files) = request(addr, "PUT", path, None, r#"{"data": "SUQz"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(
+            (&files[0]["mime"], &files[0]["size"]),
+            (&json!("audio/mpeg"), &json!(3))
+        );
+        let (_, _, file) = request(addr, "GET", path, None, "");
+        assert_eq!(file["data"], "SUQz");
+        let (status, _, _) = request(addr, "PUT", path, None, r#"{"data": "S"}"#);
+        assert_eq!(status, 400);
+        let (status, _, files) = request(addr, "DELETE", path, None, "");
+        assert_eq!((status, files.as_array().unwrap().len()), (200, 0));
+        let (status, _, _) = request(addr, "GET", path, None, "");
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn test_review() {
+        let addr = start("target/test_server_review");
+
+        let body = r#"{"name": "apple pie"}"#;
+        let (status, _, _) = request(addr, "POST", "/libraries/a/words", None, body);
+        assert_eq!(status, 201);
+
+        let (_, _, due) = request(addr, "GET", "/libraries/a/due", None, "");
+        assert_eq!(due[0]["name"], "apple pie");
+
+        let path = "/libraries/a/words/apple%20pie/review";
+        let (status, _, s) = request(addr, "POST", path, None, r#"{"grade": 4}"#);
+        assert_eq!(status, 200);
+        assert_eq!(s["interval"], 1);
+        let (status, _, _) = request(addr, "POST", path, None, r#"{"grade": 9}"#);
+        assert_eq!(status, 400);
+
+        let (_, _, due) = request(addr, "GET", "/libraries/a/due", None, "");
+        assert_eq!(due.as_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_sync() {
+        let addr = start("target/test_server_sync");
+        let body = r#"{"name": "apple", "message": "a fruit"}"#;
+        request(addr, "POST", "/libraries/a/words", None, body);
+
+        let path = "target/test_server_sync.sql";
+        let _ = std::fs::remove_file(path);
+        let local = dbstore::Sqlite::new(path.to_owned()).unwrap();
+        store::Write::insert(
+            &local,
+            &store::World {
+                name: String::from("book"),
+                message: String::from("to read"),
+            },
+        )
+        .unwrap();
+
+        let url = format!("http://{}/libraries/a", addr);
+        let peer = sync::http::Http::parse(&url).unwrap();
+        let report = sync::sync(&local, &peer, &url, false).unwrap();
+        assert_eq!((report.pulled, report.pushed), (1, 1));
+        let (status, _, word) = request(addr, "GET", "/libraries/a/words/book", None, "");
+        assert_eq!((status, word["message"].as_str()), (200, Some("to read")));
+        assert_eq!(
+            store::Read::get(&local, "apple").unwrap().message,
+            "a fruit"
+        );
+
+        let (status, _, _) = request(addr, "POST", "/libraries/a/records", None, "{}");
+        assert_eq!(status, 400);
+    }
+}
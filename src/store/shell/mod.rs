@@ -0,0 +1,976 @@
+use crate::db::sqlite::store as dbstore;
+use crate::store;
+use crate::store::dict;
+use crate::store::smart;
+use crate::store::tags::Expr;
+use crate::store::trash;
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+type Library = store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>;
+
+const COMMANDS: [&str; 25] = [
+    "help", "libs", "open", "use", "list", "get", "search", "add", "edit", "rm", "mv", "rename",
+    "tag", "untag", "tags", "links", "broken", "attach", "detach", "files", "fetch", "save",
+    "unsave", "define", "quit",
+];
+
+const HELP: &str = r#"commands:
+  libs                    list opened libraries and smart libraries, current one marked by *
+  open <path>...          open library files, or every .sql file in a directory
+  use <lib>               switch the current library
+  list [size] [-t <expr>] list words of current library, -t for the words the tag expression
+                          holds for, as `verb AND NOT mastered`
+  get <word>              look up a word, other libraries and dictionaries are tried if not found
+  search [-a] <pattern> [-t <expr>]
+                          fuzzy search in current library, -a for all libraries
+  add <word>              add a word and edit its message inline, filled by dictionaries
+  edit <word>             edit the message of a word inline
+  rm <word>               delete a word into the trash
+  mv <word> <lib>         move a word to another library
+  rename <word> <new> [-l]
+                          rename a word, -l to rename the [[links]] to it too
+  tag <word> <tag>...     add tags to a word
+  untag <word> <tag>...   remove tags from a word
+  tags [-a]               list the tags of current library with their word counts, -a for all
+  links <word>            list the [[links]] of a word and the words linking to it
+  broken                  list the links of current library to missing words
+  attach <word> <file>... attach audio, images or other files to a word
+  detach <word> <name>    remove an attached file from a word
+  files <word>            list the files attached to a word
+  fetch <word> <name> [<path>]
+                          write an attached file to path, the current directory by default
+  save <name> [--name <pattern>] [--text <words>] [--tag <expr>] [--due <days>] [--lib <lib>]...
+                          save a search as a smart library, its words are edited in their libraries
+  unsave <name>           remove a smart library
+  define <word>           look up a word in the registered dictionaries
+  quit                    exit the shell
+message editing ends with a line holding a single '.', Ctrl-C to cancel"#;
+
+// print a line of the command output, a closed output is ignored
+macro_rules! say {
+    ($s:expr, $($arg:tt)*) => {{
+        let _ = writeln!($s.out, $($arg)*);
+    }};
+}
+
+// split the tag expression after -t off the arguments
+fn tag_expr<'a, 'b>(args: &'a [&'b str]) -> Result<(&'a [&'b str], Option<Expr>), String> {
+    match args.iter().position(|a| *a == "-t") {
+        Some(i) => Ok((&args[..i], Some(args[i + 1..].join(" ").parse()?))),
+        None => Ok((args, None)),
+    }
+}
+
+/// line editor helper, complete commands, word names and library keys
+struct ShellHelper {
+    words: Vec<String>,
+    keys: Vec<String>,
+    files: FilenameCompleter,
+    // true while editing a multi-line message
+    editing: Cell<bool>,
+}
+
+impl ShellHelper {
+    fn candidates(list: &[String], prefix: &str) -> Vec<Pair> {
+        list.iter()
+            .filter(|c| c.starts_with(prefix))
+            .map(|c| Pair {
+                display: c.clone(),
+                replacement: c.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        if self.editing.get() {
+            return Ok((pos, Vec::new()));
+        }
+
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+        let prefix = &line[start..];
+        let args: Vec<&str> = line[..start].split_whitespace().collect();
+
+        let list: Vec<Pair> = match (args.first(), args.len()) {
+            (None, _) => {
+                let cmds: Vec<String> = COMMANDS.iter().map(|c| c.to_string()).collect();
+                ShellHelper::candidates(&cmds, prefix)
+            }
+            (Some(&"open"), _) | (Some(&"attach"), 2..) | (Some(&"fetch"), 3) => {
+                return self.files.complete(line, pos, ctx)
+            }
+            (Some(&"use"), 1) => ShellHelper::candidates(&self.keys, prefix),
+            (Some(&"mv"), 2) => ShellHelper::candidates(&self.keys, prefix),
+            (Some(&"get"), 1)
+            | (Some(&"edit"), 1)
+            | (Some(&"rm"), 1)
+            | (Some(&"mv"), 1)
+            | (Some(&"rename"), 1)
+            | (Some(&"links"), 1)
+            | (Some(&"attach"), 1)
+            | (Some(&"detach"), 1)
+            | (Some(&"files"), 1)
+            | (Some(&"fetch"), 1)
+            | (Some(&"tag"), 1)
+            | (Some(&"untag"), 1) => ShellHelper::candidates(&self.words, prefix),
+            _ => Vec::new(),
+        };
+        Ok((start, list))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if !self.editing.get() {
+            return Ok(ValidationResult::Valid(None));
+        }
+
+        if finished(ctx.input()) {
+            Ok(ValidationResult::Valid(None))
+        } else {
+            Ok(ValidationResult::Incomplete)
+        }
+    }
+}
+
+impl Helper for ShellHelper {}
+
+// a message being edited ends with a line holding a single '.'
+fn finished(input: &str) -> bool {
+    input == "." || input.ends_with("\n.")
+}
+
+// the message of a finished edit, without the ending line
+fn message_of(text: &str) -> String {
+    let text = text.strip_suffix('.').unwrap_or(text);
+    text.strip_suffix('\n').unwrap_or(text).to_owned()
+}
+
+/// read a multi-line message inline, None if the edit is canceled
+fn read_message(
+    editor: &mut Editor<ShellHelper, DefaultHistory>,
+    initial: &str,
+) -> rustyline::Result<Option<String>> {
+    println!("-- editing, end with a line holding a single '.' --");
+    if let Some(h) = editor.helper_mut() {
+        h.editing.set(true);
+    }
+
+    let mut init = String::from(initial);
+    if !init.is_empty() && !init.ends_with('\n') {
+        init.push('\n');
+    }
+    let res = editor.readline_with_initial("", (&init, ""));
+
+    if let Some(h) = editor.helper_mut() {
+        h.editing.set(false);
+    }
+
+    match res {
+        Ok(text) => Ok(Some(message_of(&text))),
+        Err(ReadlineError::Interrupted) => {
+            println!("edit canceled");
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// the libraries of the shell and its commands, the output of the commands
+/// is written to out
+struct Session<W: Write> {
+    lib: Library,
+    current: Option<String>,
+    dicts: dict::Dictionaries,
+    // the saved searches of the first workspace
+    searches: Option<PathBuf>,
+    out: W,
+}
+
+pub struct Shell {
+    session: Session<io::Stdout>,
+    editor: Editor<ShellHelper, DefaultHistory>,
+}
+
+impl Shell {
+    pub fn new() -> rustyline::Result<Shell> {
+        let mut editor = Editor::new()?;
+        editor.set_helper(Some(ShellHelper {
+            words: Vec::new(),
+            keys: Vec::new(),
+            files: FilenameCompleter::new(),
+            editing: Cell::new(false),
+        }));
+
+        let (dicts, errs) = dict::Dictionaries::load();
+        for err in errs {
+            println!("warn: {}", err);
+        }
+
+        Ok(Shell {
+            session: Session::new(dicts, io::stdout()),
+            editor,
+        })
+    }
+
+    fn history_path() -> Option<String> {
+        std::env::var("HOME")
+            .ok()
+            .map(|home| format!("{}/.word_library_history", home))
+    }
+
+    /// open a library file, or every .sql file under a directory
+    pub fn open(&mut self, path: &str) {
+        self.session.open(path)
+    }
+
+    // refresh completion candidates after the library changed
+    fn refresh(&mut self) {
+        let (keys, words) = self.session.candidates();
+        if let Some(h) = self.editor.helper_mut() {
+            h.keys = keys;
+            h.words = words;
+        }
+    }
+
+    pub fn run(&mut self) -> rustyline::Result<()> {
+        let history = Shell::history_path();
+        if let Some(path) = &history {
+            // the history file does not exist at the first run
+            let _ = self.editor.load_history(path);
+        }
+
+        loop {
+            self.refresh();
+            let prompt = self.session.prompt();
+            match self.editor.readline(&prompt) {
+                Ok(line) => {
+                    if !line.trim().is_empty() {
+                        self.editor.add_history_entry(line.as_str())?;
+                    }
+                    let editor = &mut self.editor;
+                    if !self
+                        .session
+                        .exec(&line, |init| read_message(editor, init))?
+                    {
+                        break;
+                    }
+                }
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        if let Some(path) = &history {
+            self.editor.save_history(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Session<W> {
+    fn new(dicts: dict::Dictionaries, out: W) -> Session<W> {
+        Session {
+            lib: store::WordLibrary::new(),
+            current: None,
+            dicts,
+            searches: None,
+            out,
+        }
+    }
+
+    /// open a library file, or every .sql file under a directory
+    fn open(&mut self, path: &str) {
+        let files = match store::library_files(path) {
+            Ok(files) => files,
+            Err(err) => {
+                say!(self, "error: read dir {} fail, {}", path, err);
+                return;
+            }
+        };
+
+        for f in files {
+            match dbstore::Sqlite::new(f.clone()) {
+                Ok(s) => {
+                    self.lib.add_store(&f, Box::new(s));
+                    if self.current.is_none() {
+                        self.current = Some(f);
+                    }
+                }
+                Err(err) => say!(self, "error: open {} fail, {}", f, err),
+            }
+        }
+        // deleted words are kept in the trash of the first workspace
+        if !self.lib.has_trash() {
+            if let Err(err) = trash::attach(&mut self.lib, &trash::path(path)) {
+                say!(self, "error: {}", err);
+            }
+        }
+        if self.searches.is_none() {
+            let file = smart::path(path);
+            match smart::load(&file) {
+                Ok(searches) => {
+                    for (name, q) in searches {
+                        self.lib.add_search(&name, q);
+                    }
+                }
+                Err(err) => say!(self, "error: {}", err),
+            }
+            self.searches = Some(file);
+        }
+    }
+
+    // keep the saved searches in the workspace
+    fn save_searches(&mut self) {
+        match &self.searches {
+            Some(file) => {
+                if let Err(err) = smart::save(file, &self.lib.searches()) {
+                    say!(self, "error: {}", err);
+                }
+            }
+            None => say!(
+                self,
+                "warn: no workspace opened, the search is kept until exit"
+            ),
+        }
+    }
+
+    fn prompt(&self) -> String {
+        match &self.current {
+            Some(key) => {
+                let name = Path::new(key)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| key.clone());
+                format!("wordlib({})> ", name)
+            }
+            None => String::from("wordlib> "),
+        }
+    }
+
+    // the library keys and the words of the current library to complete
+    fn candidates(&self) -> (Vec<String>, Vec<String>) {
+        let mut keys = self.lib.keys();
+        keys.extend(self.lib.searches().into_iter().map(|(name, _)| name));
+        let words = match &self.current {
+            Some(key) => self
+                .lib
+                .list(0, key)
+                .map(|ws| ws.into_iter().map(|w| w.name).collect())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+        (keys, words)
+    }
+
+    fn current(&mut self) -> Option<String> {
+        if self.current.is_none() {
+            say!(self, "error: no library in use, open one first");
+        }
+        self.current.clone()
+    }
+
+    fn lookup(&mut self, name: &str) {
+        if let Some(key) = &self.current {
+            match self.lib.get(name, key) {
+                Ok(word) => {
+                    say!(self, "{}\n{}", word.name, word.message);
+                    return;
+                }
+                Err(store::InternalError::NotFound) => {}
+                Err(err) => {
+                    say!(self, "error: {}", err);
+                    return;
+                }
+            }
+        }
+
+        for key in self.lib.keys() {
+            if let Ok(word) = self.lib.get(name, &key) {
+                say!(self, "{} (in {})\n{}", word.name, key, word.message);
+                return;
+            }
+        }
+        if !self.define(name) {
+            say!(self, "{} not found", name);
+        }
+    }
+
+    // print the definitions of the dictionaries, false if there is none
+    fn define(&mut self, name: &str) -> bool {
+        match self.dicts.lookup(name) {
+            Ok(defs) if !defs.is_empty() => {
+                let _ = write!(self.out, "{}", dict::format(&defs));
+                true
+            }
+            Ok(_) => false,
+            Err(err) => {
+                say!(self, "error: {}", err);
+                true
+            }
+        }
+    }
+
+    fn search(&mut self, args: &[&str]) {
+        let (args, expr) = match tag_expr(args) {
+            Ok(res) => res,
+            Err(err) => {
+                say!(self, "error: {}", err);
+                return;
+            }
+        };
+        let (all, pattern) = match args {
+            ["-a", pattern] => (true, *pattern),
+            [pattern] => (false, *pattern),
+            _ => {
+                say!(self, "usage: search [-a] <pattern> [-t <expr>]");
+                return;
+            }
+        };
+
+        if all {
+            let res = match &expr {
+                Some(expr) => self.lib.search_all_tagged(pattern, expr, 20),
+                None => self.lib.search_all(pattern, 20),
+            };
+            match res {
+                Ok(ws) => {
+                    for (key, w) in ws {
+                        say!(self, "{}\t{}", w.name, key);
+                    }
+                }
+                Err(err) => say!(self, "error: {}", err),
+            }
+        } else if let Some(key) = self.current() {
+            let res = match &expr {
+                Some(expr) => self.lib.search_tagged(pattern, expr, &key, 20),
+                None => self.lib.search(pattern, &key, 20),
+            };
+            match res {
+                Ok(ws) => {
+                    for w in ws {
+                        say!(self, "{}", w.name);
+                    }
+                }
+                Err(err) => say!(self, "error: {}", err),
+            }
+        }
+    }
+
+    // the links of the word marked when broken, then the words linking to it
+    fn links(&mut self, name: &str, key: &str) {
+        let links = match self.lib.links(name, key) {
+            Ok(links) => links,
+            Err(err) => {
+                say!(self, "error: {}", err);
+                return;
+            }
+        };
+        for link in links {
+            match self.lib.follow(&link, name, key) {
+                Ok((k, _)) if k == key => say!(self, "-> {}", link),
+                Ok((k, _)) => say!(self, "-> {}\tin {}", link, k),
+                Err(_) => say!(self, "-> {}\tbroken", link),
+            }
+        }
+        match self.lib.backlinks(name, key) {
+            Ok(words) => {
+                for (k, w) in words {
+                    match k == key {
+                        true => say!(self, "<- {}", w),
+                        false => say!(self, "<- {}\tin {}", w, k),
+                    }
+                }
+            }
+            Err(err) => say!(self, "error: {}", err),
+        }
+    }
+
+    // edit the message of the word with read, which is given the message to
+    // start with
+    fn edit<R>(&mut self, name: &str, new: bool, read: R) -> rustyline::Result<()>
+    where
+        R: FnOnce(&str) -> rustyline::Result<Option<String>>,
+    {
+        let key = match self.current() {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+
+        let initial = if new {
+            if self.lib.get(name, &key).is_ok() {
+                say!(self, "error: {} already exists, use edit", name);
+                return Ok(());
+            }
+            let defs = self.dicts.lookup(name).unwrap_or_default();
+            dict::fill("", &defs)
+        } else {
+            match self.lib.get(name, &key) {
+                Ok(w) => w.message,
+                Err(err) => {
+                    say!(self, "error: {}", err);
+                    return Ok(());
+                }
+            }
+        };
+
+        if let Some(message) = read(&initial)? {
+            let word = store::World {
+                name: name.to_owned(),
+                message,
+            };
+            let res = if new {
+                self.lib.insert(&word, &key)
+            } else {
+                self.lib.update(&word, &key)
+            };
+            if let Err(err) = res {
+                say!(self, "error: {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    // run one command line, return false to exit the shell. read edits the
+    // message of add and edit
+    fn exec<R>(&mut self, line: &str, read: R) -> rustyline::Result<bool>
+    where
+        R: FnOnce(&str) -> rustyline::Result<Option<String>>,
+    {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        let (cmd, args) = match args.split_first() {
+            Some((cmd, args)) => (*cmd, args),
+            None => return Ok(true),
+        };
+
+        match (cmd, args) {
+            ("help", _) => say!(self, "{}", HELP),
+            ("quit", _) | ("exit", _) => return Ok(false),
+            ("libs", _) => {
+                for key in self.lib.keys() {
+                    let mark = if Some(&key) == self.current.as_ref() {
+                        "*"
+                    } else {
+                        " "
+                    };
+                    say!(self, "{} {}", mark, key);
+                }
+                for (name, q) in self.lib.searches() {
+                    let mark = if Some(&name) == self.current.as_ref() {
+                        "*"
+                    } else {
+                        " "
+                    };
+                    say!(self, "{} {}\t{}", mark, name, q);
+                }
+            }
+            ("open", paths) if !paths.is_empty() => {
+                for p in paths {
+                    self.open(p);
+                }
+            }
+            ("use", [key]) => {
+                if self.lib.contains(key) {
+                    self.current = Some(key.to_string());
+                } else {
+                    say!(self, "error: library {} is not opened", key);
+                }
+            }
+            ("list", args) => {
+                let (size, expr) = match tag_expr(args) {
+                    Ok(res) => res,
+                    Err(err) => {
+                        say!(self, "error: {}", err);
+                        return Ok(true);
+                    }
+                };
+                let size = match size.first().map(|s| s.parse::<i64>()) {
+                    Some(Ok(size)) => size,
+                    Some(Err(_)) => {
+                        say!(self, "usage: list [size] [-t <expr>]");
+                        return Ok(true);
+                    }
+                    None => 0,
+                };
+                if let Some(key) = self.current() {
+                    let res = match &expr {
+                        Some(expr) => self.lib.filter(expr, &key).map(|mut ws| {
+                            if size > 0 {
+                                ws.truncate(size as usize);
+                            }
+                            ws
+                        }),
+                        None => self.lib.list(size, &key),
+                    };
+                    match res {
+                        Ok(ws) => {
+                            for w in ws {
+                                say!(self, "{}", w.name);
+                            }
+                        }
+                        Err(err) => say!(self, "error: {}", err),
+                    }
+                }
+            }
+            ("get", [name]) => self.lookup(name),
+            ("search", args) => self.search(args),
+            ("add", [name]) => self.edit(name, true, read)?,
+            ("edit", [name]) => self.edit(name, false, read)?,
+            ("rm", [name]) => {
+                if let Some(key) = self.current() {
+                    if let Err(err) = self.lib.delete(name, &key) {
+                        say!(self, "error: {}", err);
+                    }
+                }
+            }
+            ("tag", [name, tags @ ..]) | ("untag", [name, tags @ ..]) if !tags.is_empty() => {
+                if let Some(key) = self.current() {
+                    let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+                    let res = match cmd {
+                        "tag" => self.lib.add_tags(name, &tags, &key),
+                        _ => self.lib.remove_tags(name, &tags, &key),
+                    };
+                    if let Err(err) = res {
+                        say!(self, "error: {}", err);
+                    }
+                }
+            }
+            ("tags", args) => {
+                let res = match args {
+                    ["-a"] => self.lib.tags_all(),
+                    [] => match self.current() {
+                        Some(key) => self.lib.tags(&key),
+                        None => return Ok(true),
+                    },
+                    _ => {
+                        say!(self, "usage: tags [-a]");
+                        return Ok(true);
+                    }
+                };
+                match res {
+                    Ok(tags) => {
+                        for (tag, n) in tags {
+                            say!(self, "{:<20}{}", tag, n);
+                        }
+                    }
+                    Err(err) => say!(self, "error: {}", err),
+                }
+            }
+            ("save", [name, opts @ ..]) => match smart::Query::from_args(opts) {
+                Ok(_) if self.lib.keys().iter().any(|k| k == name) => {
+                    say!(self, "error: {} is a library", name)
+                }
+                Ok(q) => {
+                    self.lib.add_search(name, q);
+                    self.save_searches();
+                }
+                Err(err) => say!(self, "error: {}", err),
+            },
+            ("unsave", [name]) => {
+                if self.lib.remove_search(name).is_none() {
+                    say!(self, "error: no smart library {}", name);
+                    return Ok(true);
+                }
+                if self.current.as_deref() == Some(*name) {
+                    self.current = self.lib.keys().into_iter().next();
+                }
+                self.save_searches();
+            }
+            ("rename", [name, new, opts @ ..]) if opts.is_empty() || opts == ["-l"] => {
+                if let Some(key) = self.current() {
+                    match self.lib.rename(name, new, &key, !opts.is_empty()) {
+                        Err(store::InternalError::Conflict) => {
+                            say!(self, "error: {} already exists", new)
+                        }
+                        Err(err) => say!(self, "error: {}", err),
+                        Ok(_) => {}
+                    }
+                }
+            }
+            ("links", [name]) => {
+                if let Some(key) = self.current() {
+                    self.links(name, &key);
+                }
+            }
+            ("broken", []) => {
+                if let Some(key) = self.current() {
+                    match self.lib.broken_links(&key) {
+                        Ok(links) => {
+                            for (w, link) in links {
+                                say!(self, "{:<20}{}", w, link);
+                            }
+                        }
+                        Err(err) => say!(self, "error: {}", err),
+                    }
+                }
+            }
+            ("attach", [name, files @ ..]) if !files.is_empty() => {
+                if let Some(key) = self.current() {
+                    for file in files {
+                        let res = store::attachment::Attachment::read(Path::new(file))
+                            .map_err(|e| e.to_string())
+                            .and_then(|a| {
+                                self.lib.attach(name, &a, &key).map_err(|e| e.to_string())
+                            });
+                        if let Err(err) = res {
+                            say!(self, "error: attach {}: {}", file, err);
+                        }
+                    }
+                }
+            }
+            ("detach", [name, file]) => {
+                if let Some(key) = self.current() {
+                    if let Err(err) = self.lib.detach(name, file, &key) {
+                        say!(self, "error: {}", err);
+                    }
+                }
+            }
+            ("files", [name]) => {
+                if let Some(key) = self.current() {
+                    match self.lib.attachments(name, &key) {
+                        Ok(files) => {
+                            for a in files {
+                                say!(self, "{:<30}{:<20}{} bytes", a.name, a.mime, a.data.len());
+                            }
+                        }
+                        Err(err) => say!(self, "error: {}", err),
+                    }
+                }
+            }
+            ("fetch", [name, file, dst @ ..]) if dst.len() <= 1 => {
+                if let Some(key) = self.current() {
+                    let res = self
+                        .lib
+                        .attachment(name, file, &key)
+                        .map_err(|e| e.to_string());
+                    let res = res.and_then(|a| {
+                        // a directory keeps the file under its name
+                        let path = match dst.first().map(Path::new) {
+                            Some(p) if p.is_dir() => p.join(&a.name),
+                            Some(p) => p.to_path_buf(),
+                            None => PathBuf::from(&a.name),
+                        };
+                        std::fs::write(&path, &a.data)
+                            .map(|_| path)
+                            .map_err(|e| e.to_string())
+                    });
+                    match res {
+                        Ok(path) => say!(self, "{} written", path.display()),
+                        Err(err) => say!(self, "error: {}", err),
+                    }
+                }
+            }
+            ("define", [name]) => {
+                if !self.define(name) {
+                    say!(self, "{} not found in dictionaries", name);
+                }
+            }
+            ("mv", [name, dst]) => {
+                if let Some(key) = self.current() {
+                    if let Err(err) = self.lib.move_to(name, &key, dst) {
+                        say!(self, "error: {}", err);
+                    }
+                }
+            }
+            _ => say!(self, "unknow command: {}, type help for usage", line.trim()),
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a session on two new libraries in their own directory, the words are
+    // added to the first one
+    fn new_session(name: &str, words: &[(&str, &str)]) -> (Session<Vec<u8>>, Vec<String>) {
+        let dir =
+            std::env::temp_dir().join(format!("wordlib_shell_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut s = Session::new(dict::Dictionaries::new(), Vec::new());
+        let keys: Vec<String> = ["english", "french"]
+            .iter()
+            .map(|n| {
+                dir.join(format!("{}.sql", n))
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        for key in &keys {
+            s.open(key);
+        }
+        for (name, message) in words {
+            let word = store::World {
+                name: name.to_string(),
+                message: message.to_string(),
+            };
+            s.lib.insert(&word, &keys[0]).unwrap();
+        }
+        (s, keys)
+    }
+
+    // remove the directory of the libraries of the session
+    fn remove(s: Session<Vec<u8>>, keys: &[String]) {
+        drop(s);
+        std::fs::remove_dir_all(Path::new(&keys[0]).parent().unwrap()).unwrap();
+    }
+
+    // run the line with the message read by add and edit, return the output
+    fn run(s: &mut Session<Vec<u8>>, line: &str, message: Option<&str>) -> String {
+        let message = message.map(|m| m.to_owned());
+        assert!(s.exec(line, |_| Ok(message)).unwrap());
+        String::from_utf8(std::mem::take(&mut s.out)).unwrap()
+    }
+
+    fn complete(h: &ShellHelper, line: &str) -> Vec<String> {
+        let history = DefaultHistory::new();
+        let (_, list) = h
+            .complete(line, line.len(), &Context::new(&history))
+            .unwrap();
+        list.into_iter().map(|p| p.replacement).collect()
+    }
+
+    #[test]
+    fn test_use() {
+        let (mut s, keys) = new_session("use", &[]);
+        assert_eq!(s.current.as_ref(), Some(&keys[0]));
+        run(&mut s, &format!("use {}", keys[1]), None);
+        assert_eq!(s.current.as_ref(), Some(&keys[1]));
+        assert!(run(&mut s, "use nothing", None).contains("not opened"));
+        assert_eq!(s.current.as_ref(), Some(&keys[1]));
+        assert!(s.prompt().contains("french"));
+
+        remove(s, &keys);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let (mut s, keys) = new_session("lookup", &[("apple", "a fruit")]);
+        assert_eq!(run(&mut s, "get apple", None), "apple\na fruit\n");
+        // the other libraries are tried
+        run(&mut s, &format!("use {}", keys[1]), None);
+        assert_eq!(
+            run(&mut s, "get apple", None),
+            format!("apple (in {})\na fruit\n", keys[0])
+        );
+        assert_eq!(run(&mut s, "get pear", None), "pear not found\n");
+
+        remove(s, &keys);
+    }
+
+    #[test]
+    fn test_search() {
+        let (mut s, keys) = new_session("search", &[("apple", ""), ("apply", ""), ("pear", "")]);
+        assert_eq!(run(&mut s, "search apl", None), "apple\napply\n");
+        run(&mut s, &format!("use {}", keys[1]), None);
+        assert_eq!(run(&mut s, "search apl", None), "");
+        assert_eq!(
+            run(&mut s, "search -a pea", None),
+            format!("pear\t{}\n", keys[0])
+        );
+        assert!(run(&mut s, "search", None).starts_with("usage"));
+
+        remove(s, &keys);
+    }
+
+    #[test]
+    fn test_add_edit() {
+        let (mut s, keys) = new_session("edit", &[]);
+        run(&mut s, "add apple", Some("a fruit\nred or green"));
+        assert_eq!(
+            s.lib.get("apple", &keys[0]).unwrap().message,
+            "a fruit\nred or green"
+        );
+        assert!(run(&mut s, "add apple", Some("again")).contains("already exists"));
+
+        // the edit starts with the message, a canceled edit keeps it
+        let mut initial = String::new();
+        s.exec("edit apple", |init| {
+            initial = init.to_owned();
+            Ok(None)
+        })
+        .unwrap();
+        assert_eq!(initial, "a fruit\nred or green");
+        assert_eq!(
+            s.lib.get("apple", &keys[0]).unwrap().message,
+            "a fruit\nred or green"
+        );
+        run(&mut s, "edit apple", Some("a red fruit"));
+        assert_eq!(s.lib.get("apple", &keys[0]).unwrap().message, "a red fruit");
+        assert!(run(&mut s, "edit pear", Some("")).starts_with("error"));
+
+        remove(s, &keys);
+    }
+
+    #[test]
+    fn test_move() {
+        let (mut s, keys) = new_session("move", &[("apple", "a fruit")]);
+        assert_eq!(run(&mut s, &format!("mv apple {}", keys[1]), None), "");
+        assert!(s.lib.get("apple", &keys[0]).is_err());
+        assert_eq!(s.lib.get("apple", &keys[1]).unwrap().message, "a fruit");
+        assert!(run(&mut s, "mv apple nothing", None).starts_with("error"));
+
+        remove(s, &keys);
+    }
+
+    #[test]
+    fn test_complete() {
+        let (s, keys) = new_session("complete", &[("apple", ""), ("apply", ""), ("pear", "")]);
+        let (candidates, words) = s.candidates();
+        assert_eq!(candidates, keys);
+        let h = ShellHelper {
+            words,
+            keys: candidates,
+            files: FilenameCompleter::new(),
+            editing: Cell::new(false),
+        };
+        assert_eq!(complete(&h, "us"), vec!["use"]);
+        assert_eq!(complete(&h, "get ap"), vec!["apple", "apply"]);
+        assert_eq!(complete(&h, "mv pear "), keys);
+        assert_eq!(complete(&h, "use "), keys);
+        assert!(complete(&h, "list ap").is_empty());
+
+        // nothing is completed in a message
+        h.editing.set(true);
+        assert!(complete(&h, "get ap").is_empty());
+
+        remove(s, &keys);
+    }
+
+    #[test]
+    fn test_message() {
+        assert!(finished("."));
+        assert!(finished("a fruit\n."));
+        assert!(!finished("a fruit."));
+        assert!(!finished("a fruit\n.\nmore"));
+        assert_eq!(message_of("a fruit\nred\n."), "a fruit\nred");
+        assert_eq!(message_of("."), "");
+    }
+}
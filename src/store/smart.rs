@@ -0,0 +1,450 @@
+// saved searches as smart libraries
+//
+// a smart library keeps a query instead of words, opening it runs the query
+// across the stores of the library. it reads like a store, a word in it is
+// read from and written back to the store it's found in, the first store by
+// key if more than one has it
+use super::attachment::Attachment;
+use super::links::{self, Link};
+use super::review::{self, Schedule, DAY};
+use super::tags::Expr;
+use super::{
+    search, Conflict, Entry, InternalError, Read, Record, Snapshot, Transaction, Trashed,
+    WordLibrary, World,
+};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// the saved searches of a workspace, not listed as a library
+pub const SEARCHES_FILE: &str = ".searches";
+
+/// what the words of a smart library are
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    /// fuzzy matched with the word names, empty for any
+    pub pattern: String,
+    /// words all found in the name or message, case insensitive, empty for any
+    pub text: String,
+    pub tags: Option<Expr>,
+    /// due for review in the days, 0 for due now
+    pub due: Option<i64>,
+    /// keys of the stores searched, every store if empty
+    pub libraries: Vec<String>,
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.pattern.is_empty() {
+            parts.push(format!("name ~ {}", self.pattern));
+        }
+        if !self.text.is_empty() {
+            parts.push(format!("text {:?}", self.text));
+        }
+        if let Some(tags) = &self.tags {
+            parts.push(format!("tags {}", tags));
+        }
+        match self.due {
+            Some(0) => parts.push(String::from("due now")),
+            Some(days) => parts.push(format!("due in {} days", days)),
+            None => {}
+        }
+        if !self.libraries.is_empty() {
+            parts.push(format!("in {}", self.libraries.join(", ")));
+        }
+        match parts.is_empty() {
+            true => write!(f, "every word"),
+            false => write!(f, "{}", parts.join(", ")),
+        }
+    }
+}
+
+impl Query {
+    /// the query of the options, as `--name sch --tag verb AND NOT mastered --due 3`
+    pub fn from_args(args: &[&str]) -> Result<Query, String> {
+        let mut q = Query::default();
+        let mut i = 0;
+        while i < args.len() {
+            let opt = args[i];
+            let end = args[i + 1..]
+                .iter()
+                .position(|a| a.starts_with("--"))
+                .map(|n| i + 1 + n)
+                .unwrap_or(args.len());
+            let value = args[i + 1..end].join(" ");
+            if value.is_empty() {
+                return Err(format!("{} without a value", opt));
+            }
+            match opt {
+                "--name" => q.pattern = value,
+                "--text" => q.text = value,
+                "--tag" => q.tags = Some(value.parse()?),
+                "--due" => {
+                    q.due = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid days {}", value))?,
+                    )
+                }
+                "--lib" => q
+                    .libraries
+                    .extend(args[i + 1..end].iter().map(|s| s.to_string())),
+                _ => return Err(format!("unknow option {}", opt)),
+            }
+            i = end;
+        }
+        Ok(q)
+    }
+
+    // the score of the name for the pattern, None if the word is not matched by
+    // the pattern or the text
+    fn score(&self, word: &World) -> Option<usize> {
+        let text = format!("{}\n{}", word.name, word.message).to_lowercase();
+        if !self
+            .text
+            .split_whitespace()
+            .all(|t| text.contains(&t.to_lowercase()))
+        {
+            return None;
+        }
+        search::score(&self.pattern, &word.name)
+    }
+
+    // the tags and the schedule are read only when they are queried
+    fn needs_entry(&self) -> bool {
+        self.tags.is_some() || self.due.is_some()
+    }
+
+    /// the query holds for the word at the time now
+    pub fn matches(&self, entry: &Entry, now: i64) -> bool {
+        let due = entry.schedule.as_ref().map(|s| s.due).unwrap_or(0);
+        self.score(&entry.word).is_some()
+            && self.tags.as_ref().is_none_or(|e| e.matches(&entry.tags))
+            && self.due.is_none_or(|days| due <= now + days * DAY)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.pattern,
+            "text": self.text,
+            "tag": self.tags.as_ref().map(|e| e.to_string()),
+            "due": self.due,
+            "libraries": self.libraries,
+        })
+    }
+
+    pub fn from_json(v: &Value) -> Result<Query, String> {
+        let str = |name: &str| v[name].as_str().unwrap_or("").to_owned();
+        Ok(Query {
+            pattern: str("name"),
+            text: str("text"),
+            tags: match v["tag"].as_str() {
+                Some(t) => Some(t.parse()?),
+                None => None,
+            },
+            due: v["due"].as_i64(),
+            libraries: v["libraries"]
+                .as_array()
+                .map(|ls| {
+                    ls.iter()
+                        .filter_map(|l| l.as_str().map(|l| l.to_owned()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// the saved searches of the workspace directory, or of the directory of a library file
+pub fn path(workspace: &str) -> PathBuf {
+    let p = Path::new(workspace);
+    let dir = if p.is_dir() {
+        p
+    } else {
+        p.parent().unwrap_or_else(|| Path::new(""))
+    };
+    dir.join(SEARCHES_FILE)
+}
+
+/// the saved searches of the gui
+pub fn home_path() -> Option<PathBuf> {
+    std::env::var("HOME")
+        .ok()
+        .map(|home| Path::new(&home).join(".word_library_searches"))
+}
+
+/// the saved searches in the file by name, none if there is no file
+pub fn load(path: &Path) -> Result<Vec<(String, Query)>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(format!("read {} fail, {}", path.display(), err)),
+    };
+    let invalid = |e: String| format!("invalid search file {}, {}", path.display(), e);
+    let v: Value = serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+    let searches = v
+        .as_object()
+        .ok_or_else(|| invalid(String::from("not an object")))?;
+    searches
+        .iter()
+        .map(|(name, q)| Ok((name.clone(), Query::from_json(q).map_err(invalid)?)))
+        .collect()
+}
+
+pub fn save(path: &Path, searches: &[(String, Query)]) -> Result<(), String> {
+    let v: Map<String, Value> = searches
+        .iter()
+        .map(|(name, q)| (name.clone(), q.to_json()))
+        .collect();
+    std::fs::write(path, Value::Object(v).to_string())
+        .map_err(|e| format!("write {} fail, {}", path.display(), e))
+}
+
+/// a saved search of the library read as a store
+pub struct Smart<'a, T, E, F>
+where
+    F: Transaction<T, InternalError<E>>,
+{
+    pub(super) lib: &'a WordLibrary<T, E, F>,
+    pub(super) query: &'a Query,
+}
+
+impl<'a, T, E: fmt::Debug, F: Transaction<T, InternalError<E>>> Smart<'a, T, E, F> {
+    // the keys of the stores searched, sorted. the libraries of the query not
+    // opened are skipped
+    fn keys(&self) -> Vec<String> {
+        let opened = self.lib.keys();
+        if self.query.libraries.is_empty() {
+            return opened;
+        }
+        let mut keys: Vec<String> = self
+            .query
+            .libraries
+            .iter()
+            .filter(|k| opened.contains(k))
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// the words the query holds for with the key of their stores, best match
+    /// first, a word in more than one store is taken from the first one
+    pub fn words(&self) -> Result<Vec<(String, World)>, InternalError<E>> {
+        let now = review::now();
+        let mut seen = HashSet::new();
+        let mut res: Vec<(usize, String, World)> = Vec::new();
+        for key in self.keys() {
+            let s = self.lib.get_store(&key)?;
+            for word in s.list(0)? {
+                let score = match self.query.score(&word) {
+                    Some(score) => score,
+                    None => continue,
+                };
+                if self.query.needs_entry() && !self.query.matches(&s.entry(&word.name)?, now) {
+                    continue;
+                }
+                if seen.insert(word.name.clone()) {
+                    res.push((score, key.clone(), word));
+                }
+            }
+        }
+        res.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.2.name.cmp(&b.2.name)));
+        Ok(res.into_iter().map(|(_, k, w)| (k, w)).collect())
+    }
+
+    /// the key of the store the word is read from
+    pub fn owner(&self, name: &str) -> Result<String, InternalError<E>> {
+        let now = review::now();
+        for key in self.keys() {
+            match self.lib.get_store(&key)?.entry(name) {
+                Ok(entry) if self.query.matches(&entry, now) => return Ok(key),
+                Ok(_) | Err(InternalError::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Err(InternalError::NotFound)
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>, InternalError<E>> {
+        self.words()?
+            .into_iter()
+            .map(|(key, w)| self.lib.get_store(&key)?.entry(&w.name))
+            .collect()
+    }
+}
+
+fn truncate<V>(mut list: Vec<V>, size: i64) -> Vec<V> {
+    if size > 0 {
+        list.truncate(size as usize);
+    }
+    list
+}
+
+impl<'a, T, E: fmt::Debug, F: Transaction<T, InternalError<E>>> Read<T, InternalError<E>>
+    for Smart<'a, T, E, F>
+{
+    fn get(&self, id: &str) -> Result<World, InternalError<E>> {
+        self.lib.get_store(&self.owner(id)?)?.get(id)
+    }
+
+    fn list(&self, size: i64) -> Result<Vec<World>, InternalError<E>> {
+        let words = self.words()?.into_iter().map(|(_, w)| w).collect();
+        Ok(truncate(words, size))
+    }
+
+    fn scan(&self, after: &str, size: i64) -> Result<Vec<World>, InternalError<E>> {
+        let mut words: Vec<World> = self
+            .words()?
+            .into_iter()
+            .map(|(_, w)| w)
+            .filter(|w| w.name.as_str() > after)
+            .collect();
+        words.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(truncate(words, size))
+    }
+
+    fn version(&self, id: &str) -> Result<i64, InternalError<E>> {
+        self.lib.get_store(&self.owner(id)?)?.version(id)
+    }
+
+    fn schedule(&self, id: &str) -> Result<Schedule, InternalError<E>> {
+        self.lib.get_store(&self.owner(id)?)?.schedule(id)
+    }
+
+    fn due(&self, now: i64, size: i64) -> Result<Vec<World>, InternalError<E>> {
+        let mut due: Vec<(i64, World)> = self
+            .entries()?
+            .into_iter()
+            .map(|e| (e.schedule.map(|s| s.due).unwrap_or(0), e.word))
+            .filter(|(due, _)| *due <= now)
+            .collect();
+        due.sort_by_key(|(due, _)| *due);
+        Ok(truncate(due.into_iter().map(|(_, w)| w).collect(), size))
+    }
+
+    fn entry(&self, id: &str) -> Result<Entry, InternalError<E>> {
+        self.lib.get_store(&self.owner(id)?)?.entry(id)
+    }
+
+    fn metadata(&self) -> Result<Vec<(String, String)>, InternalError<E>> {
+        Ok(vec![(String::from("description"), self.query.to_string())])
+    }
+
+    // a smart library has no words of its own to sync, conflict or delete
+    fn records(&self, _after: &str, _size: i64) -> Result<Vec<Record>, InternalError<E>> {
+        Ok(Vec::new())
+    }
+
+    fn conflicts(&self) -> Result<Vec<Conflict>, InternalError<E>> {
+        Ok(Vec::new())
+    }
+
+    fn history(&self, id: &str) -> Result<Vec<Snapshot>, InternalError<E>> {
+        self.lib.get_store(&self.owner(id)?)?.history(id)
+    }
+
+    fn deleted(&self, _size: i64) -> Result<Vec<Snapshot>, InternalError<E>> {
+        Ok(Vec::new())
+    }
+
+    fn trashed(&self, _size: i64) -> Result<Vec<Trashed>, InternalError<E>> {
+        Ok(Vec::new())
+    }
+
+    fn last_trashed(&self) -> Result<i64, InternalError<E>> {
+        Ok(0)
+    }
+
+    fn tags(&self) -> Result<Vec<(String, i64)>, InternalError<E>> {
+        let mut counts: HashMap<String, i64> = HashMap::new();
+        for e in self.entries()? {
+            for tag in e.tags {
+                *counts.entry(tag).or_insert(0) += 1;
+            }
+        }
+        let mut res: Vec<(String, i64)> = counts.into_iter().collect();
+        res.sort();
+        Ok(res)
+    }
+
+    fn word_tags(&self, id: &str) -> Result<Vec<String>, InternalError<E>> {
+        self.lib.get_store(&self.owner(id)?)?.word_tags(id)
+    }
+
+    fn attachments(&self, id: &str) -> Result<Vec<Attachment>, InternalError<E>> {
+        self.lib.get_store(&self.owner(id)?)?.attachments(id)
+    }
+
+    fn attachment(&self, id: &str, name: &str) -> Result<Attachment, InternalError<E>> {
+        self.lib.get_store(&self.owner(id)?)?.attachment(id, name)
+    }
+
+    fn links(&self, id: &str) -> Result<Vec<Link>, InternalError<E>> {
+        self.lib.get_store(&self.owner(id)?)?.links(id)
+    }
+
+    // the links of the words of the smart library, read from their messages
+    fn backlinks(&self, target: &str) -> Result<Vec<(String, Link)>, InternalError<E>> {
+        let mut res = self.all_links()?;
+        res.retain(|(_, link)| link.word == target);
+        Ok(res)
+    }
+
+    fn all_links(&self) -> Result<Vec<(String, Link)>, InternalError<E>> {
+        let mut words: Vec<World> = self.words()?.into_iter().map(|(_, w)| w).collect();
+        words.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(words
+            .into_iter()
+            .flat_map(|w| {
+                links::parse(&w.message)
+                    .into_iter()
+                    .map(move |link| (w.name.clone(), link))
+            })
+            .collect())
+    }
+
+    fn tagged(&self, tag: &str) -> Result<Vec<String>, InternalError<E>> {
+        let mut names: Vec<String> = self
+            .entries()?
+            .into_iter()
+            .filter(|e| e.tags.iter().any(|t| t == tag))
+            .map(|e| e.word.name)
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let q = Query::from_args(&["--name", "sch", "--tag", "verb", "AND", "NOT", "b1"]).unwrap();
+        assert_eq!(q.pattern, "sch");
+        assert_eq!(q.to_string(), "name ~ sch, tags (verb AND NOT b1)");
+        assert_eq!(Query::from_json(&q.to_json()).unwrap(), q);
+        assert!(Query::from_args(&["--due"]).is_err());
+        assert!(Query::from_args(&["--due", "soon"]).is_err());
+
+        let mut entry = Entry::from(World {
+            name: String::from("school"),
+            message: String::from("A place to learn"),
+        });
+        entry.tags = vec![String::from("verb")];
+        let q = Query::from_args(&["--text", "learn", "place", "--due", "0"]).unwrap();
+        assert!(q.matches(&entry, 0));
+        entry.schedule = Some(Schedule {
+            due: 2 * DAY,
+            ..Schedule::default()
+        });
+        assert!(!q.matches(&entry, 0));
+        assert!(Query::from_args(&["--due", "2"])
+            .unwrap()
+            .matches(&entry, 0));
+    }
+}
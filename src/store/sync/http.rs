@@ -0,0 +1,136 @@
+// a library served by `wordlib serve` as the peer of a sync, the records are
+// read and written by GET and POST /libraries/<name>/records
+use super::{record_from, record_json, Peer, Record};
+use crate::store::{Error, InternalError};
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+// seconds to wait for the peer
+const TIMEOUT: u64 = 30;
+
+pub struct Http {
+    /// host:port of the server
+    addr: String,
+    /// path of the library, /libraries/<name>
+    path: String,
+}
+
+fn fail<E>(code: i32, message: String) -> InternalError<E> {
+    InternalError::Unknow(Error {
+        message,
+        code,
+        err: None,
+    })
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+impl Http {
+    /// a peer of http://host:port/libraries/<name>
+    pub fn parse(url: &str) -> Result<Http, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("{} is not an http url", url))?;
+        let (addr, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let path = path.trim_end_matches('/');
+        match path.split('/').collect::<Vec<&str>>().as_slice() {
+            ["libraries", name] if !name.is_empty() && !addr.is_empty() => Ok(Http {
+                addr: addr.to_owned(),
+                path: format!("/{}", path),
+            }),
+            _ => Err(format!(
+                "{} should be http://host:port/libraries/<name>",
+                url
+            )),
+        }
+    }
+
+    // send a request, the json body of a 2xx reply
+    fn request<E>(&self, method: &str, path: &str, body: &str) -> Result<Value, InternalError<E>> {
+        let io = |e: std::io::Error| fail(0, format!("request {} fail, {}", self.addr, e));
+        let mut stream = TcpStream::connect(&self.addr).map_err(io)?;
+        let timeout = Some(Duration::from_secs(TIMEOUT));
+        stream.set_read_timeout(timeout).map_err(io)?;
+        stream.set_write_timeout(timeout).map_err(io)?;
+        let req = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\
+            Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            self.addr,
+            body.len(),
+            body
+        );
+        stream.write_all(req.as_bytes()).map_err(io)?;
+        let mut resp = Vec::new();
+        stream.read_to_end(&mut resp).map_err(io)?;
+
+        let resp = String::from_utf8_lossy(&resp);
+        let (head, body) = resp
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| fail(0, String::from("invalid http reply")))?;
+        let status: i32 = head
+            .split(' ')
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| fail(0, String::from("invalid http status")))?;
+        let value: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+        if !(200..300).contains(&status) {
+            let msg = value["error"].as_str().unwrap_or(body).to_owned();
+            return Err(fail(status, msg));
+        }
+        Ok(value)
+    }
+}
+
+impl<E> Peer<E> for Http {
+    fn records(&self, after: &str, size: i64) -> Result<Vec<Record>, InternalError<E>> {
+        let path = format!(
+            "{}/records?after={}&limit={}",
+            self.path,
+            percent_encode(after),
+            size
+        );
+        let value = self.request("GET", &path, "")?;
+        value
+            .as_array()
+            .ok_or_else(|| fail(0, String::from("records should be an array")))?
+            .iter()
+            .map(|v| record_from(v).map_err(|e| fail(0, e)))
+            .collect()
+    }
+
+    fn apply(&self, records: &[Record]) -> Result<(), InternalError<E>> {
+        let body = Value::Array(records.iter().map(record_json).collect()).to_string();
+        self.request("POST", &format!("{}/records", self.path), &body)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let h = Http::parse("http://127.0.0.1:8737/libraries/english/").unwrap();
+        assert_eq!(
+            (h.addr.as_str(), h.path.as_str()),
+            ("127.0.0.1:8737", "/libraries/english")
+        );
+        assert!(Http::parse("http://127.0.0.1:8737/english").is_err());
+        assert!(Http::parse("english.sql").is_err());
+        assert_eq!(percent_encode("a b/ü"), "a%20b%2F%C3%BC");
+    }
+}
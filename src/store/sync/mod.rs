@@ -0,0 +1,691 @@
+// two-way sync of the copies of a library
+//
+// every write of a store records the time and the device of the change of a
+// word's message and of its schedule, a deleted word keeps its revision as a
+// tombstone. A sync reads the records of both copies in name order, takes the
+// newer revision of every field, and writes the merged records back to the
+// copies that differ. Fields changed on both copies since their last sync are
+// kept as conflicts in the local copy for a manual review.
+//
+// the revisions are ordered by wall clock time, then by device id
+pub mod http;
+
+use super::review::Schedule;
+use super::{InternalError, Read, Store, Transaction, World, Write};
+use crate::db::sqlite::store::Sqlite;
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const USAGE: &str = r#"  sync <library> <peer> [--dry-run]
+                     two-way sync of the library file with another copy, a library file
+                     or a library served by `wordlib serve` as http://host:port/libraries/<name>,
+                     the tags and attachments stay on each copy, a word deleted by the peer
+                     loses them
+  conflicts <library> [--dismiss <id> | --take <id>]
+                     list the conflicts kept by sync, dismiss one or take its value"#;
+
+// records read from a copy at once
+const BATCH: i64 = 1000;
+
+/// the change of a field
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Revision {
+    /// milliseconds since the unix epoch, 0 for a word written before revisions
+    pub updated_at: i64,
+    pub device: String,
+}
+
+impl Revision {
+    /// a change made now on this device
+    pub fn now() -> Revision {
+        Revision {
+            updated_at: now(),
+            device: device_id().to_owned(),
+        }
+    }
+}
+
+/// a word with the revisions of its fields
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    /// None for a deleted word
+    pub message: Option<String>,
+    pub message_rev: Revision,
+    pub schedule: Option<Schedule>,
+    pub schedule_rev: Revision,
+}
+
+impl Record {
+    // a word never written to the copy
+    fn absent(name: &str) -> Record {
+        Record {
+            name: name.to_owned(),
+            message: None,
+            message_rev: Revision::default(),
+            schedule: None,
+            schedule_rev: Revision::default(),
+        }
+    }
+}
+
+/// a field changed on both copies, the value lost by the sync is kept for review
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    /// 0 before it is stored
+    pub id: i64,
+    pub name: String,
+    /// "message" or "schedule"
+    pub field: String,
+    /// the value lost, None for a deleted word or a word never reviewed
+    pub value: Option<String>,
+    pub rev: Revision,
+    /// the value kept by the sync
+    pub kept: Option<String>,
+    pub recorded_at: i64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    /// records written to the local copy
+    pub pulled: usize,
+    /// records written to the peer
+    pub pushed: usize,
+    pub conflicts: usize,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pulled {}, pushed {}, conflicts {}",
+            self.pulled, self.pushed, self.conflicts
+        )
+    }
+}
+
+/// milliseconds since the unix epoch
+pub fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// the file keeping the id of this device
+pub fn device_path() -> Option<PathBuf> {
+    std::env::var("HOME")
+        .ok()
+        .map(|home| Path::new(&home).join(".word_library_device"))
+}
+
+fn new_device_id() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut h = std::collections::hash_map::RandomState::new().build_hasher();
+    h.write_i64(now());
+    h.write_u32(std::process::id());
+    format!("{:016x}", h.finish())
+}
+
+/// the id of this device, WORDLIB_DEVICE or an id created once and kept in device_path
+pub fn device_id() -> &'static str {
+    static ID: OnceLock<String> = OnceLock::new();
+    ID.get_or_init(|| {
+        if let Ok(id) = std::env::var("WORDLIB_DEVICE") {
+            return id;
+        }
+        let path = device_path();
+        if let Some(id) = path.as_ref().and_then(|p| std::fs::read_to_string(p).ok()) {
+            if !id.trim().is_empty() {
+                return id.trim().to_owned();
+            }
+        }
+        let id = new_device_id();
+        if let Some(p) = path {
+            if let Err(err) = std::fs::write(&p, &id) {
+                eprintln!("keep device id in {} fail, {}", p.display(), err);
+            }
+        }
+        id
+    })
+}
+
+pub fn schedule_json(s: &Schedule) -> Value {
+    json!({"due": s.due, "interval": s.interval, "ease": s.ease, "reps": s.reps})
+}
+
+pub fn schedule_from(v: &Value) -> Option<Schedule> {
+    Some(Schedule {
+        due: v["due"].as_i64()?,
+        interval: v["interval"].as_i64()?,
+        ease: v["ease"].as_f64()?,
+        reps: v["reps"].as_i64()?,
+    })
+}
+
+pub fn record_json(r: &Record) -> Value {
+    json!({
+        "name": r.name,
+        "message": r.message,
+        "message_at": r.message_rev.updated_at,
+        "message_device": r.message_rev.device,
+        "schedule": r.schedule.as_ref().map(schedule_json),
+        "schedule_at": r.schedule_rev.updated_at,
+        "schedule_device": r.schedule_rev.device,
+    })
+}
+
+pub fn record_from(v: &Value) -> Result<Record, String> {
+    let rev = |at: &str, device: &str| -> Result<Revision, String> {
+        Ok(Revision {
+            updated_at: v[at]
+                .as_i64()
+                .ok_or_else(|| format!("{} is not a number", at))?,
+            device: v[device].as_str().unwrap_or("").to_owned(),
+        })
+    };
+    Ok(Record {
+        name: v["name"].as_str().ok_or("record without name")?.to_owned(),
+        message: v["message"].as_str().map(|s| s.to_owned()),
+        message_rev: rev("message_at", "message_device")?,
+        schedule: match &v["schedule"] {
+            Value::Null => None,
+            s => Some(schedule_from(s).ok_or("invalid schedule")?),
+        },
+        schedule_rev: rev("schedule_at", "schedule_device")?,
+    })
+}
+
+/// the other copy of a library in a sync
+pub trait Peer<E> {
+    /// the records of the words and tombstones after the name, in name order
+    fn records(&self, after: &str, size: i64) -> Result<Vec<Record>, InternalError<E>>;
+    /// write the records in one transaction
+    fn apply(&self, records: &[Record]) -> Result<(), InternalError<E>>;
+}
+
+/// a store as the peer of a sync, for two library files
+pub struct StorePeer<'a, T, F, S: ?Sized> {
+    store: &'a S,
+    _marker: std::marker::PhantomData<(T, F)>,
+}
+
+impl<'a, T, F, S: ?Sized> StorePeer<'a, T, F, S> {
+    pub fn new(store: &'a S) -> Self {
+        StorePeer {
+            store,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, T, E, F, S> Peer<E> for StorePeer<'a, T, F, S>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    fn records(&self, after: &str, size: i64) -> Result<Vec<Record>, InternalError<E>> {
+        self.store.records(after, size)
+    }
+
+    fn apply(&self, records: &[Record]) -> Result<(), InternalError<E>> {
+        apply(self.store, records, &[], None)
+    }
+}
+
+/// write the records and conflicts in one transaction, and the time of the
+/// last sync with a peer when given
+pub fn apply<T, E, F, S>(
+    store: &S,
+    records: &[Record],
+    conflicts: &[Conflict],
+    synced: Option<(&str, i64)>,
+) -> Result<(), InternalError<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    let t = store.begin()?;
+    let res = (|| {
+        for r in records {
+            t.put_record(r)?;
+        }
+        for c in conflicts {
+            t.add_conflict(c)?;
+        }
+        if let Some((peer, at)) = synced {
+            t.set_metadata(&sync_key(peer), &at.to_string())?;
+        }
+        Ok(())
+    })();
+    match res {
+        Ok(()) => t.commit(),
+        Err(err) => {
+            t.rollback()?;
+            Err(err)
+        }
+    }
+}
+
+fn sync_key(peer: &str) -> String {
+    format!("sync:{}", peer)
+}
+
+/// the time of the last sync of the store with the peer, 0 if never
+pub fn last_sync<T, E, S>(store: &S, peer: &str) -> Result<i64, InternalError<E>>
+where
+    S: Read<T, InternalError<E>> + ?Sized,
+{
+    let key = sync_key(peer);
+    Ok(store
+        .metadata()?
+        .into_iter()
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0))
+}
+
+// the records of a copy in name order
+struct Cursor {
+    buf: VecDeque<Record>,
+    after: String,
+    done: bool,
+}
+
+impl Cursor {
+    fn new() -> Cursor {
+        Cursor {
+            buf: VecDeque::new(),
+            after: String::new(),
+            done: false,
+        }
+    }
+
+    fn next<E>(
+        &mut self,
+        fetch: impl Fn(&str, i64) -> Result<Vec<Record>, InternalError<E>>,
+    ) -> Result<Option<Record>, InternalError<E>> {
+        if self.buf.is_empty() && !self.done {
+            let rs = fetch(&self.after, BATCH)?;
+            self.done = (rs.len() as i64) < BATCH;
+            self.buf.extend(rs);
+        }
+        let record = self.buf.pop_front();
+        if let Some(r) = &record {
+            self.after = r.name.clone();
+        }
+        Ok(record)
+    }
+}
+
+// the value lost by a field, its revision and the value kept
+type Lost = (Option<String>, Revision, Option<String>);
+
+fn schedule_text(s: &Option<Schedule>) -> Option<String> {
+    s.as_ref().map(|s| schedule_json(s).to_string())
+}
+
+// the winner of a field between the local copy a and the peer b, the newer
+// revision wins, the greater value for the same revision so both copies agree.
+// A conflict is kept when both copies changed the field since the last sync.
+fn field<V: Clone>(
+    a: (&Option<V>, &Revision),
+    b: (&Option<V>, &Revision),
+    text: impl Fn(&Option<V>) -> Option<String>,
+    since: i64,
+) -> ((Option<V>, Revision), Option<Lost>) {
+    let (ta, tb) = (text(a.0), text(b.0));
+    let a_wins = (a.1, &ta).cmp(&(b.1, &tb)) != Ordering::Less;
+    let (win, lose, win_text, lose_text) = if a_wins {
+        (a, b, ta, tb)
+    } else {
+        (b, a, tb, ta)
+    };
+    let concurrent = since == 0 || (a.1.updated_at > since && b.1.updated_at > since);
+    let conflict = if win_text != lose_text && concurrent {
+        Some((lose_text, lose.1.clone(), win_text))
+    } else {
+        None
+    };
+    ((win.0.clone(), win.1.clone()), conflict)
+}
+
+// the merged record of a word and its conflicts
+fn merge_record(
+    a: Option<&Record>,
+    b: Option<&Record>,
+    since: i64,
+    at: i64,
+) -> (Record, Vec<Conflict>) {
+    let name = a.or(b).map(|r| r.name.as_str()).unwrap_or("");
+    let (ra, rb) = (
+        a.cloned().unwrap_or_else(|| Record::absent(name)),
+        b.cloned().unwrap_or_else(|| Record::absent(name)),
+    );
+    let both = a.is_some() && b.is_some();
+
+    let mut conflicts = Vec::new();
+    let mut conflict = |field: &str, c: Option<Lost>| {
+        if let (true, Some((value, rev, kept))) = (both, c) {
+            conflicts.push(Conflict {
+                id: 0,
+                name: name.to_owned(),
+                field: field.to_owned(),
+                value,
+                rev,
+                kept,
+                recorded_at: at,
+            });
+        }
+    };
+
+    let ((message, message_rev), c) = field(
+        (&ra.message, &ra.message_rev),
+        (&rb.message, &rb.message_rev),
+        |m| m.clone(),
+        since,
+    );
+    conflict("message", c);
+    let ((schedule, schedule_rev), c) = field(
+        (&ra.schedule, &ra.schedule_rev),
+        (&rb.schedule, &rb.schedule_rev),
+        schedule_text,
+        since,
+    );
+    // a schedule missing on a copy is no review, not a change
+    if ra.schedule.is_some() && rb.schedule.is_some() && message.is_some() {
+        conflict("schedule", c);
+    }
+
+    let record = Record {
+        name: name.to_owned(),
+        // a deleted word has no schedule
+        schedule: if message.is_some() { schedule } else { None },
+        message,
+        message_rev,
+        schedule_rev,
+    };
+    (record, conflicts)
+}
+
+/// sync the local store with the peer, both copies hold the merged words
+/// after it. The peer is written first, a sync failing after it only leaves
+/// the local copy behind until the next sync.
+pub fn sync<T, E, F, S, P>(
+    local: &S,
+    remote: &P,
+    peer: &str,
+    dry_run: bool,
+) -> Result<Report, InternalError<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+    P: Peer<E> + ?Sized,
+{
+    let since = last_sync(local, peer)?;
+    let at = now();
+    let (mut pull, mut push, mut conflicts) = (Vec::new(), Vec::new(), Vec::new());
+
+    let (mut ca, mut cb) = (Cursor::new(), Cursor::new());
+    let fetch_a = |after: &str, size: i64| local.records(after, size);
+    let fetch_b = |after: &str, size: i64| remote.records(after, size);
+    let (mut x, mut y) = (ca.next(fetch_a)?, cb.next(fetch_b)?);
+    while x.is_some() || y.is_some() {
+        let order = match (&x, &y) {
+            (Some(a), Some(b)) => a.name.cmp(&b.name),
+            (Some(_), None) => Ordering::Less,
+            _ => Ordering::Greater,
+        };
+        let (a, b) = match order {
+            Ordering::Less => (x.take(), None),
+            Ordering::Greater => (None, y.take()),
+            Ordering::Equal => (x.take(), y.take()),
+        };
+        let (record, cs) = merge_record(a.as_ref(), b.as_ref(), since, at);
+        if a.as_ref() != Some(&record) {
+            pull.push(record.clone());
+        }
+        if b.as_ref() != Some(&record) {
+            push.push(record);
+        }
+        conflicts.extend(cs);
+
+        if a.is_some() {
+            x = ca.next(fetch_a)?;
+        }
+        if b.is_some() {
+            y = cb.next(fetch_b)?;
+        }
+    }
+
+    let report = Report {
+        pulled: pull.len(),
+        pushed: push.len(),
+        conflicts: conflicts.len(),
+    };
+    if !dry_run {
+        if !push.is_empty() {
+            remote.apply(&push)?;
+        }
+        apply(local, &pull, &conflicts, Some((peer, at)))?;
+    }
+    Ok(report)
+}
+
+/// write the value lost in a conflict as a new change, and remove the conflict
+pub fn take<T, E, F, S>(store: &S, conflict: &Conflict) -> Result<(), InternalError<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    let t = store.begin()?;
+    let res = (|| {
+        match (conflict.field.as_str(), &conflict.value) {
+            ("message", Some(message)) => {
+                let word = World {
+                    name: conflict.name.clone(),
+                    message: message.clone(),
+                };
+                match t.version(&word.name) {
+                    Ok(_) => t.update(&word)?,
+                    Err(InternalError::NotFound) => t.insert(&word)?,
+                    Err(err) => return Err(err),
+                };
+            }
+            ("message", None) => {
+                t.delete(&conflict.name)?;
+            }
+            (_, Some(s)) => {
+                let schedule = serde_json::from_str(s)
+                    .ok()
+                    .as_ref()
+                    .and_then(schedule_from)
+                    .unwrap_or_default();
+                t.set_schedule(&conflict.name, &schedule)?;
+            }
+            (_, None) => {}
+        }
+        t.remove_conflict(conflict.id)
+    })();
+    match res {
+        Ok(_) => t.commit(),
+        Err(err) => {
+            t.rollback()?;
+            Err(err)
+        }
+    }
+}
+
+/// the utc time of milliseconds since the unix epoch, as "2024-01-31 08:05"
+pub fn format_time(ms: i64) -> String {
+    let (days, secs) = (ms.div_euclid(86_400_000), ms.rem_euclid(86_400_000) / 1000);
+    // the civil date of days since 1970-01-01
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+fn open(path: &str) -> Result<Sqlite, String> {
+    if !Path::new(path).is_file() {
+        return Err(format!("{} is not a library file", path));
+    }
+    Sqlite::new(path.to_owned()).map_err(|e| format!("open {}: {}", path, e))
+}
+
+// a value of a conflict on one line
+fn short(value: &Option<String>) -> String {
+    match value {
+        Some(v) => v.replace('\n', " "),
+        None => String::from("(none)"),
+    }
+}
+
+/// sync <library> <peer> [--dry-run] and conflicts <library> [options]
+pub fn command(args: &[String]) -> Result<(), String> {
+    let strs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    match strs.as_slice() {
+        ["sync", path, peer, opts @ ..] => {
+            let dry_run = match opts {
+                [] => false,
+                ["--dry-run"] => true,
+                _ => return Err(USAGE.to_owned()),
+            };
+            let local = open(path)?;
+            let res = if peer.starts_with("http://") {
+                let remote = http::Http::parse(peer)?;
+                sync(&local, &remote, peer, dry_run)
+            } else {
+                // the same peer is known by its absolute path from any directory
+                let id = std::fs::canonicalize(peer)
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_else(|_| peer.to_string());
+                let remote = open(peer)?;
+                sync(&local, &StorePeer::new(&remote), &id, dry_run)
+            };
+            let report = res.map_err(|e| format!("sync fail, {}", e))?;
+            if dry_run {
+                println!("dry run: {}", report);
+            } else {
+                println!("{}", report);
+            }
+        }
+        ["conflicts", path] => {
+            let local = open(path)?;
+            for c in local.conflicts().map_err(|e| e.to_string())? {
+                // a word written before the revisions has no time of change
+                let when = match c.rev.updated_at {
+                    0 => String::from("unknown time"),
+                    at => format!("{} on {}", format_time(at), c.rev.device),
+                };
+                println!(
+                    "{:<6}{} {}, changed at {}\n      lost: {}\n      kept: {}",
+                    c.id,
+                    c.name,
+                    c.field,
+                    when,
+                    short(&c.value),
+                    short(&c.kept)
+                );
+            }
+        }
+        ["conflicts", path, op @ ("--dismiss" | "--take"), id] => {
+            let local = open(path)?;
+            let id: i64 = id.parse().map_err(|_| format!("invalid id {}", id))?;
+            let conflicts = local.conflicts().map_err(|e| e.to_string())?;
+            let c = conflicts
+                .iter()
+                .find(|c| c.id == id)
+                .ok_or_else(|| format!("conflict {} not found", id))?;
+            let res = if *op == "--take" {
+                take(&local, c)
+            } else {
+                local.remove_conflict(id).map(|_| ())
+            };
+            res.map_err(|e| e.to_string())?;
+        }
+        _ => return Err(USAGE.to_owned()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(path: &str) -> Sqlite {
+        let _ = std::fs::remove_file(path);
+        Sqlite::new(path.to_owned()).unwrap()
+    }
+
+    fn word(name: &str, message: &str) -> World {
+        // revisions of the writes are in milliseconds
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        World {
+            name: name.to_owned(),
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_sync() {
+        let (a, b) = (
+            store("target/test_sync_a.sql"),
+            store("target/test_sync_b.sql"),
+        );
+        a.insert(&word("apple", "fruit")).unwrap();
+        a.insert(&word("book", "read")).unwrap();
+        b.insert(&word("book", "to reserve")).unwrap();
+        b.insert(&word("cat", "pet")).unwrap();
+        b.set_schedule("cat", &Schedule::default().next(4, 100))
+            .unwrap();
+
+        let peer = StorePeer::new(&b);
+        let report = sync(&a, &peer, "b", true).unwrap();
+        assert_eq!((report.pulled, report.pushed), (2, 1));
+        assert!(a.get("cat").is_err());
+
+        // the newer book of b wins, the change of a is kept as a conflict
+        let report = sync(&a, &peer, "b", false).unwrap();
+        assert_eq!(report.conflicts, 1);
+        assert_eq!(a.get("book").unwrap().message, "to reserve");
+        assert_eq!(b.get("apple").unwrap().message, "fruit");
+        assert_eq!(a.schedule("cat").unwrap(), b.schedule("cat").unwrap());
+        let conflicts = a.conflicts().unwrap();
+        assert_eq!(conflicts[0].value.as_deref(), Some("read"));
+        assert_eq!(conflicts[0].kept.as_deref(), Some("to reserve"));
+
+        // changes on one copy since the last sync are no conflict
+        a.update(&word("apple", "a red fruit")).unwrap();
+        b.delete("cat").unwrap();
+        let report = sync(&a, &peer, "b", false).unwrap();
+        assert_eq!((report.pulled, report.pushed, report.conflicts), (1, 1, 0));
+        assert!(a.get("cat").is_err());
+        assert_eq!(b.get("apple").unwrap().message, "a red fruit");
+        assert_eq!(a.records("", 0).unwrap(), b.records("", 0).unwrap());
+
+        take(&a, &conflicts[0]).unwrap();
+        assert!(a.conflicts().unwrap().is_empty());
+        sync(&a, &peer, "b", false).unwrap();
+        assert_eq!(b.get("book").unwrap().message, "read");
+        let report = sync(&a, &peer, "b", false).unwrap();
+        assert_eq!((report.pulled, report.pushed), (0, 0));
+    }
+}
@@ -0,0 +1,212 @@
+// tags of the words
+//
+// a word has any number of tags, a tag is a word without spaces or brackets.
+// words are filtered by a tag expression such as `verb AND NOT mastered`,
+// AND binds tighter than OR and tags next to each other are joined by AND
+use super::{Error, InternalError, Read};
+use std::collections::HashSet;
+use std::fmt;
+
+const KEYWORDS: &[&str] = &["AND", "OR", "NOT"];
+
+/// a tag can be written in an expression as it is
+pub fn valid(tag: &str) -> bool {
+    !tag.is_empty()
+        && !KEYWORDS.contains(&tag)
+        && !tag
+            .chars()
+            .any(|c| c.is_whitespace() || c == '(' || c == ')')
+}
+
+/// the error of an invalid tag
+pub fn invalid<E>(tag: &str) -> InternalError<E> {
+    InternalError::Unknow(Error {
+        message: format!("invalid tag {:?}", tag),
+        code: 400,
+        err: None,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Tag(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Tag(t) => write!(f, "{}", t),
+            Expr::Not(e) => write!(f, "NOT {}", e),
+            Expr::And(a, b) => write!(f, "({} AND {})", a, b),
+            Expr::Or(a, b) => write!(f, "({} OR {})", a, b),
+        }
+    }
+}
+
+fn tokens(s: &str) -> Vec<String> {
+    s.replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(|t| t.to_owned())
+        .collect()
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    // or := and (OR and)*
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut e = self.and()?;
+        while self.peek() == Some("OR") {
+            self.next();
+            e = Expr::Or(Box::new(e), Box::new(self.and()?));
+        }
+        Ok(e)
+    }
+
+    // and := not (AND? not)*
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut e = self.not()?;
+        loop {
+            match self.peek() {
+                Some("AND") => {
+                    self.next();
+                }
+                Some("OR") | Some(")") | None => return Ok(e),
+                Some(_) => {}
+            }
+            e = Expr::And(Box::new(e), Box::new(self.not()?));
+        }
+    }
+
+    // not := NOT not | ( or ) | tag
+    fn not(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(t) if t == "NOT" => Ok(Expr::Not(Box::new(self.not()?))),
+            Some(t) if t == "(" => {
+                let e = self.or()?;
+                match self.next() {
+                    Some(t) if t == ")" => Ok(e),
+                    _ => Err(String::from("missing )")),
+                }
+            }
+            Some(t) if valid(&t) => Ok(Expr::Tag(t)),
+            Some(t) => Err(format!("unexpected {}", t)),
+            None => Err(String::from("unexpected end")),
+        }
+    }
+}
+
+impl std::str::FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Expr, String> {
+        let mut p = Parser {
+            tokens: tokens(s),
+            pos: 0,
+        };
+        let e = p.or()?;
+        match p.peek() {
+            None => Ok(e),
+            Some(t) => Err(format!("unexpected {}", t)),
+        }
+        .map_err(|e| format!("invalid tag expression {:?}, {}", s, e))
+    }
+}
+
+impl Expr {
+    /// the expression holds for a word with the tags
+    pub fn matches(&self, tags: &[String]) -> bool {
+        match self {
+            Expr::Tag(t) => tags.contains(t),
+            Expr::Not(e) => !e.matches(tags),
+            Expr::And(a, b) => a.matches(tags) && b.matches(tags),
+            Expr::Or(a, b) => a.matches(tags) || b.matches(tags),
+        }
+    }
+
+    fn negated(&self) -> bool {
+        match self {
+            Expr::Tag(_) => false,
+            Expr::Not(_) => true,
+            Expr::And(a, b) | Expr::Or(a, b) => a.negated() || b.negated(),
+        }
+    }
+
+    /// names of the words of the store the expression holds for
+    pub fn eval<T, E, S>(&self, store: &S) -> Result<HashSet<String>, InternalError<E>>
+    where
+        S: Read<T, InternalError<E>> + ?Sized,
+    {
+        // every word is needed only for a NOT
+        let all = match self.negated() {
+            true => store.list(0)?.into_iter().map(|w| w.name).collect(),
+            false => HashSet::new(),
+        };
+        self.eval_in(store, &all)
+    }
+
+    fn eval_in<T, E, S>(
+        &self,
+        store: &S,
+        all: &HashSet<String>,
+    ) -> Result<HashSet<String>, InternalError<E>>
+    where
+        S: Read<T, InternalError<E>> + ?Sized,
+    {
+        Ok(match self {
+            Expr::Tag(t) => store.tagged(t)?.into_iter().collect(),
+            Expr::Not(e) => all.difference(&e.eval_in(store, all)?).cloned().collect(),
+            Expr::And(a, b) => {
+                let a = a.eval_in(store, all)?;
+                a.intersection(&b.eval_in(store, all)?).cloned().collect()
+            }
+            Expr::Or(a, b) => {
+                let mut a = a.eval_in(store, all)?;
+                a.extend(b.eval_in(store, all)?);
+                a
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expr() {
+        let e: Expr = "verb AND NOT mastered".parse().unwrap();
+        assert_eq!(e.to_string(), "(verb AND NOT mastered)");
+        let tags = |ts: &[&str]| -> Vec<String> { ts.iter().map(|t| t.to_string()).collect() };
+        assert!(e.matches(&tags(&["verb", "b1"])));
+        assert!(!e.matches(&tags(&["verb", "mastered"])));
+
+        // AND binds tighter, tags next to each other are joined by AND
+        let e: Expr = "a OR b c".parse().unwrap();
+        assert_eq!(e.to_string(), "(a OR (b AND c))");
+        let e: Expr = "(a OR b) NOT c".parse().unwrap();
+        assert_eq!(e.to_string(), "((a OR b) AND NOT c)");
+
+        for bad in ["", "a AND", "(a", "a)", "NOT", "a OR OR b"] {
+            assert!(bad.parse::<Expr>().is_err(), "{}", bad);
+        }
+        assert!(valid("b1:grammar") && !valid("two words") && !valid("AND"));
+    }
+}
@@ -0,0 +1,260 @@
+// the trash of a workspace
+//
+// a word deleted from a library of the workspace is kept in the trash file of
+// the workspace with the library it came from, it can be put back there until
+// it is purged, words older than the retention are purged on open
+use super::sync::{format_time, now};
+use super::{Entry, InternalError, Store, Transaction, WordLibrary, World};
+use crate::db::sqlite::store::{Sqlite, Tansaction};
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = r#"  trash <workspace> [--restore <id> | --purge <id> | --expire [<days>]]
+                     list the deleted words of the workspace or a trash file, put one
+                     back to its library, drop one, or drop the words deleted more than
+                     days ago, default 30, 0 empties the trash"#;
+
+/// the trash file under a workspace directory, not listed as a library
+pub const TRASH_FILE: &str = ".trash";
+
+/// days a deleted word is kept
+pub const RETENTION_DAYS: i64 = 30;
+
+const DAY: i64 = 86_400_000;
+
+/// a word in the trash
+#[derive(Debug, Clone)]
+pub struct Trashed {
+    pub id: i64,
+    /// key of the library the word is deleted from
+    pub origin: String,
+    pub entry: Entry,
+    /// milliseconds since the unix epoch
+    pub deleted_at: i64,
+}
+
+/// the trash of the workspace directory, or of the directory of a library file
+pub fn path(workspace: &str) -> PathBuf {
+    let p = Path::new(workspace);
+    let dir = if p.is_dir() {
+        p
+    } else {
+        p.parent().unwrap_or_else(|| Path::new(""))
+    };
+    dir.join(TRASH_FILE)
+}
+
+/// the trash of the libraries opened one by one, as the gui does
+pub fn home_path() -> Option<PathBuf> {
+    std::env::var("HOME")
+        .ok()
+        .map(|home| Path::new(&home).join(".word_library_trash"))
+}
+
+/// the time before which the trashed words are expired
+pub fn expiry(days: i64) -> i64 {
+    now() - days * DAY
+}
+
+/// keep the deleted words of lib in the trash file at path, the expired words
+/// in it are purged
+pub fn attach(
+    lib: &mut WordLibrary<World, sqlite::Error, Tansaction>,
+    path: &Path,
+) -> Result<(), String> {
+    lib.set_trash(Box::new(open(path)?));
+    lib.purge_trash(expiry(RETENTION_DAYS))
+        .map(|_| ())
+        .map_err(|e| format!("purge trash {}: {}", path.display(), e))
+}
+
+/// delete the word in the transaction of its store, the entry is kept in trash,
+/// which is committed first so a failed delete leaves the word in both.
+/// return the id of the word in the trash
+pub fn discard<T, E, F, S>(
+    trash: &S,
+    t: &F,
+    name: &str,
+    origin: &str,
+) -> Result<i64, InternalError<E>>
+where
+    S: Store<T, InternalError<E>, F> + ?Sized,
+    F: Transaction<T, InternalError<E>>,
+{
+    let tt = trash.begin()?;
+    match discard_in(&tt, t, name, origin) {
+        Ok(id) => {
+            tt.commit()?;
+            Ok(id)
+        }
+        Err(err) => {
+            tt.rollback()?;
+            Err(err)
+        }
+    }
+}
+
+/// discard in the transaction tt of the trash, committed by the caller
+pub fn discard_in<T, E, F>(tt: &F, t: &F, name: &str, origin: &str) -> Result<i64, InternalError<E>>
+where
+    F: Transaction<T, InternalError<E>>,
+{
+    let trashed = Trashed {
+        id: 0,
+        origin: origin.to_owned(),
+        entry: t.entry(name)?,
+        deleted_at: now(),
+    };
+    tt.put_trashed(&trashed)?;
+    // the trash is locked by tt, no other word is put in since
+    let id = tt.last_trashed()?;
+    t.delete(name)?;
+    Ok(id)
+}
+
+fn open(path: &Path) -> Result<Sqlite, String> {
+    Sqlite::new(path.to_string_lossy().into_owned())
+        .map_err(|e| format!("open {}: {}", path.display(), e))
+}
+
+/// trash <workspace> [options]
+pub fn command(args: &[String]) -> Result<(), String> {
+    let strs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    let (workspace, rest) = match strs.as_slice() {
+        [workspace, rest @ ..] => (*workspace, rest),
+        _ => return Err(USAGE.to_owned()),
+    };
+    // a workspace directory, or the trash file itself
+    let file = if Path::new(workspace).is_dir() {
+        path(workspace)
+    } else {
+        PathBuf::from(workspace)
+    };
+    if !file.is_file() {
+        return Err(format!("no trash at {}", file.display()));
+    }
+    let dir = file.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+
+    let mut lib = WordLibrary::new();
+    lib.set_trash(Box::new(open(&file)?));
+    let id = |s: &str| s.parse::<i64>().map_err(|_| format!("invalid id {}", s));
+    let err = |e: InternalError<sqlite::Error>| e.to_string();
+
+    match rest {
+        [] => {
+            for t in lib.trashed(0).map_err(err)? {
+                println!(
+                    "{:<6}{:<20}{}  from {}",
+                    t.id,
+                    t.entry.word.name,
+                    format_time(t.deleted_at),
+                    t.origin
+                );
+            }
+        }
+        ["--restore", s] => {
+            let id = id(s)?;
+            let t = lib
+                .trashed(0)
+                .map_err(err)?
+                .into_iter()
+                .find(|t| t.id == id)
+                .ok_or_else(|| format!("no word {} in the trash", id))?;
+            // the origin is a library path, or a library of the workspace by its name
+            let origin = if Path::new(&t.origin).is_file() {
+                PathBuf::from(&t.origin)
+            } else {
+                dir.join(format!("{}.sql", t.origin))
+            };
+            if !origin.is_file() {
+                return Err(format!("the library {} is gone", t.origin));
+            }
+            lib.add_store(&t.origin, Box::new(open(&origin)?));
+            match lib.restore_trashed(id) {
+                Err(InternalError::Conflict) => {
+                    return Err(format!("{} is in {} again", t.entry.word.name, t.origin))
+                }
+                res => res.map_err(err)?,
+            };
+            println!("{} restored to {}", t.entry.word.name, t.origin);
+        }
+        ["--purge", s] => {
+            lib.purge_trashed(id(s)?).map_err(err)?;
+        }
+        ["--expire", days @ ..] => {
+            let days = match days {
+                [] => RETENTION_DAYS,
+                [d] => d.parse().map_err(|_| format!("invalid days {}", d))?,
+                _ => return Err(USAGE.to_owned()),
+            };
+            let n = lib.purge_trash(expiry(days)).map_err(err)?;
+            println!("{} words purged", n);
+        }
+        _ => return Err(USAGE.to_owned()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash() {
+        let (k, path) = ("english", "target/test_trash.sql");
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file("target/test_trash");
+        let mut lib: WordLibrary<World, sqlite::Error, Tansaction> = WordLibrary::new();
+        lib.add_store(k, Box::new(Sqlite::new(path.to_owned()).unwrap()));
+        lib.set_trash(Box::new(
+            Sqlite::new("target/test_trash".to_owned()).unwrap(),
+        ));
+
+        let word = World {
+            name: String::from("apple"),
+            message: String::from("a fruit"),
+        };
+        lib.insert(&word, k).unwrap();
+        lib.review("apple", k, 4, 0).unwrap();
+        lib.delete("apple", k).unwrap();
+        assert!(matches!(lib.get("apple", k), Err(InternalError::NotFound)));
+
+        let trashed = lib.trashed(0).unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(
+            (
+                trashed[0].origin.as_str(),
+                trashed[0].entry.word.name.as_str()
+            ),
+            (k, "apple")
+        );
+
+        // a word added again with the same name is not replaced
+        lib.insert(&word, k).unwrap();
+        let id = trashed[0].id;
+        assert!(matches!(
+            lib.restore_trashed(id),
+            Err(InternalError::Conflict)
+        ));
+        lib.store(k).unwrap().delete("apple").unwrap();
+
+        lib.restore_trashed(id).unwrap();
+        assert_eq!(lib.get("apple", k).unwrap().message, "a fruit");
+        assert!(lib.store(k).unwrap().schedule("apple").is_ok());
+        assert!(lib.trashed(0).unwrap().is_empty());
+
+        lib.delete("apple", k).unwrap();
+        assert_eq!(lib.purge_trash(expiry(1)).unwrap(), 0);
+        assert_eq!(lib.purge_trash(expiry(0) + 1).unwrap(), 1);
+        assert!(lib.trashed(0).unwrap().is_empty());
+
+        // an undone delete takes the word out of the trash
+        lib.set_journal(crate::store::undo::Journal::new(10));
+        lib.insert(&word, k).unwrap();
+        lib.delete("apple", k).unwrap();
+        assert_eq!(lib.trashed(0).unwrap().len(), 1);
+        lib.undo().unwrap();
+        assert!(lib.trashed(0).unwrap().is_empty());
+        lib.redo().unwrap();
+        assert_eq!(lib.trashed(0).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,671 @@
+use crate::db::sqlite::store as dbstore;
+use crate::store;
+use crate::store::trash;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeSet;
+use std::path::Path;
+use tui_textarea::TextArea;
+
+type Library = store::WordLibrary<store::World, sqlite::Error, dbstore::Tansaction>;
+
+const HELP: &str = "←/→ library  ↑/↓ word  space mark  enter edit  / search  \
+n new  d delete  m move  c copy  ^S save  q quit";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Action {
+    Move,
+    Copy,
+}
+
+enum Mode {
+    Normal,
+    // typing the incremental search query
+    Search,
+    // typing the name of a new word
+    NewWord(String),
+    // choosing the target library of move/copy
+    Target(Action, usize),
+    ConfirmDelete,
+    // the edited word is not saved, the key leaving it waits for the answer
+    Unsaved(KeyEvent),
+}
+
+/// terminal front end with the same panes as gui::APP:
+/// library tabs, the word index browser and the message editor
+pub struct TUI {
+    lib: Library,
+    keys: Vec<String>,
+    tab: usize,
+    words: Vec<String>,
+    index: ListState,
+    marked: BTreeSet<String>,
+    query: String,
+    editor: TextArea<'static>,
+    // the word loaded in the editor
+    editing: Option<String>,
+    editor_focus: bool,
+    // the editor has changes not saved
+    dirty: bool,
+    mode: Mode,
+    status: String,
+}
+
+impl TUI {
+    pub fn new() -> TUI {
+        TUI {
+            lib: store::WordLibrary::new(),
+            keys: Vec::new(),
+            tab: 0,
+            words: Vec::new(),
+            index: ListState::default(),
+            marked: BTreeSet::new(),
+            query: String::new(),
+            editor: TextArea::default(),
+            editing: None,
+            editor_focus: false,
+            dirty: false,
+            mode: Mode::Normal,
+            status: String::from(HELP),
+        }
+    }
+
+    /// open a library file, or every .sql file under a directory
+    pub fn open(&mut self, path: &str) -> Result<(), String> {
+        let files = store::library_files(path).map_err(|e| format!("read {} fail, {}", path, e))?;
+        for f in files {
+            let s =
+                dbstore::Sqlite::new(f.clone()).map_err(|e| format!("open {} fail, {}", f, e))?;
+            self.lib.add_store(&f, Box::new(s));
+        }
+        // deleted words are kept in the trash of the first workspace
+        if !self.lib.has_trash() {
+            trash::attach(&mut self.lib, &trash::path(path))?;
+        }
+        self.keys = self.lib.keys();
+        Ok(())
+    }
+
+    pub fn run(&mut self) -> std::io::Result<()> {
+        let mut terminal = ratatui::init();
+        self.reload();
+        let res = self.main_loop(&mut terminal);
+        ratatui::restore();
+        res
+    }
+
+    fn main_loop(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|f| self.draw(f))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn current_key(&self) -> Option<&String> {
+        self.keys.get(self.tab)
+    }
+
+    fn selected(&self) -> Option<&String> {
+        self.index.selected().and_then(|i| self.words.get(i))
+    }
+
+    // words the operation applies to: the marked ones, or the selected one
+    fn targets(&self) -> Vec<String> {
+        if self.marked.is_empty() {
+            self.selected().cloned().into_iter().collect()
+        } else {
+            self.marked.iter().cloned().collect()
+        }
+    }
+
+    /// reload the word index of current library, filtered by the search query
+    fn reload(&mut self) {
+        let key = match self.current_key() {
+            Some(key) => key.clone(),
+            None => return,
+        };
+
+        let res = if self.query.is_empty() {
+            self.lib.list(0, &key)
+        } else {
+            self.lib.search(&self.query, &key, usize::MAX)
+        };
+        match res {
+            Ok(ws) => self.words = ws.into_iter().map(|w| w.name).collect(),
+            Err(err) => {
+                self.words.clear();
+                self.status = format!("get the library index fail, file: {}, err: {}", key, err);
+            }
+        }
+
+        self.marked.retain(|w| self.words.contains(w));
+        match self.index.selected() {
+            _ if self.words.is_empty() => self.index.select(None),
+            Some(i) if i < self.words.len() => {}
+            _ => self.index.select(Some(0)),
+        }
+        self.load_word();
+    }
+
+    // load the selected word into the editor
+    fn load_word(&mut self) {
+        let name = self.selected().cloned();
+        if name == self.editing {
+            return;
+        }
+
+        let message = match (&name, self.current_key()) {
+            (Some(name), Some(key)) => match self.lib.get(name, key) {
+                Ok(w) => w.message,
+                Err(err) => {
+                    self.status = format!("get the word fail, word: {}, err: {}", name, err);
+                    String::new()
+                }
+            },
+            _ => String::new(),
+        };
+
+        self.editor = TextArea::new(message.lines().map(String::from).collect());
+        self.editing = name;
+        self.dirty = false;
+    }
+
+    // drop the changes of the editor, the saved message is loaded again
+    fn discard(&mut self) {
+        self.editing = None;
+        self.load_word();
+    }
+
+    // return false if the word is not saved
+    fn save(&mut self) -> bool {
+        let (name, key) = match (&self.editing, self.current_key()) {
+            (Some(name), Some(key)) => (name.clone(), key.clone()),
+            _ => return true,
+        };
+
+        let word = store::World {
+            name,
+            message: self.editor.lines().join("\n"),
+        };
+        match self.lib.update(&word, &key) {
+            Ok(_) => {
+                self.status = format!("{} saved", word.name);
+                self.dirty = false;
+                true
+            }
+            Err(err) => {
+                self.status = format!("save doc error: {}", err);
+                false
+            }
+        }
+    }
+
+    fn new_word(&mut self, name: &str) {
+        let key = match self.current_key() {
+            Some(key) => key.clone(),
+            None => return,
+        };
+
+        let word = store::World {
+            name: name.to_owned(),
+            message: String::new(),
+        };
+        if let Err(err) = self.lib.insert(&word, &key) {
+            self.status = format!("new doc error: {}", err);
+            return;
+        }
+
+        self.query.clear();
+        self.reload();
+        let pos = self.words.iter().position(|w| w == name);
+        self.index.select(pos);
+        self.load_word();
+        self.editor_focus = true;
+    }
+
+    fn delete(&mut self) {
+        let key = match self.current_key() {
+            Some(key) => key.clone(),
+            None => return,
+        };
+
+        let names = self.targets();
+        let mut errs = Vec::new();
+        for name in &names {
+            if let Err(err) = self.lib.delete(name, &key) {
+                errs.push(format!("{}: {}", name, err));
+            }
+        }
+        self.finish("deleted", names.len(), errs);
+    }
+
+    fn transfer(&mut self, action: Action, dst: &str) {
+        let key = match self.current_key() {
+            Some(key) => key.clone(),
+            None => return,
+        };
+
+        let names = self.targets();
+        let mut errs = Vec::new();
+        for name in &names {
+            let res = match action {
+                Action::Move => self.lib.move_to(name, &key, dst),
+                Action::Copy => self.lib.copy_to(name, &key, dst),
+            };
+            if let Err(err) = res {
+                errs.push(format!("{}: {}", name, err));
+            }
+        }
+        let done = match action {
+            Action::Move => "moved",
+            Action::Copy => "copied",
+        };
+        self.finish(done, names.len(), errs);
+    }
+
+    fn finish(&mut self, done: &str, count: usize, errs: Vec<String>) {
+        self.status = if errs.is_empty() {
+            format!("{} {} word(s)", done, count)
+        } else {
+            format!("error: {}", errs.join("; "))
+        };
+        self.marked.clear();
+        self.editing = None;
+        self.reload();
+    }
+
+    fn switch_tab(&mut self, tab: usize) {
+        if tab < self.keys.len() && tab != self.tab {
+            self.tab = tab;
+            self.marked.clear();
+            self.index.select(None);
+            self.editing = None;
+            self.reload();
+        }
+    }
+
+    fn move_cursor(&mut self, down: bool) {
+        if self.words.is_empty() {
+            return;
+        }
+        let i = match (self.index.selected(), down) {
+            (Some(i), true) => (i + 1).min(self.words.len() - 1),
+            (Some(i), false) => i.saturating_sub(1),
+            (None, _) => 0,
+        };
+        self.index.select(Some(i));
+        self.load_word();
+    }
+
+    // handle a key press, return false to quit
+    fn handle(&mut self, key: KeyEvent) -> bool {
+        let ctrl = TUI::ctrl(&key);
+        let others = self.other_keys();
+        let leaving = self.dirty && self.leaves(&key, ctrl);
+        match (&mut self.mode, key.code) {
+            (Mode::Search, KeyCode::Esc) => {
+                self.mode = Mode::Normal;
+                self.query.clear();
+                self.reload();
+            }
+            (Mode::Search, KeyCode::Enter) => self.mode = Mode::Normal,
+            (Mode::Search, KeyCode::Backspace) => {
+                self.query.pop();
+                self.reload();
+            }
+            (Mode::Search, KeyCode::Char(c)) => {
+                self.query.push(c);
+                self.reload();
+            }
+            (Mode::NewWord(_), KeyCode::Esc) => self.mode = Mode::Normal,
+            (Mode::NewWord(name), KeyCode::Enter) => {
+                let name = name.trim().to_owned();
+                self.mode = Mode::Normal;
+                if !name.is_empty() {
+                    self.new_word(&name);
+                }
+            }
+            (Mode::NewWord(name), KeyCode::Backspace) => {
+                name.pop();
+            }
+            (Mode::NewWord(name), KeyCode::Char(c)) => name.push(c),
+            (Mode::Target(..), KeyCode::Esc) => self.mode = Mode::Normal,
+            (Mode::Target(_, sel), KeyCode::Up) => *sel = sel.saturating_sub(1),
+            (Mode::Target(_, sel), KeyCode::Down) => *sel += 1,
+            (Mode::Target(action, sel), KeyCode::Enter) => {
+                let action = *action;
+                if let Some(dst) = others.get(*sel) {
+                    self.mode = Mode::Normal;
+                    self.transfer(action, dst);
+                }
+            }
+            (Mode::ConfirmDelete, KeyCode::Char('y')) => {
+                self.mode = Mode::Normal;
+                self.delete();
+            }
+            (Mode::ConfirmDelete, _) => self.mode = Mode::Normal,
+            (Mode::Unsaved(pending), KeyCode::Char('y')) => {
+                let pending = *pending;
+                self.mode = Mode::Normal;
+                if self.save() {
+                    return self.handle_normal(pending, TUI::ctrl(&pending));
+                }
+            }
+            (Mode::Unsaved(pending), KeyCode::Char('n')) => {
+                let pending = *pending;
+                self.mode = Mode::Normal;
+                self.discard();
+                return self.handle_normal(pending, TUI::ctrl(&pending));
+            }
+            (Mode::Unsaved(_), KeyCode::Esc) => self.mode = Mode::Normal,
+            (Mode::Normal, _) if leaving => self.mode = Mode::Unsaved(key),
+            (Mode::Normal, _) => return self.handle_normal(key, ctrl),
+            _ => {}
+        }
+        true
+    }
+
+    fn ctrl(key: &KeyEvent) -> bool {
+        key.modifiers.contains(KeyModifiers::CONTROL)
+    }
+
+    // the key loads another word into the editor or quits
+    fn leaves(&self, key: &KeyEvent, ctrl: bool) -> bool {
+        match key.code {
+            KeyCode::Char('c') if ctrl => true,
+            _ if ctrl || self.editor_focus => false,
+            KeyCode::Char('q' | 'h' | 'l' | 'k' | 'j' | ' ' | '/' | 'n' | 'd' | 'm' | 'c') => true,
+            KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Esc => true,
+            _ => false,
+        }
+    }
+
+    fn handle_normal(&mut self, key: KeyEvent, ctrl: bool) -> bool {
+        match key.code {
+            KeyCode::Char('s') if ctrl => {
+                self.save();
+            }
+            KeyCode::Char('c') if ctrl => return false,
+            KeyCode::Esc | KeyCode::Tab if self.editor_focus => self.editor_focus = false,
+            _ if self.editor_focus => self.dirty |= self.editor.input(key),
+            KeyCode::Char('q') => return false,
+            KeyCode::Left | KeyCode::Char('h') => self.switch_tab(self.tab.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Char('l') => self.switch_tab(self.tab + 1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(false),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(true),
+            KeyCode::Char(' ') => {
+                if let Some(w) = self.selected().cloned() {
+                    if !self.marked.remove(&w) {
+                        self.marked.insert(w);
+                    }
+                }
+                self.move_cursor(true);
+            }
+            KeyCode::Enter | KeyCode::Tab if self.editing.is_some() => self.editor_focus = true,
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Esc => {
+                self.marked.clear();
+                self.query.clear();
+                self.reload();
+            }
+            KeyCode::Char('n') if self.current_key().is_some() => {
+                self.mode = Mode::NewWord(String::new())
+            }
+            KeyCode::Char('d') if !self.targets().is_empty() => self.mode = Mode::ConfirmDelete,
+            KeyCode::Char('m') if !self.targets().is_empty() => {
+                self.mode = Mode::Target(Action::Move, 0)
+            }
+            KeyCode::Char('c') if !self.targets().is_empty() => {
+                self.mode = Mode::Target(Action::Copy, 0)
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn other_keys(&self) -> Vec<String> {
+        self.keys
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != self.tab)
+            .map(|(_, k)| k.clone())
+            .collect()
+    }
+
+    fn title(key: &str) -> String {
+        Path::new(key)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| key.to_owned())
+    }
+
+    fn draw(&mut self, f: &mut Frame) {
+        let [tabs, body, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(f.area());
+        let [index, editor] =
+            Layout::horizontal([Constraint::Percentage(25), Constraint::Percentage(75)])
+                .areas(body);
+
+        let titles: Vec<String> = self.keys.iter().map(|k| TUI::title(k)).collect();
+        f.render_widget(
+            Tabs::new(titles)
+                .select(self.tab)
+                .block(Block::default().borders(Borders::ALL).title("libraries"))
+                .highlight_style(Style::default().fg(Color::White).bg(Color::Blue)),
+            tabs,
+        );
+
+        let items: Vec<ListItem> = self
+            .words
+            .iter()
+            .map(|w| {
+                let mark = if self.marked.contains(w) { "* " } else { "  " };
+                ListItem::new(format!("{}{}", mark, w))
+            })
+            .collect();
+        let mut title = format!("words ({})", self.words.len());
+        if !self.query.is_empty() || matches!(self.mode, Mode::Search) {
+            title = format!("{} /{}", title, self.query);
+        }
+        let focus = |on: bool| {
+            if on {
+                Style::default().fg(Color::Blue)
+            } else {
+                Style::default()
+            }
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .border_style(focus(!self.editor_focus)),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, index, &mut self.index);
+
+        let name = self.editing.clone().unwrap_or_default();
+        self.editor.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(name)
+                .border_style(focus(self.editor_focus)),
+        );
+        f.render_widget(&self.editor, editor);
+
+        let line = match &self.mode {
+            Mode::NewWord(name) => format!("word key: {}", name),
+            Mode::ConfirmDelete => format!("delete {} word(s)? (y/n)", self.targets().len()),
+            Mode::Unsaved(_) => format!(
+                "save {}? (y)es (n)o, esc to go back",
+                self.editing.clone().unwrap_or_default()
+            ),
+            _ => self.status.clone(),
+        };
+        f.render_widget(Paragraph::new(Line::from(line)), status);
+
+        let others = self.other_keys();
+        if let Mode::Target(action, sel) = &mut self.mode {
+            *sel = (*sel).min(others.len().saturating_sub(1));
+            let title = match action {
+                Action::Move => "move to",
+                Action::Copy => "copy to",
+            };
+            let area = TUI::popup(f.area());
+            let items: Vec<ListItem> = others.iter().map(|k| ListItem::new(k.as_str())).collect();
+            let mut state = ListState::default().with_selected(Some(*sel));
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(
+                List::new(items)
+                    .block(Block::default().borders(Borders::ALL).title(title))
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+                area,
+                &mut state,
+            );
+        }
+    }
+
+    fn popup(area: Rect) -> Rect {
+        let [_, mid, _] = Layout::vertical([
+            Constraint::Percentage(30),
+            Constraint::Percentage(40),
+            Constraint::Percentage(30),
+        ])
+        .areas(area);
+        let [_, mid, _] = Layout::horizontal([
+            Constraint::Percentage(20),
+            Constraint::Percentage(60),
+            Constraint::Percentage(20),
+        ])
+        .areas(mid);
+        mid
+    }
+}
+
+impl Default for TUI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a tui on a new library in its own directory
+    fn new_tui(name: &str, words: &[&str]) -> (TUI, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("wordlib_tui_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut tui = TUI::new();
+        tui.open(&dir.join("words.sql").to_string_lossy()).unwrap();
+        let key = tui.keys[0].clone();
+        for name in words {
+            let word = store::World {
+                name: name.to_string(),
+                message: String::new(),
+            };
+            tui.lib.insert(&word, &key).unwrap();
+        }
+        tui.reload();
+        (tui, dir)
+    }
+
+    fn press(tui: &mut TUI, code: KeyCode) -> bool {
+        tui.handle(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn message(tui: &TUI, name: &str) -> String {
+        tui.lib.get(name, &tui.keys[0]).unwrap().message
+    }
+
+    #[test]
+    fn test_unsaved() {
+        let (mut tui, dir) = new_tui("unsaved", &["apple", "pear"]);
+        assert_eq!(tui.selected().map(String::as_str), Some("apple"));
+
+        press(&mut tui, KeyCode::Enter);
+        press(&mut tui, KeyCode::Char('x'));
+        assert!(tui.editor_focus && tui.dirty);
+        press(&mut tui, KeyCode::Esc);
+        // leaving the word waits for the answer, esc stays on it
+        press(&mut tui, KeyCode::Down);
+        assert!(matches!(tui.mode, Mode::Unsaved(_)));
+        press(&mut tui, KeyCode::Esc);
+        assert!(matches!(tui.mode, Mode::Normal) && tui.dirty);
+        assert_eq!(tui.selected().map(String::as_str), Some("apple"));
+
+        // yes saves the word and moves on
+        press(&mut tui, KeyCode::Down);
+        press(&mut tui, KeyCode::Char('y'));
+        assert_eq!(message(&tui, "apple"), "x");
+        assert_eq!(tui.selected().map(String::as_str), Some("pear"));
+        assert!(!tui.dirty);
+
+        // no drops the changes, quitting goes on
+        press(&mut tui, KeyCode::Enter);
+        press(&mut tui, KeyCode::Char('z'));
+        press(&mut tui, KeyCode::Esc);
+        assert!(press(&mut tui, KeyCode::Char('q')));
+        assert!(!press(&mut tui, KeyCode::Char('n')));
+        assert_eq!(message(&tui, "pear"), "");
+        assert!(tui.editor.lines().join("").is_empty());
+
+        // ctrl+c in the editor asks too
+        press(&mut tui, KeyCode::Enter);
+        press(&mut tui, KeyCode::Char('z'));
+        assert!(tui.handle(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(matches!(tui.mode, Mode::Unsaved(_)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keys() {
+        let (mut tui, dir) = new_tui("keys", &["apple", "pear"]);
+
+        // the search filters the index until esc
+        press(&mut tui, KeyCode::Char('/'));
+        press(&mut tui, KeyCode::Char('e'));
+        press(&mut tui, KeyCode::Char('a'));
+        assert!(matches!(tui.mode, Mode::Search));
+        press(&mut tui, KeyCode::Enter);
+        assert!(matches!(tui.mode, Mode::Normal));
+        assert_eq!(tui.words, vec!["pear"]);
+        press(&mut tui, KeyCode::Esc);
+        assert_eq!(tui.words.len(), 2);
+
+        // a new word is selected and edited
+        press(&mut tui, KeyCode::Char('n'));
+        for c in "fig".chars() {
+            press(&mut tui, KeyCode::Char(c));
+        }
+        press(&mut tui, KeyCode::Enter);
+        assert_eq!(tui.selected().map(String::as_str), Some("fig"));
+        assert!(tui.editor_focus && !tui.dirty);
+        press(&mut tui, KeyCode::Esc);
+
+        // space marks the word and moves down, d deletes the marked ones
+        press(&mut tui, KeyCode::Up);
+        let marked = tui.selected().cloned().unwrap();
+        press(&mut tui, KeyCode::Char(' '));
+        assert_eq!(tui.targets(), vec![marked.clone()]);
+        press(&mut tui, KeyCode::Char('d'));
+        assert!(matches!(tui.mode, Mode::ConfirmDelete));
+        press(&mut tui, KeyCode::Char('y'));
+        assert!(!tui.words.contains(&marked) && tui.marked.is_empty());
+        assert_eq!(tui.words.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,419 @@
+// undo and redo of the word operations of a library
+//
+// an action keeps every word it changed as it was before and after, undo puts
+// the words back as they were before and redo as they were after, only if no
+// one has changed them since. the recent actions are kept in a json file so
+// they can be undone after a restart, the data of the attached files in a
+// directory beside it
+use super::attachment::Attachment;
+use super::sync::{schedule_from, schedule_json};
+use super::{Entry, World};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// actions kept for undo
+pub const LIMIT: usize = 100;
+
+/// a word changed by an action
+#[derive(Debug, Clone)]
+pub struct Change {
+    /// key of the store of the word
+    pub key: String,
+    pub name: String,
+    /// None if the word is not in the store
+    pub before: Option<Entry>,
+    pub after: Option<Entry>,
+    /// the id of the word in the trash if it's deleted into it
+    pub trashed: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Action {
+    /// what is done, as "move apple"
+    pub label: String,
+    pub changes: Vec<Change>,
+}
+
+/// the actions to undo and redo, the last done last
+#[derive(Debug, Default)]
+pub struct Journal {
+    undo: VecDeque<Action>,
+    redo: Vec<Action>,
+    limit: usize,
+    path: Option<PathBuf>,
+}
+
+/// the file keeping the actions of the gui
+pub fn home_path() -> Option<PathBuf> {
+    std::env::var("HOME")
+        .ok()
+        .map(|home| Path::new(&home).join(".word_library_undo"))
+}
+
+// write the file whole or not at all
+fn write_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// the data of the attached files of a journal, kept once in a file named by
+/// its hash, so the journal is not rewritten with them on every action
+struct Files {
+    dir: PathBuf,
+    // the files the journal refers to
+    used: HashSet<String>,
+}
+
+impl Files {
+    // the directory beside the journal file
+    fn new(journal: &Path) -> Files {
+        let mut dir = journal.as_os_str().to_owned();
+        dir.push(".files");
+        Files {
+            dir: dir.into(),
+            used: HashSet::new(),
+        }
+    }
+
+    // keep the data of the attachment, return the name of its file
+    fn put(&mut self, a: &Attachment) -> io::Result<String> {
+        let mut h = DefaultHasher::new();
+        a.data.hash(&mut h);
+        let name = format!("{:016x}-{}", h.finish(), a.data.len());
+        let path = self.dir.join(&name);
+        if !path.exists() {
+            std::fs::create_dir_all(&self.dir)?;
+            write_file(&path, &a.data)?;
+        }
+        self.used.insert(name.clone());
+        Ok(name)
+    }
+
+    fn get(&self, name: &str) -> Result<Vec<u8>, String> {
+        // the name is made by put, never a path
+        if name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(format!("invalid attachment file {}", name));
+        }
+        std::fs::read(self.dir.join(name)).map_err(|e| format!("read attachment {}: {}", name, e))
+    }
+
+    // remove the files no action refers to any more
+    fn prune(&self) {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for e in entries.filter_map(|e| e.ok()) {
+            if !self.used.contains(e.file_name().to_string_lossy().as_ref()) {
+                let _ = std::fs::remove_file(e.path());
+            }
+        }
+    }
+}
+
+fn entry_json(entry: &Option<Entry>, files: &mut Files) -> io::Result<Value> {
+    let e = match entry {
+        Some(e) => e,
+        None => return Ok(Value::Null),
+    };
+    let mut attachments = Vec::new();
+    for a in &e.attachments {
+        attachments.push(json!({"name": a.name, "mime": a.mime, "file": files.put(a)?}));
+    }
+    Ok(json!({
+        "name": e.word.name,
+        "message": e.word.message,
+        "version": e.version,
+        "schedule": e.schedule.as_ref().map(schedule_json),
+        "tags": e.tags,
+        "attachments": attachments,
+    }))
+}
+
+// an attachment of the journal, the data is in a file or, in the journals
+// written before, inline
+fn attachment_from(v: &Value, files: &Files) -> Result<Attachment, String> {
+    let file = match v["file"].as_str() {
+        Some(file) => file,
+        None => return Attachment::from_json(v),
+    };
+    let mut a = Attachment::from_json(&json!({"name": v["name"], "mime": v["mime"], "data": ""}))?;
+    a.data = files.get(file)?;
+    Ok(a)
+}
+
+fn entry_from(v: &Value, files: &Files) -> Result<Option<Entry>, String> {
+    if v.is_null() {
+        return Ok(None);
+    }
+    let field = |name: &str| {
+        v[name]
+            .as_str()
+            .map(|s| s.to_owned())
+            .ok_or_else(|| format!("entry without {}", name))
+    };
+    Ok(Some(Entry {
+        word: World {
+            name: field("name")?,
+            message: field("message")?,
+        },
+        version: v["version"].as_i64().unwrap_or(1),
+        schedule: match &v["schedule"] {
+            Value::Null => None,
+            s => Some(schedule_from(s).ok_or("invalid schedule")?),
+        },
+        tags: v["tags"]
+            .as_array()
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(|t| t.as_str().map(|t| t.to_owned()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        attachments: match v["attachments"].as_array() {
+            Some(list) => list
+                .iter()
+                .map(|a| attachment_from(a, files))
+                .collect::<Result<Vec<Attachment>, String>>()?,
+            None => Vec::new(),
+        },
+    }))
+}
+
+fn action_json(a: &Action, files: &mut Files) -> io::Result<Value> {
+    let mut changes = Vec::new();
+    for c in &a.changes {
+        changes.push(json!({
+            "key": c.key,
+            "name": c.name,
+            "before": entry_json(&c.before, files)?,
+            "after": entry_json(&c.after, files)?,
+            "trashed": c.trashed,
+        }));
+    }
+    Ok(json!({"label": a.label, "changes": changes}))
+}
+
+fn action_from(v: &Value, files: &Files) -> Result<Action, String> {
+    let str = |v: &Value, name: &str| {
+        v[name]
+            .as_str()
+            .map(|s| s.to_owned())
+            .ok_or_else(|| format!("action without {}", name))
+    };
+    let changes = v["changes"]
+        .as_array()
+        .ok_or("action without changes")?
+        .iter()
+        .map(|c| {
+            Ok(Change {
+                key: str(c, "key")?,
+                name: str(c, "name")?,
+                before: entry_from(&c["before"], files)?,
+                after: entry_from(&c["after"], files)?,
+                trashed: c["trashed"].as_i64(),
+            })
+        })
+        .collect::<Result<Vec<Change>, String>>()?;
+    Ok(Action {
+        label: str(v, "label")?,
+        changes,
+    })
+}
+
+impl Journal {
+    /// a journal in memory keeping the last limit actions
+    pub fn new(limit: usize) -> Journal {
+        Journal {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit,
+            path: None,
+        }
+    }
+
+    /// a journal kept in the file, which is created on the first action
+    pub fn open(path: &Path, limit: usize) -> Result<Journal, String> {
+        let mut journal = Journal::new(limit);
+        journal.path = Some(path.to_path_buf());
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(journal),
+            Err(err) => return Err(format!("read {} fail, {}", path.display(), err)),
+        };
+
+        let invalid = |e: String| format!("invalid undo file {}, {}", path.display(), e);
+        let v: Value = serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+        let files = Files::new(path);
+        let actions = |name: &str| -> Result<Vec<Action>, String> {
+            match v[name].as_array() {
+                Some(list) => list.iter().map(|a| action_from(a, &files)).collect(),
+                None => Ok(Vec::new()),
+            }
+        };
+        journal.undo = actions("undo").map_err(invalid)?.into();
+        journal.redo = actions("redo").map_err(invalid)?;
+        Ok(journal)
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        // losing the journal only loses the undo, the words are written already
+        if let Err(err) = self.write(path) {
+            eprintln!("keep undo in {} fail, {}", path.display(), err);
+        }
+    }
+
+    // replace the file at once, a crash leaves the journal written before
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let mut files = Files::new(path);
+        let mut actions = |list: Vec<&Action>| -> io::Result<Vec<Value>> {
+            list.into_iter()
+                .map(|a| action_json(a, &mut files))
+                .collect()
+        };
+        let v = json!({
+            "undo": actions(self.undo.iter().collect())?,
+            "redo": actions(self.redo.iter().collect())?,
+        });
+        write_file(path, v.to_string().as_bytes())?;
+        files.prune();
+        Ok(())
+    }
+
+    /// record a done action, the undone ones can not be redone after it
+    pub fn push(&mut self, action: Action) {
+        if action.changes.is_empty() {
+            return;
+        }
+        self.undo.push_back(action);
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+        self.save();
+    }
+
+    /// the action to undo
+    pub fn last(&self) -> Option<&Action> {
+        self.undo.back()
+    }
+
+    /// the action to redo
+    pub fn next(&self) -> Option<&Action> {
+        self.redo.last()
+    }
+
+    /// move the last action to redo after it is undone
+    pub fn undone(&mut self) {
+        if let Some(a) = self.undo.pop_back() {
+            self.redo.push(a);
+            self.save();
+        }
+    }
+
+    /// move the next action back to undo after it is redone as action
+    pub fn redone(&mut self, action: Action) {
+        if self.redo.pop().is_some() {
+            self.undo.push_back(action);
+            self.save();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::review::Schedule;
+
+    fn change(name: &str, before: Option<&str>) -> Change {
+        let entry = |message: &str| Entry {
+            word: World {
+                name: name.to_owned(),
+                message: message.to_owned(),
+            },
+            version: 2,
+            schedule: Some(Schedule::default()),
+            tags: vec![String::from("fruit")],
+            attachments: vec![Attachment::new(&format!("{}.mp3", name), vec![1, 2])],
+        };
+        Change {
+            key: String::from("english"),
+            name: name.to_owned(),
+            before: before.map(entry),
+            after: Some(entry("after")),
+            trashed: None,
+        }
+    }
+
+    #[test]
+    fn test_journal() {
+        let path = Path::new("target/test_journal.json");
+        let files = Path::new("target/test_journal.json.files");
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_dir_all(files);
+        let mut j = Journal::open(path, 2).unwrap();
+        for name in ["a", "b", "c"] {
+            j.push(Action {
+                label: format!("insert {}", name),
+                changes: vec![change(name, None)],
+            });
+        }
+        j.undone();
+        assert_eq!(j.next().map(|a| a.label.as_str()), Some("insert c"));
+
+        // the oldest one is dropped over the limit, the undone one is kept to redo
+        let mut j = Journal::open(path, 2).unwrap();
+        assert_eq!((j.undo.len(), j.redo.len()), (1, 1));
+        let last = j.last().unwrap();
+        assert_eq!(last.label, "insert b");
+        assert!(last.changes[0].before.is_none());
+        let after = last.changes[0].after.as_ref().unwrap();
+        assert_eq!((after.word.message.as_str(), after.version), ("after", 2));
+        assert!(after.schedule.is_some());
+        assert_eq!(after.attachments[0].data, vec![1, 2]);
+
+        // the data is kept once beside the journal, not in it
+        assert!(!std::fs::read_to_string(path).unwrap().contains("\"data\""));
+        assert_eq!(std::fs::read_dir(files).unwrap().count(), 1);
+        let mut other = change("d", None);
+        other.after.as_mut().unwrap().attachments[0].data = vec![3];
+        j.push(Action {
+            label: String::from("insert d"),
+            changes: vec![other],
+        });
+        j.push(Action {
+            label: String::from("insert e"),
+            changes: vec![change("e", None)],
+        });
+        assert_eq!(std::fs::read_dir(files).unwrap().count(), 2);
+        j.push(Action {
+            label: String::from("insert f"),
+            changes: vec![change("f", None)],
+        });
+        // the file of d is removed with its action
+        assert_eq!(std::fs::read_dir(files).unwrap().count(), 1);
+        let j = Journal::open(path, 2).unwrap();
+        assert_eq!(
+            j.last().unwrap().changes[0]
+                .after
+                .as_ref()
+                .unwrap()
+                .attachments[0]
+                .data,
+            vec![1, 2]
+        );
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_dir_all(files).unwrap();
+    }
+}
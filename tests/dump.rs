@@ -0,0 +1,46 @@
+// the dump and restore commands of the wordlib binary
+use std::process::{Command, Output};
+
+fn wordlib(args: &[&str]) -> Output {
+    let out = Command::new(env!("CARGO_BIN_EXE_wordlib"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "wordlib {:?}: {}",
+        args,
+        String::from_utf8_lossy(&out.stderr)
+    );
+    out
+}
+
+#[test]
+fn dump_restore_stdout() {
+    let dir = "target/test_cli_dump";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir_all(dir).unwrap();
+    let (csv, lib, file) = (
+        format!("{}/words.csv", dir),
+        format!("{}/english.sql", dir),
+        format!("{}/dump.jsonl", dir),
+    );
+    std::fs::write(
+        &csv,
+        "word,message\napple,a fruit\nbook,\"to read, \"\"quoted\"\"\"\n",
+    )
+    .unwrap();
+    wordlib(&["import", &lib, &csv]);
+
+    // nothing but the dump is written to stdout
+    let dumped = wordlib(&["dump", &lib]).stdout;
+    std::fs::write(&file, &dumped).unwrap();
+    let restored = format!("{}/restored", dir);
+    wordlib(&["restore", &file, &restored]);
+
+    let again = wordlib(&["dump", &format!("{}/english.sql", restored)]).stdout;
+    assert_eq!(
+        String::from_utf8(again).unwrap(),
+        String::from_utf8(dumped).unwrap()
+    );
+}